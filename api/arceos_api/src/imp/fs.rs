@@ -6,6 +6,7 @@ pub use axfs::fops::DirEntry as AxDirEntry;
 pub use axfs::fops::FileAttr as AxFileAttr;
 pub use axfs::fops::FilePerm as AxFilePerm;
 pub use axfs::fops::FileType as AxFileType;
+pub use axfs::fops::LockKind as AxLockKind;
 pub use axfs::fops::OpenOptions as AxOpenOptions;
 pub use axio::SeekFrom as AxSeekFrom;
 
@@ -50,6 +51,10 @@ pub fn ax_flush_file(file: &AxFileHandle) -> AxResult {
     file.0.flush()
 }
 
+pub fn ax_sync_file(file: &AxFileHandle) -> AxResult {
+    file.0.sync_all()
+}
+
 pub fn ax_seek_file(file: &mut AxFileHandle, pos: AxSeekFrom) -> AxResult<u64> {
     file.0.seek(pos)
 }
@@ -58,6 +63,14 @@ pub fn ax_file_attr(file: &AxFileHandle) -> AxResult<AxFileAttr> {
     file.0.get_attr()
 }
 
+pub fn ax_lock_file(file: &AxFileHandle, kind: AxLockKind, wait: bool) -> AxResult {
+    file.0.locker().lock(kind, wait)
+}
+
+pub fn ax_unlock_file(file: &AxFileHandle) -> AxResult {
+    file.0.locker().unlock()
+}
+
 pub fn ax_read_dir(dir: &mut AxDirHandle, dirents: &mut [AxDirEntry]) -> AxResult<usize> {
     dir.0.read_dir(dirents)
 }
@@ -78,6 +91,22 @@ pub fn ax_rename(old: &str, new: &str) -> AxResult {
     axfs::api::rename(old, new)
 }
 
+pub fn ax_symlink(target: &str, path: &str) -> AxResult {
+    axfs::api::symlink(target, path)
+}
+
+pub fn ax_read_link(path: &str) -> AxResult<String> {
+    axfs::api::read_link(path)
+}
+
+pub fn ax_hard_link(old: &str, new: &str) -> AxResult {
+    axfs::api::hard_link(old, new)
+}
+
+pub fn ax_symlink_attr(path: &str) -> AxResult<AxFileAttr> {
+    axfs::fops::symlink_attr(path)
+}
+
 pub fn ax_current_dir() -> AxResult<String> {
     axfs::api::current_dir()
 }
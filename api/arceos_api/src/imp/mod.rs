@@ -1,4 +1,5 @@
 mod mem;
+mod sync;
 mod task;
 
 cfg_fs! {
@@ -47,6 +48,7 @@ mod time {
 
 pub use self::mem::*;
 pub use self::stdio::*;
+pub use self::sync::*;
 pub use self::task::*;
 pub use self::time::*;
 
@@ -0,0 +1,23 @@
+cfg_task! {
+    /// A writer-preferring readers-writer lock.
+    pub use axsync::RwLock as AxRwLock;
+    /// A guard that provides immutable data access of [`AxRwLock`].
+    pub use axsync::RwLockReadGuard as AxRwLockReadGuard;
+    /// A guard that provides mutable data access of [`AxRwLock`].
+    pub use axsync::RwLockWriteGuard as AxRwLockWriteGuard;
+    /// A condition variable, which can be used with any lock through the
+    /// `*_unlocking` methods.
+    pub use axsync::Condvar as AxCondvar;
+    /// The result of [`AxCondvar::wait_timeout_unlocking`].
+    #[cfg(feature = "irq")]
+    pub use axsync::WaitTimeoutResult as AxWaitTimeoutResult;
+    /// A counting semaphore.
+    pub use axsync::Semaphore as AxSemaphore;
+    /// A guard that releases the permit of [`AxSemaphore`] when dropped.
+    pub use axsync::SemaphoreGuard as AxSemaphoreGuard;
+    /// A barrier to synchronize a group of tasks.
+    pub use axsync::Barrier as AxBarrier;
+    /// The result of [`AxBarrier::wait`].
+    pub use axsync::BarrierWaitResult as AxBarrierWaitResult;
+    pub use axsync::mpsc;
+}
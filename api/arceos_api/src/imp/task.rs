@@ -135,4 +135,148 @@ cfg_task! {
             }
         }
     }
+
+    /// A handle to a kernel timer.
+    ///
+    /// The timer is cancelled when the handle is dropped.
+    #[cfg(feature = "irq")]
+    pub struct AxTimerHandle(axtask::Timer);
+
+    #[cfg(feature = "irq")]
+    impl AxTimerHandle {
+        /// Returns the next expiration time, or [`None`] if not armed.
+        pub fn deadline(&self) -> Option<crate::time::AxTimeValue> {
+            self.0.deadline()
+        }
+
+        /// Returns the period of the timer, or [`None`] if it is one-shot or
+        /// not armed.
+        pub fn interval(&self) -> Option<Duration> {
+            self.0.interval()
+        }
+    }
+
+    #[cfg(feature = "irq")]
+    pub fn ax_timer_new<F>(callback: F) -> AxTimerHandle
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        AxTimerHandle(axtask::Timer::new(callback))
+    }
+
+    #[cfg(feature = "irq")]
+    pub fn ax_timer_arm(
+        timer: &AxTimerHandle,
+        deadline: crate::time::AxTimeValue,
+        interval: Option<Duration>,
+    ) {
+        timer.0.arm(deadline, interval);
+    }
+
+    #[cfg(feature = "irq")]
+    pub fn ax_timer_cancel(timer: &AxTimerHandle) -> bool {
+        timer.0.cancel()
+    }
+}
+
+cfg_task! {
+    /// A handle to a task group.
+    #[cfg(feature = "task_group")]
+    #[derive(Clone)]
+    pub struct AxTaskGroupHandle(alloc::sync::Arc<axtask::TaskGroup>);
+
+    #[cfg(feature = "task_group")]
+    impl AxTaskGroupHandle {
+        /// Returns the name of the group.
+        pub fn name(&self) -> &str {
+            self.0.name()
+        }
+    }
+
+    #[cfg(feature = "task_group")]
+    pub fn ax_task_group_create(name: &str) -> AxTaskGroupHandle {
+        AxTaskGroupHandle(axtask::TaskGroup::new(name))
+    }
+
+    #[cfg(feature = "task_group")]
+    pub fn ax_task_group_set_bandwidth(
+        group: &AxTaskGroupHandle,
+        quota: Option<Duration>,
+        period: Duration,
+    ) -> crate::AxResult {
+        if period.is_zero() {
+            return axerrno::ax_err!(InvalidInput, "ax_task_group_set_bandwidth: zero period");
+        }
+        group.0.set_bandwidth(quota, period);
+        Ok(())
+    }
+
+    #[cfg(feature = "task_group")]
+    pub fn ax_task_group_set_weight(group: &AxTaskGroupHandle, weight: usize) -> crate::AxResult {
+        if weight == 0 {
+            return axerrno::ax_err!(InvalidInput, "ax_task_group_set_weight: zero weight");
+        }
+        group.0.set_weight(weight);
+        Ok(())
+    }
+
+    #[cfg(feature = "task_group")]
+    pub fn ax_task_group_usage(group: &AxTaskGroupHandle) -> Duration {
+        group.0.usage()
+    }
+
+    #[cfg(feature = "task_group")]
+    pub fn ax_task_group_add(group: &AxTaskGroupHandle, task: &AxTaskHandle) {
+        group.0.add_task(&task.inner);
+    }
+
+    #[cfg(feature = "task_group")]
+    pub fn ax_task_group_add_current(group: &AxTaskGroupHandle) {
+        group.0.add_task(axtask::current().as_task_ref());
+    }
+
+    #[cfg(feature = "task_group")]
+    pub fn ax_task_group_remove(group: &AxTaskGroupHandle, task: &AxTaskHandle) -> bool {
+        group.0.remove_task(&task.inner)
+    }
+}
+
+#[cfg(feature = "stack_usage")]
+pub fn ax_current_stack_max_usage() -> Option<usize> {
+    axtask::current().kernel_stack_max_usage()
+}
+
+#[cfg(feature = "stack_usage")]
+pub fn ax_set_stack_usage_warn_percent(percent: usize) {
+    axtask::set_stack_usage_warn_percent(percent);
+}
+
+#[cfg(feature = "sched_trace")]
+pub fn ax_sched_trace_enable(enable: bool) {
+    if enable {
+        axtask::trace::start();
+    } else {
+        axtask::trace::stop();
+    }
+}
+
+#[cfg(feature = "sched_trace")]
+pub fn ax_sched_trace_dump(path: Option<&str>) -> crate::AxResult {
+    match path {
+        None => {
+            axtask::trace::dump_to_console();
+            Ok(())
+        }
+        #[cfg(feature = "fs")]
+        Some(path) => {
+            let mut buf = alloc::string::String::new();
+            axtask::trace::dump_chrome_trace(&mut buf).map_err(|_| axerrno::AxError::NoMemory)?;
+            axfs::api::write(path, buf)
+        }
+        #[cfg(not(feature = "fs"))]
+        Some(_) => axerrno::ax_err!(
+            Unsupported,
+            "ax_sched_trace_dump: dumping into a file requires the `fs` feature"
+        ),
+    }
 }
@@ -122,6 +122,8 @@ pub mod task {
         pub type AxTaskHandle;
         pub type AxWaitQueueHandle;
         pub type AxCpuMask;
+        #[cfg(feature = "irq")]
+        pub type AxTimerHandle;
     }
 
     define_api! {
@@ -175,7 +177,97 @@ pub mod task {
         /// The maximum number of tasks to wake up is specified by `count`. If
         /// `count` is `u32::MAX`, it will wake up all tasks in the wait queue.
         pub fn ax_wait_queue_wake(wq: &AxWaitQueueHandle, count: u32);
+
+        /// Creates a new disarmed timer. When it expires, `callback` is run
+        /// in the timer task.
+        #[cfg(feature = "irq")]
+        pub fn ax_timer_new(callback: impl Fn() + Send + Sync + 'static) -> AxTimerHandle;
+        /// Arms the timer to expire at the given deadline (in wall time), then
+        /// every `interval` if specified. A previous arming is cancelled.
+        #[cfg(feature = "irq")]
+        pub fn ax_timer_arm(
+            timer: &AxTimerHandle,
+            deadline: crate::time::AxTimeValue,
+            interval: Option<core::time::Duration>,
+        );
+        /// Disarms the timer, returns whether it was armed.
+        #[cfg(feature = "irq")]
+        pub fn ax_timer_cancel(timer: &AxTimerHandle) -> bool;
+    }
+
+    define_api_type! {
+        @cfg "task_group";
+        pub type AxTaskGroupHandle;
+    }
+
+    define_api! {
+        @cfg "task_group";
+
+        /// Creates a new empty task group, with no CPU quota and the default
+        /// weight.
+        pub fn ax_task_group_create(name: &str) -> AxTaskGroupHandle;
+        /// Limits the CPU time of the group to `quota` per `period`, or
+        /// removes the limit if `quota` is [`None`].
+        pub fn ax_task_group_set_bandwidth(
+            group: &AxTaskGroupHandle,
+            quota: Option<core::time::Duration>,
+            period: core::time::Duration,
+        ) -> crate::AxResult;
+        /// Sets the weight shared by the members of the group.
+        pub fn ax_task_group_set_weight(group: &AxTaskGroupHandle, weight: usize) -> crate::AxResult;
+        /// Returns the total CPU time used by the members of the group.
+        pub fn ax_task_group_usage(group: &AxTaskGroupHandle) -> core::time::Duration;
+        /// Moves the given task into the group, from the group it was in.
+        pub fn ax_task_group_add(group: &AxTaskGroupHandle, task: &AxTaskHandle);
+        /// Moves the current task into the group, from the group it was in.
+        pub fn ax_task_group_add_current(group: &AxTaskGroupHandle);
+        /// Removes the given task from the group, and restores its priority
+        /// before joining. Returns `false` if it is not a member.
+        pub fn ax_task_group_remove(group: &AxTaskGroupHandle, task: &AxTaskHandle) -> bool;
+    }
+
+    define_api! {
+        @cfg "stack_usage";
+
+        /// Returns the deepest usage of the current task's stack so far, in
+        /// bytes, or `None` if it runs on the boot stack.
+        pub fn ax_current_stack_max_usage() -> Option<usize>;
+        /// Sets the percentage of its stack a task may use without a warning
+        /// when it exits.
+        pub fn ax_set_stack_usage_warn_percent(percent: usize);
     }
+
+    define_api! {
+        @cfg "sched_trace";
+
+        /// Starts or stops recording scheduler events.
+        pub fn ax_sched_trace_enable(enable: bool);
+        /// Dumps the recorded scheduler events in the Chrome trace format,
+        /// into the file at `path` if specified (requires the `fs` feature),
+        /// or to the console otherwise.
+        pub fn ax_sched_trace_dump(path: Option<&str>) -> crate::AxResult;
+    }
+}
+
+/// Synchronization primitives based on wait queues.
+pub mod sync {
+    define_api_type! {
+        @cfg "multitask";
+        pub type AxRwLock;
+        pub type AxRwLockReadGuard;
+        pub type AxRwLockWriteGuard;
+        pub type AxCondvar;
+        #[cfg(feature = "irq")]
+        pub type AxWaitTimeoutResult;
+        pub type AxSemaphore;
+        pub type AxSemaphoreGuard;
+        pub type AxBarrier;
+        pub type AxBarrierWaitResult;
+    }
+
+    /// Multi-producer FIFO queue communication primitives.
+    #[cfg(feature = "multitask")]
+    pub use crate::imp::mpsc;
 }
 
 /// Filesystem manipulation operations.
@@ -190,6 +282,7 @@ pub mod fs {
         pub type AxFileAttr;
         pub type AxFileType;
         pub type AxFilePerm;
+        pub type AxLockKind;
         pub type AxDirEntry;
         pub type AxSeekFrom;
         #[cfg(feature = "myfs")]
@@ -231,11 +324,19 @@ pub mod fs {
         pub fn ax_truncate_file(file: &AxFileHandle, size: u64) -> AxResult;
         /// Flushes the file, writes all buffered data to the underlying device.
         pub fn ax_flush_file(file: &AxFileHandle) -> AxResult;
+        /// Writes all data and metadata of the file to the underlying device.
+        pub fn ax_sync_file(file: &AxFileHandle) -> AxResult;
         /// Sets the cursor of the file to the specified offset. Returns the new
         /// position after the seek.
         pub fn ax_seek_file(file: &mut AxFileHandle, pos: AxSeekFrom) -> AxResult<u64>;
         /// Returns attributes of the file.
         pub fn ax_file_attr(file: &AxFileHandle) -> AxResult<AxFileAttr>;
+        /// Applies an advisory lock of `kind` on the whole file, owned by the
+        /// file handle. If others hold conflicting locks, it waits for them
+        /// to be released if `wait` is true, or fails with `WouldBlock`.
+        pub fn ax_lock_file(file: &AxFileHandle, kind: AxLockKind, wait: bool) -> AxResult;
+        /// Releases the advisory lock owned by the file handle.
+        pub fn ax_unlock_file(file: &AxFileHandle) -> AxResult;
 
         /// Reads directory entries starts from the current position into the
         /// given buffer, returns the number of entries read.
@@ -255,6 +356,15 @@ pub mod fs {
         ///
         /// It will delete the original file if `old` already exists.
         pub fn ax_rename(old: &str, new: &str) -> AxResult;
+        /// Creates a symbolic link at `path` which points to `target`.
+        pub fn ax_symlink(target: &str, path: &str) -> AxResult;
+        /// Returns the target of the symbolic link at `path`.
+        pub fn ax_read_link(path: &str) -> AxResult<alloc::string::String>;
+        /// Creates a hard link `new` to the file `old`.
+        pub fn ax_hard_link(old: &str, new: &str) -> AxResult;
+        /// Returns attributes of the file at `path`, without following the
+        /// symbolic link at the last component.
+        pub fn ax_symlink_attr(path: &str) -> AxResult<AxFileAttr>;
 
         /// Returns the current working directory.
         pub fn ax_current_dir() -> AxResult<alloc::string::String>;
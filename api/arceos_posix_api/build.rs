@@ -3,11 +3,17 @@ fn main() {
 
     fn gen_pthread_mutex(out_file: &str) -> std::io::Result<()> {
         // TODO: generate size and initial content automatically.
+        // `PthreadMutex` is a `#[repr(C, usize)]` enum of `axsync::Mutex<()>` and
+        // `axsync::PiMutex<()>`, the initializer is the tag (0) followed by
+        // `axsync::Mutex::new(())`, the rest is zero-filled.
+        // With `lockdep`, both mutexes have two more words for the lock class,
+        // which is valid if zero-filled.
         let (mutex_size, mutex_init) = if cfg!(feature = "multitask") {
-            if cfg!(feature = "smp") {
-                (6, "{0, 0, 8, 0, 0, 0}") // core::mem::transmute::<_, [usize; 6]>(axsync::Mutex::new(()))
-            } else {
-                (5, "{0, 8, 0, 0, 0}") // core::mem::transmute::<_, [usize; 5]>(axsync::Mutex::new(()))
+            match (cfg!(feature = "smp"), cfg!(feature = "lockdep")) {
+                (true, false) => (13, "{0, 0, 0, 8, 0, 0, 0}"), // core::mem::transmute::<_, [usize; 13]>(PthreadMutex::new(false))
+                (false, false) => (11, "{0, 0, 8, 0, 0, 0}"), // core::mem::transmute::<_, [usize; 11]>(PthreadMutex::new(false))
+                (true, true) => (15, "{0, 0, 0, 8, 0, 0, 0, 0, 0}"), // core::mem::transmute::<_, [usize; 15]>(PthreadMutex::new(false))
+                (false, true) => (13, "{0, 0, 8, 0, 0, 0, 0, 0}"), // core::mem::transmute::<_, [usize; 13]>(PthreadMutex::new(false))
             }
         } else {
             (1, "{0}")
@@ -37,6 +43,7 @@ typedef struct {{
 
         let allow_types = [
             "stat",
+            "flock",
             "size_t",
             "ssize_t",
             "off_t",
@@ -53,6 +60,11 @@ typedef struct {{
             "clockid_t",
             "rlimit",
             "aibuf",
+            "sigaction",
+            "sigevent",
+            "siginfo_t",
+            "itimerspec",
+            "timer_t",
         ];
         let allow_vars = [
             "CLOCK_.*",
@@ -62,12 +74,20 @@ typedef struct {{
             "IPPROTO_.*",
             "FD_.*",
             "F_.*",
+            "LOCK_.*",
+            "IN_.*",
             "_SC_.*",
             "EPOLL_CTL_.*",
             "EPOLL.*",
             "RLIMIT_.*",
             "EAI_.*",
             "MAXADDRS",
+            "SIGALRM",
+            "SIGEV_.*",
+            "SI_TIMER",
+            "SI_KERNEL",
+            "SA_.*",
+            "TIMER_ABSTIME",
         ];
 
         #[derive(Debug)]
@@ -19,6 +19,16 @@ pub trait FileLike: Send + Sync {
     fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync>;
     fn poll(&self) -> LinuxResult<PollState>;
     fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult;
+
+    /// Performs the device-specific operation `cmd`. Only device files
+    /// support it by default.
+    ///
+    /// # Safety
+    ///
+    /// If `cmd` takes a pointer in `arg`, it must be valid for its type.
+    unsafe fn ioctl(&self, _cmd: u32, _arg: usize) -> LinuxResult<c_int> {
+        Err(LinuxError::ENOTTY)
+    }
 }
 
 lazy_static::lazy_static! {
@@ -101,6 +111,16 @@ pub fn sys_dup2(old_fd: c_int, new_fd: c_int) -> c_int {
     })
 }
 
+/// Perform the device-specific operation `request` on a file descriptor.
+///
+/// # Safety
+///
+/// If `request` takes a pointer in `arg`, it must be valid for its type.
+pub unsafe fn sys_ioctl(fd: c_int, request: usize, arg: usize) -> c_int {
+    debug!("sys_ioctl <= fd: {} request: {:#x}", fd, request);
+    syscall_body!(sys_ioctl, get_file_like(fd)?.ioctl(request as u32, arg))
+}
+
 /// Manipulate file descriptor.
 ///
 /// TODO: `SET/GET` command is ignored, hard-code stdin/stdout
@@ -120,6 +140,10 @@ pub fn sys_fcntl(fd: c_int, cmd: c_int, arg: usize) -> c_int {
                 get_file_like(fd)?.set_nonblocking(arg & (ctypes::O_NONBLOCK as usize) > 0)?;
                 Ok(0)
             }
+            #[cfg(feature = "fs")]
+            ctypes::F_GETLK | ctypes::F_SETLK | ctypes::F_SETLKW => unsafe {
+                super::fs::fcntl_lock(fd, cmd as u32, arg as *mut ctypes::flock)
+            },
             _ => {
                 warn!("unsupported fcntl parameters: cmd {}", cmd);
                 Ok(0)
@@ -1,21 +1,25 @@
 use alloc::sync::Arc;
 use core::ffi::{c_char, c_int};
 
-use axerrno::{LinuxError, LinuxResult};
-use axfs::fops::OpenOptions;
+use axerrno::{AxError, LinuxError, LinuxResult};
+use axfs::fops::{FileAttr, FileLock, FileLocker, LockKind, OpenOptions};
 use axio::{PollState, SeekFrom};
 use axsync::Mutex;
 
 use super::fd_ops::{get_file_like, FileLike};
+use super::task::sys_getpid;
 use crate::{ctypes, utils::char_ptr_to_str};
 
 pub struct File {
     inner: Mutex<axfs::fops::File>,
+    /// Applies locks without holding `inner` while waiting for them.
+    locker: Arc<FileLocker>,
 }
 
 impl File {
     fn new(inner: axfs::fops::File) -> Self {
         Self {
+            locker: inner.locker().clone(),
             inner: Mutex::new(inner),
         }
     }
@@ -42,21 +46,7 @@ impl FileLike for File {
     }
 
     fn stat(&self) -> LinuxResult<ctypes::stat> {
-        let metadata = self.inner.lock().get_attr()?;
-        let ty = metadata.file_type() as u8;
-        let perm = metadata.perm().bits() as u32;
-        let st_mode = ((ty as u32) << 12) | perm;
-        Ok(ctypes::stat {
-            st_ino: 1,
-            st_nlink: 1,
-            st_mode,
-            st_uid: 1000,
-            st_gid: 1000,
-            st_size: metadata.size() as _,
-            st_blocks: metadata.blocks() as _,
-            st_blksize: 512,
-            ..Default::default()
-        })
+        Ok(attr_to_stat(&self.inner.lock().get_attr()?))
     }
 
     fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
@@ -73,6 +63,47 @@ impl FileLike for File {
     fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
         Ok(())
     }
+
+    unsafe fn ioctl(&self, cmd: u32, arg: usize) -> LinuxResult<c_int> {
+        match self.inner.lock().ioctl(cmd, arg) {
+            Ok(ret) => Ok(ret as c_int),
+            Err(AxError::Unsupported) => Err(LinuxError::ENOTTY),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+impl Drop for File {
+    fn drop(&mut self) {
+        // record locks are released when the file is closed
+        self.locker.unlock_ranges(lock_owner());
+    }
+}
+
+/// Returns the owner of record locks, which is the current process.
+fn lock_owner() -> u64 {
+    sys_getpid() as u64
+}
+
+/// Convert file attributes to [`ctypes::stat`].
+fn attr_to_stat(metadata: &FileAttr) -> ctypes::stat {
+    let ty = metadata.file_type() as u8;
+    let perm = metadata.perm().bits() as u32;
+    let st_mode = ((ty as u32) << 12) | perm;
+    ctypes::stat {
+        st_ino: 1,
+        st_nlink: 1,
+        st_mode,
+        st_uid: metadata.uid(),
+        st_gid: metadata.gid(),
+        st_size: metadata.size() as _,
+        st_blocks: metadata.blocks() as _,
+        st_blksize: 512,
+        st_atim: metadata.atime().into(),
+        st_mtim: metadata.mtime().into(),
+        st_ctim: metadata.ctime().into(),
+        ..Default::default()
+    }
 }
 
 /// Convert open flags to [`OpenOptions`].
@@ -146,8 +177,7 @@ pub unsafe fn sys_stat(path: *const c_char, buf: *mut ctypes::stat) -> c_int {
         let mut options = OpenOptions::new();
         options.read(true);
         let file = axfs::fops::File::open(path?, &options)?;
-        let st = File::new(file).stat()?;
-        unsafe { *buf = st };
+        unsafe { *buf = attr_to_stat(&file.get_attr()?) };
         Ok(0)
     })
 }
@@ -177,7 +207,53 @@ pub unsafe fn sys_lstat(path: *const c_char, buf: *mut ctypes::stat) -> ctypes::
         if buf.is_null() {
             return Err(LinuxError::EFAULT);
         }
-        unsafe { *buf = Default::default() }; // TODO
+        let attr = axfs::fops::symlink_attr(path?)?;
+        unsafe { *buf = attr_to_stat(&attr) };
+        Ok(0)
+    })
+}
+
+/// Create a symbolic link `linkpath` which points to `target`.
+///
+/// Return 0 if success.
+pub fn sys_symlink(target: *const c_char, linkpath: *const c_char) -> c_int {
+    syscall_body!(sys_symlink, {
+        let target = char_ptr_to_str(target)?;
+        let linkpath = char_ptr_to_str(linkpath)?;
+        debug!("sys_symlink <= {:?} {:?}", target, linkpath);
+        axfs::api::symlink(target, linkpath)?;
+        Ok(0)
+    })
+}
+
+/// Read the target of the symbolic link `path` into `buf`, which is not
+/// null-terminated.
+///
+/// Return the number of bytes placed in `buf`.
+pub fn sys_readlink(path: *const c_char, buf: *mut c_char, bufsiz: usize) -> ctypes::ssize_t {
+    let path = char_ptr_to_str(path);
+    debug!("sys_readlink <= {:?} {:#x} {}", path, buf as usize, bufsiz);
+    syscall_body!(sys_readlink, {
+        if buf.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        let target = axfs::api::read_link(path?)?;
+        let len = target.len().min(bufsiz);
+        let dst = unsafe { core::slice::from_raw_parts_mut(buf as *mut u8, len) };
+        dst.copy_from_slice(&target.as_bytes()[..len]);
+        Ok(len as ctypes::ssize_t)
+    })
+}
+
+/// Create a hard link `new` to the file `old`.
+///
+/// Return 0 if success.
+pub fn sys_link(old: *const c_char, new: *const c_char) -> c_int {
+    syscall_body!(sys_link, {
+        let old_path = char_ptr_to_str(old)?;
+        let new_path = char_ptr_to_str(new)?;
+        debug!("sys_link <= old: {:?}, new: {:?}", old_path, new_path);
+        axfs::api::hard_link(old_path, new_path)?;
         Ok(0)
     })
 }
@@ -202,6 +278,25 @@ pub fn sys_getcwd(buf: *mut c_char, size: usize) -> *mut c_char {
     })
 }
 
+/// Synchronize the data and metadata of the file `fd` to the device.
+///
+/// Return 0 if success.
+pub fn sys_fsync(fd: c_int) -> c_int {
+    debug!("sys_fsync <= {}", fd);
+    syscall_body!(sys_fsync, {
+        File::from_fd(fd)?.inner.lock().sync_all()?;
+        Ok(0)
+    })
+}
+
+/// Write all cached filesystem data to the devices.
+pub fn sys_sync() {
+    debug!("sys_sync");
+    if let Err(e) = axfs::api::sync() {
+        warn!("sys_sync: {:?}", e);
+    }
+}
+
 /// Rename `old` to `new`
 /// If new exists, it is first removed.
 ///
@@ -215,3 +310,97 @@ pub fn sys_rename(old: *const c_char, new: *const c_char) -> c_int {
         Ok(0)
     })
 }
+
+/// Apply or remove an advisory lock on the whole file `fd`.
+///
+/// `operation` is one of `LOCK_SH`, `LOCK_EX` and `LOCK_UN`, optionally with
+/// `LOCK_NB` to fail with `EWOULDBLOCK` instead of waiting.
+///
+/// Return 0 if success.
+pub fn sys_flock(fd: c_int, operation: c_int) -> c_int {
+    debug!("sys_flock <= {} {:#x}", fd, operation);
+    syscall_body!(sys_flock, {
+        let locker = &File::from_fd(fd)?.locker;
+        let wait = operation as u32 & ctypes::LOCK_NB == 0;
+        match operation as u32 & !ctypes::LOCK_NB {
+            ctypes::LOCK_SH => locker.lock(LockKind::Shared, wait)?,
+            ctypes::LOCK_EX => locker.lock(LockKind::Exclusive, wait)?,
+            ctypes::LOCK_UN => locker.unlock()?,
+            _ => return Err(LinuxError::EINVAL),
+        }
+        Ok(0)
+    })
+}
+
+/// Get, apply or remove a record lock described by `lock` on the file `fd`,
+/// for `fcntl` with `F_GETLK`, `F_SETLK` or `F_SETLKW`.
+///
+/// # Safety
+///
+/// `lock` must be valid for reads and writes of [`ctypes::flock`].
+pub(super) unsafe fn fcntl_lock(
+    fd: c_int,
+    cmd: u32,
+    lock: *mut ctypes::flock,
+) -> LinuxResult<c_int> {
+    if lock.is_null() {
+        return Err(LinuxError::EFAULT);
+    }
+    let lock = unsafe { &mut *lock };
+    let file = File::from_fd(fd)?;
+    let base = match lock.l_whence as c_int {
+        0 => 0,
+        1 => file.inner.lock().seek(SeekFrom::Current(0))?,
+        2 => file.inner.lock().get_attr()?.size(),
+        _ => return Err(LinuxError::EINVAL),
+    };
+    let start = base
+        .checked_add_signed(lock.l_start)
+        .ok_or(LinuxError::EINVAL)?;
+    // a negative length locks the bytes before `start`
+    let range = match lock.l_len {
+        0 => start..u64::MAX,
+        len if len > 0 => start..start.saturating_add(len as u64),
+        len => {
+            let len = len.unsigned_abs();
+            start.checked_sub(len).ok_or(LinuxError::EINVAL)?..start
+        }
+    };
+    let kind = match lock.l_type as u32 {
+        ctypes::F_RDLCK => Some(LockKind::Shared),
+        ctypes::F_WRLCK => Some(LockKind::Exclusive),
+        ctypes::F_UNLCK => None,
+        _ => return Err(LinuxError::EINVAL),
+    };
+
+    let owner = lock_owner();
+    if cmd == ctypes::F_GETLK {
+        let kind = kind.ok_or(LinuxError::EINVAL)?;
+        match file.locker.conflicting_lock(owner, kind, range) {
+            Some(FileLock { kind, range, owner }) => {
+                lock.l_type = match kind {
+                    LockKind::Shared => ctypes::F_RDLCK,
+                    LockKind::Exclusive => ctypes::F_WRLCK,
+                } as _;
+                lock.l_whence = 0;
+                lock.l_start = range.start as _;
+                lock.l_len = match range.end {
+                    u64::MAX => 0,
+                    end => (end - range.start) as _,
+                };
+                lock.l_pid = owner as _;
+            }
+            None => lock.l_type = ctypes::F_UNLCK as _,
+        }
+        return Ok(0);
+    }
+    match file
+        .locker
+        .lock_range(owner, kind, range, cmd == ctypes::F_SETLKW)
+    {
+        Ok(()) => Ok(0),
+        // not opened for reading or writing
+        Err(AxError::PermissionDenied) => Err(LinuxError::EBADF),
+        Err(e) => Err(e.into()),
+    }
+}
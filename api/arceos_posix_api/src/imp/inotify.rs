@@ -0,0 +1,213 @@
+//! `inotify` implementation, based on the watches of [`axfs`].
+
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use core::ffi::{c_char, c_int};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use axerrno::{LinuxError, LinuxResult};
+use axfs::api::{WatchEvent, WatchEventKind, Watcher};
+use axio::PollState;
+use axsync::Mutex;
+
+use crate::imp::fd_ops::{add_file_like, get_file_like, FileLike};
+use crate::{ctypes, utils::char_ptr_to_str};
+
+/// The size of `struct inotify_event` without the name.
+const EVENT_HEADER_SIZE: usize = 16;
+
+pub struct InotifyInstance {
+    watcher: Watcher,
+    /// Event masks of the watches.
+    masks: Mutex<BTreeMap<usize, u32>>,
+    /// An event which did not fit in the buffer of the last read.
+    unread: Mutex<Option<WatchEvent>>,
+    nonblocking: AtomicBool,
+}
+
+impl InotifyInstance {
+    fn new(nonblocking: bool) -> Self {
+        Self {
+            watcher: Watcher::new(),
+            masks: Mutex::new(BTreeMap::new()),
+            unread: Mutex::new(None),
+            nonblocking: AtomicBool::new(nonblocking),
+        }
+    }
+
+    fn from_fd(fd: c_int) -> LinuxResult<Arc<Self>> {
+        get_file_like(fd)?
+            .into_any()
+            .downcast::<Self>()
+            .map_err(|_| LinuxError::EINVAL)
+    }
+
+    /// Returns the mask of `event`, or [`None`] if it is not watched.
+    fn event_mask(&self, event: &WatchEvent) -> Option<u32> {
+        let mask = match (event.kind, event.name.is_some()) {
+            (WatchEventKind::Create, true) => ctypes::IN_CREATE,
+            (WatchEventKind::Modify, _) => ctypes::IN_MODIFY,
+            (WatchEventKind::Delete, true) => ctypes::IN_DELETE,
+            (WatchEventKind::Delete, false) => ctypes::IN_DELETE_SELF,
+            (WatchEventKind::MovedFrom, true) => ctypes::IN_MOVED_FROM,
+            (WatchEventKind::MovedFrom, false) => ctypes::IN_MOVE_SELF,
+            (WatchEventKind::MovedTo, true) => ctypes::IN_MOVED_TO,
+            // another file is created at the watched path
+            (WatchEventKind::Create | WatchEventKind::MovedTo, false) => return None,
+            // reported regardless of the watches
+            (WatchEventKind::Overflow, _) => return Some(ctypes::IN_Q_OVERFLOW),
+        };
+        let watched = *self.masks.lock().get(&event.wd)?;
+        if watched & mask == 0 {
+            return None;
+        }
+        let is_dir = if event.is_dir { ctypes::IN_ISDIR } else { 0 };
+        Some(mask | is_dir)
+    }
+
+    /// Writes `event` as `struct inotify_event` to `buf`. Returns the number
+    /// of bytes written, or [`None`] if it does not fit.
+    fn write_event(event: &WatchEvent, mask: u32, buf: &mut [u8]) -> Option<usize> {
+        // the name is terminated and padded with NULs
+        let name = event.name.as_deref().unwrap_or_default().as_bytes();
+        let name_len = match name.len() {
+            0 => 0,
+            len => (len + 1).next_multiple_of(EVENT_HEADER_SIZE),
+        };
+        let size = EVENT_HEADER_SIZE + name_len;
+        if size > buf.len() {
+            return None;
+        }
+        let wd = match event.kind {
+            WatchEventKind::Overflow => -1,
+            _ => event.wd as i32,
+        };
+        buf[..4].copy_from_slice(&wd.to_ne_bytes());
+        buf[4..8].copy_from_slice(&mask.to_ne_bytes());
+        buf[8..12].copy_from_slice(&event.cookie.to_ne_bytes());
+        buf[12..16].copy_from_slice(&(name_len as u32).to_ne_bytes());
+        buf[16..16 + name.len()].copy_from_slice(name);
+        buf[16 + name.len()..size].fill(0);
+        Some(size)
+    }
+}
+
+impl FileLike for InotifyInstance {
+    fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
+        let nonblocking = self.nonblocking.load(Ordering::Relaxed);
+        let mut unread = self.unread.lock().take();
+        let mut len = 0;
+        loop {
+            let event = match unread.take() {
+                Some(event) => event,
+                // only wait for the first event
+                None => match self.watcher.next_event(len == 0 && !nonblocking) {
+                    Ok(event) => event,
+                    Err(_) if len > 0 => break,
+                    Err(e) => return Err(e.into()),
+                },
+            };
+            let Some(mask) = self.event_mask(&event) else {
+                continue;
+            };
+            match Self::write_event(&event, mask, &mut buf[len..]) {
+                Some(size) => len += size,
+                None => {
+                    *self.unread.lock() = Some(event);
+                    if len == 0 {
+                        return Err(LinuxError::EINVAL);
+                    }
+                    break;
+                }
+            }
+        }
+        Ok(len)
+    }
+
+    fn write(&self, _buf: &[u8]) -> LinuxResult<usize> {
+        Err(LinuxError::EBADF)
+    }
+
+    fn stat(&self) -> LinuxResult<ctypes::stat> {
+        let st_mode = 0o600u32; // rw-------
+        Ok(ctypes::stat {
+            st_ino: 1,
+            st_nlink: 1,
+            st_mode,
+            ..Default::default()
+        })
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
+        self
+    }
+
+    fn poll(&self) -> LinuxResult<PollState> {
+        let mut state = self.watcher.poll()?;
+        state.readable |= self.unread.lock().is_some();
+        Ok(state)
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult {
+        self.nonblocking.store(nonblocking, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+/// Create a new inotify instance with `flags`, which may contain
+/// `IN_NONBLOCK` and `IN_CLOEXEC`.
+///
+/// Return its file descriptor.
+pub fn sys_inotify_init1(flags: c_int) -> c_int {
+    debug!("sys_inotify_init1 <= {:#x}", flags);
+    syscall_body!(sys_inotify_init1, {
+        let flags = flags as u32;
+        if flags & !(ctypes::IN_NONBLOCK | ctypes::IN_CLOEXEC) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let instance = InotifyInstance::new(flags & ctypes::IN_NONBLOCK != 0);
+        add_file_like(Arc::new(instance))
+    })
+}
+
+/// Watch the file or directory at `path` for the events in `mask` on the
+/// inotify instance `fd`.
+///
+/// Return the watch descriptor, which is the existing one if `path` is
+/// already watched, whose mask is replaced or extended by `IN_MASK_ADD`.
+pub fn sys_inotify_add_watch(fd: c_int, path: *const c_char, mask: u32) -> c_int {
+    let path = char_ptr_to_str(path);
+    debug!("sys_inotify_add_watch <= {} {:?} {:#x}", fd, path, mask);
+    syscall_body!(sys_inotify_add_watch, {
+        let path = path?;
+        let instance = InotifyInstance::from_fd(fd)?;
+        if mask & ctypes::IN_ALL_EVENTS == 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        if mask & ctypes::IN_ONLYDIR != 0 && !axfs::api::metadata(path)?.is_dir() {
+            return Err(LinuxError::ENOTDIR);
+        }
+        let wd = instance.watcher.add(path)?;
+        let mut masks = instance.masks.lock();
+        let watched = masks.entry(wd).or_default();
+        if mask & ctypes::IN_MASK_ADD == 0 {
+            *watched = 0;
+        }
+        *watched |= mask & ctypes::IN_ALL_EVENTS;
+        Ok(wd as c_int)
+    })
+}
+
+/// Remove the watch `wd` from the inotify instance `fd`.
+///
+/// Return 0 if success.
+pub fn sys_inotify_rm_watch(fd: c_int, wd: c_int) -> c_int {
+    debug!("sys_inotify_rm_watch <= {} {}", fd, wd);
+    syscall_body!(sys_inotify_rm_watch, {
+        let instance = InotifyInstance::from_fd(fd)?;
+        let wd = usize::try_from(wd).map_err(|_| LinuxError::EINVAL)?;
+        instance.watcher.remove(wd)?;
+        instance.masks.lock().remove(&wd);
+        Ok(0)
+    })
+}
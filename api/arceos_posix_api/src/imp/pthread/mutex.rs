@@ -1,7 +1,7 @@
 use crate::{ctypes, utils::check_null_mut_ptr};
 
 use axerrno::LinuxResult;
-use axsync::Mutex;
+use axsync::{Mutex, PiMutex};
 
 use core::ffi::c_int;
 use core::mem::{size_of, ManuallyDrop};
@@ -11,35 +11,63 @@ static_assertions::const_assert_eq!(
     size_of::<PthreadMutex>()
 );
 
-#[repr(C)]
-pub struct PthreadMutex(Mutex<()>);
+/// The bit in `pthread_mutexattr_t` that selects the `PTHREAD_PRIO_INHERIT`
+/// protocol (same as musl).
+const MUTEXATTR_PRIO_INHERIT: u32 = 8;
+
+#[repr(C, usize)]
+pub enum PthreadMutex {
+    Normal(Mutex<()>),
+    PrioInherit(PiMutex<()>),
+}
 
 impl PthreadMutex {
-    const fn new() -> Self {
-        Self(Mutex::new(()))
+    const fn new(prio_inherit: bool) -> Self {
+        if prio_inherit {
+            Self::PrioInherit(PiMutex::new(()))
+        } else {
+            Self::Normal(Mutex::new(()))
+        }
     }
 
     fn lock(&self) -> LinuxResult {
-        let _guard = ManuallyDrop::new(self.0.lock());
+        match self {
+            Self::Normal(m) => {
+                let _guard = ManuallyDrop::new(m.lock());
+            }
+            Self::PrioInherit(m) => {
+                let _guard = ManuallyDrop::new(m.lock());
+            }
+        }
         Ok(())
     }
 
     fn unlock(&self) -> LinuxResult {
-        unsafe { self.0.force_unlock() };
+        match self {
+            Self::Normal(m) => unsafe { m.force_unlock() },
+            Self::PrioInherit(m) => unsafe { m.force_unlock() },
+        }
         Ok(())
     }
 }
 
 /// Initialize a mutex.
+///
+/// If `PTHREAD_PRIO_INHERIT` is set in `attr`, the mutex uses the priority
+/// inheritance protocol.
 pub fn sys_pthread_mutex_init(
     mutex: *mut ctypes::pthread_mutex_t,
-    _attr: *const ctypes::pthread_mutexattr_t,
+    attr: *const ctypes::pthread_mutexattr_t,
 ) -> c_int {
     debug!("sys_pthread_mutex_init <= {:#x}", mutex as usize);
     syscall_body!(sys_pthread_mutex_init, {
         check_null_mut_ptr(mutex)?;
+        let prio_inherit =
+            !attr.is_null() && unsafe { (*attr).__attr } & MUTEXATTR_PRIO_INHERIT != 0;
         unsafe {
-            mutex.cast::<PthreadMutex>().write(PthreadMutex::new());
+            mutex
+                .cast::<PthreadMutex>()
+                .write(PthreadMutex::new(prio_inherit));
         }
         Ok(0)
     })
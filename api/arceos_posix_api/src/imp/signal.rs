@@ -0,0 +1,108 @@
+use core::ffi::{c_int, c_void};
+
+use axerrno::LinuxError;
+use spin::RwLock;
+
+use crate::ctypes;
+
+/// Number of signals, including the invalid signal 0.
+const NSIG: usize = 65;
+
+const SIG_DFL: usize = 0;
+const SIG_IGN: usize = 1;
+const SIGKILL: c_int = 9;
+const SIGSTOP: c_int = 19;
+
+#[derive(Clone, Copy)]
+struct SigAction {
+    handler: usize,
+    flags: c_int,
+}
+
+/// The registered signal handlers.
+///
+/// Signals are not delivered to threads asynchronously. They are only raised
+/// by timers (e.g., `alarm` and `timer_create`), and the handlers run in the
+/// task that runs timer callbacks.
+static SIG_ACTIONS: RwLock<[SigAction; NSIG]> = RwLock::new(
+    [SigAction {
+        handler: SIG_DFL,
+        flags: 0,
+    }; NSIG],
+);
+
+/// Examine and change a signal action.
+pub unsafe fn sys_sigaction(
+    signum: c_int,
+    act: *const ctypes::sigaction,
+    oldact: *mut ctypes::sigaction,
+) -> c_int {
+    debug!("sys_sigaction <= {}", signum);
+    syscall_body!(sys_sigaction, {
+        if signum <= 0 || signum as usize >= NSIG {
+            return Err(LinuxError::EINVAL);
+        }
+        if !act.is_null() && (signum == SIGKILL || signum == SIGSTOP) {
+            return Err(LinuxError::EINVAL);
+        }
+
+        let mut actions = SIG_ACTIONS.write();
+        let old = actions[signum as usize];
+        if !oldact.is_null() {
+            let mut res = ctypes::sigaction::default();
+            res.sa_flags = old.flags;
+            // Safety: both members of the union are function pointers.
+            res.__sa_handler.sa_handler = unsafe { core::mem::transmute(old.handler) };
+            unsafe { *oldact = res };
+        }
+        if !act.is_null() {
+            let act = unsafe { &*act };
+            actions[signum as usize] = SigAction {
+                handler: unsafe { act.__sa_handler.sa_handler }.map_or(SIG_DFL, |f| f as usize),
+                flags: act.sa_flags,
+            };
+        }
+        Ok(0)
+    })
+}
+
+/// Raises a signal on behalf of a timer, `timerid` and `value` are passed to
+/// the handler with `SA_SIGINFO`.
+#[cfg(all(feature = "multitask", feature = "irq"))]
+pub(crate) fn raise_timer_signal(
+    signum: c_int,
+    code: c_int,
+    timerid: c_int,
+    value: ctypes::sigval,
+) {
+    let Some(action) = SIG_ACTIONS.read().get(signum as usize).copied() else {
+        return;
+    };
+    match action.handler {
+        SIG_IGN => {}
+        SIG_DFL => {
+            // The default action of timer signals is to terminate.
+            warn!("terminated by signal {}", signum);
+            axhal::misc::terminate();
+        }
+        handler if action.flags & ctypes::SA_SIGINFO as c_int != 0 => {
+            let mut info = ctypes::siginfo_t {
+                si_signo: signum,
+                si_code: code,
+                ..Default::default()
+            };
+            unsafe {
+                let common = &mut info.__si_fields.__si_common;
+                common.__first.__timer.si_timerid = timerid;
+                common.__second.si_value = value;
+                let handler: unsafe extern "C" fn(c_int, *mut ctypes::siginfo_t, *mut c_void) =
+                    core::mem::transmute(handler);
+                handler(signum, &mut info, core::ptr::null_mut());
+            }
+        }
+        handler => unsafe {
+            let handler: unsafe extern "C" fn(c_int) = core::mem::transmute(handler);
+            handler(signum);
+        },
+    }
+}
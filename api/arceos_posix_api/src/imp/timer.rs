@@ -0,0 +1,226 @@
+use alloc::collections::BTreeMap;
+use core::ffi::{c_int, c_uint, c_void};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::time::Duration;
+
+use axerrno::{LinuxError, LinuxResult};
+use axhal::time::{monotonic_time, wall_time, TimeValue};
+use axtask::Timer;
+use spin::Mutex;
+
+use super::signal::raise_timer_signal;
+use crate::ctypes;
+use crate::ctypes::{CLOCK_MONOTONIC, CLOCK_REALTIME};
+
+/// Maximum number of POSIX timers.
+const AX_TIMER_MAX: usize = 1024;
+
+struct PosixTimer {
+    clock: u32,
+    timer: Timer,
+}
+
+static NEXT_TIMER_ID: AtomicUsize = AtomicUsize::new(1);
+static POSIX_TIMERS: Mutex<BTreeMap<usize, PosixTimer>> = Mutex::new(BTreeMap::new());
+
+lazy_static::lazy_static! {
+    static ref ALARM_TIMER: Timer = Timer::new(|| {
+        raise_timer_signal(
+            ctypes::SIGALRM as c_int,
+            ctypes::SI_KERNEL as c_int,
+            0,
+            ctypes::sigval { sival_int: 0 },
+        )
+    });
+}
+
+fn check_timespec(ts: &ctypes::timespec) -> LinuxResult {
+    if ts.tv_sec < 0 || !(0..1_000_000_000).contains(&ts.tv_nsec) {
+        return Err(LinuxError::EINVAL);
+    }
+    Ok(())
+}
+
+/// Converts a deadline of `clock` to wall time, which kernel timers use.
+fn to_wall_time(clock: u32, deadline: TimeValue) -> TimeValue {
+    match clock {
+        CLOCK_MONOTONIC => (wall_time() + deadline).saturating_sub(monotonic_time()),
+        _ => deadline,
+    }
+}
+
+fn get_itimerspec(timer: &Timer) -> ctypes::itimerspec {
+    let value = timer.deadline().map_or(Duration::ZERO, |deadline| {
+        // A timer about to expire still has time left.
+        deadline
+            .saturating_sub(wall_time())
+            .max(Duration::from_nanos(1))
+    });
+    ctypes::itimerspec {
+        it_interval: timer.interval().unwrap_or(Duration::ZERO).into(),
+        it_value: value.into(),
+    }
+}
+
+/// Create a per-process timer.
+///
+/// The notification methods `SIGEV_NONE`, `SIGEV_SIGNAL` and `SIGEV_THREAD`
+/// are supported. Signal handlers and notification functions are called in
+/// the timer task, not in a new thread.
+pub unsafe fn sys_timer_create(
+    clockid: ctypes::clockid_t,
+    sevp: *const ctypes::sigevent,
+    timerid: *mut ctypes::timer_t,
+) -> c_int {
+    debug!("sys_timer_create <= {}", clockid);
+    syscall_body!(sys_timer_create, {
+        if timerid.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        let clock = clockid as u32;
+        if clock != CLOCK_REALTIME && clock != CLOCK_MONOTONIC {
+            return Err(LinuxError::EINVAL);
+        }
+        if POSIX_TIMERS.lock().len() >= AX_TIMER_MAX {
+            return Err(LinuxError::EAGAIN);
+        }
+
+        let id = NEXT_TIMER_ID.fetch_add(1, Ordering::Relaxed);
+        let timer = if sevp.is_null() {
+            // Defaults to `SIGEV_SIGNAL` with `SIGALRM` and the timer ID.
+            Timer::new(move || {
+                let value = ctypes::sigval {
+                    sival_int: id as c_int,
+                };
+                raise_timer_signal(
+                    ctypes::SIGALRM as c_int,
+                    ctypes::SI_TIMER,
+                    id as c_int,
+                    value,
+                )
+            })
+        } else {
+            let sev = unsafe { &*sevp };
+            // Only keep the bits of the value, as `sigval` holds a pointer.
+            let value = unsafe { sev.sigev_value.sival_ptr } as usize;
+            match sev.sigev_notify as u32 {
+                ctypes::SIGEV_NONE => Timer::new(|| {}),
+                ctypes::SIGEV_SIGNAL => {
+                    let signo = sev.sigev_signo;
+                    if signo <= 0 || signo >= 65 {
+                        return Err(LinuxError::EINVAL);
+                    }
+                    Timer::new(move || {
+                        let value = ctypes::sigval {
+                            sival_ptr: value as *mut c_void,
+                        };
+                        raise_timer_signal(signo, ctypes::SI_TIMER, id as c_int, value)
+                    })
+                }
+                ctypes::SIGEV_THREAD => {
+                    let func = sev.sigev_notify_function.ok_or(LinuxError::EINVAL)?;
+                    Timer::new(move || unsafe {
+                        func(ctypes::sigval {
+                            sival_ptr: value as *mut c_void,
+                        })
+                    })
+                }
+                _ => return Err(LinuxError::EINVAL),
+            }
+        };
+
+        POSIX_TIMERS.lock().insert(id, PosixTimer { clock, timer });
+        unsafe { *timerid = id as ctypes::timer_t };
+        Ok(0)
+    })
+}
+
+/// Delete a per-process timer.
+pub fn sys_timer_delete(timerid: ctypes::timer_t) -> c_int {
+    debug!("sys_timer_delete <= {:#x}", timerid as usize);
+    syscall_body!(sys_timer_delete, {
+        // Dropping the timer disarms it.
+        POSIX_TIMERS
+            .lock()
+            .remove(&(timerid as usize))
+            .ok_or(LinuxError::EINVAL)?;
+        Ok(0)
+    })
+}
+
+/// Arm or disarm a per-process timer.
+pub unsafe fn sys_timer_settime(
+    timerid: ctypes::timer_t,
+    flags: c_int,
+    new_value: *const ctypes::itimerspec,
+    old_value: *mut ctypes::itimerspec,
+) -> c_int {
+    debug!("sys_timer_settime <= {:#x} {:#x}", timerid as usize, flags);
+    syscall_body!(sys_timer_settime, {
+        if new_value.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        let new_value = unsafe { *new_value };
+        check_timespec(&new_value.it_value)?;
+        check_timespec(&new_value.it_interval)?;
+
+        let timers = POSIX_TIMERS.lock();
+        let posix_timer = timers.get(&(timerid as usize)).ok_or(LinuxError::EINVAL)?;
+        let timer = &posix_timer.timer;
+        if !old_value.is_null() {
+            unsafe { *old_value = get_itimerspec(timer) };
+        }
+
+        let value = Duration::from(new_value.it_value);
+        if value.is_zero() {
+            timer.cancel();
+            return Ok(0);
+        }
+        let deadline = if flags & ctypes::TIMER_ABSTIME as c_int != 0 {
+            to_wall_time(posix_timer.clock, value)
+        } else {
+            wall_time() + value
+        };
+        let interval = Duration::from(new_value.it_interval);
+        timer.arm(deadline, (!interval.is_zero()).then_some(interval));
+        Ok(0)
+    })
+}
+
+/// Get the time remaining on a per-process timer, and its interval.
+pub unsafe fn sys_timer_gettime(
+    timerid: ctypes::timer_t,
+    curr_value: *mut ctypes::itimerspec,
+) -> c_int {
+    syscall_body!(sys_timer_gettime, {
+        if curr_value.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        let timers = POSIX_TIMERS.lock();
+        let posix_timer = timers.get(&(timerid as usize)).ok_or(LinuxError::EINVAL)?;
+        unsafe { *curr_value = get_itimerspec(&posix_timer.timer) };
+        Ok(0)
+    })
+}
+
+/// Schedule a `SIGALRM` after `seconds`, or cancel it if `seconds` is zero.
+///
+/// Returns the number of seconds remaining of the previous alarm, or zero if
+/// there was none.
+pub fn sys_alarm(seconds: c_uint) -> c_uint {
+    debug!("sys_alarm <= {}", seconds);
+    let remaining = ALARM_TIMER.deadline().map_or(Duration::ZERO, |deadline| {
+        deadline.saturating_sub(wall_time())
+    });
+    if seconds == 0 {
+        ALARM_TIMER.cancel();
+    } else {
+        ALARM_TIMER.arm_oneshot(wall_time() + Duration::from_secs(seconds as u64));
+    }
+    // Round up so that a pending alarm never reports zero.
+    let mut secs = remaining.as_secs() as c_uint;
+    if remaining.subsec_nanos() > 0 {
+        secs += 1;
+    }
+    secs
+}
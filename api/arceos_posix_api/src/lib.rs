@@ -32,14 +32,24 @@ pub mod ctypes;
 
 pub use imp::io::{sys_read, sys_write, sys_writev};
 pub use imp::resources::{sys_getrlimit, sys_setrlimit};
+pub use imp::signal::sys_sigaction;
 pub use imp::sys::sys_sysconf;
 pub use imp::task::{sys_exit, sys_getpid, sys_sched_yield};
 pub use imp::time::{sys_clock_gettime, sys_nanosleep};
+#[cfg(all(feature = "multitask", feature = "irq"))]
+pub use imp::timer::{
+    sys_alarm, sys_timer_create, sys_timer_delete, sys_timer_gettime, sys_timer_settime,
+};
 
 #[cfg(feature = "fd")]
-pub use imp::fd_ops::{sys_close, sys_dup, sys_dup2, sys_fcntl};
+pub use imp::fd_ops::{sys_close, sys_dup, sys_dup2, sys_fcntl, sys_ioctl};
+#[cfg(feature = "fs")]
+pub use imp::fs::{
+    sys_flock, sys_fstat, sys_fsync, sys_getcwd, sys_link, sys_lseek, sys_lstat, sys_open,
+    sys_readlink, sys_rename, sys_stat, sys_symlink, sys_sync,
+};
 #[cfg(feature = "fs")]
-pub use imp::fs::{sys_fstat, sys_getcwd, sys_lseek, sys_lstat, sys_open, sys_rename, sys_stat};
+pub use imp::inotify::{sys_inotify_add_watch, sys_inotify_init1, sys_inotify_rm_watch};
 #[cfg(feature = "select")]
 pub use imp::io_mpx::sys_select;
 #[cfg(feature = "epoll")]
@@ -22,6 +22,10 @@
 //! - Upperlayer stacks (fs, net, display)
 //!     - `fs`: Enable file system support.
 //!     - `myfs`: Allow users to define their custom filesystems to override the default.
+//!     - `extfs`: Use an ext2/ext4 filesystem on the root device if there is one.
+//!     - `initramfs`: Use an initramfs (loaded by the bootloader or embedded in the kernel image) as the root filesystem, without requiring a block device.
+//!     - `overlayfs`: Overlay the root filesystem with a RAM filesystem, so that it is never modified.
+//!     - `virtio-9p`: Mount directories shared by the host through virtio-9p (e.g., QEMU `-virtfs`) on `/mnt/<tag>`.
 //!     - `net`: Enable networking support.
 //!     - `display`: Enable graphics support.
 //! - Device drivers
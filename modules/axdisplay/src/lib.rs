@@ -25,6 +25,11 @@ pub fn init_display(mut display_devs: AxDeviceContainer<AxDisplayDevice>) {
     MAIN_DISPLAY.init_once(Mutex::new(dev));
 }
 
+/// Whether the graphics subsystem is initialized by [`init_display`].
+pub fn is_initialized() -> bool {
+    MAIN_DISPLAY.is_inited()
+}
+
 /// Gets the framebuffer information.
 pub fn framebuffer_info() -> DisplayInfo {
     MAIN_DISPLAY.lock().info()
@@ -1,5 +1,5 @@
 #[allow(unused_imports)]
-use crate::{prelude::*, AllDevices};
+use crate::{prelude::*, AllDevices, BusInfo};
 
 impl AllDevices {
     pub(crate) fn probe_bus_devices(&mut self) {
@@ -14,10 +14,20 @@ impl AllDevices {
                         reg.0, reg.0 + reg.1,
                         dev.device_name(),
                     );
-                    self.add_device(dev);
+                    self.add_device(dev, BusInfo::Mmio { base: reg.0, size: reg.1 });
                     continue; // skip to the next device
                 }
             });
+            #[cfg(feature = "virtio-9p")]
+            if let Some(dev) = crate::VirtIo9pDev::probe_mmio(reg.0, reg.1) {
+                info!(
+                    "registered a new 9p transport at [PA:{:#x}, PA:{:#x}): {:?}",
+                    reg.0,
+                    reg.0 + reg.1,
+                    dev.mount_tag(),
+                );
+                self.ninep.push(dev);
+            }
         }
     }
 }
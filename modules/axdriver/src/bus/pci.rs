@@ -1,11 +1,35 @@
-use crate::{prelude::*, AllDevices};
+use crate::{prelude::*, AllDevices, BusInfo, PciInfo};
 use axdriver_pci::{
-    BarInfo, Cam, Command, DeviceFunction, HeaderType, MemoryBarType, PciRangeAllocator, PciRoot,
+    BarInfo, Cam, Command, DeviceFunction, DeviceFunctionInfo, HeaderType, MemoryBarType,
+    PciRangeAllocator, PciRoot,
 };
-use axhal::mem::phys_to_virt;
+use axhal::mem::{phys_to_virt, VirtAddr};
 
 const PCI_BAR_NUM: u8 = 6;
 
+/// Offset of the `Interrupt Line` register in the configuration space,
+/// followed by the `Interrupt Pin` register.
+const PCI_INTERRUPT_LINE: usize = 0x3c;
+
+fn pci_info(base_vaddr: VirtAddr, bdf: DeviceFunction, dev_info: &DeviceFunctionInfo) -> PciInfo {
+    let offset = ((bdf.bus as usize) << 20)
+        | ((bdf.device as usize) << 15)
+        | ((bdf.function as usize) << 12)
+        | PCI_INTERRUPT_LINE;
+    let ptr = (base_vaddr.as_usize() + offset) as *const u16;
+    let [line, pin] = unsafe { ptr.read_volatile() }.to_le_bytes();
+    PciInfo {
+        bus: bdf.bus,
+        device: bdf.device,
+        function: bdf.function,
+        vendor_id: dev_info.vendor_id,
+        device_id: dev_info.device_id,
+        class: u32::from_be_bytes([0, dev_info.class, dev_info.subclass, dev_info.prog_if]),
+        // no interrupt pin, or the line is not connected (0xff)
+        irq: Some(line).filter(|&line| pin != 0 && line != 0xff),
+    }
+}
+
 fn config_pci_device(
     root: &mut PciRoot,
     bdf: DeviceFunction,
@@ -99,18 +123,31 @@ impl AllDevices {
                     continue;
                 }
                 match config_pci_device(&mut root, bdf, &mut allocator) {
-                    Ok(_) => for_each_drivers!(type Driver, {
-                        if let Some(dev) = Driver::probe_pci(&mut root, bdf, &dev_info) {
+                    Ok(_) => {
+                        for_each_drivers!(type Driver, {
+                            if let Some(dev) = Driver::probe_pci(&mut root, bdf, &dev_info) {
+                                info!(
+                                    "registered a new {:?} device at {}: {:?}",
+                                    dev.device_type(),
+                                    bdf,
+                                    dev.device_name(),
+                                );
+                                let info = pci_info(base_vaddr, bdf, &dev_info);
+                                self.add_device(dev, BusInfo::Pci(info));
+                                continue; // skip to the next device
+                            }
+                        });
+                        #[cfg(feature = "virtio-9p")]
+                        if let Some(dev) = crate::VirtIo9pDev::probe_pci(&mut root, bdf, &dev_info)
+                        {
                             info!(
-                                "registered a new {:?} device at {}: {:?}",
-                                dev.device_type(),
+                                "registered a new 9p transport at {}: {:?}",
                                 bdf,
-                                dev.device_name(),
+                                dev.mount_tag()
                             );
-                            self.add_device(dev);
-                            continue; // skip to the next device
+                            self.ninep.push(dev);
                         }
-                    }),
+                    }
                     Err(e) => warn!(
                         "failed to enable PCI device at {}({}): {:?}",
                         bdf, dev_info, e
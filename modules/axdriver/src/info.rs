@@ -0,0 +1,69 @@
+//! Information of probed devices, e.g., to be shown in `/sys`.
+
+use alloc::{string::String, vec::Vec};
+use axdriver_base::DeviceType;
+use lazyinit::LazyInit;
+
+static DEVICES: LazyInit<Vec<DeviceInfo>> = LazyInit::new();
+
+/// The location of a PCI device and its identification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PciInfo {
+    /// The bus number.
+    pub bus: u8,
+    /// The device number on the bus.
+    pub device: u8,
+    /// The function number of the device.
+    pub function: u8,
+    /// The vendor ID.
+    pub vendor_id: u16,
+    /// The device ID.
+    pub device_id: u16,
+    /// The class code, subclass and programming interface, in the lower 24 bits.
+    pub class: u32,
+    /// The legacy interrupt line, if the device uses one.
+    pub irq: Option<u8>,
+}
+
+/// How a device is found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BusInfo {
+    /// Not on a bus, e.g., a RAM disk or a device at a fixed address.
+    Platform,
+    /// A MMIO device in the physical memory range `[base, base + size)`.
+    Mmio {
+        /// The base physical address.
+        base: usize,
+        /// The size of the memory range.
+        size: usize,
+    },
+    /// A PCI device.
+    Pci(PciInfo),
+}
+
+/// Information of a probed device.
+#[derive(Debug, Clone)]
+pub struct DeviceInfo {
+    /// The device category.
+    pub device_type: DeviceType,
+    /// The device name given by the driver.
+    pub name: String,
+    /// How the device is found.
+    pub bus: BusInfo,
+}
+
+pub(crate) fn init(devices: Vec<DeviceInfo>) {
+    DEVICES.init_once(devices);
+}
+
+/// Returns the information of all devices probed by [`init_drivers`], in
+/// the order they are registered. It is empty before that.
+///
+/// [`init_drivers`]: crate::init_drivers
+pub fn devices() -> &'static [DeviceInfo] {
+    if DEVICES.is_inited() {
+        &DEVICES
+    } else {
+        &[]
+    }
+}
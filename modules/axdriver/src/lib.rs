@@ -5,7 +5,8 @@
 //! All detected devices are composed into a large struct [`AllDevices`]
 //! and returned by the [`init_drivers`] function. The upperlayer subsystems
 //! (e.g., the network stack) may unpack the struct to get the specified device
-//! driver they want.
+//! driver they want. The information of all devices (e.g., the bus they are
+//! found on) is kept and can be queried by [`devices`].
 //!
 //! For each device category (i.e., net, block, display, etc.), an unified type
 //! is used to represent all devices in that category. Currently, there are 3
@@ -34,6 +35,7 @@
 //! | Block | `virtio-blk` | VirtIO block device |
 //! | Network | `virtio-net` | VirtIO network device |
 //! | Display | `virtio-gpu` | VirtIO graphics device |
+//! | - | `virtio-9p` | VirtIO 9P transport, to share host directories |
 //!
 //! # Other Cargo Features
 //!
@@ -42,7 +44,7 @@
 //! - `bus-pci`: use PCI bus to probe all PCI devices. This feature is
 //!    enabeld by default.
 //! - `virtio`: use VirtIO devices. This is enabled if any of `virtio-blk`,
-//!   `virtio-net` or `virtio-gpu` is enabled.
+//!   `virtio-net`, `virtio-gpu` or `virtio-9p` is enabled.
 //! - `net`: use network devices. This is enabled if any feature of network
 //!    devices is selected. If this feature is enabled without any network device
 //!    features, a dummy struct is used for [`AxNetDevice`].
@@ -61,7 +63,6 @@
 #[macro_use]
 extern crate log;
 
-#[cfg(feature = "dyn")]
 extern crate alloc;
 
 #[macro_use]
@@ -70,6 +71,7 @@ mod macros;
 mod bus;
 mod drivers;
 mod dummy;
+mod info;
 mod structs;
 
 #[cfg(feature = "virtio")]
@@ -78,8 +80,12 @@ mod virtio;
 #[cfg(feature = "ixgbe")]
 mod ixgbe;
 
+#[cfg(feature = "virtio-9p")]
+mod virtio_9p;
+
 pub mod prelude;
 
+pub use self::info::{devices, BusInfo, DeviceInfo, PciInfo};
 #[allow(unused_imports)]
 use self::prelude::*;
 pub use self::structs::{AxDeviceContainer, AxDeviceEnum};
@@ -90,6 +96,8 @@ pub use self::structs::AxBlockDevice;
 pub use self::structs::AxDisplayDevice;
 #[cfg(feature = "net")]
 pub use self::structs::AxNetDevice;
+#[cfg(feature = "virtio-9p")]
+pub use self::virtio_9p::VirtIo9pDev;
 
 /// A structure that contains all device drivers, organized by their category.
 #[derive(Default)]
@@ -103,6 +111,11 @@ pub struct AllDevices {
     /// All graphics device drivers.
     #[cfg(feature = "display")]
     pub display: AxDeviceContainer<AxDisplayDevice>,
+    /// All VirtIO 9P transports, which are not in any device category.
+    #[cfg(feature = "virtio-9p")]
+    pub ninep: alloc::vec::Vec<VirtIo9pDev>,
+    /// Information of all devices, in the order they are added.
+    info: alloc::vec::Vec<DeviceInfo>,
 }
 
 impl AllDevices {
@@ -126,7 +139,7 @@ impl AllDevices {
                     dev.device_type(),
                     dev.device_name(),
                 );
-                self.add_device(dev);
+                self.add_device(dev, BusInfo::Platform);
             }
         });
 
@@ -135,7 +148,12 @@ impl AllDevices {
 
     /// Adds one device into the corresponding container, according to its device category.
     #[allow(dead_code)]
-    fn add_device(&mut self, dev: AxDeviceEnum) {
+    fn add_device(&mut self, dev: AxDeviceEnum, bus: BusInfo) {
+        self.info.push(DeviceInfo {
+            device_type: dev.device_type(),
+            name: dev.device_name().into(),
+            bus,
+        });
         match dev {
             #[cfg(feature = "net")]
             AxDeviceEnum::Net(dev) => self.net.push(dev),
@@ -154,6 +172,7 @@ pub fn init_drivers() -> AllDevices {
 
     let mut all_devs = AllDevices::default();
     all_devs.probe();
+    info::init(core::mem::take(&mut all_devs.info));
 
     #[cfg(feature = "net")]
     {
@@ -179,6 +198,13 @@ pub fn init_drivers() -> AllDevices {
             debug!("  graphics device {}: {:?}", i, dev.device_name());
         }
     }
+    #[cfg(feature = "virtio-9p")]
+    {
+        debug!("number of 9p transports: {}", all_devs.ninep.len());
+        for (i, dev) in all_devs.ninep.iter().enumerate() {
+            debug!("  9p transport {}: {:?}", i, dev.mount_tag());
+        }
+    }
 
     all_devs
 }
@@ -12,9 +12,9 @@ use crate::{drivers::DriverProbe, AxDeviceEnum};
 cfg_if! {
     if #[cfg(bus = "pci")] {
         use axdriver_pci::{PciRoot, DeviceFunction, DeviceFunctionInfo};
-        type VirtIoTransport = axdriver_virtio::PciTransport;
+        pub(crate) type VirtIoTransport = axdriver_virtio::PciTransport;
     } else if #[cfg(bus =  "mmio")] {
-        type VirtIoTransport = axdriver_virtio::MmioTransport;
+        pub(crate) type VirtIoTransport = axdriver_virtio::MmioTransport;
     }
 }
 
@@ -0,0 +1,281 @@
+//! VirtIO 9P transport, which carries 9P messages to access a directory
+//! shared by the host, e.g., by QEMU `-virtfs`.
+//!
+//! It is not in any device category, and [`axdriver_virtio`] does not support
+//! it, so it is driven here by a minimal split virtqueue. Requests are sent
+//! one by one, and their responses are polled for at most
+//! [`REQUEST_TIMEOUT`], after which the device is reset and no longer used.
+
+use alloc::{string::String, vec::Vec};
+use core::ptr::NonNull;
+use core::sync::atomic::{fence, Ordering};
+use core::time::Duration;
+
+use axdriver_base::{DevError, DevResult};
+use axdriver_virtio::{BufferDirection, VirtIoHal};
+use virtio_drivers::transport::{DeviceStatus, DeviceType, Transport};
+
+use crate::virtio::{VirtIoHalImpl, VirtIoTransport};
+
+const PAGE_SIZE: usize = 0x1000;
+
+/// The only queue of the device, for requests.
+const QUEUE: u16 = 0;
+/// A request takes two descriptors, one for the request and the other for
+/// the response.
+const QUEUE_SIZE: usize = 2;
+/// The maximum size of a message, in pages.
+const MESSAGE_PAGES: usize = 16;
+/// How long to wait for a response.
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The mount tag is in the configuration space.
+const VIRTIO_9P_F_MOUNT_TAG: u64 = 1 << 0;
+const VIRTIO_F_VERSION_1: u64 = 1 << 32;
+
+const VIRTQ_DESC_F_NEXT: u16 = 1;
+const VIRTQ_DESC_F_WRITE: u16 = 2;
+const VIRTQ_AVAIL_F_NO_INTERRUPT: u16 = 1;
+
+/// Offsets in the queue memory, in the layout of legacy devices: the
+/// descriptor table, the available ring, and the used ring on the next page.
+const DESC_OFFSET: usize = 0;
+const AVAIL_OFFSET: usize = 16 * QUEUE_SIZE;
+const USED_OFFSET: usize = PAGE_SIZE;
+
+/// Pages allocated for DMA.
+struct DmaPages {
+    paddr: usize,
+    vaddr: NonNull<u8>,
+    pages: usize,
+}
+
+impl DmaPages {
+    fn new(pages: usize) -> DevResult<Self> {
+        let (paddr, vaddr) = VirtIoHalImpl::dma_alloc(pages, BufferDirection::Both);
+        if paddr == 0 {
+            return Err(DevError::NoMemory);
+        }
+        unsafe { vaddr.as_ptr().write_bytes(0, pages * PAGE_SIZE) };
+        Ok(Self {
+            paddr,
+            vaddr,
+            pages,
+        })
+    }
+
+    fn ptr<T>(&self, offset: usize) -> *mut T {
+        unsafe { self.vaddr.as_ptr().add(offset).cast() }
+    }
+}
+
+impl Drop for DmaPages {
+    fn drop(&mut self) {
+        unsafe { VirtIoHalImpl::dma_dealloc(self.paddr, self.vaddr, self.pages) };
+    }
+}
+
+/// The VirtIO 9P device driver.
+pub struct VirtIo9pDev {
+    transport: VirtIoTransport,
+    queue: DmaPages,
+    req_buf: DmaPages,
+    resp_buf: DmaPages,
+    /// The index of the next request in the available ring.
+    avail_idx: u16,
+    tag: String,
+    /// Whether a request has timed out, and the device has been reset.
+    failed: bool,
+}
+
+// The DMA pages are owned by the driver.
+unsafe impl Send for VirtIo9pDev {}
+
+impl VirtIo9pDev {
+    /// Creates a new driver instance and initializes the device.
+    pub fn try_new(mut transport: VirtIoTransport) -> DevResult<Self> {
+        transport.set_status(DeviceStatus::empty());
+        let mut status = DeviceStatus::ACKNOWLEDGE | DeviceStatus::DRIVER;
+        transport.set_status(status);
+        let features = transport.read_device_features();
+        if features & VIRTIO_9P_F_MOUNT_TAG == 0 {
+            return Err(DevError::Unsupported);
+        }
+        transport.write_driver_features(features & (VIRTIO_9P_F_MOUNT_TAG | VIRTIO_F_VERSION_1));
+        status |= DeviceStatus::FEATURES_OK;
+        transport.set_status(status);
+        // the device clears it if the features are not accepted
+        if !transport.get_status().contains(DeviceStatus::FEATURES_OK) {
+            transport.set_status(status | DeviceStatus::FAILED);
+            return Err(DevError::Unsupported);
+        }
+        transport.set_guest_page_size(PAGE_SIZE as u32);
+
+        let tag = read_mount_tag(&transport)?;
+        if transport.max_queue_size(QUEUE) < QUEUE_SIZE as u32 {
+            return Err(DevError::BadState);
+        }
+        let queue = DmaPages::new(2)?;
+        transport.queue_set(
+            QUEUE,
+            QUEUE_SIZE as u32,
+            queue.paddr + DESC_OFFSET,
+            queue.paddr + AVAIL_OFFSET,
+            queue.paddr + USED_OFFSET,
+        );
+        // responses are polled
+        unsafe {
+            queue
+                .ptr::<u16>(AVAIL_OFFSET)
+                .write_volatile(VIRTQ_AVAIL_F_NO_INTERRUPT)
+        };
+        let mut dev = Self {
+            transport,
+            queue,
+            req_buf: DmaPages::new(MESSAGE_PAGES)?,
+            resp_buf: DmaPages::new(MESSAGE_PAGES)?,
+            avail_idx: 0,
+            tag,
+            failed: false,
+        };
+        dev.transport.set_status(status | DeviceStatus::DRIVER_OK);
+        Ok(dev)
+    }
+
+    /// The tag to identify the shared directory.
+    pub fn mount_tag(&self) -> &str {
+        &self.tag
+    }
+
+    /// The maximum size of a request or a response.
+    pub const fn max_message_size(&self) -> usize {
+        MESSAGE_PAGES * PAGE_SIZE
+    }
+
+    /// Sends the 9P message `req`, and waits for the response, which is
+    /// received into `resp`. Returns the size of the response.
+    ///
+    /// Returns [`DevError::Io`] if there is no response in
+    /// [`REQUEST_TIMEOUT`], and for all later requests, as the device is
+    /// reset then.
+    pub fn request(&mut self, req: &[u8], resp: &mut [u8]) -> DevResult<usize> {
+        if self.failed {
+            return Err(DevError::Io);
+        }
+        let max_size = self.max_message_size();
+        if req.len() > max_size || resp.is_empty() {
+            return Err(DevError::InvalidParam);
+        }
+        let resp_len = resp.len().min(max_size);
+        unsafe {
+            let req_ptr = self.req_buf.ptr::<u8>(0);
+            req_ptr.copy_from_nonoverlapping(req.as_ptr(), req.len());
+            self.write_desc(0, self.req_buf.paddr, req.len(), VIRTQ_DESC_F_NEXT, 1);
+            self.write_desc(1, self.resp_buf.paddr, resp_len, VIRTQ_DESC_F_WRITE, 0);
+
+            // available ring: flags, idx, ring[QUEUE_SIZE]
+            let avail = self.queue.ptr::<u16>(AVAIL_OFFSET);
+            let slot = self.avail_idx as usize % QUEUE_SIZE;
+            avail.add(2 + slot).write_volatile(0); // the head descriptor
+            fence(Ordering::SeqCst);
+            self.avail_idx = self.avail_idx.wrapping_add(1);
+            avail.add(1).write_volatile(self.avail_idx);
+            fence(Ordering::SeqCst);
+            self.transport.notify(QUEUE);
+
+            // used ring: flags, idx, ring[QUEUE_SIZE] of (id: u32, len: u32)
+            let used = self.queue.ptr::<u16>(USED_OFFSET);
+            let deadline = axhal::time::monotonic_time() + REQUEST_TIMEOUT;
+            while used.add(1).read_volatile() != self.avail_idx {
+                if axhal::time::monotonic_time() >= deadline {
+                    warn!("virtio-9p: request timed out, resetting the device");
+                    // stop the device from using the buffers
+                    self.transport.set_status(DeviceStatus::empty());
+                    self.failed = true;
+                    return Err(DevError::Io);
+                }
+                core::hint::spin_loop();
+            }
+            fence(Ordering::SeqCst);
+            let used_elem = self.queue.ptr::<u32>(USED_OFFSET + 4 + 8 * slot);
+            let len = (used_elem.add(1).read_volatile() as usize).min(resp_len);
+            let resp_ptr = self.resp_buf.ptr::<u8>(0);
+            resp_ptr.copy_to_nonoverlapping(resp.as_mut_ptr(), len);
+            Ok(len)
+        }
+    }
+
+    /// Writes the descriptor at `index`.
+    unsafe fn write_desc(&self, index: usize, paddr: usize, len: usize, flags: u16, next: u16) {
+        // addr: u64, len: u32, flags: u16, next: u16
+        let desc = DESC_OFFSET + 16 * index;
+        self.queue.ptr::<u64>(desc).write_volatile(paddr as u64);
+        self.queue.ptr::<u32>(desc + 8).write_volatile(len as u32);
+        self.queue.ptr::<u16>(desc + 12).write_volatile(flags);
+        self.queue.ptr::<u16>(desc + 14).write_volatile(next);
+    }
+
+    #[cfg(bus = "mmio")]
+    pub(crate) fn probe_mmio(mmio_base: usize, mmio_size: usize) -> Option<Self> {
+        use virtio_drivers::transport::mmio::{MmioTransport, VirtIOHeader};
+
+        let base_vaddr = axhal::mem::phys_to_virt(mmio_base.into());
+        let header = NonNull::new(base_vaddr.as_mut_ptr() as *mut VirtIOHeader)?;
+        let transport = unsafe { MmioTransport::new(header) }.ok()?;
+        if transport.device_type() != DeviceType::_9P {
+            return None;
+        }
+        Self::try_new(transport)
+            .inspect_err(|e| {
+                warn!(
+                    "failed to initialize MMIO device at [PA:{:#x}, PA:{:#x}): {:?}",
+                    mmio_base,
+                    mmio_base + mmio_size,
+                    e
+                )
+            })
+            .ok()
+    }
+
+    #[cfg(bus = "pci")]
+    pub(crate) fn probe_pci(
+        root: &mut axdriver_pci::PciRoot,
+        bdf: axdriver_pci::DeviceFunction,
+        dev_info: &axdriver_pci::DeviceFunctionInfo,
+    ) -> Option<Self> {
+        use virtio_drivers::transport::pci::PciTransport;
+
+        // transitional and modern devices
+        if dev_info.vendor_id != 0x1af4 || !matches!(dev_info.device_id, 0x1009 | 0x1049) {
+            return None;
+        }
+        let transport = PciTransport::new::<VirtIoHalImpl>(root, bdf).ok()?;
+        if transport.device_type() != DeviceType::_9P {
+            return None;
+        }
+        Self::try_new(transport)
+            .inspect_err(|e| warn!("failed to initialize PCI device at {}: {:?}", bdf, e))
+            .ok()
+    }
+}
+
+impl Drop for VirtIo9pDev {
+    fn drop(&mut self) {
+        // reset the device before the queue is freed
+        self.transport.set_status(DeviceStatus::empty());
+    }
+}
+
+/// Reads the mount tag from the configuration space, which is its length in
+/// 2 bytes followed by the bytes.
+fn read_mount_tag(transport: &VirtIoTransport) -> DevResult<String> {
+    let config = transport
+        .config_space::<u16>()
+        .map_err(|_| DevError::Unsupported)?;
+    let len = unsafe { config.as_ptr().read_volatile() } as usize;
+    let bytes = config.as_ptr().cast::<u8>();
+    let tag: Vec<u8> = (0..len)
+        .map(|i| unsafe { bytes.add(2 + i).read_volatile() })
+        .collect();
+    String::from_utf8(tag).map_err(|_| DevError::BadState)
+}
@@ -0,0 +1,19 @@
+use std::{env, fs, path::PathBuf};
+
+/// Copies the initramfs at the path in `AX_INITRAMFS` to be embedded, or
+/// writes an empty one if it is not set.
+fn main() {
+    println!("cargo:rerun-if-env-changed=AX_INITRAMFS");
+    if env::var_os("CARGO_FEATURE_INITRAMFS").is_none() {
+        return;
+    }
+    let out_path = PathBuf::from(env::var("OUT_DIR").unwrap()).join("initramfs.cpio");
+    match env::var("AX_INITRAMFS") {
+        Ok(path) if !path.is_empty() => {
+            println!("cargo:rerun-if-changed={}", path);
+            fs::copy(&path, &out_path)
+                .unwrap_or_else(|e| panic!("failed to read initramfs {:?}: {}", path, e));
+        }
+        _ => fs::write(&out_path, []).unwrap(),
+    }
+}
@@ -26,5 +26,45 @@ create_test_img() {
 	sudo umount mnt
 }
 
+create_ext_test_img() {
+	local name=$1
+	local blkcount=$2
+	local fsType=$3
+	mkdir -p root/very/long/path root/very-long-dir-name
+	for i in $(seq 1 1000); do
+	  echo "Rust is cool!" >>"root/long.txt"
+	done
+	echo "Rust is cool!" >>"root/short.txt"
+	echo "Rust is cool!" >>"root/very/long/path/test.txt"
+	echo "Rust is cool!" >>"root/very-long-dir-name/very-long-file-name.txt"
+	mkfs.$fsType -b 1024 -L "Test!" -E root_owner=0:0 -d root "$name" $blkcount
+	rm -rf root
+}
+
+# A read-only ext4 image with extents, metadata checksums and a hash-indexed
+# directory. The mount points are created ahead as the root is not writable.
+create_ext4_test_img() {
+	local name=$1
+	local blkcount=$2
+	mkdir -p root/very/long/path root/many root/dev root/tmp root/proc root/sys
+	for i in $(seq 1 1000); do
+	  echo "Rust is cool!" >>"root/long.txt"
+	done
+	echo "Rust is cool!" >>"root/short.txt"
+	echo "Rust is cool!" >>"root/very/long/path/test.txt"
+	for i in $(seq 1 300); do
+	  echo "$i" >>"root/many/file-with-a-long-name-$i.txt"
+	done
+	printf "head" >"root/sparse.bin"
+	truncate -s 100000 "root/sparse.bin"
+	printf "tail" >>"root/sparse.bin"
+	mkfs.ext4 -b 1024 -L "Test!" -O metadata_csum,64bit,dir_index -E root_owner=0:0 -d root "$name" $blkcount
+	# index the large directory
+	e2fsck -fyD "$name"
+	rm -rf root
+}
+
 create_test_img "$CUR_DIR/fat16.img" 2500 16
 create_test_img "$CUR_DIR/fat32.img" 34000 32
+create_ext_test_img "$CUR_DIR/ext2.img" 2048 ext2
+create_ext4_test_img "$CUR_DIR/ext4.img" 4096
@@ -1,5 +1,5 @@
 use axio::{prelude::*, Result, SeekFrom};
-use core::fmt;
+use core::{fmt, time::Duration};
 
 use crate::fops;
 
@@ -16,7 +16,7 @@ pub struct File {
 }
 
 /// Metadata information about a file.
-pub struct Metadata(fops::FileAttr);
+pub struct Metadata(pub(super) fops::FileAttr);
 
 /// Options and flags which can be used to configure how a file is opened.
 #[derive(Clone, Debug)]
@@ -90,6 +90,12 @@ impl Metadata {
         self.0.is_file()
     }
 
+    /// Returns `true` if this metadata is for a symbolic link, which is only
+    /// possible for [`symlink_metadata`](super::symlink_metadata).
+    pub const fn is_symlink(&self) -> bool {
+        matches!(self.0.file_type(), FileType::SymLink)
+    }
+
     /// Returns the size of the file, in bytes, this metadata is for.
     #[allow(clippy::len_without_is_empty)]
     pub const fn len(&self) -> u64 {
@@ -110,6 +116,16 @@ impl Metadata {
     pub const fn blocks(&self) -> u64 {
         self.0.blocks()
     }
+
+    /// Returns the last modification time, since the Unix epoch.
+    pub fn modified(&self) -> Result<Duration> {
+        Ok(self.0.mtime())
+    }
+
+    /// Returns the last access time, since the Unix epoch.
+    pub fn accessed(&self) -> Result<Duration> {
+        Ok(self.0.atime())
+    }
 }
 
 impl fmt::Debug for Metadata {
@@ -162,6 +178,43 @@ impl File {
     pub fn metadata(&self) -> Result<Metadata> {
         self.inner.get_attr().map(Metadata)
     }
+
+    /// Attempts to sync all data and metadata of the file to the device.
+    pub fn sync_all(&self) -> Result<()> {
+        self.inner.sync_all()
+    }
+
+    /// Acquires an exclusive lock on the file, blocks until it can be
+    /// acquired.
+    ///
+    /// The lock is advisory and owned by this file handle. It is released by
+    /// [`unlock`](File::unlock), or when the handle is dropped.
+    pub fn lock(&self) -> Result<()> {
+        self.inner.locker().lock(fops::LockKind::Exclusive, true)
+    }
+
+    /// Acquires a shared lock on the file, blocks until it can be acquired.
+    pub fn lock_shared(&self) -> Result<()> {
+        self.inner.locker().lock(fops::LockKind::Shared, true)
+    }
+
+    /// Tries to acquire an exclusive lock on the file, returns
+    /// [`WouldBlock`](axio::Error::WouldBlock) if others hold locks on it.
+    pub fn try_lock(&self) -> Result<()> {
+        self.inner.locker().lock(fops::LockKind::Exclusive, false)
+    }
+
+    /// Tries to acquire a shared lock on the file, returns
+    /// [`WouldBlock`](axio::Error::WouldBlock) if others hold an exclusive
+    /// lock on it.
+    pub fn try_lock_shared(&self) -> Result<()> {
+        self.inner.locker().lock(fops::LockKind::Shared, false)
+    }
+
+    /// Releases the lock held by this file handle.
+    pub fn unlock(&self) -> Result<()> {
+        self.inner.locker().unlock()
+    }
 }
 
 impl Read for File {
@@ -5,8 +5,11 @@ mod file;
 
 pub use self::dir::{DirBuilder, DirEntry, ReadDir};
 pub use self::file::{File, FileType, Metadata, OpenOptions, Permissions};
+pub use crate::root::MountInfo;
+pub use crate::watch::{WatchEvent, WatchEventKind, Watcher};
 
-use alloc::{string::String, vec::Vec};
+use alloc::{string::String, sync::Arc, vec::Vec};
+use axfs_vfs::VfsOps;
 use axio::{self as io, prelude::*};
 
 /// Returns an iterator over the entries within a directory.
@@ -59,6 +62,29 @@ pub fn metadata(path: &str) -> io::Result<Metadata> {
     File::open(path)?.metadata()
 }
 
+/// Queries the metadata about a file without following symlinks.
+pub fn symlink_metadata(path: &str) -> io::Result<Metadata> {
+    crate::fops::symlink_attr(path).map(Metadata)
+}
+
+/// Reads a symbolic link, returning the file that the link points to.
+pub fn read_link(path: &str) -> io::Result<String> {
+    crate::root::read_link(None, path)
+}
+
+/// Creates a new symbolic link `link` on the filesystem, which points to
+/// `original`.
+pub fn symlink(original: &str, link: &str) -> io::Result<()> {
+    crate::root::symlink(None, original, link)
+}
+
+/// Creates a new hard link `link` to the file `original`.
+///
+/// This only works then the new path is in the same mounted fs.
+pub fn hard_link(original: &str, link: &str) -> io::Result<()> {
+    crate::root::hard_link(original, link)
+}
+
 /// Creates a new, empty directory at the provided path.
 pub fn create_dir(path: &str) -> io::Result<()> {
     DirBuilder::new().create(path)
@@ -87,3 +113,50 @@ pub fn remove_file(path: &str) -> io::Result<()> {
 pub fn rename(old: &str, new: &str) -> io::Result<()> {
     crate::root::rename(old, new)
 }
+
+/// Mounts the filesystem `fs` of type `fs_type` on the directory `path`.
+///
+/// The directory is created if it does not exist. It can be inside another
+/// mounted filesystem, but not be a mount point already. Files in `fs` have
+/// no timestamps or owners in their [`Metadata`].
+pub fn mount(path: &str, fs: Arc<dyn VfsOps>, fs_type: &str) -> io::Result<()> {
+    crate::root::mount(path, fs, fs_type)
+}
+
+/// Mounts an overlay on the directory `path`, which merges the read-only
+/// filesystem `lower` with a RAM filesystem.
+///
+/// `lower` is never modified. Changes are kept in the RAM filesystem, and
+/// discarded when unmounted.
+#[cfg(feature = "overlayfs")]
+pub fn mount_overlay(path: &str, lower: Arc<dyn VfsOps>) -> io::Result<()> {
+    crate::root::mount_overlay(path, lower)
+}
+
+/// Mounts the directory shared by the host with the mount `tag` on `path`,
+/// e.g., by QEMU `-virtfs local,path=<dir>,mount_tag=<tag>,...`.
+///
+/// Nothing is cached, so changes on the host are seen immediately, and vice
+/// versa. A shared directory can be mounted again after unmounted.
+#[cfg(feature = "9p")]
+pub fn mount_9p(path: &str, tag: &str) -> io::Result<()> {
+    crate::root::mount_9p(path, tag)
+}
+
+/// Unmounts the filesystem mounted on `path`.
+///
+/// It fails if other filesystems are mounted under it, or the current
+/// directory is inside it.
+pub fn umount(path: &str) -> io::Result<()> {
+    crate::root::umount(path)
+}
+
+/// Returns the active mounts, starting with the root filesystem.
+pub fn mounts() -> Vec<MountInfo> {
+    crate::root::mounts()
+}
+
+/// Writes all cached data of block devices back.
+pub fn sync() -> io::Result<()> {
+    crate::dev::sync_all().map_err(|_| io::Error::Io)
+}
@@ -0,0 +1,190 @@
+//! LRU block cache between filesystems and block devices.
+//!
+//! Writes are kept in the cache as dirty blocks and written back when they
+//! are evicted, or when the device is flushed. Misses on sequential blocks
+//! read ahead several blocks in one device request.
+
+use alloc::{boxed::Box, collections::BTreeMap, vec};
+use axdriver::prelude::*;
+
+/// Maximum size of cached blocks of a device in bytes.
+const CACHE_SIZE: usize = 512 * 1024;
+
+/// Maximum size read at once on sequential misses in bytes.
+const READ_AHEAD_SIZE: usize = 16 * 1024;
+
+/// Statistics of a block cache.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Number of block accesses served by the cache.
+    pub hits: u64,
+    /// Number of block accesses that read the device.
+    pub misses: u64,
+    /// Number of blocks currently cached.
+    pub cached_blocks: usize,
+    /// Number of cached blocks not written back yet.
+    pub dirty_blocks: usize,
+}
+
+struct CacheEntry {
+    data: Box<[u8]>,
+    dirty: bool,
+    /// The time of last access, the key in [`CachedDevice::lru`].
+    tick: u64,
+}
+
+/// A block device with an LRU cache of its blocks.
+pub(crate) struct CachedDevice {
+    dev: Box<dyn BlockDriverOps>,
+    block_size: usize,
+    /// Maximum number of cached blocks.
+    capacity: usize,
+    blocks: BTreeMap<u64, CacheEntry>,
+    /// Block IDs ordered by the time of last access.
+    lru: BTreeMap<u64, u64>,
+    tick: u64,
+    /// The block following the last miss, for detecting sequential access.
+    next_miss: u64,
+    stats: CacheStats,
+}
+
+impl CachedDevice {
+    pub fn new(dev: Box<dyn BlockDriverOps>) -> Self {
+        let block_size = dev.block_size();
+        Self {
+            dev,
+            block_size,
+            capacity: (CACHE_SIZE / block_size).max(1),
+            blocks: BTreeMap::new(),
+            lru: BTreeMap::new(),
+            tick: 0,
+            next_miss: u64::MAX,
+            stats: CacheStats::default(),
+        }
+    }
+
+    /// Returns the underlying device.
+    pub fn device(&self) -> &dyn BlockDriverOps {
+        &*self.dev
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            cached_blocks: self.blocks.len(),
+            ..self.stats
+        }
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    fn insert(&mut self, block_id: u64, data: Box<[u8]>, dirty: bool) {
+        let tick = self.next_tick();
+        self.lru.insert(tick, block_id);
+        self.blocks
+            .insert(block_id, CacheEntry { data, dirty, tick });
+        if dirty {
+            self.stats.dirty_blocks += 1;
+        }
+    }
+
+    /// Evicts the least recently used blocks until `n` more blocks fit.
+    fn make_room(&mut self, n: usize) -> DevResult {
+        while self.blocks.len() + n > self.capacity {
+            let Some((&tick, &block_id)) = self.lru.first_key_value() else {
+                break;
+            };
+            let entry = &self.blocks[&block_id];
+            if entry.dirty {
+                self.dev.write_block(block_id, &entry.data)?;
+                self.stats.dirty_blocks -= 1;
+            }
+            self.lru.remove(&tick);
+            self.blocks.remove(&block_id);
+        }
+        Ok(())
+    }
+
+    /// Returns the cached block, reads it (and the following blocks if the
+    /// access is sequential) from the device on a miss.
+    fn entry(&mut self, block_id: u64) -> DevResult<&mut CacheEntry> {
+        if self.blocks.contains_key(&block_id) {
+            self.stats.hits += 1;
+            let tick = self.next_tick();
+            let entry = self.blocks.get_mut(&block_id).unwrap();
+            self.lru.remove(&entry.tick);
+            self.lru.insert(tick, block_id);
+            entry.tick = tick;
+            return Ok(entry);
+        }
+
+        self.stats.misses += 1;
+        let max_blocks = if block_id == self.next_miss {
+            (READ_AHEAD_SIZE / self.block_size).max(1)
+        } else {
+            1
+        };
+        let mut count = 1;
+        while count < max_blocks
+            && block_id + (count as u64) < self.dev.num_blocks()
+            && !self.blocks.contains_key(&(block_id + count as u64))
+        {
+            count += 1;
+        }
+        self.make_room(count)?;
+        let mut buf = vec![0; count * self.block_size];
+        self.dev.read_block(block_id, &mut buf)?;
+        // insert the requested block last, as the most recently used one
+        for (i, data) in buf.chunks_exact(self.block_size).enumerate().rev() {
+            self.insert(block_id + i as u64, data.into(), false);
+        }
+        self.next_miss = block_id + count as u64;
+        Ok(self.blocks.get_mut(&block_id).unwrap())
+    }
+
+    /// Reads the block `block_id` into `buf` from `offset` within the block.
+    pub fn read(&mut self, block_id: u64, offset: usize, buf: &mut [u8]) -> DevResult {
+        let entry = self.entry(block_id)?;
+        buf.copy_from_slice(&entry.data[offset..offset + buf.len()]);
+        Ok(())
+    }
+
+    /// Writes `buf` to the block `block_id` from `offset` within the block.
+    ///
+    /// The block is only marked dirty, and will be written to the device
+    /// later.
+    pub fn write(&mut self, block_id: u64, offset: usize, buf: &[u8]) -> DevResult {
+        if buf.len() == self.block_size && !self.blocks.contains_key(&block_id) {
+            // whole block, no need to read it
+            self.make_room(1)?;
+            self.insert(block_id, buf.into(), true);
+            return Ok(());
+        }
+        let entry = self.entry(block_id)?;
+        entry.data[offset..offset + buf.len()].copy_from_slice(buf);
+        if !core::mem::replace(&mut entry.dirty, true) {
+            self.stats.dirty_blocks += 1;
+        }
+        Ok(())
+    }
+
+    /// Writes all dirty blocks back to the device, and flushes the device.
+    pub fn flush(&mut self) -> DevResult {
+        for (&block_id, entry) in self.blocks.iter_mut().filter(|(_, e)| e.dirty) {
+            self.dev.write_block(block_id, &entry.data)?;
+            entry.dirty = false;
+            self.stats.dirty_blocks -= 1;
+        }
+        self.dev.flush()
+    }
+}
+
+impl Drop for CachedDevice {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            warn!("failed to write back cached blocks: {:?}", e);
+        }
+    }
+}
@@ -1,18 +1,327 @@
+use alloc::{boxed::Box, format, string::String, sync::Arc, vec::Vec};
 use axdriver::prelude::*;
+use axsync::Mutex;
+use core::sync::atomic::{AtomicBool, Ordering};
 
-const BLOCK_SIZE: usize = 512;
+use crate::cache::{CacheStats, CachedDevice};
+use crate::partition::{self, Partition};
+
+/// The smallest supported block size.
+const MIN_BLOCK_SIZE: usize = 512;
+/// The largest supported block size.
+const MAX_BLOCK_SIZE: usize = 64 * 1024;
+
+static BLOCK_DEVICES: Mutex<Vec<BlockDevice>> = Mutex::new(Vec::new());
+
+/// A device probed by axdriver as a [`BlockDriverOps`] object.
+///
+/// [`AxBlockDevice`] is a concrete driver, or already a boxed trait object
+/// if the `dyn` feature of axdriver is enabled, so it is wrapped rather than
+/// boxed directly.
+struct AxDriver(AxBlockDevice);
+
+impl BaseDriverOps for AxDriver {
+    fn device_type(&self) -> DeviceType {
+        self.0.device_type()
+    }
+
+    fn device_name(&self) -> &str {
+        self.0.device_name()
+    }
+}
+
+impl BlockDriverOps for AxDriver {
+    fn num_blocks(&self) -> u64 {
+        self.0.num_blocks()
+    }
+
+    fn block_size(&self) -> usize {
+        self.0.block_size()
+    }
+
+    fn read_block(&mut self, block_id: u64, buf: &mut [u8]) -> DevResult {
+        self.0.read_block(block_id, buf)
+    }
+
+    fn write_block(&mut self, block_id: u64, buf: &[u8]) -> DevResult {
+        self.0.write_block(block_id, buf)
+    }
+
+    fn flush(&mut self) -> DevResult {
+        self.0.flush()
+    }
+}
+
+/// A block device registered in axfs, or a partition of it.
+///
+/// It can be cloned cheaply, and the clones share the underlying device and
+/// its block cache.
+#[derive(Clone)]
+pub struct BlockDevice {
+    name: String,
+    dev: Arc<Mutex<CachedDevice>>,
+    block_size: usize,
+    start_block: u64,
+    num_blocks: u64,
+    partition: Option<Partition>,
+    /// The index among block devices in [`axdriver::devices`].
+    driver_index: Option<usize>,
+}
+
+impl BlockDevice {
+    fn new(name: String, dev: Box<dyn BlockDriverOps>) -> Self {
+        let num_blocks = dev.num_blocks();
+        let block_size = dev.block_size();
+        Self {
+            name,
+            dev: Arc::new(Mutex::new(CachedDevice::new(dev))),
+            block_size,
+            start_block: 0,
+            num_blocks,
+            partition: None,
+            driver_index: None,
+        }
+    }
+
+    fn partition(&self, part: Partition) -> Self {
+        Self {
+            name: partition_name(&self.name, part.number),
+            dev: self.dev.clone(),
+            block_size: self.block_size,
+            start_block: part.start_block,
+            num_blocks: part.num_blocks,
+            partition: Some(part),
+            driver_index: self.driver_index,
+        }
+    }
+
+    /// Returns the name of the device, e.g., `vda`, or the partition, e.g.,
+    /// `vda1`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the name of the underlying driver.
+    pub fn driver_name(&self) -> String {
+        self.dev.lock().device().device_name().into()
+    }
+
+    /// Returns the partition information, or [`None`] if it is a whole device.
+    pub fn partition_info(&self) -> Option<&Partition> {
+        self.partition.as_ref()
+    }
+
+    /// Returns the index of the underlying device among block devices in
+    /// [`axdriver::devices`], or [`None`] if it is not probed by axdriver.
+    pub(crate) fn driver_index(&self) -> Option<usize> {
+        self.driver_index
+    }
+
+    /// Returns the number of blocks.
+    pub fn num_blocks(&self) -> u64 {
+        self.num_blocks
+    }
+
+    /// Returns the size of a block in bytes, which is a power of two and at
+    /// least 512.
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    /// Returns the size in bytes.
+    pub fn size(&self) -> u64 {
+        self.num_blocks * self.block_size as u64
+    }
+
+    /// Reads the block `block_id` (relative to the partition start) into
+    /// `buf`, which must be one block in size.
+    pub fn read_block(&self, block_id: u64, buf: &mut [u8]) -> DevResult {
+        if buf.len() != self.block_size {
+            return Err(DevError::InvalidParam);
+        }
+        self.read_at(block_id, 0, buf)
+    }
+
+    /// Writes `buf`, which must be one block in size, to the block `block_id`
+    /// (relative to the partition start).
+    ///
+    /// The data is cached, and written to the device by [`flush`] or when the
+    /// block is evicted.
+    ///
+    /// [`flush`]: BlockDevice::flush
+    pub fn write_block(&self, block_id: u64, buf: &[u8]) -> DevResult {
+        if buf.len() != self.block_size {
+            return Err(DevError::InvalidParam);
+        }
+        self.write_at(block_id, 0, buf)
+    }
+
+    /// Reads part of the block `block_id` from `offset` within the block.
+    fn read_at(&self, block_id: u64, offset: usize, buf: &mut [u8]) -> DevResult {
+        if block_id >= self.num_blocks || offset + buf.len() > self.block_size {
+            return Err(DevError::InvalidParam);
+        }
+        self.dev
+            .lock()
+            .read(self.start_block + block_id, offset, buf)
+    }
+
+    /// Writes part of the block `block_id` from `offset` within the block.
+    fn write_at(&self, block_id: u64, offset: usize, buf: &[u8]) -> DevResult {
+        if block_id >= self.num_blocks || offset + buf.len() > self.block_size {
+            return Err(DevError::InvalidParam);
+        }
+        self.dev
+            .lock()
+            .write(self.start_block + block_id, offset, buf)
+    }
+
+    /// Writes all cached data of the underlying device back, and flushes it.
+    pub fn flush(&self) -> DevResult {
+        self.dev.lock().flush()
+    }
+
+    /// Returns the statistics of the block cache, which is shared by the
+    /// whole device and its partitions.
+    pub fn cache_stats(&self) -> CacheStats {
+        self.dev.lock().stats()
+    }
+
+    /// Whether `spec` refers to this device, where `spec` is the device name
+    /// (e.g., `vda2`), `PARTLABEL=<label>`, or `PARTUUID=<uuid>`.
+    pub fn matches(&self, spec: &str) -> bool {
+        if let Some(label) = spec.strip_prefix("PARTLABEL=") {
+            self.partition.as_ref().is_some_and(|p| p.label == label)
+        } else if let Some(uuid) = spec.strip_prefix("PARTUUID=") {
+            self.partition
+                .as_ref()
+                .is_some_and(|p| p.uuid.eq_ignore_ascii_case(uuid))
+        } else {
+            self.name == spec.trim_start_matches("/dev/")
+        }
+    }
+}
+
+/// Block sizes are powers of two between 512 bytes and 64 KiB.
+fn is_supported_block_size(size: usize) -> bool {
+    size.is_power_of_two() && (MIN_BLOCK_SIZE..=MAX_BLOCK_SIZE).contains(&size)
+}
+
+/// `vda` + 1 is `vda1`, `mmcblk0` + 1 is `mmcblk0p1`, as Linux does.
+fn partition_name(disk: &str, number: usize) -> String {
+    if disk.ends_with(|c: char| c.is_ascii_digit()) {
+        format!("{}p{}", disk, number)
+    } else {
+        format!("{}{}", disk, number)
+    }
+}
+
+/// Registers a block device and its partitions, returns the device and its
+/// partitions in order. `index` is the order of the device given by axdriver.
+pub(crate) fn register_device(dev: AxBlockDevice, index: usize) -> Vec<BlockDevice> {
+    if !is_supported_block_size(dev.block_size()) {
+        warn!(
+            "  skip block device {}: unsupported block size {}",
+            dev.device_name(),
+            dev.block_size()
+        );
+        return Vec::new();
+    }
+    let prefix = if dev.device_name().starts_with("virtio") {
+        "vd"
+    } else {
+        "sd"
+    };
+    let letter = BLOCK_DEVICES
+        .lock()
+        .iter()
+        .filter(|d| d.partition.is_none() && d.name.starts_with(prefix))
+        .count();
+    let name = format!("{}{}", prefix, (b'a' + letter as u8) as char);
+    let mut disk = BlockDevice::new(name, Box::new(AxDriver(dev)));
+    disk.driver_index = Some(index);
+    add_device(disk)
+}
+
+/// Adds a block device and its partitions to the registered ones, returns
+/// the device and its partitions in order.
+fn add_device(disk: BlockDevice) -> Vec<BlockDevice> {
+    static SHUTDOWN_HOOK: AtomicBool = AtomicBool::new(false);
+    if !SHUTDOWN_HOOK.swap(true, Ordering::AcqRel) {
+        axhal::misc::register_shutdown_hook(sync_on_shutdown);
+    }
+    info!(
+        "  block device {}: {}, {} blocks of {} bytes",
+        disk.name,
+        disk.driver_name(),
+        disk.num_blocks,
+        disk.block_size
+    );
+
+    let mut registered = alloc::vec![disk.clone()];
+    let read_block = |id, buf: &mut [u8]| disk.read_block(id, buf);
+    match partition::parse_partitions(disk.num_blocks, disk.block_size, read_block) {
+        Ok(parts) => {
+            for part in parts {
+                let part = disk.partition(part);
+                info!(
+                    "    partition {}: start {}, {} blocks, label {:?}, uuid {}",
+                    part.name,
+                    part.start_block,
+                    part.num_blocks,
+                    part.partition.as_ref().unwrap().label,
+                    part.partition.as_ref().unwrap().uuid,
+                );
+                registered.push(part);
+            }
+        }
+        Err(e) => warn!("failed to read partition table of {}: {:?}", disk.name, e),
+    }
+    BLOCK_DEVICES.lock().extend(registered.iter().cloned());
+    registered
+}
+
+/// Returns all registered block devices and partitions.
+pub fn block_devices() -> Vec<BlockDevice> {
+    BLOCK_DEVICES.lock().clone()
+}
+
+/// Writes cached data of all registered block devices back.
+pub(crate) fn sync_all() -> DevResult {
+    for dev in block_devices().iter().filter(|d| d.partition.is_none()) {
+        dev.flush()?;
+    }
+    Ok(())
+}
+
+/// Writes cached data back before the system is shut down, which is not
+/// done by the periodic writeback.
+fn sync_on_shutdown() {
+    if let Err(e) = sync_all() {
+        warn!("failed to write back block devices on shutdown: {:?}", e);
+    }
+}
 
 /// A disk device with a cursor.
 pub struct Disk {
     block_id: u64,
     offset: usize,
-    dev: AxBlockDevice,
+    dev: BlockDevice,
 }
 
 impl Disk {
-    /// Create a new disk.
+    /// Create a new disk on the whole device.
     pub fn new(dev: AxBlockDevice) -> Self {
-        assert_eq!(BLOCK_SIZE, dev.block_size());
+        assert!(
+            is_supported_block_size(dev.block_size()),
+            "unsupported block size {}",
+            dev.block_size()
+        );
+        Self::from_block_device(BlockDevice::new("disk".into(), Box::new(AxDriver(dev))))
+    }
+
+    /// Create a new disk on a registered block device or partition.
+    pub fn from_block_device(dev: BlockDevice) -> Self {
         Self {
             block_id: 0,
             offset: 0,
@@ -22,71 +331,246 @@ impl Disk {
 
     /// Get the size of the disk.
     pub fn size(&self) -> u64 {
-        self.dev.num_blocks() * BLOCK_SIZE as u64
+        self.dev.size()
+    }
+
+    /// Get the block size of the underlying device.
+    pub fn block_size(&self) -> usize {
+        self.dev.block_size()
+    }
+
+    /// Writes cached data back and flushes the underlying device.
+    pub fn flush(&mut self) -> DevResult {
+        self.dev.flush()
     }
 
     /// Get the position of the cursor.
     pub fn position(&self) -> u64 {
-        self.block_id * BLOCK_SIZE as u64 + self.offset as u64
+        self.block_id * self.block_size() as u64 + self.offset as u64
     }
 
     /// Set the position of the cursor.
     pub fn set_position(&mut self, pos: u64) {
-        self.block_id = pos / BLOCK_SIZE as u64;
-        self.offset = pos as usize % BLOCK_SIZE;
+        let block_size = self.block_size() as u64;
+        self.block_id = pos / block_size;
+        self.offset = (pos % block_size) as usize;
     }
 
     /// Read within one block, returns the number of bytes read.
     pub fn read_one(&mut self, buf: &mut [u8]) -> DevResult<usize> {
-        let read_size = if self.offset == 0 && buf.len() >= BLOCK_SIZE {
-            // whole block
-            self.dev
-                .read_block(self.block_id, &mut buf[0..BLOCK_SIZE])?;
-            self.block_id += 1;
-            BLOCK_SIZE
-        } else {
-            // partial block
-            let mut data = [0u8; BLOCK_SIZE];
-            let start = self.offset;
-            let count = buf.len().min(BLOCK_SIZE - self.offset);
-
-            self.dev.read_block(self.block_id, &mut data)?;
-            buf[..count].copy_from_slice(&data[start..start + count]);
-
-            self.offset += count;
-            if self.offset >= BLOCK_SIZE {
-                self.block_id += 1;
-                self.offset -= BLOCK_SIZE;
-            }
-            count
-        };
-        Ok(read_size)
+        let count = buf.len().min(self.block_size() - self.offset);
+        self.dev
+            .read_at(self.block_id, self.offset, &mut buf[..count])?;
+        self.advance(count);
+        Ok(count)
     }
 
     /// Write within one block, returns the number of bytes written.
     pub fn write_one(&mut self, buf: &[u8]) -> DevResult<usize> {
-        let write_size = if self.offset == 0 && buf.len() >= BLOCK_SIZE {
-            // whole block
-            self.dev.write_block(self.block_id, &buf[0..BLOCK_SIZE])?;
+        let count = buf.len().min(self.block_size() - self.offset);
+        self.dev
+            .write_at(self.block_id, self.offset, &buf[..count])?;
+        self.advance(count);
+        Ok(count)
+    }
+
+    fn advance(&mut self, count: usize) {
+        self.offset += count;
+        if self.offset >= self.block_size() {
             self.block_id += 1;
+            self.offset -= self.block_size();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex as StdMutex;
+
+    use axdriver_block::ramdisk::RamDisk;
+
+    use super::*;
+
+    const BLOCK_SIZE: usize = 4096;
+    const SECTORS_PER_BLOCK: u64 = (BLOCK_SIZE / 512) as u64;
+
+    /// A RAM disk with 4096-byte blocks, which can be shared to check the
+    /// written data.
+    #[derive(Clone)]
+    struct LargeBlockDisk(Arc<StdMutex<RamDisk>>);
+
+    impl LargeBlockDisk {
+        fn new(data: &[u8]) -> Self {
+            Self(Arc::new(StdMutex::new(RamDisk::from(data))))
+        }
+
+        fn to_disk(&self) -> Disk {
+            Disk::from_block_device(BlockDevice::new("disk".into(), Box::new(self.clone())))
+        }
+
+        fn read(&self, block_id: u64) -> Vec<u8> {
+            let mut buf = vec![0; BLOCK_SIZE];
+            self.clone().read_block(block_id, &mut buf).unwrap();
+            buf
+        }
+    }
+
+    impl BaseDriverOps for LargeBlockDisk {
+        fn device_type(&self) -> DeviceType {
+            DeviceType::Block
+        }
+
+        fn device_name(&self) -> &str {
+            "ramdisk-4k"
+        }
+    }
+
+    impl BlockDriverOps for LargeBlockDisk {
+        fn num_blocks(&self) -> u64 {
+            self.0.lock().unwrap().num_blocks() / SECTORS_PER_BLOCK
+        }
+
+        fn block_size(&self) -> usize {
             BLOCK_SIZE
-        } else {
-            // partial block
-            let mut data = [0u8; BLOCK_SIZE];
-            let start = self.offset;
-            let count = buf.len().min(BLOCK_SIZE - self.offset);
-
-            self.dev.read_block(self.block_id, &mut data)?;
-            data[start..start + count].copy_from_slice(&buf[..count]);
-            self.dev.write_block(self.block_id, &data)?;
-
-            self.offset += count;
-            if self.offset >= BLOCK_SIZE {
-                self.block_id += 1;
-                self.offset -= BLOCK_SIZE;
-            }
-            count
+        }
+
+        fn read_block(&mut self, block_id: u64, buf: &mut [u8]) -> DevResult {
+            let mut ramdisk = self.0.lock().unwrap();
+            ramdisk.read_block(block_id * SECTORS_PER_BLOCK, buf)
+        }
+
+        fn write_block(&mut self, block_id: u64, buf: &[u8]) -> DevResult {
+            let mut ramdisk = self.0.lock().unwrap();
+            ramdisk.write_block(block_id * SECTORS_PER_BLOCK, buf)
+        }
+
+        fn flush(&mut self) -> DevResult {
+            self.0.lock().unwrap().flush()
+        }
+    }
+
+    fn test_partial_io() {
+        let data = (0..16 * BLOCK_SIZE)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<_>>();
+        let ramdisk = LargeBlockDisk::new(&data);
+        let mut disk = ramdisk.to_disk();
+        assert_eq!(disk.block_size(), BLOCK_SIZE);
+        assert_eq!(disk.size(), data.len() as u64);
+
+        // reads stop at the end of a block
+        let mut buf = [0; 100];
+        disk.set_position(BLOCK_SIZE as u64 - 10);
+        assert_eq!(disk.read_one(&mut buf).unwrap(), 10);
+        assert_eq!(disk.position(), BLOCK_SIZE as u64);
+        assert_eq!(disk.read_one(&mut buf[10..]).unwrap(), 90);
+        assert_eq!(buf[..], data[BLOCK_SIZE - 10..BLOCK_SIZE + 90]);
+
+        // writes keep the rest of the block
+        disk.set_position(BLOCK_SIZE as u64 + 1000);
+        assert_eq!(disk.write_one(b"Rust is cool!").unwrap(), 13);
+        disk.set_position(3 * BLOCK_SIZE as u64 - 2);
+        assert_eq!(disk.write_one(b"abcd").unwrap(), 2);
+        assert_eq!(disk.write_one(b"cd").unwrap(), 2);
+        disk.flush().unwrap();
+
+        let mut expected = data.clone();
+        expected[BLOCK_SIZE + 1000..BLOCK_SIZE + 1013].copy_from_slice(b"Rust is cool!");
+        expected[3 * BLOCK_SIZE - 2..3 * BLOCK_SIZE + 2].copy_from_slice(b"abcd");
+        for block_id in 0..4 {
+            let start = block_id as usize * BLOCK_SIZE;
+            assert_eq!(ramdisk.read(block_id), expected[start..start + BLOCK_SIZE]);
+        }
+    }
+
+    #[cfg(all(feature = "fatfs", not(feature = "use-ramdisk")))]
+    fn test_fatfs() {
+        use crate::fs::fatfs::FatFileSystem;
+        use axfs_vfs::{VfsNodeOps, VfsNodeType, VfsOps};
+
+        // FAT sectors of 512 bytes are parts of the device blocks
+        let image = std::fs::read("resources/fat16.img").unwrap();
+        let ramdisk = LargeBlockDisk::new(&image);
+        let fs: &'static FatFileSystem = Box::leak(Box::new(FatFileSystem::new(ramdisk.to_disk())));
+        fs.init();
+
+        let root = fs.root_dir();
+        let file = root.clone().lookup("long.txt").unwrap();
+        let mut buf = vec![0; 14000];
+        assert_eq!(file.read_at(0, &mut buf).unwrap(), 14000);
+        assert_eq!(buf, "Rust is cool!\n".repeat(1000).as_bytes());
+
+        let contents = "Rust is cool!\n".repeat(500);
+        root.create("large.txt", VfsNodeType::File).unwrap();
+        let file = root.clone().lookup("large.txt").unwrap();
+        assert_eq!(file.write_at(0, contents.as_bytes()).unwrap(), 7000);
+        file.fsync().unwrap();
+
+        // the data is on the device after fsync
+        let fs = FatFileSystem::new(ramdisk.to_disk());
+        let fs: &'static FatFileSystem = Box::leak(Box::new(fs));
+        fs.init();
+        let file = fs.root_dir().lookup("large.txt").unwrap();
+        assert_eq!(file.get_attr().unwrap().size(), 7000);
+        let mut buf = vec![0; 7000];
+        assert_eq!(file.read_at(0, &mut buf).unwrap(), 7000);
+        assert_eq!(buf, contents.as_bytes());
+    }
+
+    /// Cached data is written back on shutdown, without syncing explicitly.
+    fn test_shutdown() {
+        let ramdisk = LargeBlockDisk::new(&[0; 4 * BLOCK_SIZE]);
+        let dev = BlockDevice::new("shutdown".into(), Box::new(ramdisk.clone()));
+        let mut disk = Disk::from_block_device(add_device(dev)[0].clone());
+        disk.set_position(BLOCK_SIZE as u64 + 100);
+        assert_eq!(disk.write_one(b"Rust is cool!").unwrap(), 13);
+        drop(disk);
+
+        #[cfg(all(feature = "fatfs", not(feature = "use-ramdisk")))]
+        let fat_ramdisk = {
+            use crate::fs::fatfs::FatFileSystem;
+            use axfs_vfs::{VfsNodeOps, VfsNodeType, VfsOps};
+
+            let image = std::fs::read("resources/fat16.img").unwrap();
+            let ramdisk = LargeBlockDisk::new(&image);
+            let dev = BlockDevice::new("shutdown-fat".into(), Box::new(ramdisk.clone()));
+            let disk = Disk::from_block_device(add_device(dev)[0].clone());
+            let fs: &'static FatFileSystem = Box::leak(Box::new(FatFileSystem::new(disk)));
+            fs.init();
+            let root = fs.root_dir();
+            root.create("shutdown.txt", VfsNodeType::File).unwrap();
+            let file = root.lookup("shutdown.txt").unwrap();
+            assert_eq!(file.write_at(0, b"Rust is cool!").unwrap(), 13);
+            drop(file);
+            ramdisk
         };
-        Ok(write_size)
+
+        // still in the cache
+        assert!(ramdisk.read(1).iter().all(|&b| b == 0));
+        axhal::misc::run_shutdown_hooks();
+        assert_eq!(&ramdisk.read(1)[100..113], b"Rust is cool!");
+
+        #[cfg(all(feature = "fatfs", not(feature = "use-ramdisk")))]
+        {
+            use crate::fs::fatfs::FatFileSystem;
+            use axfs_vfs::{VfsNodeOps, VfsOps};
+
+            let fs = FatFileSystem::new(fat_ramdisk.to_disk());
+            let fs: &'static FatFileSystem = Box::leak(Box::new(fs));
+            fs.init();
+            let file = fs.root_dir().lookup("shutdown.txt").unwrap();
+            let mut buf = [0; 16];
+            assert_eq!(file.read_at(0, &mut buf).unwrap(), 13);
+            assert_eq!(&buf[..13], b"Rust is cool!");
+        }
+    }
+
+    #[test]
+    fn large_blocks() {
+        axtask::init_scheduler(); // call this to use `axsync::Mutex`.
+        test_partial_io();
+        #[cfg(all(feature = "fatfs", not(feature = "use-ramdisk")))]
+        test_fatfs();
+        test_shutdown();
     }
 }
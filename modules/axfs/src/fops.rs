@@ -1,10 +1,17 @@
 //! Low-level filesystem operations.
 
+use alloc::{string::String, sync::Arc};
 use axerrno::{ax_err, ax_err_type, AxError, AxResult};
 use axfs_vfs::{VfsError, VfsNodeRef};
 use axio::SeekFrom;
 use cap_access::{Cap, WithCap};
-use core::fmt;
+use core::{fmt, time::Duration};
+
+use crate::fs::NodeMeta;
+use crate::root::BaseDir;
+
+pub use crate::lock::{FileLock, FileLocker, LockKind};
+pub use crate::watch::{WatchEvent, WatchEventKind, Watcher};
 
 #[cfg(feature = "myfs")]
 pub use crate::dev::Disk;
@@ -15,16 +22,28 @@ pub use crate::fs::myfs::MyFileSystemIf;
 pub type FileType = axfs_vfs::VfsNodeType;
 /// Alias of [`axfs_vfs::VfsDirEntry`].
 pub type DirEntry = axfs_vfs::VfsDirEntry;
-/// Alias of [`axfs_vfs::VfsNodeAttr`].
-pub type FileAttr = axfs_vfs::VfsNodeAttr;
 /// Alias of [`axfs_vfs::VfsNodePerm`].
 pub type FilePerm = axfs_vfs::VfsNodePerm;
 
+/// File attributes: the ones in [`axfs_vfs::VfsNodeAttr`], the timestamps and
+/// the ownership.
+///
+/// Timestamps are since the Unix epoch. Filesystems that do not keep them
+/// report zeros, and files are owned by root if they have no owners.
+#[derive(Debug, Clone, Copy)]
+pub struct FileAttr {
+    attr: axfs_vfs::VfsNodeAttr,
+    meta: NodeMeta,
+}
+
 /// An opened file object, with open permissions and a cursor.
 pub struct File {
     node: WithCap<VfsNodeRef>,
     is_append: bool,
     offset: u64,
+    locker: Arc<FileLocker>,
+    /// The absolute path, with symbolic links resolved.
+    path: String,
 }
 
 /// An opened directory object, with open permissions and a cursor for
@@ -32,6 +51,8 @@ pub struct File {
 pub struct Directory {
     node: WithCap<VfsNodeRef>,
     entry_idx: usize,
+    /// The absolute path, with symbolic links resolved.
+    path: String,
 }
 
 /// Options and flags which can be used to configure how a file is opened.
@@ -111,16 +132,88 @@ impl OpenOptions {
     }
 }
 
+impl FileAttr {
+    fn of(node: &VfsNodeRef) -> AxResult<Self> {
+        Ok(Self {
+            attr: node.get_attr()?,
+            meta: crate::fs::node_meta(node)?,
+        })
+    }
+
+    /// Returns the type of the file.
+    pub const fn file_type(&self) -> FileType {
+        self.attr.file_type()
+    }
+
+    /// Returns the permission of the file.
+    pub const fn perm(&self) -> FilePerm {
+        self.attr.perm()
+    }
+
+    /// Returns the size of the file, in bytes.
+    pub const fn size(&self) -> u64 {
+        self.attr.size()
+    }
+
+    /// Returns the number of blocks allocated to the file, in 512-byte units.
+    pub const fn blocks(&self) -> u64 {
+        self.attr.blocks()
+    }
+
+    /// Whether the file is a directory.
+    pub const fn is_dir(&self) -> bool {
+        self.attr.is_dir()
+    }
+
+    /// Whether the file is a regular file.
+    pub const fn is_file(&self) -> bool {
+        self.attr.is_file()
+    }
+
+    /// Returns the time of last access.
+    pub const fn atime(&self) -> Duration {
+        self.meta.atime
+    }
+
+    /// Returns the time of last modification of the content.
+    pub const fn mtime(&self) -> Duration {
+        self.meta.mtime
+    }
+
+    /// Returns the time of last change of the content or attributes.
+    pub const fn ctime(&self) -> Duration {
+        self.meta.ctime
+    }
+
+    /// Returns the user ID of the owner.
+    pub const fn uid(&self) -> u32 {
+        self.meta.uid
+    }
+
+    /// Returns the group ID of the owner.
+    pub const fn gid(&self) -> u32 {
+        self.meta.gid
+    }
+}
+
 impl File {
     fn access_node(&self, cap: Cap) -> AxResult<&VfsNodeRef> {
         self.node.access_or_err(cap, AxError::PermissionDenied)
     }
 
-    fn _open_at(dir: Option<&VfsNodeRef>, path: &str, opts: &OpenOptions) -> AxResult<Self> {
+    fn notify_modify(&self) {
+        crate::watch::notify(&self.path, WatchEventKind::Modify, false);
+    }
+
+    fn _open_at(parent: Option<&Directory>, path: &str, opts: &OpenOptions) -> AxResult<Self> {
         debug!("open file: {} {:?}", path, opts);
         if !opts.is_valid() {
             return ax_err!(InvalidInput);
         }
+        let dir = match parent {
+            Some(parent) => parent.access_at(path)?,
+            None => None,
+        };
 
         let node_option = crate::root::lookup(dir, path);
         let node = if opts.create || opts.create_new {
@@ -153,13 +246,18 @@ impl File {
         }
 
         node.open()?;
+        let real_path = Directory::real_path(parent, path)?;
         if opts.truncate {
             node.truncate(0)?;
+            crate::watch::notify(&real_path, WatchEventKind::Modify, false);
         }
+        let locker = FileLocker::new(&node, &real_path, access_cap)?;
         Ok(Self {
             node: WithCap::new(node, access_cap),
             is_append: opts.append,
             offset: 0,
+            locker: Arc::new(locker),
+            path: real_path,
         })
     }
 
@@ -172,6 +270,7 @@ impl File {
     /// Truncates the file to the specified size.
     pub fn truncate(&self, size: u64) -> AxResult {
         self.access_node(Cap::WRITE)?.truncate(size)?;
+        self.notify_modify();
         Ok(())
     }
 
@@ -202,13 +301,14 @@ impl File {
     /// written.
     pub fn write(&mut self, buf: &[u8]) -> AxResult<usize> {
         let offset = if self.is_append {
-            self.get_attr()?.size()
+            self.access_node(Cap::empty())?.get_attr()?.size()
         } else {
             self.offset
         };
         let node = self.access_node(Cap::WRITE)?;
         let write_len = node.write_at(offset, buf)?;
         self.offset = offset + write_len as u64;
+        self.notify_modify();
         Ok(write_len)
     }
 
@@ -219,6 +319,7 @@ impl File {
     pub fn write_at(&self, offset: u64, buf: &[u8]) -> AxResult<usize> {
         let node = self.access_node(Cap::WRITE)?;
         let write_len = node.write_at(offset, buf)?;
+        self.notify_modify();
         Ok(write_len)
     }
 
@@ -228,10 +329,32 @@ impl File {
         Ok(())
     }
 
+    /// Writes all data and metadata of the file to the underlying device.
+    ///
+    /// Unlike [`File::flush`], it does not require the file to be writable.
+    pub fn sync_all(&self) -> AxResult {
+        self.access_node(Cap::empty())?.fsync()?;
+        Ok(())
+    }
+
+    /// Performs the device-specific operation `cmd`, as `ioctl` in Linux.
+    /// Returns a non-negative value, which is 0 for most operations.
+    ///
+    /// Only device files in `/dev` support it, others get
+    /// [`AxError::Unsupported`].
+    ///
+    /// # Safety
+    ///
+    /// If the operation takes a pointer in `arg`, it must be valid for reads
+    /// or writes of the type the operation expects.
+    pub unsafe fn ioctl(&self, cmd: u32, arg: usize) -> AxResult<usize> {
+        crate::fs::ioctl(self.access_node(Cap::empty())?, cmd, arg)
+    }
+
     /// Sets the cursor of the file to the specified offset. Returns the new
     /// position after the seek.
     pub fn seek(&mut self, pos: SeekFrom) -> AxResult<u64> {
-        let size = self.get_attr()?.size();
+        let size = self.access_node(Cap::empty())?.get_attr()?.size();
         let new_offset = match pos {
             SeekFrom::Start(pos) => Some(pos),
             SeekFrom::Current(off) => self.offset.checked_add_signed(off),
@@ -244,7 +367,13 @@ impl File {
 
     /// Gets the file attributes.
     pub fn get_attr(&self) -> AxResult<FileAttr> {
-        self.access_node(Cap::empty())?.get_attr()
+        FileAttr::of(self.access_node(Cap::empty())?)
+    }
+
+    /// Returns the locker to apply advisory locks on the file, which owns
+    /// the whole-file lock of this opened file.
+    pub fn locker(&self) -> &Arc<FileLocker> {
+        &self.locker
     }
 }
 
@@ -253,7 +382,7 @@ impl Directory {
         self.node.access_or_err(cap, AxError::PermissionDenied)
     }
 
-    fn _open_dir_at(dir: Option<&VfsNodeRef>, path: &str, opts: &OpenOptions) -> AxResult<Self> {
+    fn _open_dir_at(parent: Option<&Self>, path: &str, opts: &OpenOptions) -> AxResult<Self> {
         debug!("open dir: {}", path);
         if !opts.read {
             return ax_err!(InvalidInput);
@@ -262,28 +391,49 @@ impl Directory {
             return ax_err!(InvalidInput);
         }
 
+        let dir = match parent {
+            Some(parent) => parent.access_at(path)?,
+            None => None,
+        };
         let node = crate::root::lookup(dir, path)?;
         let attr = node.get_attr()?;
         if !attr.is_dir() {
             return ax_err!(NotADirectory);
         }
         let access_cap = opts.into();
-        if !perm_to_cap(attr.perm()).contains(access_cap) {
+        let perm_cap = perm_to_cap(attr.perm());
+        if !perm_cap.contains(access_cap) {
             return ax_err!(PermissionDenied);
         }
 
         node.open()?;
         Ok(Self {
-            node: WithCap::new(node, access_cap),
+            // paths relative to the directory need the search permission
+            node: WithCap::new(node, access_cap | (perm_cap & Cap::EXECUTE)),
             entry_idx: 0,
+            path: Self::real_path(parent, path)?,
         })
     }
 
-    fn access_at(&self, path: &str) -> AxResult<Option<&VfsNodeRef>> {
+    /// Returns the absolute path of `path` relative to `parent`, or the
+    /// current directory if `parent` is [`None`], with symbolic links
+    /// resolved.
+    fn real_path(parent: Option<&Self>, path: &str) -> AxResult<String> {
+        let dir = match parent {
+            Some(parent) => parent.access_at(path)?,
+            None => None,
+        };
+        crate::root::real_path(dir, path)
+    }
+
+    fn access_at(&self, path: &str) -> AxResult<Option<BaseDir<'_>>> {
         if path.starts_with('/') {
             Ok(None)
         } else {
-            Ok(Some(self.access_node(Cap::EXECUTE)?))
+            Ok(Some(BaseDir {
+                node: self.access_node(Cap::EXECUTE)?,
+                path: &self.path,
+            }))
         }
     }
 
@@ -296,13 +446,13 @@ impl Directory {
     /// Opens a directory at the path relative to this directory. Returns a
     /// [`Directory`] object.
     pub fn open_dir_at(&self, path: &str, opts: &OpenOptions) -> AxResult<Self> {
-        Self::_open_dir_at(self.access_at(path)?, path, opts)
+        Self::_open_dir_at(Some(self), path, opts)
     }
 
     /// Opens a file at the path relative to this directory. Returns a [`File`]
     /// object.
     pub fn open_file_at(&self, path: &str, opts: &OpenOptions) -> AxResult<File> {
-        File::_open_at(self.access_at(path)?, path, opts)
+        File::_open_at(Some(self), path, opts)
     }
 
     /// Creates an empty file at the path relative to this directory.
@@ -325,6 +475,24 @@ impl Directory {
         crate::root::remove_dir(self.access_at(path)?, path)
     }
 
+    /// Creates a symbolic link at the path relative to this directory, which
+    /// points to `target`.
+    pub fn symlink(&self, target: &str, path: &str) -> AxResult {
+        crate::root::symlink(self.access_at(path)?, target, path)
+    }
+
+    /// Returns the target of the symbolic link at the path relative to this
+    /// directory.
+    pub fn read_link(&self, path: &str) -> AxResult<String> {
+        crate::root::read_link(self.access_at(path)?, path)
+    }
+
+    /// Returns the attributes of the file at the path relative to this
+    /// directory, without following the symbolic link at the last component.
+    pub fn symlink_attr_at(&self, path: &str) -> AxResult<FileAttr> {
+        FileAttr::of(&crate::root::lookup_no_follow(self.access_at(path)?, path)?)
+    }
+
     /// Reads directory entries starts from the current position into the
     /// given buffer. Returns the number of entries read.
     ///
@@ -345,6 +513,18 @@ impl Directory {
     pub fn rename(&self, old: &str, new: &str) -> AxResult {
         crate::root::rename(old, new)
     }
+
+    /// Creates a hard link `new` to the file `old`, which must be in the same
+    /// mounted fs.
+    pub fn hard_link(&self, old: &str, new: &str) -> AxResult {
+        crate::root::hard_link(old, new)
+    }
+}
+
+/// Returns the attributes of the file at the path relative to the current
+/// directory, without following the symbolic link at the last component.
+pub fn symlink_attr(path: &str) -> AxResult<FileAttr> {
+    FileAttr::of(&crate::root::lookup_no_follow(None, path)?)
 }
 
 impl Drop for File {
@@ -0,0 +1,120 @@
+use alloc::vec;
+use axfs_vfs::{VfsError, VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeType, VfsResult};
+
+use super::{write_arg, DeviceOps};
+use crate::dev::BlockDevice;
+
+/// Returns the number of 512-byte sectors, as `u64`.
+const BLKGETSIZE: u32 = 0x1260;
+/// Writes all cached data back.
+const BLKFLSBUF: u32 = 0x1261;
+/// Returns the logical block size, as `i32`.
+const BLKSSZGET: u32 = 0x1268;
+/// Returns the size in bytes, as `u64`.
+const BLKGETSIZE64: u32 = 0x8008_1272;
+/// Returns the physical block size, as `u32`.
+const BLKPBSZGET: u32 = 0x127b;
+
+/// A block device or partition, e.g., `/dev/vda1`.
+///
+/// Reads and writes go through the block cache shared with mounted
+/// filesystems, and may be at any offset.
+pub struct BlockDev {
+    dev: BlockDevice,
+}
+
+impl BlockDev {
+    /// Creates a device file for `dev`.
+    pub const fn new(dev: BlockDevice) -> Self {
+        Self { dev }
+    }
+}
+
+impl VfsNodeOps for BlockDev {
+    axfs_vfs::impl_vfs_non_dir_default! {}
+
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        let perm = VfsNodePerm::from_bits_truncate(0o660);
+        let blocks = self.dev.size() / 512;
+        let attr = VfsNodeAttr::new(perm, VfsNodeType::BlockDevice, self.dev.size(), blocks);
+        Ok(attr)
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        let size = self.dev.size();
+        if offset >= size {
+            return Ok(0);
+        }
+        let len = buf.len().min((size - offset) as usize);
+        let block_size = self.dev.block_size();
+        let mut block = vec![0; block_size];
+        let mut read = 0;
+        while read < len {
+            let pos = offset + read as u64;
+            let start = (pos % block_size as u64) as usize;
+            let n = (block_size - start).min(len - read);
+            self.dev
+                .read_block(pos / block_size as u64, &mut block)
+                .map_err(|_| VfsError::Io)?;
+            buf[read..read + n].copy_from_slice(&block[start..start + n]);
+            read += n;
+        }
+        Ok(read)
+    }
+
+    fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        let size = self.dev.size();
+        if buf.is_empty() {
+            return Ok(0);
+        } else if offset >= size {
+            return Err(VfsError::StorageFull);
+        }
+        let len = buf.len().min((size - offset) as usize);
+        let block_size = self.dev.block_size();
+        let mut block = vec![0; block_size];
+        let mut written = 0;
+        while written < len {
+            let pos = offset + written as u64;
+            let block_id = pos / block_size as u64;
+            let start = (pos % block_size as u64) as usize;
+            let n = (block_size - start).min(len - written);
+            if n < block_size {
+                // partial block, read-modify-write
+                self.dev
+                    .read_block(block_id, &mut block)
+                    .map_err(|_| VfsError::Io)?;
+            }
+            block[start..start + n].copy_from_slice(&buf[written..written + n]);
+            self.dev
+                .write_block(block_id, &block)
+                .map_err(|_| VfsError::Io)?;
+            written += n;
+        }
+        Ok(written)
+    }
+
+    fn fsync(&self) -> VfsResult {
+        self.dev.flush().map_err(|_| VfsError::Io)
+    }
+
+    fn truncate(&self, _size: u64) -> VfsResult {
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+}
+
+impl DeviceOps for BlockDev {
+    unsafe fn ioctl(&self, cmd: u32, arg: usize) -> VfsResult<usize> {
+        match cmd {
+            BLKGETSIZE => write_arg(arg, self.dev.size() / 512),
+            BLKGETSIZE64 => write_arg(arg, self.dev.size()),
+            BLKSSZGET => write_arg(arg, self.dev.block_size() as i32),
+            BLKPBSZGET => write_arg(arg, self.dev.block_size() as u32),
+            BLKFLSBUF => self.fsync().map(|_| 0),
+            _ => Err(VfsError::Unsupported),
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use axfs_vfs::{VfsError, VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeType, VfsResult};
+
+use super::{write_arg, DeviceOps};
+
+/// Gets the terminal attributes, as `Termios`.
+const TCGETS: u32 = 0x5401;
+/// Sets the terminal attributes now.
+const TCSETS: u32 = 0x5402;
+/// Sets the terminal attributes after output is written.
+const TCSETSW: u32 = 0x5403;
+/// Sets the terminal attributes after output is written, discarding input.
+const TCSETSF: u32 = 0x5404;
+/// Gets the window size, as `WinSize`.
+const TIOCGWINSZ: u32 = 0x5413;
+
+/// `struct termios` in Linux.
+#[repr(C)]
+struct Termios {
+    c_iflag: u32,
+    c_oflag: u32,
+    c_cflag: u32,
+    c_lflag: u32,
+    c_line: u8,
+    c_cc: [u8; 32],
+    c_ispeed: u32,
+    c_ospeed: u32,
+}
+
+/// `struct winsize` in Linux.
+#[repr(C)]
+struct WinSize {
+    ws_row: u16,
+    ws_col: u16,
+    ws_xpixel: u16,
+    ws_ypixel: u16,
+}
+
+/// The console, e.g., the UART, as `/dev/console` and `/dev/ttyS0`.
+///
+/// Reads block until some input is available, with `\r` translated to `\n`.
+pub struct ConsoleDev;
+
+impl VfsNodeOps for ConsoleDev {
+    axfs_vfs::impl_vfs_non_dir_default! {}
+
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        let perm = VfsNodePerm::from_bits_truncate(0o620);
+        Ok(VfsNodeAttr::new(perm, VfsNodeType::CharDevice, 0, 0))
+    }
+
+    fn read_at(&self, _offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            let len = axhal::console::read_bytes(buf);
+            if len > 0 {
+                for c in &mut buf[..len] {
+                    if *c == b'\r' {
+                        *c = b'\n';
+                    }
+                }
+                return Ok(len);
+            }
+            #[cfg(feature = "multitask")]
+            axtask::yield_now();
+            #[cfg(not(feature = "multitask"))]
+            core::hint::spin_loop();
+        }
+    }
+
+    fn write_at(&self, _offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        axhal::console::write_bytes(buf);
+        Ok(buf.len())
+    }
+
+    fn fsync(&self) -> VfsResult {
+        Ok(())
+    }
+
+    fn truncate(&self, _size: u64) -> VfsResult {
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+}
+
+impl DeviceOps for ConsoleDev {
+    unsafe fn ioctl(&self, cmd: u32, arg: usize) -> VfsResult<usize> {
+        match cmd {
+            TCGETS => {
+                let mut c_cc = [0; 32];
+                c_cc[0] = 3; // VINTR: ^C
+                c_cc[2] = 0x7f; // VERASE: DEL
+                c_cc[4] = 4; // VEOF: ^D
+                c_cc[6] = 1; // VMIN
+                let termios = Termios {
+                    c_iflag: 0o400,  // ICRNL
+                    c_oflag: 0o5,    // OPOST | ONLCR
+                    c_cflag: 0o277,  // CS8 | CREAD | B38400
+                    c_lflag: 0o1073, // ISIG | ICANON | ECHO | ECHOE | ECHOK | ECHOCTL
+                    c_line: 0,
+                    c_cc,
+                    c_ispeed: 0o17, // B38400
+                    c_ospeed: 0o17,
+                };
+                write_arg(arg, termios)
+            }
+            // the console has fixed attributes
+            TCSETS | TCSETSW | TCSETSF => Ok(0),
+            TIOCGWINSZ => {
+                let size = WinSize {
+                    ws_row: 24,
+                    ws_col: 80,
+                    ws_xpixel: 0,
+                    ws_ypixel: 0,
+                };
+                write_arg(arg, size)
+            }
+            _ => Err(VfsError::Unsupported),
+        }
+    }
+}
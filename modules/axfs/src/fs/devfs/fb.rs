@@ -0,0 +1,207 @@
+use alloc::string::String;
+use axfs_vfs::{VfsError, VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeType, VfsResult};
+
+use super::{write_arg, DeviceOps};
+
+/// Gets the variable screen information, as `VarScreenInfo`.
+const FBIOGET_VSCREENINFO: u32 = 0x4600;
+/// Gets the fixed screen information, as `FixScreenInfo`.
+const FBIOGET_FSCREENINFO: u32 = 0x4602;
+/// Pans the display, which only flushes the framebuffer here.
+const FBIOPAN_DISPLAY: u32 = 0x4606;
+
+/// Packed pixels.
+const FB_TYPE_PACKED_PIXELS: u32 = 0;
+/// Pixels in true colors.
+const FB_VISUAL_TRUECOLOR: u32 = 2;
+
+/// `struct fb_bitfield` in Linux.
+#[repr(C)]
+#[derive(Default)]
+struct BitField {
+    offset: u32,
+    length: u32,
+    msb_right: u32,
+}
+
+impl BitField {
+    const fn new(offset: u32, length: u32) -> Self {
+        Self {
+            offset,
+            length,
+            msb_right: 0,
+        }
+    }
+}
+
+/// `struct fb_var_screeninfo` in Linux.
+#[repr(C)]
+#[derive(Default)]
+struct VarScreenInfo {
+    xres: u32,
+    yres: u32,
+    xres_virtual: u32,
+    yres_virtual: u32,
+    xoffset: u32,
+    yoffset: u32,
+    bits_per_pixel: u32,
+    grayscale: u32,
+    red: BitField,
+    green: BitField,
+    blue: BitField,
+    transp: BitField,
+    nonstd: u32,
+    activate: u32,
+    height: u32,
+    width: u32,
+    accel_flags: u32,
+    pixclock: u32,
+    left_margin: u32,
+    right_margin: u32,
+    upper_margin: u32,
+    lower_margin: u32,
+    hsync_len: u32,
+    vsync_len: u32,
+    sync: u32,
+    vmode: u32,
+    rotate: u32,
+    colorspace: u32,
+    reserved: [u32; 4],
+}
+
+/// `struct fb_fix_screeninfo` in Linux.
+#[repr(C)]
+#[derive(Default)]
+struct FixScreenInfo {
+    id: [u8; 16],
+    smem_start: usize,
+    smem_len: u32,
+    type_: u32,
+    type_aux: u32,
+    visual: u32,
+    xpanstep: u16,
+    ypanstep: u16,
+    ywrapstep: u16,
+    line_length: u32,
+    mmio_start: usize,
+    mmio_len: u32,
+    accel: u32,
+    capabilities: u16,
+    reserved: [u16; 2],
+}
+
+/// The framebuffer of [`axdisplay`], as `/dev/fb0`.
+///
+/// Writes are shown on the screen immediately.
+pub struct FrameBufferDev {
+    name: String,
+}
+
+impl FrameBufferDev {
+    /// Creates a device file for the display device named `name`.
+    pub const fn new(name: String) -> Self {
+        Self { name }
+    }
+
+    /// Returns the framebuffer, or [`VfsError::BadState`] if the display is
+    /// not initialized.
+    fn framebuffer(&self) -> VfsResult<&'static mut [u8]> {
+        if !axdisplay::is_initialized() {
+            return Err(VfsError::BadState);
+        }
+        let info = axdisplay::framebuffer_info();
+        let base = info.fb_base_vaddr as *mut u8;
+        // SAFETY: the framebuffer is mapped for the whole lifetime
+        Ok(unsafe { core::slice::from_raw_parts_mut(base, info.fb_size) })
+    }
+}
+
+impl VfsNodeOps for FrameBufferDev {
+    axfs_vfs::impl_vfs_non_dir_default! {}
+
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        let perm = VfsNodePerm::from_bits_truncate(0o660);
+        let size = self.framebuffer().map_or(0, |fb| fb.len() as u64);
+        Ok(VfsNodeAttr::new(perm, VfsNodeType::CharDevice, size, 0))
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        let fb = self.framebuffer()?;
+        let start = fb.len().min(offset as usize);
+        let len = buf.len().min(fb.len() - start);
+        buf[..len].copy_from_slice(&fb[start..start + len]);
+        Ok(len)
+    }
+
+    fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        let fb = self.framebuffer()?;
+        if buf.is_empty() {
+            return Ok(0);
+        } else if offset >= fb.len() as u64 {
+            return Err(VfsError::StorageFull);
+        }
+        let start = offset as usize;
+        let len = buf.len().min(fb.len() - start);
+        fb[start..start + len].copy_from_slice(&buf[..len]);
+        axdisplay::framebuffer_flush();
+        Ok(len)
+    }
+
+    fn fsync(&self) -> VfsResult {
+        Ok(())
+    }
+
+    fn truncate(&self, _size: u64) -> VfsResult {
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+}
+
+impl DeviceOps for FrameBufferDev {
+    unsafe fn ioctl(&self, cmd: u32, arg: usize) -> VfsResult<usize> {
+        self.framebuffer()?;
+        let info = axdisplay::framebuffer_info();
+        let (width, height) = (info.width, info.height);
+        let bpp = (info.fb_size * 8 / (width * height) as usize) as u32;
+        match cmd {
+            FBIOGET_VSCREENINFO => {
+                let var = VarScreenInfo {
+                    xres: width,
+                    yres: height,
+                    xres_virtual: width,
+                    yres_virtual: height,
+                    bits_per_pixel: bpp,
+                    red: BitField::new(16, 8),
+                    green: BitField::new(8, 8),
+                    blue: BitField::new(0, 8),
+                    // the physical size in millimeters is unknown
+                    height: u32::MAX,
+                    width: u32::MAX,
+                    ..Default::default()
+                };
+                write_arg(arg, var)
+            }
+            FBIOGET_FSCREENINFO => {
+                let mut fix = FixScreenInfo {
+                    smem_start: axhal::mem::virt_to_phys(info.fb_base_vaddr.into()).as_usize(),
+                    smem_len: info.fb_size as u32,
+                    type_: FB_TYPE_PACKED_PIXELS,
+                    visual: FB_VISUAL_TRUECOLOR,
+                    line_length: width * bpp / 8,
+                    ..Default::default()
+                };
+                let len = self.name.len().min(fix.id.len() - 1);
+                fix.id[..len].copy_from_slice(&self.name.as_bytes()[..len]);
+                write_arg(arg, fix)
+            }
+            FBIOPAN_DISPLAY => {
+                axdisplay::framebuffer_flush();
+                Ok(0)
+            }
+            _ => Err(VfsError::Unsupported),
+        }
+    }
+}
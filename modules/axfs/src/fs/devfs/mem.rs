@@ -0,0 +1,80 @@
+use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeType, VfsResult};
+
+use super::DeviceOps;
+
+fn char_dev_attr() -> VfsResult<VfsNodeAttr> {
+    let perm = VfsNodePerm::from_bits_truncate(0o666);
+    Ok(VfsNodeAttr::new(perm, VfsNodeType::CharDevice, 0, 0))
+}
+
+/// A null device behaves like `/dev/null`.
+///
+/// Nothing can be read and all writes are discarded.
+pub struct NullDev;
+
+impl VfsNodeOps for NullDev {
+    axfs_vfs::impl_vfs_non_dir_default! {}
+
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        char_dev_attr()
+    }
+
+    fn read_at(&self, _offset: u64, _buf: &mut [u8]) -> VfsResult<usize> {
+        Ok(0)
+    }
+
+    fn write_at(&self, _offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        Ok(buf.len())
+    }
+
+    fn fsync(&self) -> VfsResult {
+        Ok(())
+    }
+
+    fn truncate(&self, _size: u64) -> VfsResult {
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+}
+
+impl DeviceOps for NullDev {}
+
+/// A zero device behaves like `/dev/zero`.
+///
+/// It always returns a chunk of `\0` bytes when read, and all writes are
+/// discarded.
+pub struct ZeroDev;
+
+impl VfsNodeOps for ZeroDev {
+    axfs_vfs::impl_vfs_non_dir_default! {}
+
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        char_dev_attr()
+    }
+
+    fn read_at(&self, _offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        buf.fill(0);
+        Ok(buf.len())
+    }
+
+    fn write_at(&self, _offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        Ok(buf.len())
+    }
+
+    fn fsync(&self) -> VfsResult {
+        Ok(())
+    }
+
+    fn truncate(&self, _size: u64) -> VfsResult {
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+}
+
+impl DeviceOps for ZeroDev {}
@@ -0,0 +1,77 @@
+//! Device files, mounted on `/dev` in a pseudo filesystem.
+//!
+//! Besides reads and writes, devices support device-specific operations by
+//! [`DeviceOps::ioctl`], as `ioctl` in Linux.
+
+mod block;
+mod console;
+#[cfg(feature = "display")]
+mod fb;
+mod mem;
+mod random;
+mod rtc;
+
+use axfs_vfs::{VfsError, VfsNodeRef, VfsResult};
+
+pub use self::block::BlockDev;
+pub use self::console::ConsoleDev;
+#[cfg(feature = "display")]
+pub use self::fb::FrameBufferDev;
+pub use self::mem::{NullDev, ZeroDev};
+pub use self::random::RandomDev;
+pub use self::rtc::RtcDev;
+
+/// Device-specific operations, as `ioctl` in Linux.
+pub trait DeviceOps {
+    /// Performs the operation `cmd` with the argument `arg`, which is usually
+    /// a pointer to the input or output. Returns a non-negative value, which
+    /// is 0 for most operations.
+    ///
+    /// Returns [`VfsError::Unsupported`] if the operation is unknown.
+    ///
+    /// # Safety
+    ///
+    /// If the operation takes a pointer, `arg` must be valid for reads or
+    /// writes of its type.
+    unsafe fn ioctl(&self, cmd: u32, arg: usize) -> VfsResult<usize> {
+        let _ = (cmd, arg);
+        Err(VfsError::Unsupported)
+    }
+}
+
+/// Returns the device operations of `node`, if it is a device defined here.
+pub(crate) fn as_device(node: &VfsNodeRef) -> Option<&dyn DeviceOps> {
+    let any = node.as_any();
+    if let Some(dev) = any.downcast_ref::<BlockDev>() {
+        return Some(dev);
+    } else if let Some(dev) = any.downcast_ref::<ConsoleDev>() {
+        return Some(dev);
+    } else if let Some(dev) = any.downcast_ref::<RandomDev>() {
+        return Some(dev);
+    } else if let Some(dev) = any.downcast_ref::<RtcDev>() {
+        return Some(dev);
+    } else if let Some(dev) = any.downcast_ref::<NullDev>() {
+        return Some(dev);
+    } else if let Some(dev) = any.downcast_ref::<ZeroDev>() {
+        return Some(dev);
+    }
+    #[cfg(feature = "display")]
+    if let Some(dev) = any.downcast_ref::<FrameBufferDev>() {
+        return Some(dev);
+    }
+    None
+}
+
+/// Writes the output of [`DeviceOps::ioctl`] to `arg`, which is a pointer
+/// to `T`.
+///
+/// # Safety
+///
+/// `arg` must be null or valid for writes of `T`.
+unsafe fn write_arg<T>(arg: usize, value: T) -> VfsResult<usize> {
+    if arg == 0 {
+        return Err(VfsError::BadAddress);
+    }
+    (arg as *mut T).write_unaligned(value);
+    Ok(0)
+}
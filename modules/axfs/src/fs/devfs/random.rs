@@ -0,0 +1,261 @@
+//! Random numbers for `/dev/random` and `/dev/urandom`.
+//!
+//! There is no entropy pool as in Linux. Numbers come from a ChaCha20-based
+//! generator, keyed on first use by the hardware random number generator
+//! (`RDRAND` on x86_64, `RNDR` on aarch64), and rekeyed from it every
+//! [`RESEED_INTERVAL`]. As in Linux, the key is replaced after each read, so
+//! that earlier output cannot be recovered from the state.
+//!
+//! Without a hardware generator, the key comes from the wall time (which is
+//! from the RTC if the `rtc` feature of axhal is enabled) and is easy to
+//! guess, so a warning is printed. Therefore the entropy count ioctl
+//! (`RNDGETENTCNT`) is not supported.
+
+use core::time::Duration;
+
+use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeType, VfsResult};
+use axsync::Mutex;
+
+use super::DeviceOps;
+
+/// How often the key is renewed from the hardware random number generator.
+const RESEED_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The key of the generator.
+type Key = [u32; 8];
+
+struct Crng {
+    key: Key,
+    /// When the key was last renewed from the hardware generator, [`None`]
+    /// if it has not been seeded yet.
+    seeded_at: Option<Duration>,
+}
+
+static CRNG: Mutex<Crng> = Mutex::new(Crng {
+    key: [0; 8],
+    seeded_at: None,
+});
+
+/// Reads a random number from `RDRAND`, if the CPU supports it.
+#[cfg(target_arch = "x86_64")]
+fn hw_random() -> Option<u64> {
+    use core::arch::x86_64::{__cpuid, _rdrand64_step};
+    // CPUID.01H:ECX.RDRAND[bit 30]
+    if unsafe { __cpuid(1) }.ecx & (1 << 30) == 0 {
+        return None;
+    }
+    let mut value = 0;
+    // retry as recommended by Intel, it fails only if the DRNG is exhausted
+    (0..10).find_map(|_| (unsafe { _rdrand64_step(&mut value) } == 1).then_some(value))
+}
+
+/// Reads a random number from `RNDR`, if the CPU supports it.
+#[cfg(target_arch = "aarch64")]
+fn hw_random() -> Option<u64> {
+    let isar0: u64;
+    unsafe { core::arch::asm!("mrs {}, id_aa64isar0_el1", out(reg) isar0) };
+    // ID_AA64ISAR0_EL1.RNDR[63:60]
+    if isar0 >> 60 == 0 {
+        return None;
+    }
+    let (value, ok): (u64, u64);
+    // RNDR is `s3_3_c2_c4_0`, which sets NZCV to 0b0100 on failure
+    unsafe {
+        core::arch::asm!(
+            "mrs {value}, s3_3_c2_c4_0",
+            "cset {ok}, ne",
+            value = out(reg) value,
+            ok = out(reg) ok,
+            options(nomem, nostack),
+        )
+    };
+    (ok != 0).then_some(value)
+}
+
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+fn hw_random() -> Option<u64> {
+    None
+}
+
+fn quarter_round(x: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    x[a] = x[a].wrapping_add(x[b]);
+    x[d] = (x[d] ^ x[a]).rotate_left(16);
+    x[c] = x[c].wrapping_add(x[d]);
+    x[b] = (x[b] ^ x[c]).rotate_left(12);
+    x[a] = x[a].wrapping_add(x[b]);
+    x[d] = (x[d] ^ x[a]).rotate_left(8);
+    x[c] = x[c].wrapping_add(x[d]);
+    x[b] = (x[b] ^ x[c]).rotate_left(7);
+}
+
+/// Returns the ChaCha20 block of `key`, `counter` and `nonce` (RFC 8439).
+fn chacha20_block(key: &Key, counter: u32, nonce: [u32; 3]) -> [u32; 16] {
+    let mut init = [0; 16];
+    // "expand 32-byte k"
+    init[..4].copy_from_slice(&[0x6170_7865, 0x3320_646e, 0x7962_2d32, 0x6b20_6574]);
+    init[4..12].copy_from_slice(key);
+    init[12] = counter;
+    init[13..].copy_from_slice(&nonce);
+    let mut x = init;
+    for _ in 0..10 {
+        quarter_round(&mut x, 0, 4, 8, 12);
+        quarter_round(&mut x, 1, 5, 9, 13);
+        quarter_round(&mut x, 2, 6, 10, 14);
+        quarter_round(&mut x, 3, 7, 11, 15);
+        quarter_round(&mut x, 0, 5, 10, 15);
+        quarter_round(&mut x, 1, 6, 11, 12);
+        quarter_round(&mut x, 2, 7, 8, 13);
+        quarter_round(&mut x, 3, 4, 9, 14);
+    }
+    for (x, init) in x.iter_mut().zip(init) {
+        *x = x.wrapping_add(init);
+    }
+    x
+}
+
+/// Replaces `key` by the first half of its block, and returns the second
+/// half, so that neither can be computed from the new key.
+fn rekey(key: &mut Key) -> Key {
+    let block = chacha20_block(key, 0, [0; 3]);
+    key.copy_from_slice(&block[..8]);
+    block[8..].try_into().unwrap()
+}
+
+impl Crng {
+    /// Mixes fresh hardware random numbers into the key, at first use and
+    /// every [`RESEED_INTERVAL`].
+    fn reseed_if_needed(&mut self) {
+        let now = axhal::time::monotonic_time();
+        if self
+            .seeded_at
+            .is_some_and(|seeded_at| now < seeded_at + RESEED_INTERVAL)
+        {
+            return;
+        }
+        let first = self.seeded_at.is_none();
+        self.seeded_at = Some(now);
+        let mut seed = [0; 8];
+        for words in seed.chunks_mut(2) {
+            let Some(value) = hw_random() else {
+                if first {
+                    warn!("no hardware random number generator, /dev/random is predictable");
+                    let time = axhal::time::wall_time_nanos();
+                    seed[0] ^= time as u32;
+                    seed[1] ^= (time >> 32) as u32;
+                    break;
+                }
+                return;
+            };
+            words.copy_from_slice(&[value as u32, (value >> 32) as u32]);
+        }
+        self.mix(&seed);
+    }
+
+    /// Mixes `words` into the key.
+    fn mix(&mut self, words: &[u32]) {
+        for chunk in words.chunks(8) {
+            for (key, word) in self.key.iter_mut().zip(chunk) {
+                *key ^= word;
+            }
+            rekey(&mut self.key);
+        }
+    }
+}
+
+/// Fills `buf` with random bytes.
+fn fill_bytes(buf: &mut [u8]) {
+    // The output comes from a key used only for this call, so that the lock
+    // is not held while generating it.
+    let key = {
+        let mut crng = CRNG.lock();
+        crng.reseed_if_needed();
+        rekey(&mut crng.key)
+    };
+    for (counter, chunk) in buf.chunks_mut(64).enumerate() {
+        let block = chacha20_block(&key, counter as u32, [0; 3]);
+        for (bytes, word) in chunk.chunks_mut(4).zip(block) {
+            bytes.copy_from_slice(&word.to_le_bytes()[..bytes.len()]);
+        }
+    }
+}
+
+/// A random number generator, as `/dev/random` and `/dev/urandom`.
+///
+/// It never blocks, and is cryptographically secure if the CPU has a
+/// hardware random number generator. Data written is mixed into the key.
+pub struct RandomDev;
+
+impl VfsNodeOps for RandomDev {
+    axfs_vfs::impl_vfs_non_dir_default! {}
+
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        let perm = VfsNodePerm::from_bits_truncate(0o666);
+        Ok(VfsNodeAttr::new(perm, VfsNodeType::CharDevice, 0, 0))
+    }
+
+    fn read_at(&self, _offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        fill_bytes(buf);
+        Ok(buf.len())
+    }
+
+    fn write_at(&self, _offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        let mut crng = CRNG.lock();
+        crng.reseed_if_needed();
+        for chunk in buf.chunks(32) {
+            let mut words = [0; 8];
+            for (word, bytes) in words.iter_mut().zip(chunk.chunks(4)) {
+                let mut le = [0; 4];
+                le[..bytes.len()].copy_from_slice(bytes);
+                *word = u32::from_le_bytes(le);
+            }
+            crng.mix(&words);
+        }
+        Ok(buf.len())
+    }
+
+    fn fsync(&self) -> VfsResult {
+        Ok(())
+    }
+
+    fn truncate(&self, _size: u64) -> VfsResult {
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+}
+
+impl DeviceOps for RandomDev {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 8439, section 2.3.2
+    #[rustfmt::skip]
+    const RFC8439_BLOCK: [u32; 16] = [
+        0xe4e7_f110, 0x1559_3bd1, 0x1fdd_0f50, 0xc471_20a3,
+        0xc7f4_d1c7, 0x0368_c033, 0x9aaa_2204, 0x4e6c_d4c3,
+        0x4664_82d2, 0x09aa_9f07, 0x05d7_c214, 0xa202_8bd9,
+        0xd19c_12b5, 0xb94e_16de, 0xe883_d0cb, 0x4e3c_50a2,
+    ];
+
+    #[test]
+    fn chacha20_rfc8439() {
+        let key: Key = core::array::from_fn(|i| {
+            u32::from_le_bytes(core::array::from_fn(|j| (i * 4 + j) as u8))
+        });
+        let block = chacha20_block(&key, 1, [0x0900_0000, 0x4a00_0000, 0]);
+        assert_eq!(block, RFC8439_BLOCK);
+    }
+
+    #[test]
+    fn fill() {
+        let (mut a, mut b) = ([0; 100], [0; 100]);
+        fill_bytes(&mut a);
+        fill_bytes(&mut b);
+        // each read has a new key
+        assert_ne!(a, b);
+    }
+}
@@ -0,0 +1,121 @@
+use axfs_vfs::{VfsError, VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeType, VfsResult};
+
+use super::{write_arg, DeviceOps};
+
+/// Reads the time, as `RtcTime`.
+const RTC_RD_TIME: u32 = 0x8024_7009;
+/// The update interrupt flag in the data read.
+const RTC_UF: usize = 0x10;
+
+/// `struct rtc_time` in Linux.
+#[repr(C)]
+struct RtcTime {
+    tm_sec: i32,
+    tm_min: i32,
+    tm_hour: i32,
+    tm_mday: i32,
+    /// Months since January, from 0 to 11.
+    tm_mon: i32,
+    /// Years since 1900.
+    tm_year: i32,
+    /// Days since Sunday, from 0 to 6.
+    tm_wday: i32,
+    /// Days since January 1, from 0 to 365.
+    tm_yday: i32,
+    tm_isdst: i32,
+}
+
+impl RtcTime {
+    /// Converts seconds since the Unix epoch to UTC.
+    fn from_secs(secs: u64) -> Self {
+        let days = (secs / 86400) as i64;
+        // the civil date from days since 1970-01-01, see
+        // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+        let z = days + 719468;
+        let era = z / 146097;
+        let doe = z - era * 146097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + (month <= 2) as i64;
+
+        // March 1 is day 59 of the year, or 60 in leap years
+        let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+        let yday = if month <= 2 {
+            doy - 306
+        } else {
+            doy + 59 + leap as i64
+        };
+        let rem = secs % 86400;
+        Self {
+            tm_sec: (rem % 60) as i32,
+            tm_min: (rem / 60 % 60) as i32,
+            tm_hour: (rem / 3600) as i32,
+            tm_mday: day as i32,
+            tm_mon: month as i32 - 1,
+            tm_year: year as i32 - 1900,
+            // 1970-01-01 is a Thursday
+            tm_wday: ((days + 4) % 7) as i32,
+            tm_yday: yday as i32,
+            tm_isdst: 0,
+        }
+    }
+}
+
+/// The real-time clock, as `/dev/rtc0`, backed by [`axhal::time::wall_time`].
+///
+/// Reads block until the next second, as the update interrupt in Linux.
+pub struct RtcDev;
+
+impl VfsNodeOps for RtcDev {
+    axfs_vfs::impl_vfs_non_dir_default! {}
+
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        let perm = VfsNodePerm::from_bits_truncate(0o600);
+        Ok(VfsNodeAttr::new(perm, VfsNodeType::CharDevice, 0, 0))
+    }
+
+    fn read_at(&self, _offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        const LEN: usize = core::mem::size_of::<usize>();
+        if buf.len() < LEN {
+            return Err(VfsError::InvalidInput);
+        }
+        let start = axhal::time::wall_time().as_secs();
+        while axhal::time::wall_time().as_secs() == start {
+            #[cfg(feature = "multitask")]
+            axtask::yield_now();
+            #[cfg(not(feature = "multitask"))]
+            core::hint::spin_loop();
+        }
+        // one interrupt occurred
+        let data = (1 << 8) | RTC_UF;
+        buf[..LEN].copy_from_slice(&data.to_ne_bytes());
+        Ok(LEN)
+    }
+
+    fn write_at(&self, _offset: u64, _buf: &[u8]) -> VfsResult<usize> {
+        Err(VfsError::InvalidInput)
+    }
+
+    fn fsync(&self) -> VfsResult {
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+}
+
+impl DeviceOps for RtcDev {
+    unsafe fn ioctl(&self, cmd: u32, arg: usize) -> VfsResult<usize> {
+        match cmd {
+            RTC_RD_TIME => {
+                let time = RtcTime::from_secs(axhal::time::wall_time().as_secs());
+                write_arg(arg, time)
+            }
+            _ => Err(VfsError::Unsupported),
+        }
+    }
+}
@@ -0,0 +1,455 @@
+//! On-disk structures of ext2/3/4.
+
+use alloc::{vec, vec::Vec};
+use core::time::Duration;
+
+use axfs_vfs::VfsNodeType;
+
+pub const SUPERBLOCK_OFFSET: u64 = 1024;
+pub const SUPERBLOCK_SIZE: usize = 1024;
+pub const EXT_MAGIC: u16 = 0xef53;
+pub const ROOT_INO: u32 = 2;
+
+pub const COMPAT_HAS_JOURNAL: u32 = 0x4;
+
+pub const INCOMPAT_FILETYPE: u32 = 0x2;
+pub const INCOMPAT_RECOVER: u32 = 0x4;
+pub const INCOMPAT_META_BG: u32 = 0x10;
+pub const INCOMPAT_EXTENTS: u32 = 0x40;
+pub const INCOMPAT_64BIT: u32 = 0x80;
+pub const INCOMPAT_MMP: u32 = 0x100;
+pub const INCOMPAT_FLEX_BG: u32 = 0x200;
+pub const INCOMPAT_CSUM_SEED: u32 = 0x2000;
+pub const INCOMPAT_LARGEDIR: u32 = 0x4000;
+
+/// Incompatible features that can be mounted read-only.
+pub const INCOMPAT_SUPPORTED: u32 = INCOMPAT_FILETYPE
+    | INCOMPAT_RECOVER
+    | INCOMPAT_EXTENTS
+    | INCOMPAT_64BIT
+    | INCOMPAT_MMP
+    | INCOMPAT_FLEX_BG
+    | INCOMPAT_CSUM_SEED
+    | INCOMPAT_LARGEDIR;
+/// Incompatible features that can be mounted read-write.
+pub const INCOMPAT_WRITABLE: u32 = INCOMPAT_FILETYPE;
+
+pub const RO_COMPAT_SPARSE_SUPER: u32 = 0x1;
+pub const RO_COMPAT_LARGE_FILE: u32 = 0x2;
+/// Read-only compatible features that can be mounted read-write.
+pub const RO_COMPAT_WRITABLE: u32 = RO_COMPAT_SPARSE_SUPER | RO_COMPAT_LARGE_FILE;
+
+pub const INODE_FLAG_INDEX: u32 = 0x1000;
+pub const INODE_FLAG_HUGE_FILE: u32 = 0x40000;
+pub const INODE_FLAG_EXTENTS: u32 = 0x80000;
+pub const INODE_FLAG_INLINE_DATA: u32 = 0x1000_0000;
+
+pub const EXTENT_MAGIC: u16 = 0xf30a;
+
+/// Number of direct block pointers in an inode.
+pub const DIRECT_BLOCKS: usize = 12;
+/// Size of `i_block` in an inode, which holds a fast symlink target.
+pub const INODE_BLOCK_SIZE: usize = 60;
+/// Size of the inode fields before `i_extra_isize`.
+const INODE_GOOD_OLD_SIZE: usize = 128;
+
+pub const S_IFMT: u16 = 0o170000;
+pub const S_IFIFO: u16 = 0o010000;
+pub const S_IFCHR: u16 = 0o020000;
+pub const S_IFDIR: u16 = 0o040000;
+pub const S_IFBLK: u16 = 0o060000;
+pub const S_IFREG: u16 = 0o100000;
+pub const S_IFLNK: u16 = 0o120000;
+pub const S_IFSOCK: u16 = 0o140000;
+
+pub fn read_u16(buf: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(buf[offset..offset + 2].try_into().unwrap())
+}
+
+pub fn read_u32(buf: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(buf[offset..offset + 4].try_into().unwrap())
+}
+
+pub fn write_u16(buf: &mut [u8], offset: usize, val: u16) {
+    buf[offset..offset + 2].copy_from_slice(&val.to_le_bytes());
+}
+
+pub fn write_u32(buf: &mut [u8], offset: usize, val: u32) {
+    buf[offset..offset + 4].copy_from_slice(&val.to_le_bytes());
+}
+
+/// Combines the low and high 32-bit halves.
+pub fn lo_hi(lo: u32, hi: u32) -> u64 {
+    lo as u64 | (hi as u64) << 32
+}
+
+/// The fields of the superblock in use.
+pub struct Superblock {
+    pub inodes_count: u32,
+    pub blocks_count: u64,
+    pub free_blocks_count: u64,
+    pub free_inodes_count: u32,
+    pub first_data_block: u32,
+    pub log_block_size: u32,
+    pub blocks_per_group: u32,
+    pub inodes_per_group: u32,
+    pub magic: u16,
+    pub first_ino: u32,
+    pub inode_size: u16,
+    pub feature_compat: u32,
+    pub feature_incompat: u32,
+    pub feature_ro_compat: u32,
+    pub desc_size: u16,
+}
+
+impl Superblock {
+    pub fn parse(raw: &[u8]) -> Self {
+        let is_64bit = read_u32(raw, 96) & INCOMPAT_64BIT != 0;
+        let hi = |offset| if is_64bit { read_u32(raw, offset) } else { 0 };
+        let rev_level = read_u32(raw, 76);
+        Self {
+            inodes_count: read_u32(raw, 0),
+            blocks_count: lo_hi(read_u32(raw, 4), hi(336)),
+            free_blocks_count: lo_hi(read_u32(raw, 12), hi(344)),
+            free_inodes_count: read_u32(raw, 16),
+            first_data_block: read_u32(raw, 20),
+            log_block_size: read_u32(raw, 24),
+            blocks_per_group: read_u32(raw, 32),
+            inodes_per_group: read_u32(raw, 40),
+            magic: read_u16(raw, 56),
+            first_ino: if rev_level == 0 {
+                11
+            } else {
+                read_u32(raw, 84)
+            },
+            inode_size: if rev_level == 0 {
+                128
+            } else {
+                read_u16(raw, 88)
+            },
+            feature_compat: read_u32(raw, 92),
+            feature_incompat: read_u32(raw, 96),
+            feature_ro_compat: read_u32(raw, 100),
+            desc_size: if is_64bit { read_u16(raw, 254) } else { 32 },
+        }
+    }
+
+    /// Writes the free counts back to the raw superblock.
+    pub fn write_counts(&self, raw: &mut [u8]) {
+        write_u32(raw, 12, self.free_blocks_count as u32);
+        write_u32(raw, 16, self.free_inodes_count);
+        if self.feature_incompat & INCOMPAT_64BIT != 0 {
+            write_u32(raw, 344, (self.free_blocks_count >> 32) as u32);
+        }
+    }
+}
+
+/// A block group descriptor.
+pub struct GroupDesc {
+    pub block_bitmap: u64,
+    pub inode_bitmap: u64,
+    pub inode_table: u64,
+    pub free_blocks_count: u32,
+    pub free_inodes_count: u32,
+    pub used_dirs_count: u32,
+}
+
+impl GroupDesc {
+    pub fn parse(raw: &[u8]) -> Self {
+        let hi32 = |offset| {
+            if raw.len() >= 64 {
+                read_u32(raw, offset)
+            } else {
+                0
+            }
+        };
+        let hi16 = |offset| {
+            if raw.len() >= 64 {
+                read_u16(raw, offset)
+            } else {
+                0
+            }
+        };
+        Self {
+            block_bitmap: lo_hi(read_u32(raw, 0), hi32(32)),
+            inode_bitmap: lo_hi(read_u32(raw, 4), hi32(36)),
+            inode_table: lo_hi(read_u32(raw, 8), hi32(40)),
+            free_blocks_count: read_u16(raw, 12) as u32 | (hi16(44) as u32) << 16,
+            free_inodes_count: read_u16(raw, 14) as u32 | (hi16(46) as u32) << 16,
+            used_dirs_count: read_u16(raw, 16) as u32 | (hi16(48) as u32) << 16,
+        }
+    }
+
+    /// Writes the counts back to the raw 32-byte descriptor, only used for
+    /// writable (non-64-bit) filesystems.
+    pub fn write_counts(&self, raw: &mut [u8]) {
+        write_u16(raw, 12, self.free_blocks_count as u16);
+        write_u16(raw, 14, self.free_inodes_count as u16);
+        write_u16(raw, 16, self.used_dirs_count as u16);
+    }
+}
+
+/// An inode, kept in its raw form so that unknown fields are preserved.
+pub struct Inode {
+    pub ino: u32,
+    pub raw: Vec<u8>,
+}
+
+impl Inode {
+    pub fn new(ino: u32, size: usize) -> Self {
+        Self {
+            ino,
+            raw: vec![0; size],
+        }
+    }
+
+    pub fn mode(&self) -> u16 {
+        read_u16(&self.raw, 0)
+    }
+
+    pub fn set_mode(&mut self, mode: u16) {
+        write_u16(&mut self.raw, 0, mode)
+    }
+
+    pub fn node_type(&self) -> VfsNodeType {
+        mode_to_type(self.mode())
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.mode() & S_IFMT == S_IFDIR
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        self.mode() & S_IFMT == S_IFLNK
+    }
+
+    pub fn size(&self) -> u64 {
+        lo_hi(read_u32(&self.raw, 4), read_u32(&self.raw, 108))
+    }
+
+    pub fn set_size(&mut self, size: u64) {
+        write_u32(&mut self.raw, 4, size as u32);
+        write_u32(&mut self.raw, 108, (size >> 32) as u32);
+    }
+
+    pub fn uid(&self) -> u32 {
+        read_u16(&self.raw, 2) as u32 | (read_u16(&self.raw, 120) as u32) << 16
+    }
+
+    pub fn gid(&self) -> u32 {
+        read_u16(&self.raw, 24) as u32 | (read_u16(&self.raw, 122) as u32) << 16
+    }
+
+    pub fn atime(&self) -> Duration {
+        self.time(8, 140)
+    }
+
+    pub fn set_atime(&mut self, time: Duration) {
+        self.set_time(8, 140, time)
+    }
+
+    pub fn ctime(&self) -> Duration {
+        self.time(12, 132)
+    }
+
+    pub fn set_ctime(&mut self, time: Duration) {
+        self.set_time(12, 132, time)
+    }
+
+    pub fn mtime(&self) -> Duration {
+        self.time(16, 136)
+    }
+
+    pub fn set_mtime(&mut self, time: Duration) {
+        self.set_time(16, 136, time)
+    }
+
+    pub fn set_dtime(&mut self, time: u32) {
+        write_u32(&mut self.raw, 20, time)
+    }
+
+    /// Whether the 32-bit field at `offset` in the large inode area is in use.
+    fn has_extra(&self, offset: usize) -> bool {
+        self.raw.len() >= offset + 4
+            && INODE_GOOD_OLD_SIZE + read_u16(&self.raw, INODE_GOOD_OLD_SIZE) as usize >= offset + 4
+    }
+
+    /// Reads the timestamp with the seconds at `offset`, and the nanoseconds
+    /// and epoch bits at `extra` if it is in use.
+    fn time(&self, offset: usize, extra: usize) -> Duration {
+        let mut secs = read_u32(&self.raw, offset) as i32 as i64;
+        let mut nanos = 0;
+        if self.has_extra(extra) {
+            let extra = read_u32(&self.raw, extra);
+            secs += ((extra & 3) as i64) << 32;
+            nanos = (extra >> 2).min(999_999_999);
+        }
+        // times before the epoch are not supported
+        Duration::new(secs.max(0) as u64, nanos)
+    }
+
+    fn set_time(&mut self, offset: usize, extra: usize, time: Duration) {
+        let secs = time.as_secs() as i64;
+        write_u32(&mut self.raw, offset, secs as u32);
+        if self.has_extra(extra) {
+            let epoch = ((secs - secs as i32 as i64) >> 32) as u32 & 3;
+            write_u32(&mut self.raw, extra, time.subsec_nanos() << 2 | epoch);
+        }
+    }
+
+    pub fn links_count(&self) -> u16 {
+        read_u16(&self.raw, 26)
+    }
+
+    pub fn set_links_count(&mut self, links: u16) {
+        write_u16(&mut self.raw, 26, links)
+    }
+
+    /// Number of 512-byte sectors in use, including the indirect blocks.
+    pub fn sectors(&self, block_size: usize) -> u64 {
+        let count = lo_hi(read_u32(&self.raw, 28), read_u16(&self.raw, 116) as u32);
+        if self.flags() & INODE_FLAG_HUGE_FILE != 0 {
+            // in units of filesystem blocks
+            count * (block_size / 512) as u64
+        } else {
+            count
+        }
+    }
+
+    pub fn set_sectors(&mut self, sectors: u64) {
+        write_u32(&mut self.raw, 28, sectors as u32);
+        write_u16(&mut self.raw, 116, (sectors >> 32) as u16);
+    }
+
+    pub fn flags(&self) -> u32 {
+        read_u32(&self.raw, 32)
+    }
+
+    pub fn set_flags(&mut self, flags: u32) {
+        write_u32(&mut self.raw, 32, flags)
+    }
+
+    /// The raw `i_block` field: block pointers, an extent tree root, or a
+    /// fast symlink target.
+    pub fn i_block(&self) -> &[u8] {
+        &self.raw[40..40 + INODE_BLOCK_SIZE]
+    }
+
+    pub fn i_block_mut(&mut self) -> &mut [u8] {
+        &mut self.raw[40..40 + INODE_BLOCK_SIZE]
+    }
+
+    pub fn block(&self, idx: usize) -> u32 {
+        read_u32(&self.raw, 40 + idx * 4)
+    }
+
+    pub fn set_block(&mut self, idx: usize, block: u32) {
+        write_u32(&mut self.raw, 40 + idx * 4, block)
+    }
+
+    pub fn uses_extents(&self) -> bool {
+        self.flags() & INODE_FLAG_EXTENTS != 0
+    }
+
+    /// Whether it is a symlink whose target is stored in `i_block`.
+    pub fn is_fast_symlink(&self) -> bool {
+        self.is_symlink()
+            && self.size() < INODE_BLOCK_SIZE as u64
+            && self.flags() & (INODE_FLAG_EXTENTS | INODE_FLAG_INLINE_DATA) == 0
+            && read_u32(&self.raw, 104) == 0 // no extended attribute block
+            && read_u32(&self.raw, 28) == 0
+    }
+}
+
+pub fn mode_to_type(mode: u16) -> VfsNodeType {
+    match mode & S_IFMT {
+        S_IFIFO => VfsNodeType::Fifo,
+        S_IFCHR => VfsNodeType::CharDevice,
+        S_IFDIR => VfsNodeType::Dir,
+        S_IFBLK => VfsNodeType::BlockDevice,
+        S_IFLNK => VfsNodeType::SymLink,
+        S_IFSOCK => VfsNodeType::Socket,
+        _ => VfsNodeType::File,
+    }
+}
+
+/// Returns the file type stored in directory entries.
+pub fn dirent_file_type(ty: VfsNodeType) -> u8 {
+    match ty {
+        VfsNodeType::File => 1,
+        VfsNodeType::Dir => 2,
+        VfsNodeType::CharDevice => 3,
+        VfsNodeType::BlockDevice => 4,
+        VfsNodeType::Fifo => 5,
+        VfsNodeType::Socket => 6,
+        VfsNodeType::SymLink => 7,
+    }
+}
+
+pub fn dirent_type_to_node_type(ty: u8) -> Option<VfsNodeType> {
+    Some(match ty {
+        1 => VfsNodeType::File,
+        2 => VfsNodeType::Dir,
+        3 => VfsNodeType::CharDevice,
+        4 => VfsNodeType::BlockDevice,
+        5 => VfsNodeType::Fifo,
+        6 => VfsNodeType::Socket,
+        7 => VfsNodeType::SymLink,
+        _ => return None,
+    })
+}
+
+/// The size of a directory entry with a name of `name_len` bytes.
+pub const fn dirent_size(name_len: usize) -> usize {
+    (8 + name_len + 3) & !3
+}
+
+/// A directory entry in a directory block.
+pub struct RawDirEntry<'a> {
+    pub ino: u32,
+    pub rec_len: usize,
+    pub file_type: u8,
+    pub name: &'a [u8],
+}
+
+/// Iterates over the entries in a directory block, as `(offset, entry)`.
+///
+/// Stops at the first malformed entry.
+pub fn dir_block_entries(block: &[u8]) -> impl Iterator<Item = (usize, RawDirEntry<'_>)> {
+    let mut offset = 0;
+    core::iter::from_fn(move || {
+        if offset + 8 > block.len() {
+            return None;
+        }
+        let rec_len = read_u16(block, offset + 4) as usize;
+        let name_len = block[offset + 6] as usize;
+        if rec_len < 8 || offset + rec_len > block.len() || 8 + name_len > rec_len {
+            return None;
+        }
+        let entry = RawDirEntry {
+            ino: read_u32(block, offset),
+            rec_len,
+            file_type: block[offset + 7],
+            name: &block[offset + 8..offset + 8 + name_len],
+        };
+        let cur = offset;
+        offset += rec_len;
+        Some((cur, entry))
+    })
+}
+
+/// Writes a directory entry at `offset` of a directory block.
+pub fn write_dirent(
+    block: &mut [u8],
+    offset: usize,
+    ino: u32,
+    rec_len: usize,
+    file_type: u8,
+    name: &[u8],
+) {
+    write_u32(block, offset, ino);
+    write_u16(block, offset + 4, rec_len as u16);
+    block[offset + 6] = name.len() as u8;
+    block[offset + 7] = file_type;
+    block[offset + 8..offset + 8 + name.len()].copy_from_slice(name);
+}
@@ -0,0 +1,289 @@
+//! ext2/3/4 filesystem.
+//!
+//! ext2 (and ext3 with a clean journal) can be read and written. Other ext4
+//! features, e.g., extents and 64-bit block numbers, are supported read-only,
+//! and such volumes are mounted read-only. Hash-indexed (htree) directories
+//! are read by scanning their blocks linearly, which the format is designed
+//! for. The journal is neither replayed nor written. Access times are not
+//! updated by reads, as with the `noatime` mount option.
+
+mod layout;
+mod volume;
+
+use alloc::{string::String, sync::Arc, vec::Vec};
+
+use axfs_vfs::{VfsDirEntry, VfsError, VfsNodePerm, VfsResult};
+use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType, VfsOps};
+use axsync::Mutex;
+
+use self::layout::{dirent_type_to_node_type, ROOT_INO};
+use self::volume::Volume;
+use crate::dev::Disk;
+use crate::fs::{FileId, LinkOps, NodeMeta};
+
+struct Shared {
+    volume: Mutex<Volume>,
+    /// The parent of the mount point, to look up `..` from the root.
+    parent: Mutex<Option<VfsNodeRef>>,
+}
+
+/// An ext2/3/4 filesystem.
+pub struct ExtFileSystem {
+    shared: Arc<Shared>,
+    fs_type: &'static str,
+}
+
+/// A file or directory in an ext filesystem.
+pub struct ExtNode {
+    shared: Arc<Shared>,
+    ino: u32,
+}
+
+impl ExtFileSystem {
+    /// Whether the disk contains an ext filesystem.
+    pub fn probe(disk: &mut Disk) -> bool {
+        Volume::probe(disk).is_some()
+    }
+
+    /// Opens the ext filesystem on the disk.
+    pub fn new(disk: Disk) -> VfsResult<Self> {
+        let volume = Volume::open(disk)?;
+        let fs_type = volume.fs_type();
+        Ok(Self {
+            shared: Arc::new(Shared {
+                volume: Mutex::new(volume),
+                parent: Mutex::new(None),
+            }),
+            fs_type,
+        })
+    }
+
+    /// Returns `ext2`, `ext3` or `ext4` by the features of the volume.
+    pub fn fs_type(&self) -> &'static str {
+        self.fs_type
+    }
+
+    /// Whether the volume is mounted read-only.
+    pub fn is_read_only(&self) -> bool {
+        self.shared.volume.lock().is_read_only()
+    }
+}
+
+impl VfsOps for ExtFileSystem {
+    fn mount(&self, _path: &str, mount_point: VfsNodeRef) -> VfsResult {
+        *self.shared.parent.lock() = mount_point.parent();
+        Ok(())
+    }
+
+    fn umount(&self) -> VfsResult {
+        self.shared.volume.lock().flush()
+    }
+
+    fn root_dir(&self) -> VfsNodeRef {
+        ExtNode::new(&self.shared, ROOT_INO)
+    }
+}
+
+/// Splits `path` into the parent part and the last component.
+fn split_parent(path: &str) -> (&str, &str) {
+    let path = path.trim_end_matches('/');
+    match path.rfind('/') {
+        Some(pos) => (&path[..pos], &path[pos + 1..]),
+        None => ("", path),
+    }
+}
+
+impl ExtNode {
+    fn new(shared: &Arc<Shared>, ino: u32) -> Arc<Self> {
+        Arc::new(Self {
+            shared: shared.clone(),
+            ino,
+        })
+    }
+
+    /// Looks up `path` relative to this node. `..` at the root goes to the
+    /// parent of the mount point, which is returned with the rest path.
+    fn walk<'a>(&self, path: &'a str) -> VfsResult<Result<u32, (VfsNodeRef, &'a str)>> {
+        let mut volume = self.shared.volume.lock();
+        let mut ino = self.ino;
+        let mut rest = path.trim_start_matches('/');
+        while !rest.is_empty() {
+            let (name, next) = rest.split_once('/').unwrap_or((rest, ""));
+            rest = next.trim_start_matches('/');
+            match name {
+                "" | "." => continue,
+                ".." if ino == ROOT_INO => {
+                    if let Some(parent) = self.shared.parent.lock().clone() {
+                        return Ok(Err((parent, rest)));
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+            let mut dir = volume.read_inode(ino)?;
+            ino = volume
+                .find_entry(&mut dir, name)?
+                .ok_or(VfsError::NotFound)?;
+        }
+        Ok(Ok(ino))
+    }
+
+    /// Looks up the directory containing `path` in this filesystem, returns
+    /// its inode number and the last component.
+    fn parent_of<'a>(&self, path: &'a str) -> VfsResult<(u32, &'a str)> {
+        let (parent, name) = split_parent(path);
+        if name.is_empty() || name == "." || name == ".." {
+            return Err(VfsError::InvalidInput);
+        }
+        match self.walk(parent)? {
+            Ok(ino) => Ok((ino, name)),
+            // other filesystems are not accessible
+            Err(_) => Err(VfsError::PermissionDenied),
+        }
+    }
+
+    /// Returns the identity of the inode, with the address of the
+    /// filesystem.
+    pub(crate) fn file_id(&self) -> FileId {
+        FileId::Inode(Arc::as_ptr(&self.shared) as usize, self.ino as u64)
+    }
+
+    /// Returns the timestamps and ownership of the inode.
+    pub fn meta(&self) -> VfsResult<NodeMeta> {
+        let inode = self.shared.volume.lock().read_inode(self.ino)?;
+        Ok(NodeMeta {
+            atime: inode.atime(),
+            mtime: inode.mtime(),
+            ctime: inode.ctime(),
+            uid: inode.uid(),
+            gid: inode.gid(),
+        })
+    }
+}
+
+impl LinkOps for ExtNode {
+    fn link(&self, src_path: &str, dst_path: &str) -> VfsResult {
+        let Ok(ino) = self.walk(src_path)? else {
+            return Err(VfsError::PermissionDenied);
+        };
+        let (parent, name) = self.parent_of(dst_path)?;
+        let mut volume = self.shared.volume.lock();
+        let mut dir = volume.read_inode(parent)?;
+        volume.link(&mut dir, name, ino)
+    }
+}
+
+impl VfsNodeOps for ExtNode {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        let mut volume = self.shared.volume.lock();
+        let inode = volume.read_inode(self.ino)?;
+        let perm = VfsNodePerm::from_bits_truncate(inode.mode() & 0o777);
+        let blocks = inode.sectors(volume.block_size());
+        Ok(VfsNodeAttr::new(
+            perm,
+            inode.node_type(),
+            inode.size(),
+            blocks,
+        ))
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        let mut volume = self.shared.volume.lock();
+        let mut inode = volume.read_inode(self.ino)?;
+        if inode.is_dir() {
+            return Err(VfsError::IsADirectory);
+        }
+        volume.read_data(&mut inode, offset, buf)
+    }
+
+    fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        let mut volume = self.shared.volume.lock();
+        let mut inode = volume.read_inode(self.ino)?;
+        if inode.is_dir() {
+            return Err(VfsError::IsADirectory);
+        }
+        volume.write_data(&mut inode, offset, buf)
+    }
+
+    fn fsync(&self) -> VfsResult {
+        self.shared.volume.lock().flush()
+    }
+
+    fn truncate(&self, size: u64) -> VfsResult {
+        let mut volume = self.shared.volume.lock();
+        let mut inode = volume.read_inode(self.ino)?;
+        if inode.is_dir() {
+            return Err(VfsError::IsADirectory);
+        }
+        volume.truncate(&mut inode, size)
+    }
+
+    fn parent(&self) -> Option<VfsNodeRef> {
+        match self.walk("..").ok()? {
+            Ok(ino) => Some(ExtNode::new(&self.shared, ino)),
+            Err((parent, _)) => Some(parent),
+        }
+    }
+
+    fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
+        debug!("lookup at extfs: {}", path);
+        match self.walk(path)? {
+            Ok(ino) if ino == self.ino => Ok(self),
+            Ok(ino) => Ok(ExtNode::new(&self.shared, ino)),
+            Err((parent, rest)) => parent.lookup(rest),
+        }
+    }
+
+    fn create(&self, path: &str, ty: VfsNodeType) -> VfsResult {
+        debug!("create {:?} at extfs: {}", ty, path);
+        let (parent, name) = self.parent_of(path)?;
+        let mut volume = self.shared.volume.lock();
+        let mut dir = volume.read_inode(parent)?;
+        volume.create(&mut dir, name, ty).map(|_| ())
+    }
+
+    fn remove(&self, path: &str) -> VfsResult {
+        debug!("remove at extfs: {}", path);
+        let (parent, name) = self.parent_of(path)?;
+        let mut volume = self.shared.volume.lock();
+        let mut dir = volume.read_inode(parent)?;
+        volume.remove(&mut dir, name)
+    }
+
+    fn read_dir(&self, start_idx: usize, dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
+        let mut volume = self.shared.volume.lock();
+        let mut dir = volume.read_inode(self.ino)?;
+        let entries: Vec<(String, u32, u8)> = volume.read_dir(&mut dir)?;
+        let mut count = 0;
+        for ((name, ino, file_type), out) in entries.iter().skip(start_idx).zip(dirents.iter_mut())
+        {
+            let ty = match dirent_type_to_node_type(*file_type) {
+                Some(ty) => ty,
+                None => volume.read_inode(*ino)?.node_type(),
+            };
+            *out = VfsDirEntry::new(name, ty);
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    fn rename(&self, src_path: &str, dst_path: &str) -> VfsResult {
+        debug!(
+            "rename at extfs, src_path: {}, dst_path: {}",
+            src_path, dst_path
+        );
+        let (src_parent, src_name) = self.parent_of(src_path)?;
+        let (dst_parent, dst_name) = self.parent_of(dst_path)?;
+        let mut volume = self.shared.volume.lock();
+        let mut src_dir = volume.read_inode(src_parent)?;
+        if src_parent == dst_parent {
+            return volume.rename(&mut src_dir, src_name, None, dst_name);
+        }
+        let mut dst_dir = volume.read_inode(dst_parent)?;
+        volume.rename(&mut src_dir, src_name, Some(&mut dst_dir), dst_name)
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+}
@@ -0,0 +1,914 @@
+//! Block, inode and directory operations on an ext2/3/4 volume.
+
+use alloc::{string::String, vec, vec::Vec};
+
+use axfs_vfs::{VfsError, VfsNodeType, VfsResult};
+
+use super::layout::*;
+use crate::dev::Disk;
+use crate::fs::current_time;
+
+/// The state of a mounted volume, protected by the filesystem lock.
+pub struct Volume {
+    disk: Disk,
+    sb: Superblock,
+    /// The raw superblock, to write back the free counts.
+    sb_raw: Vec<u8>,
+    groups: Vec<GroupDesc>,
+    block_size: usize,
+    /// The first block of the group descriptor table.
+    gdt_block: u64,
+    read_only: bool,
+}
+
+fn io_err<E>(_: E) -> VfsError {
+    VfsError::Io
+}
+
+/// Sets the modification and change times of `inode` to now.
+fn touch_modified(inode: &mut Inode) {
+    let now = current_time();
+    inode.set_mtime(now);
+    inode.set_ctime(now);
+}
+
+impl Volume {
+    /// Reads the superblock of `disk`, returns [`None`] if it is not an ext
+    /// filesystem.
+    pub fn probe(disk: &mut Disk) -> Option<Superblock> {
+        let mut raw = vec![0; SUPERBLOCK_SIZE];
+        disk.set_position(SUPERBLOCK_OFFSET);
+        read_exact(disk, &mut raw).ok()?;
+        let sb = Superblock::parse(&raw);
+        disk.set_position(0);
+        (sb.magic == EXT_MAGIC).then_some(sb)
+    }
+
+    pub fn open(mut disk: Disk) -> VfsResult<Self> {
+        let mut sb_raw = vec![0; SUPERBLOCK_SIZE];
+        disk.set_position(SUPERBLOCK_OFFSET);
+        read_exact(&mut disk, &mut sb_raw)?;
+        let sb = Superblock::parse(&sb_raw);
+        if sb.magic != EXT_MAGIC {
+            return Err(VfsError::InvalidData);
+        }
+        let unsupported = sb.feature_incompat & !INCOMPAT_SUPPORTED;
+        if unsupported != 0 || sb.feature_incompat & INCOMPAT_META_BG != 0 {
+            warn!(
+                "extfs: unsupported incompatible features {:#x}",
+                unsupported
+            );
+            return Err(VfsError::Unsupported);
+        }
+        if sb.log_block_size > 6
+            || sb.blocks_per_group == 0
+            || sb.inodes_per_group == 0
+            || sb.inode_size < 128
+            || !sb.inode_size.is_power_of_two()
+            || (sb.desc_size as usize) < 32
+        {
+            return Err(VfsError::InvalidData);
+        }
+        if sb.feature_incompat & INCOMPAT_RECOVER != 0 {
+            warn!("extfs: the journal needs recovery, recent changes may be missing");
+        }
+        let read_only = sb.feature_incompat & !INCOMPAT_WRITABLE != 0
+            || sb.feature_ro_compat & !RO_COMPAT_WRITABLE != 0;
+
+        let block_size = 1024 << sb.log_block_size;
+        let gdt_block = sb.first_data_block as u64 + 1;
+        let num_groups = (sb.blocks_count - sb.first_data_block as u64)
+            .div_ceil(sb.blocks_per_group as u64) as usize;
+        let desc_size = sb.desc_size as usize;
+        let mut gdt = vec![0; num_groups * desc_size];
+        disk.set_position(gdt_block * block_size as u64);
+        read_exact(&mut disk, &mut gdt)?;
+        let groups = gdt.chunks_exact(desc_size).map(GroupDesc::parse).collect();
+
+        info!(
+            "extfs: {} blocks of {} bytes, {} groups, features {:#x}/{:#x}/{:#x}{}",
+            sb.blocks_count,
+            block_size,
+            num_groups,
+            sb.feature_compat,
+            sb.feature_incompat,
+            sb.feature_ro_compat,
+            if read_only { ", read-only" } else { "" }
+        );
+        Ok(Self {
+            disk,
+            sb,
+            sb_raw,
+            groups,
+            block_size,
+            gdt_block,
+            read_only,
+        })
+    }
+
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Returns the filesystem type name by its features.
+    pub fn fs_type(&self) -> &'static str {
+        if self.sb.feature_incompat & (INCOMPAT_EXTENTS | INCOMPAT_64BIT | INCOMPAT_FLEX_BG) != 0 {
+            "ext4"
+        } else if self.sb.feature_compat & COMPAT_HAS_JOURNAL != 0 {
+            "ext3"
+        } else {
+            "ext2"
+        }
+    }
+
+    pub fn flush(&mut self) -> VfsResult {
+        self.disk.flush().map_err(io_err)
+    }
+
+    fn check_writable(&self) -> VfsResult {
+        if self.read_only {
+            Err(VfsError::PermissionDenied)
+        } else {
+            Ok(())
+        }
+    }
+
+    // ---- raw I/O ----
+
+    fn read_at(&mut self, pos: u64, buf: &mut [u8]) -> VfsResult {
+        self.disk.set_position(pos);
+        read_exact(&mut self.disk, buf)
+    }
+
+    fn write_at(&mut self, pos: u64, buf: &[u8]) -> VfsResult {
+        self.disk.set_position(pos);
+        let mut buf = buf;
+        while !buf.is_empty() {
+            match self.disk.write_one(buf).map_err(io_err)? {
+                0 => return Err(VfsError::WriteZero),
+                n => buf = &buf[n..],
+            }
+        }
+        Ok(())
+    }
+
+    fn read_block(&mut self, block: u64, buf: &mut [u8]) -> VfsResult {
+        if block >= self.sb.blocks_count {
+            return Err(VfsError::InvalidData);
+        }
+        self.read_at(block * self.block_size as u64, buf)
+    }
+
+    fn write_block(&mut self, block: u64, buf: &[u8]) -> VfsResult {
+        self.write_at(block * self.block_size as u64, buf)
+    }
+
+    fn read_u32_at(&mut self, block: u64, idx: usize) -> VfsResult<u32> {
+        let mut buf = [0; 4];
+        self.read_at(block * self.block_size as u64 + idx as u64 * 4, &mut buf)?;
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    fn write_u32_at(&mut self, block: u64, idx: usize, val: u32) -> VfsResult {
+        self.write_at(
+            block * self.block_size as u64 + idx as u64 * 4,
+            &val.to_le_bytes(),
+        )
+    }
+
+    // ---- metadata ----
+
+    fn write_super(&mut self) -> VfsResult {
+        self.sb.write_counts(&mut self.sb_raw);
+        let raw = core::mem::take(&mut self.sb_raw);
+        let res = self.write_at(SUPERBLOCK_OFFSET, &raw);
+        self.sb_raw = raw;
+        res
+    }
+
+    fn write_group_desc(&mut self, group: usize) -> VfsResult {
+        let desc_size = self.sb.desc_size as usize;
+        let pos = self.gdt_block * self.block_size as u64 + (group * desc_size) as u64;
+        let mut raw = vec![0; desc_size];
+        self.read_at(pos, &mut raw)?;
+        self.groups[group].write_counts(&mut raw);
+        self.write_at(pos, &raw)
+    }
+
+    fn inode_pos(&self, ino: u32) -> VfsResult<u64> {
+        if ino == 0 || ino > self.sb.inodes_count {
+            return Err(VfsError::InvalidData);
+        }
+        let group = ((ino - 1) / self.sb.inodes_per_group) as usize;
+        let index = ((ino - 1) % self.sb.inodes_per_group) as u64;
+        Ok(self.groups[group].inode_table * self.block_size as u64
+            + index * self.sb.inode_size as u64)
+    }
+
+    pub fn read_inode(&mut self, ino: u32) -> VfsResult<Inode> {
+        let pos = self.inode_pos(ino)?;
+        let mut inode = Inode::new(ino, self.sb.inode_size as usize);
+        self.read_at(pos, &mut inode.raw)?;
+        Ok(inode)
+    }
+
+    pub fn write_inode(&mut self, inode: &Inode) -> VfsResult {
+        let pos = self.inode_pos(inode.ino)?;
+        self.write_at(pos, &inode.raw)
+    }
+
+    /// Finds a zero bit in the bitmap block, sets it and returns its index.
+    fn alloc_bit(&mut self, bitmap_block: u64, limit: usize) -> VfsResult<Option<usize>> {
+        let mut bitmap = vec![0; self.block_size];
+        self.read_block(bitmap_block, &mut bitmap)?;
+        let Some(bit) = (0..limit).find(|&i| bitmap[i / 8] & (1 << (i % 8)) == 0) else {
+            return Ok(None);
+        };
+        bitmap[bit / 8] |= 1 << (bit % 8);
+        self.write_block(bitmap_block, &bitmap)?;
+        Ok(Some(bit))
+    }
+
+    fn free_bit(&mut self, bitmap_block: u64, bit: usize) -> VfsResult {
+        let mut bitmap = vec![0; self.block_size];
+        self.read_block(bitmap_block, &mut bitmap)?;
+        bitmap[bit / 8] &= !(1 << (bit % 8));
+        self.write_block(bitmap_block, &bitmap)
+    }
+
+    /// Allocates a zeroed block, preferably in the group of `goal_ino`.
+    fn alloc_block(&mut self, goal_ino: u32) -> VfsResult<u32> {
+        let num_groups = self.groups.len();
+        let goal = ((goal_ino - 1) / self.sb.inodes_per_group) as usize;
+        let bpg = self.sb.blocks_per_group as u64;
+        let first = self.sb.first_data_block as u64;
+        for group in (goal..num_groups).chain(0..goal) {
+            if self.groups[group].free_blocks_count == 0 {
+                continue;
+            }
+            let limit = bpg.min(self.sb.blocks_count - first - group as u64 * bpg) as usize;
+            let bitmap = self.groups[group].block_bitmap;
+            if let Some(bit) = self.alloc_bit(bitmap, limit)? {
+                self.groups[group].free_blocks_count -= 1;
+                self.sb.free_blocks_count -= 1;
+                self.write_group_desc(group)?;
+                self.write_super()?;
+                let block = first + group as u64 * bpg + bit as u64;
+                self.write_block(block, &vec![0; self.block_size])?;
+                return Ok(block as u32);
+            }
+        }
+        Err(VfsError::StorageFull)
+    }
+
+    fn free_block(&mut self, block: u32) -> VfsResult {
+        let rel = block as u64 - self.sb.first_data_block as u64;
+        let bpg = self.sb.blocks_per_group as u64;
+        let group = (rel / bpg) as usize;
+        let bitmap = self.groups[group].block_bitmap;
+        self.free_bit(bitmap, (rel % bpg) as usize)?;
+        self.groups[group].free_blocks_count += 1;
+        self.sb.free_blocks_count += 1;
+        self.write_group_desc(group)?;
+        self.write_super()
+    }
+
+    fn alloc_inode(&mut self, parent_ino: u32, is_dir: bool) -> VfsResult<u32> {
+        let ipg = self.sb.inodes_per_group;
+        let goal = ((parent_ino - 1) / ipg) as usize;
+        let num_groups = self.groups.len();
+        for group in (goal..num_groups).chain(0..goal) {
+            if self.groups[group].free_inodes_count == 0 {
+                continue;
+            }
+            let bitmap = self.groups[group].inode_bitmap;
+            if let Some(bit) = self.alloc_bit(bitmap, ipg as usize)? {
+                let ino = group as u32 * ipg + bit as u32 + 1;
+                if ino < self.sb.first_ino {
+                    // reserved inodes should have been marked in use
+                    return Err(VfsError::InvalidData);
+                }
+                self.groups[group].free_inodes_count -= 1;
+                if is_dir {
+                    self.groups[group].used_dirs_count += 1;
+                }
+                self.sb.free_inodes_count -= 1;
+                self.write_group_desc(group)?;
+                self.write_super()?;
+                return Ok(ino);
+            }
+        }
+        Err(VfsError::StorageFull)
+    }
+
+    fn free_inode(&mut self, ino: u32, is_dir: bool) -> VfsResult {
+        let ipg = self.sb.inodes_per_group;
+        let group = ((ino - 1) / ipg) as usize;
+        let bitmap = self.groups[group].inode_bitmap;
+        self.free_bit(bitmap, ((ino - 1) % ipg) as usize)?;
+        self.groups[group].free_inodes_count += 1;
+        if is_dir {
+            self.groups[group].used_dirs_count -= 1;
+        }
+        self.sb.free_inodes_count += 1;
+        self.write_group_desc(group)?;
+        self.write_super()
+    }
+
+    // ---- block mapping ----
+
+    /// Maps the logical block `lblk` of `inode` to a physical block, returns
+    /// [`None`] for a hole. If `create` is true, the hole is filled with a
+    /// newly allocated block, and `inode` is updated but not written.
+    pub fn bmap(&mut self, inode: &mut Inode, lblk: u64, create: bool) -> VfsResult<Option<u64>> {
+        if inode.flags() & INODE_FLAG_INLINE_DATA != 0 {
+            return Err(VfsError::Unsupported);
+        }
+        if inode.uses_extents() {
+            if create {
+                return Err(VfsError::PermissionDenied);
+            }
+            return self.extent_map(inode, lblk);
+        }
+
+        let per = (self.block_size / 4) as u64;
+        let (slot, path): (usize, Vec<usize>) = if lblk < DIRECT_BLOCKS as u64 {
+            (lblk as usize, vec![])
+        } else if lblk - (DIRECT_BLOCKS as u64) < per {
+            let l = lblk - DIRECT_BLOCKS as u64;
+            (12, vec![l as usize])
+        } else if lblk - DIRECT_BLOCKS as u64 - per < per * per {
+            let l = lblk - DIRECT_BLOCKS as u64 - per;
+            (13, vec![(l / per) as usize, (l % per) as usize])
+        } else if lblk - DIRECT_BLOCKS as u64 - per - per * per < per * per * per {
+            let l = lblk - DIRECT_BLOCKS as u64 - per - per * per;
+            let idx = [l / (per * per), (l / per) % per, l % per];
+            (14, idx.iter().map(|&i| i as usize).collect())
+        } else {
+            return Err(VfsError::InvalidInput);
+        };
+
+        let sectors_per_block = (self.block_size / 512) as u64;
+        let mut ptr = inode.block(slot);
+        if ptr == 0 {
+            if !create {
+                return Ok(None);
+            }
+            ptr = self.alloc_block(inode.ino)?;
+            inode.set_block(slot, ptr);
+            inode.set_sectors(inode.sectors(self.block_size) + sectors_per_block);
+        }
+        for idx in path {
+            let mut next = self.read_u32_at(ptr as u64, idx)?;
+            if next == 0 {
+                if !create {
+                    return Ok(None);
+                }
+                next = self.alloc_block(inode.ino)?;
+                self.write_u32_at(ptr as u64, idx, next)?;
+                inode.set_sectors(inode.sectors(self.block_size) + sectors_per_block);
+            }
+            ptr = next;
+        }
+        Ok(Some(ptr as u64))
+    }
+
+    fn extent_map(&mut self, inode: &Inode, lblk: u64) -> VfsResult<Option<u64>> {
+        let mut node = inode.i_block().to_vec();
+        // The depth is bounded by the format, guard against loops anyway.
+        for _ in 0..8 {
+            if read_u16(&node, 0) != EXTENT_MAGIC {
+                return Err(VfsError::InvalidData);
+            }
+            let entries = read_u16(&node, 2) as usize;
+            let depth = read_u16(&node, 6);
+            if 12 + entries * 12 > node.len() {
+                return Err(VfsError::InvalidData);
+            }
+            let entry = |i: usize| &node[12 + i * 12..24 + i * 12];
+            if depth == 0 {
+                for e in (0..entries).map(entry) {
+                    let start = read_u32(e, 0) as u64;
+                    let len = read_u16(e, 4);
+                    // the highest bit marks an uninitialized extent
+                    let (len, uninit) = if len > 32768 {
+                        (len - 32768, true)
+                    } else {
+                        (len, false)
+                    };
+                    if (start..start + len as u64).contains(&lblk) {
+                        if uninit {
+                            return Ok(None);
+                        }
+                        let phys = lo_hi(read_u32(e, 8), read_u16(e, 6) as u32);
+                        return Ok(Some(phys + lblk - start));
+                    }
+                }
+                return Ok(None);
+            }
+            let child = (0..entries)
+                .map(entry)
+                .take_while(|e| read_u32(e, 0) as u64 <= lblk)
+                .last()
+                .map(|e| lo_hi(read_u32(e, 4), read_u16(e, 8) as u32));
+            let Some(child) = child else {
+                return Ok(None);
+            };
+            node = vec![0; self.block_size];
+            self.read_block(child, &mut node)?;
+        }
+        Err(VfsError::InvalidData)
+    }
+
+    /// Frees the blocks at logical blocks `keep..` under the block pointer
+    /// `ptr`, which is at the given indirection `level` and covers the
+    /// logical blocks from `base`. Returns whether `ptr` itself is freed.
+    fn free_tree(
+        &mut self,
+        inode: &mut Inode,
+        ptr: u32,
+        level: u32,
+        base: u64,
+        keep: u64,
+    ) -> VfsResult<bool> {
+        if ptr == 0 {
+            return Ok(true);
+        }
+        let per = (self.block_size / 4) as u64;
+        let span = per.pow(level);
+        if base + span <= keep {
+            return Ok(false);
+        }
+        if level > 0 {
+            let mut table = vec![0; self.block_size];
+            self.read_block(ptr as u64, &mut table)?;
+            let child_span = per.pow(level - 1);
+            let mut modified = false;
+            for i in 0..per as usize {
+                let child = read_u32(&table, i * 4);
+                let child_base = base + i as u64 * child_span;
+                if child != 0 && self.free_tree(inode, child, level - 1, child_base, keep)? {
+                    write_u32(&mut table, i * 4, 0);
+                    modified = true;
+                }
+            }
+            if base < keep {
+                if modified {
+                    self.write_block(ptr as u64, &table)?;
+                }
+                return Ok(false);
+            }
+        } else if base < keep {
+            return Ok(false);
+        }
+        self.free_block(ptr)?;
+        let sectors = inode.sectors(self.block_size);
+        inode.set_sectors(sectors.saturating_sub((self.block_size / 512) as u64));
+        Ok(true)
+    }
+
+    /// Frees the blocks of `inode` beyond `size` bytes, and sets its size.
+    fn truncate_blocks(&mut self, inode: &mut Inode, size: u64) -> VfsResult {
+        let keep = size.div_ceil(self.block_size as u64);
+        let per = (self.block_size / 4) as u64;
+        let mut base = 0;
+        for slot in 0..DIRECT_BLOCKS + 3 {
+            let level = slot.saturating_sub(DIRECT_BLOCKS - 1) as u32;
+            let ptr = inode.block(slot);
+            if self.free_tree(inode, ptr, level, base, keep)? {
+                inode.set_block(slot, 0);
+            }
+            base += per.pow(level);
+        }
+        inode.set_size(size);
+        Ok(())
+    }
+
+    // ---- file data ----
+
+    /// Reads the data of `inode` at `offset`, returns the number of bytes
+    /// read.
+    pub fn read_data(
+        &mut self,
+        inode: &mut Inode,
+        offset: u64,
+        buf: &mut [u8],
+    ) -> VfsResult<usize> {
+        let size = inode.size();
+        if offset >= size {
+            return Ok(0);
+        }
+        if inode.is_fast_symlink() {
+            let target = &inode.i_block()[..size as usize];
+            let n = buf.len().min(target.len() - offset as usize);
+            buf[..n].copy_from_slice(&target[offset as usize..offset as usize + n]);
+            return Ok(n);
+        }
+        let len = buf.len().min((size - offset) as usize);
+        let bs = self.block_size as u64;
+        let mut done = 0;
+        while done < len {
+            let pos = offset + done as u64;
+            let in_block = (pos % bs) as usize;
+            let n = (len - done).min(self.block_size - in_block);
+            let out = &mut buf[done..done + n];
+            match self.bmap(inode, pos / bs, false)? {
+                Some(block) => self.read_at(block * bs + in_block as u64, out)?,
+                None => out.fill(0),
+            }
+            done += n;
+        }
+        Ok(len)
+    }
+
+    /// Writes the data of `inode` at `offset`, and writes back the inode.
+    pub fn write_data(&mut self, inode: &mut Inode, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        self.check_writable()?;
+        if inode.is_fast_symlink() && inode.size() != 0 {
+            // the target is written only once
+            return Err(VfsError::PermissionDenied);
+        } else if inode.is_fast_symlink() && offset == 0 && buf.len() < INODE_BLOCK_SIZE {
+            // a short target is stored in `i_block`
+            inode.i_block_mut()[..buf.len()].copy_from_slice(buf);
+            inode.set_size(buf.len() as u64);
+            touch_modified(inode);
+            self.write_inode(inode)?;
+            return Ok(buf.len());
+        }
+        let bs = self.block_size as u64;
+        let mut done = 0;
+        let res = loop {
+            if done == buf.len() {
+                break Ok(());
+            }
+            let pos = offset + done as u64;
+            let in_block = (pos % bs) as usize;
+            let n = (buf.len() - done).min(self.block_size - in_block);
+            let block = match self.bmap(inode, pos / bs, true) {
+                Ok(block) => block.unwrap(),
+                Err(e) => break Err(e),
+            };
+            if let Err(e) = self.write_at(block * bs + in_block as u64, &buf[done..done + n]) {
+                break Err(e);
+            }
+            done += n;
+        };
+        if offset + done as u64 > inode.size() {
+            inode.set_size(offset + done as u64);
+        }
+        if done != 0 {
+            touch_modified(inode);
+        }
+        self.write_inode(inode)?;
+        match res {
+            Err(e) if done == 0 => Err(e),
+            _ => Ok(done),
+        }
+    }
+
+    pub fn truncate(&mut self, inode: &mut Inode, size: u64) -> VfsResult {
+        self.check_writable()?;
+        if inode.uses_extents() || inode.is_fast_symlink() {
+            return Err(VfsError::PermissionDenied);
+        }
+        if size < inode.size() {
+            // zero the tail of the last block, which may be read after growing
+            let bs = self.block_size as u64;
+            let tail = (size % bs) as usize;
+            if tail != 0 {
+                if let Some(block) = self.bmap(inode, size / bs, false)? {
+                    let zeros = vec![0; self.block_size - tail];
+                    self.write_at(block * bs + tail as u64, &zeros)?;
+                }
+            }
+            self.truncate_blocks(inode, size)?;
+        } else {
+            inode.set_size(size);
+        }
+        touch_modified(inode);
+        self.write_inode(inode)
+    }
+
+    // ---- directories ----
+
+    /// Calls `f` on each entry of the directory, as `(lblk, block data,
+    /// offset, entry)`, until it returns `Some`.
+    fn walk_dir<T>(
+        &mut self,
+        dir: &mut Inode,
+        mut f: impl FnMut(u64, &[u8], usize, &RawDirEntry) -> Option<T>,
+    ) -> VfsResult<Option<T>> {
+        if !dir.is_dir() {
+            return Err(VfsError::NotADirectory);
+        }
+        let num_blocks = dir.size().div_ceil(self.block_size as u64);
+        let mut block = vec![0; self.block_size];
+        for lblk in 0..num_blocks {
+            let Some(phys) = self.bmap(dir, lblk, false)? else {
+                continue;
+            };
+            self.read_block(phys, &mut block)?;
+            for (offset, entry) in dir_block_entries(&block) {
+                if let Some(res) = f(lblk, &block, offset, &entry) {
+                    return Ok(Some(res));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the inode number of the entry `name` in `dir`.
+    pub fn find_entry(&mut self, dir: &mut Inode, name: &str) -> VfsResult<Option<u32>> {
+        self.walk_dir(dir, |_, _, _, entry| {
+            (entry.ino != 0 && entry.name == name.as_bytes()).then_some(entry.ino)
+        })
+    }
+
+    /// Returns the entries of a directory, including `.` and `..`.
+    pub fn read_dir(&mut self, dir: &mut Inode) -> VfsResult<Vec<(String, u32, u8)>> {
+        let mut entries = Vec::new();
+        self.walk_dir(dir, |_, _, _, entry| {
+            if entry.ino != 0 {
+                let name = String::from_utf8_lossy(entry.name).into_owned();
+                entries.push((name, entry.ino, entry.file_type));
+            }
+            None::<()>
+        })?;
+        Ok(entries)
+    }
+
+    fn file_type_byte(&self, ty: VfsNodeType) -> u8 {
+        if self.sb.feature_incompat & INCOMPAT_FILETYPE != 0 {
+            dirent_file_type(ty)
+        } else {
+            0
+        }
+    }
+
+    /// Adds an entry to the directory, and writes back the directory inode.
+    fn add_entry(&mut self, dir: &mut Inode, name: &str, ino: u32, ty: VfsNodeType) -> VfsResult {
+        if name.is_empty() || name.len() > 255 {
+            return Err(VfsError::InvalidInput);
+        }
+        let needed = dirent_size(name.len());
+        let file_type = self.file_type_byte(ty);
+        // Entries are added linearly, which invalidates the hash index.
+        dir.set_flags(dir.flags() & !INODE_FLAG_INDEX);
+
+        let found = self.walk_dir(dir, |lblk, _, offset, entry| {
+            let used = if entry.ino == 0 {
+                0
+            } else {
+                dirent_size(entry.name.len())
+            };
+            (entry.rec_len - used >= needed).then_some((lblk, offset, used, entry.rec_len))
+        })?;
+        let bs = self.block_size;
+        let mut block = vec![0; bs];
+        match found {
+            Some((lblk, offset, used, rec_len)) => {
+                let phys = self.bmap(dir, lblk, false)?.unwrap();
+                self.read_block(phys, &mut block)?;
+                if used != 0 {
+                    // split the entry
+                    write_u16(&mut block, offset + 4, used as u16);
+                }
+                let new_offset = offset + used;
+                write_dirent(
+                    &mut block,
+                    new_offset,
+                    ino,
+                    rec_len - used,
+                    file_type,
+                    name.as_bytes(),
+                );
+                self.write_block(phys, &block)?;
+            }
+            None => {
+                let lblk = dir.size() / bs as u64;
+                let phys = self.bmap(dir, lblk, true)?.unwrap();
+                write_dirent(&mut block, 0, ino, bs, file_type, name.as_bytes());
+                self.write_block(phys, &block)?;
+                dir.set_size((lblk + 1) * bs as u64);
+            }
+        }
+        touch_modified(dir);
+        self.write_inode(dir)
+    }
+
+    /// Removes an entry from the directory.
+    fn remove_entry(&mut self, dir: &mut Inode, name: &str) -> VfsResult {
+        let mut prev = None;
+        let found = self.walk_dir(dir, |lblk, _, offset, entry| {
+            if offset == 0 {
+                prev = None;
+            }
+            if entry.ino != 0 && entry.name == name.as_bytes() {
+                return Some((lblk, offset, prev));
+            }
+            prev = Some(offset);
+            None
+        })?;
+        let Some((lblk, offset, prev)) = found else {
+            return Err(VfsError::NotFound);
+        };
+        let phys = self.bmap(dir, lblk, false)?.unwrap();
+        let mut block = vec![0; self.block_size];
+        self.read_block(phys, &mut block)?;
+        match prev {
+            // merge into the previous entry
+            Some(prev) => {
+                let rec_len = read_u16(&block, prev + 4) + read_u16(&block, offset + 4);
+                write_u16(&mut block, prev + 4, rec_len);
+            }
+            // the first entry in the block is marked unused
+            None => write_u32(&mut block, offset, 0),
+        }
+        dir.set_flags(dir.flags() & !INODE_FLAG_INDEX);
+        self.write_block(phys, &block)?;
+        touch_modified(dir);
+        self.write_inode(dir)
+    }
+
+    fn is_empty_dir(&mut self, dir: &mut Inode) -> VfsResult<bool> {
+        let other = self.walk_dir(dir, |_, _, _, entry| {
+            (entry.ino != 0 && entry.name != b"." && entry.name != b"..").then_some(())
+        })?;
+        Ok(other.is_none())
+    }
+
+    /// Creates a file, directory or symbolic link named `name` in `parent`.
+    pub fn create(&mut self, parent: &mut Inode, name: &str, ty: VfsNodeType) -> VfsResult<u32> {
+        self.check_writable()?;
+        if self.find_entry(parent, name)?.is_some() {
+            return Err(VfsError::AlreadyExists);
+        }
+        let (mode, is_dir) = match ty {
+            VfsNodeType::File => (S_IFREG | 0o644, false),
+            VfsNodeType::Dir => (S_IFDIR | 0o755, true),
+            VfsNodeType::SymLink => (S_IFLNK | 0o777, false),
+            _ => return Err(VfsError::Unsupported),
+        };
+        let ino = self.alloc_inode(parent.ino, is_dir)?;
+        let mut inode = Inode::new(ino, self.sb.inode_size as usize);
+        inode.set_mode(mode);
+        let now = current_time();
+        inode.set_atime(now);
+        inode.set_ctime(now);
+        inode.set_mtime(now);
+        if is_dir {
+            let bs = self.block_size;
+            let phys = self.bmap(&mut inode, 0, true)?.unwrap();
+            let mut block = vec![0; bs];
+            let dot_len = dirent_size(1);
+            let dir_type = self.file_type_byte(VfsNodeType::Dir);
+            write_dirent(&mut block, 0, ino, dot_len, dir_type, b".");
+            write_dirent(
+                &mut block,
+                dot_len,
+                parent.ino,
+                bs - dot_len,
+                dir_type,
+                b"..",
+            );
+            self.write_block(phys, &block)?;
+            inode.set_size(bs as u64);
+            inode.set_links_count(2);
+            parent.set_links_count(parent.links_count() + 1);
+        } else {
+            inode.set_links_count(1);
+        }
+        self.write_inode(&inode)?;
+        self.add_entry(parent, name, ino, ty)?;
+        Ok(ino)
+    }
+
+    /// Adds the entry `name` in `dir` for the inode `ino`, which must not be a
+    /// directory.
+    pub fn link(&mut self, dir: &mut Inode, name: &str, ino: u32) -> VfsResult {
+        self.check_writable()?;
+        if self.find_entry(dir, name)?.is_some() {
+            return Err(VfsError::AlreadyExists);
+        }
+        let mut inode = self.read_inode(ino)?;
+        if inode.is_dir() {
+            return Err(VfsError::PermissionDenied);
+        } else if inode.links_count() == u16::MAX {
+            return Err(VfsError::StorageFull);
+        }
+        inode.set_links_count(inode.links_count() + 1);
+        inode.set_ctime(current_time());
+        self.write_inode(&inode)?;
+        self.add_entry(dir, name, ino, inode.node_type())
+    }
+
+    /// Removes the entry `name` from `parent`, and frees the inode if it has
+    /// no links.
+    pub fn remove(&mut self, parent: &mut Inode, name: &str) -> VfsResult {
+        self.check_writable()?;
+        if name == "." || name == ".." {
+            return Err(VfsError::InvalidInput);
+        }
+        let ino = self.find_entry(parent, name)?.ok_or(VfsError::NotFound)?;
+        let mut inode = self.read_inode(ino)?;
+        let is_dir = inode.is_dir();
+        if is_dir && !self.is_empty_dir(&mut inode)? {
+            return Err(VfsError::DirectoryNotEmpty);
+        }
+        self.remove_entry(parent, name)?;
+        if is_dir {
+            parent.set_links_count(parent.links_count().saturating_sub(1));
+            self.write_inode(parent)?;
+            inode.set_links_count(0);
+        } else {
+            inode.set_links_count(inode.links_count().saturating_sub(1));
+        }
+        if inode.links_count() == 0 {
+            if !inode.uses_extents() && !inode.is_fast_symlink() {
+                self.truncate_blocks(&mut inode, 0)?;
+            }
+            // Values below the inode count are taken as orphan list links by
+            // fsck, which happens if the clock is not set.
+            let now = current_time().as_secs() as u32;
+            inode.set_dtime(now.max(self.sb.inodes_count));
+            self.write_inode(&inode)?;
+            self.free_inode(inode.ino, is_dir)
+        } else {
+            inode.set_ctime(current_time());
+            self.write_inode(&inode)
+        }
+    }
+
+    /// Moves the entry `src_name` in `src_dir` to `dst_name` in `dst_dir`
+    /// (`src_dir` if [`None`]), which must not exist.
+    pub fn rename(
+        &mut self,
+        src_dir: &mut Inode,
+        src_name: &str,
+        dst_dir: Option<&mut Inode>,
+        dst_name: &str,
+    ) -> VfsResult {
+        self.check_writable()?;
+        let src_ino = self
+            .find_entry(src_dir, src_name)?
+            .ok_or(VfsError::NotFound)?;
+        let Some(dst_dir) = dst_dir else {
+            if self.find_entry(src_dir, dst_name)?.is_some() {
+                return Err(VfsError::AlreadyExists);
+            }
+            let ty = self.read_inode(src_ino)?.node_type();
+            self.add_entry(src_dir, dst_name, src_ino, ty)?;
+            return self.remove_entry(src_dir, src_name);
+        };
+        if self.find_entry(dst_dir, dst_name)?.is_some() {
+            return Err(VfsError::AlreadyExists);
+        }
+
+        let mut inode = self.read_inode(src_ino)?;
+        if inode.is_dir() {
+            // cannot move a directory into itself
+            let mut ino = dst_dir.ino;
+            while ino != ROOT_INO {
+                if ino == src_ino {
+                    return Err(VfsError::InvalidInput);
+                }
+                let mut dir = self.read_inode(ino)?;
+                ino = self
+                    .find_entry(&mut dir, "..")?
+                    .ok_or(VfsError::InvalidData)?;
+            }
+        }
+        self.add_entry(dst_dir, dst_name, src_ino, inode.node_type())?;
+        self.remove_entry(src_dir, src_name)?;
+        if inode.is_dir() {
+            // update `..` of the moved directory
+            let dotdot = self.walk_dir(&mut inode, |lblk, _, offset, entry| {
+                (entry.name == b"..").then_some((lblk, offset))
+            })?;
+            if let Some((lblk, offset)) = dotdot {
+                let phys = self.bmap(&mut inode, lblk, false)?.unwrap();
+                let pos = phys * self.block_size as u64 + offset as u64;
+                self.write_at(pos, &dst_dir.ino.to_le_bytes())?;
+            }
+            src_dir.set_links_count(src_dir.links_count().saturating_sub(1));
+            dst_dir.set_links_count(dst_dir.links_count() + 1);
+            self.write_inode(src_dir)?;
+            self.write_inode(dst_dir)?;
+        }
+        Ok(())
+    }
+}
+
+fn read_exact(disk: &mut Disk, mut buf: &mut [u8]) -> VfsResult {
+    while !buf.is_empty() {
+        match disk.read_one(buf).map_err(io_err)? {
+            0 => return Err(VfsError::UnexpectedEof),
+            n => buf = &mut buf[n..],
+        }
+    }
+    Ok(())
+}
@@ -1,22 +1,38 @@
 use alloc::sync::Arc;
-use core::cell::UnsafeCell;
+use core::{cell::UnsafeCell, time::Duration};
 
 use axfs_vfs::{VfsDirEntry, VfsError, VfsNodePerm, VfsResult};
 use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType, VfsOps};
 use axsync::Mutex;
-use fatfs::{Dir, File, LossyOemCpConverter, NullTimeProvider, Read, Seek, SeekFrom, Write};
+use fatfs::{Date, DateTime, Dir, DirEntry, File, LossyOemCpConverter, Time, TimeProvider};
+use fatfs::{Read, Seek, SeekFrom, Write};
 
 use crate::dev::Disk;
+use crate::fs::{current_time, NodeMeta};
 
 const BLOCK_SIZE: usize = 512;
+#[cfg(feature = "use-ramdisk")]
+const MAX_SECTOR_SIZE: usize = 4096;
 
 pub struct FatFileSystem {
-    inner: fatfs::FileSystem<Disk, NullTimeProvider, LossyOemCpConverter>,
+    inner: fatfs::FileSystem<Disk, RtcTimeProvider, LossyOemCpConverter>,
     root_dir: UnsafeCell<Option<VfsNodeRef>>,
 }
 
-pub struct FileWrapper<'a>(Mutex<File<'a, Disk, NullTimeProvider, LossyOemCpConverter>>);
-pub struct DirWrapper<'a>(Dir<'a, Disk, NullTimeProvider, LossyOemCpConverter>);
+/// Provides the current time from [`axhal::time::wall_time`], for the
+/// timestamps of directory entries. FAT stores local times, which are taken
+/// as UTC.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RtcTimeProvider;
+
+pub struct FileWrapper<'a>(
+    Mutex<File<'a, Disk, RtcTimeProvider, LossyOemCpConverter>>,
+    Mutex<NodeMeta>,
+);
+pub struct DirWrapper<'a>(
+    Dir<'a, Disk, RtcTimeProvider, LossyOemCpConverter>,
+    NodeMeta,
+);
 
 unsafe impl Sync for FatFileSystem {}
 unsafe impl Send for FatFileSystem {}
@@ -28,10 +44,13 @@ unsafe impl<'a> Sync for DirWrapper<'a> {}
 impl FatFileSystem {
     #[cfg(feature = "use-ramdisk")]
     pub fn new(mut disk: Disk) -> Self {
-        let opts = fatfs::FormatVolumeOptions::new();
+        // FAT sectors are 512 to 4096 bytes, use the device block size if it fits
+        let sector_size = disk.block_size().min(MAX_SECTOR_SIZE) as u16;
+        let opts = fatfs::FormatVolumeOptions::new().bytes_per_sector(sector_size);
         fatfs::format_volume(&mut disk, opts).expect("failed to format volume");
-        let inner = fatfs::FileSystem::new(disk, fatfs::FsOptions::new())
-            .expect("failed to initialize FAT filesystem");
+        let opts = fatfs::FsOptions::new().time_provider(RtcTimeProvider);
+        let inner =
+            fatfs::FileSystem::new(disk, opts).expect("failed to initialize FAT filesystem");
         Self {
             inner,
             root_dir: UnsafeCell::new(None),
@@ -40,8 +59,9 @@ impl FatFileSystem {
 
     #[cfg(not(feature = "use-ramdisk"))]
     pub fn new(disk: Disk) -> Self {
-        let inner = fatfs::FileSystem::new(disk, fatfs::FsOptions::new())
-            .expect("failed to initialize FAT filesystem");
+        let opts = fatfs::FsOptions::new().time_provider(RtcTimeProvider);
+        let inner =
+            fatfs::FileSystem::new(disk, opts).expect("failed to initialize FAT filesystem");
         Self {
             inner,
             root_dir: UnsafeCell::new(None),
@@ -49,16 +69,59 @@ impl FatFileSystem {
     }
 
     pub fn init(&'static self) {
-        // must be called before later operations
-        unsafe { *self.root_dir.get() = Some(Self::new_dir(self.inner.root_dir())) }
+        // must be called before later operations. The root directory has no
+        // entry for timestamps.
+        let root_dir = Self::new_dir(self.inner.root_dir(), NodeMeta::default());
+        unsafe { *self.root_dir.get() = Some(root_dir) }
     }
 
-    fn new_file(file: File<'_, Disk, NullTimeProvider, LossyOemCpConverter>) -> Arc<FileWrapper> {
-        Arc::new(FileWrapper(Mutex::new(file)))
+    fn new_file(
+        file: File<'_, Disk, RtcTimeProvider, LossyOemCpConverter>,
+        meta: NodeMeta,
+    ) -> Arc<FileWrapper> {
+        Arc::new(FileWrapper(Mutex::new(file), Mutex::new(meta)))
     }
 
-    fn new_dir(dir: Dir<'_, Disk, NullTimeProvider, LossyOemCpConverter>) -> Arc<DirWrapper> {
-        Arc::new(DirWrapper(dir))
+    fn new_dir(
+        dir: Dir<'_, Disk, RtcTimeProvider, LossyOemCpConverter>,
+        meta: NodeMeta,
+    ) -> Arc<DirWrapper> {
+        Arc::new(DirWrapper(dir, meta))
+    }
+}
+
+impl TimeProvider for RtcTimeProvider {
+    fn get_current_date(&self) -> Date {
+        self.get_current_date_time().date
+    }
+
+    fn get_current_date_time(&self) -> DateTime {
+        to_fat_time(current_time())
+    }
+}
+
+impl FileWrapper<'_> {
+    /// Returns the timestamps of the directory entry.
+    pub fn meta(&self) -> NodeMeta {
+        *self.1.lock()
+    }
+
+    /// Sets the modification time to now, which is also the change time as
+    /// FAT has no such one.
+    fn touch_modified(&self, file: &mut File<'_, Disk, RtcTimeProvider, LossyOemCpConverter>) {
+        // modification times are stored in 2-second units
+        let now = to_fat_time(Duration::from_secs(current_time().as_secs() & !1));
+        file.set_modified(now);
+        let mut meta = self.1.lock();
+        meta.mtime = from_fat_time(now);
+        meta.ctime = meta.mtime;
+    }
+}
+
+impl DirWrapper<'_> {
+    /// Returns the timestamps of the directory entry.
+    pub fn meta(&self) -> NodeMeta {
+        self.1
     }
 }
 
@@ -82,13 +145,21 @@ impl VfsNodeOps for FileWrapper<'static> {
     fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
         let mut file = self.0.lock();
         file.seek(SeekFrom::Start(offset)).map_err(as_vfs_err)?; // TODO: more efficient
-        file.write(buf).map_err(as_vfs_err)
+        let len = file.write(buf).map_err(as_vfs_err)?;
+        self.touch_modified(&mut file);
+        Ok(len)
+    }
+
+    fn fsync(&self) -> VfsResult {
+        self.0.lock().flush().map_err(as_vfs_err)
     }
 
     fn truncate(&self, size: u64) -> VfsResult {
         let mut file = self.0.lock();
         file.seek(SeekFrom::Start(size)).map_err(as_vfs_err)?; // TODO: more efficient
-        file.truncate().map_err(as_vfs_err)
+        file.truncate().map_err(as_vfs_err)?;
+        self.touch_modified(&mut file);
+        Ok(())
     }
 }
 
@@ -106,9 +177,9 @@ impl VfsNodeOps for DirWrapper<'static> {
     }
 
     fn parent(&self) -> Option<VfsNodeRef> {
-        self.0
-            .open_dir("..")
-            .map_or(None, |dir| Some(FatFileSystem::new_dir(dir)))
+        self.0.open_dir("..").map_or(None, |dir| {
+            Some(FatFileSystem::new_dir(dir, NodeMeta::default()))
+        })
     }
 
     fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
@@ -119,15 +190,38 @@ impl VfsNodeOps for DirWrapper<'static> {
         }
         if let Some(rest) = path.strip_prefix("./") {
             return self.lookup(rest);
+        } else if let Some(rest) = path.strip_suffix("/.") {
+            return self.lookup(rest);
         }
 
+        let (parent, name) = match path.rsplit_once('/') {
+            Some((parent, name)) => (Some(parent), name),
+            None => (None, path),
+        };
+        if name == ".." {
+            // the timestamps are in the entry of its parent
+            return match self.0.open_dir(path) {
+                Ok(dir) => Ok(FatFileSystem::new_dir(dir, NodeMeta::default())),
+                Err(_) => Err(VfsError::NotFound),
+            };
+        }
+        let dir = match parent {
+            Some(parent) => self.0.open_dir(parent).map_err(|_| VfsError::NotFound)?,
+            None => self.0.clone(),
+        };
         // TODO: use `fatfs::Dir::find_entry`, but it's not public.
-        if let Ok(file) = self.0.open_file(path) {
-            Ok(FatFileSystem::new_file(file))
-        } else if let Ok(dir) = self.0.open_dir(path) {
-            Ok(FatFileSystem::new_dir(dir))
+        let entry = dir
+            .iter()
+            .filter_map(Result::ok)
+            .find(|entry| {
+                eq_name(&entry.file_name(), name) || eq_name(&entry.short_file_name(), name)
+            })
+            .ok_or(VfsError::NotFound)?;
+        let meta = entry_meta(&entry);
+        if entry.is_dir() {
+            Ok(FatFileSystem::new_dir(entry.to_dir(), meta))
         } else {
-            Err(VfsError::NotFound)
+            Ok(FatFileSystem::new_file(entry.to_file(), meta))
         }
     }
 
@@ -243,7 +337,7 @@ impl Write for Disk {
         Ok(write_len)
     }
     fn flush(&mut self) -> Result<(), Self::Error> {
-        Ok(())
+        Disk::flush(self).map_err(|_| ())
     }
 }
 
@@ -264,6 +358,73 @@ impl Seek for Disk {
     }
 }
 
+/// Compares file names case-insensitively, as FAT does.
+fn eq_name(a: &str, b: &str) -> bool {
+    a.chars()
+        .flat_map(char::to_uppercase)
+        .eq(b.chars().flat_map(char::to_uppercase))
+}
+
+fn entry_meta(entry: &DirEntry<'_, Disk, RtcTimeProvider, LossyOemCpConverter>) -> NodeMeta {
+    let mtime = from_fat_time(entry.modified());
+    NodeMeta {
+        atime: from_fat_time(DateTime::new(entry.accessed(), Time::new(0, 0, 0, 0))),
+        mtime,
+        ctime: mtime,
+        uid: 0,
+        gid: 0,
+    }
+}
+
+/// Converts a time since the Unix epoch to a FAT date and time, clamped to
+/// the years FAT can store (1980 to 2107).
+fn to_fat_time(time: Duration) -> DateTime {
+    let secs = time.as_secs();
+    // the civil date from days since 1970-01-01, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = (secs / 86400) as i64 + 719468;
+    let era = z / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+
+    if year < 1980 {
+        DateTime::new(Date::new(1980, 1, 1), Time::new(0, 0, 0, 0))
+    } else if year > 2107 {
+        DateTime::new(Date::new(2107, 12, 31), Time::new(23, 59, 59, 999))
+    } else {
+        let rem = secs % 86400;
+        let date = Date::new(year as u16, month as u16, day as u16);
+        let time = Time::new(
+            (rem / 3600) as u16,
+            (rem / 60 % 60) as u16,
+            (rem % 60) as u16,
+            time.subsec_millis() as u16,
+        );
+        DateTime::new(date, time)
+    }
+}
+
+/// Converts a FAT date and time to the time since the Unix epoch.
+fn from_fat_time(date_time: DateTime) -> Duration {
+    let (date, time) = (date_time.date, date_time.time);
+    // days since 1970-01-01 from the civil date, see
+    // http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+    let (month, day) = (date.month as i64, date.day as i64);
+    let year = date.year as i64 - (month <= 2) as i64;
+    let era = year / 400;
+    let yoe = year - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+    let secs = days * 86400 + time.hour as i64 * 3600 + time.min as i64 * 60 + time.sec as i64;
+    Duration::new(secs.max(0) as u64, time.millis as u32 * 1_000_000)
+}
+
 const fn as_vfs_err(err: fatfs::Error<()>) -> VfsError {
     use fatfs::Error::*;
     match err {
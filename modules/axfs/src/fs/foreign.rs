@@ -0,0 +1,107 @@
+//! Wrappers of filesystems defined outside this crate.
+//!
+//! Their nodes may not implement [`VfsNodeOps::as_any`], which is called to
+//! reach what [`axfs_vfs`] does not provide, e.g., timestamps. The wrappers
+//! implement it for them, and forward everything else.
+
+use alloc::sync::Arc;
+use axfs_vfs::{FileSystemInfo, VfsDirEntry, VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType};
+use axfs_vfs::{VfsOps, VfsResult};
+
+/// A filesystem defined outside this crate.
+pub struct ForeignFileSystem(Arc<dyn VfsOps>);
+
+/// A node of a [`ForeignFileSystem`].
+pub struct ForeignNode(VfsNodeRef);
+
+impl ForeignFileSystem {
+    pub fn new(fs: Arc<dyn VfsOps>) -> Self {
+        Self(fs)
+    }
+}
+
+impl ForeignNode {
+    fn wrap(node: VfsNodeRef) -> VfsNodeRef {
+        Arc::new(Self(node))
+    }
+}
+
+impl VfsOps for ForeignFileSystem {
+    fn mount(&self, path: &str, mount_point: VfsNodeRef) -> VfsResult {
+        self.0.mount(path, mount_point)
+    }
+
+    fn umount(&self) -> VfsResult {
+        self.0.umount()
+    }
+
+    fn format(&self) -> VfsResult {
+        self.0.format()
+    }
+
+    fn statfs(&self) -> VfsResult<FileSystemInfo> {
+        self.0.statfs()
+    }
+
+    fn root_dir(&self) -> VfsNodeRef {
+        ForeignNode::wrap(self.0.root_dir())
+    }
+}
+
+impl VfsNodeOps for ForeignNode {
+    fn open(&self) -> VfsResult {
+        self.0.open()
+    }
+
+    fn release(&self) -> VfsResult {
+        self.0.release()
+    }
+
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        self.0.get_attr()
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        self.0.read_at(offset, buf)
+    }
+
+    fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        self.0.write_at(offset, buf)
+    }
+
+    fn fsync(&self) -> VfsResult {
+        self.0.fsync()
+    }
+
+    fn truncate(&self, size: u64) -> VfsResult {
+        self.0.truncate(size)
+    }
+
+    fn parent(&self) -> Option<VfsNodeRef> {
+        self.0.parent().map(ForeignNode::wrap)
+    }
+
+    fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
+        self.0.clone().lookup(path).map(ForeignNode::wrap)
+    }
+
+    fn create(&self, path: &str, ty: VfsNodeType) -> VfsResult {
+        self.0.create(path, ty)
+    }
+
+    fn remove(&self, path: &str) -> VfsResult {
+        self.0.remove(path)
+    }
+
+    fn read_dir(&self, start_idx: usize, dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
+        self.0.read_dir(start_idx, dirents)
+    }
+
+    fn rename(&self, src_path: &str, dst_path: &str) -> VfsResult {
+        self.0.rename(src_path, dst_path)
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+}
@@ -1,13 +1,207 @@
 cfg_if::cfg_if! {
     if #[cfg(feature = "myfs")] {
         pub mod myfs;
-    } else if #[cfg(feature = "fatfs")] {
+    } else {
+        #[cfg(feature = "fatfs")]
         pub mod fatfs;
+        #[cfg(feature = "extfs")]
+        pub mod extfs;
     }
 }
 
 #[cfg(feature = "devfs")]
-pub use axfs_devfs as devfs;
+pub mod devfs;
 
 #[cfg(feature = "ramfs")]
-pub use axfs_ramfs as ramfs;
+pub mod ramfs;
+
+#[cfg(feature = "overlayfs")]
+pub mod overlayfs;
+
+#[cfg(feature = "9p")]
+pub mod ninep;
+
+#[cfg(any(feature = "devfs", feature = "procfs", feature = "sysfs"))]
+pub mod pseudofs;
+
+mod foreign;
+
+pub use self::foreign::ForeignFileSystem;
+
+use alloc::string::String;
+use axfs_vfs::{VfsError, VfsNodeRef, VfsResult};
+use core::time::Duration;
+
+/// Timestamps and ownership of a node, which are not in
+/// [`axfs_vfs::VfsNodeAttr`]. Times are since the Unix epoch.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct NodeMeta {
+    /// The time of last access.
+    pub atime: Duration,
+    /// The time of last modification of the content.
+    pub mtime: Duration,
+    /// The time of last change of the content or attributes.
+    pub ctime: Duration,
+    /// The user ID of the owner.
+    pub uid: u32,
+    /// The group ID of the owner.
+    pub gid: u32,
+}
+
+impl NodeMeta {
+    /// Metadata of a node created at `now`, owned by root.
+    pub const fn new(now: Duration) -> Self {
+        Self {
+            atime: now,
+            mtime: now,
+            ctime: now,
+            uid: 0,
+            gid: 0,
+        }
+    }
+}
+
+/// Returns the current wall time, used for timestamps.
+pub(crate) fn current_time() -> Duration {
+    axhal::time::wall_time()
+}
+
+/// Returns the timestamps and ownership of `node`.
+///
+/// Only filesystems in this crate keep them, nodes of other filesystems get
+/// zeros. Such filesystems must be wrapped in [`ForeignFileSystem`].
+#[allow(unused_variables)]
+pub(crate) fn node_meta(node: &VfsNodeRef) -> VfsResult<NodeMeta> {
+    let any = node.as_any();
+    #[cfg(feature = "ramfs")]
+    if let Some(file) = any.downcast_ref::<ramfs::FileNode>() {
+        return Ok(file.meta());
+    } else if let Some(dir) = any.downcast_ref::<ramfs::DirNode>() {
+        return Ok(dir.meta());
+    }
+    #[cfg(feature = "overlayfs")]
+    if let Some(node) = any.downcast_ref::<overlayfs::OverlayNode>() {
+        return node_meta(&node.real_node()?);
+    }
+    #[cfg(any(feature = "devfs", feature = "procfs", feature = "sysfs"))]
+    if any.is::<pseudofs::FileNode>() || any.is::<pseudofs::DirNode>() {
+        // generated when accessed
+        return Ok(NodeMeta::new(current_time()));
+    }
+    #[cfg(all(feature = "extfs", not(feature = "myfs")))]
+    if let Some(node) = any.downcast_ref::<extfs::ExtNode>() {
+        return node.meta();
+    }
+    #[cfg(feature = "9p")]
+    if let Some(node) = any.downcast_ref::<ninep::NinePNode>() {
+        return node.meta();
+    }
+    #[cfg(all(feature = "fatfs", not(feature = "myfs")))]
+    if let Some(file) = any.downcast_ref::<fatfs::FileWrapper<'static>>() {
+        return Ok(file.meta());
+    } else if let Some(dir) = any.downcast_ref::<fatfs::DirWrapper<'static>>() {
+        return Ok(dir.meta());
+    }
+    Ok(NodeMeta::default())
+}
+
+/// Identifies a file, which is the same for all nodes of it, e.g., looked up
+/// by its hard links.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum FileId {
+    /// The address of the node, for filesystems which keep a node for each
+    /// file while it exists.
+    Node(usize),
+    /// The inode number in the filesystem at the address.
+    Inode(usize, u64),
+    /// The absolute path, for other filesystems, which do not support hard
+    /// links.
+    Path(String),
+}
+
+/// Returns the identity of the file of `node`, which is at the absolute
+/// `path`.
+#[allow(unused_variables)]
+pub(crate) fn file_id(node: &VfsNodeRef, path: &str) -> VfsResult<FileId> {
+    let any = node.as_any();
+    #[cfg(feature = "ramfs")]
+    if any.is::<ramfs::FileNode>() || any.is::<ramfs::DirNode>() {
+        let addr = alloc::sync::Arc::as_ptr(node) as *const () as usize;
+        return Ok(FileId::Node(addr));
+    }
+    #[cfg(feature = "overlayfs")]
+    if let Some(node) = any.downcast_ref::<overlayfs::OverlayNode>() {
+        // changed when copied up, like overlayfs in Linux
+        return file_id(&node.real_node()?, path);
+    }
+    #[cfg(all(feature = "extfs", not(feature = "myfs")))]
+    if let Some(node) = any.downcast_ref::<extfs::ExtNode>() {
+        return Ok(node.file_id());
+    }
+    #[cfg(feature = "9p")]
+    if let Some(node) = any.downcast_ref::<ninep::NinePNode>() {
+        return node.file_id();
+    }
+    Ok(FileId::Path(path.into()))
+}
+
+/// Hard links of filesystems in this crate, which are not part of
+/// [`axfs_vfs::VfsNodeOps`].
+pub(crate) trait LinkOps {
+    /// Creates a hard link `dst_path` to the file `src_path`, both relative to
+    /// this directory.
+    fn link(&self, src_path: &str, dst_path: &str) -> VfsResult;
+}
+
+/// Returns the link operations of `node`, if its filesystem supports hard
+/// links.
+#[allow(unused_variables)]
+fn as_linkable(node: &VfsNodeRef) -> Option<&dyn LinkOps> {
+    let any = node.as_any();
+    #[cfg(feature = "ramfs")]
+    if let Some(dir) = any.downcast_ref::<ramfs::DirNode>() {
+        return Some(dir);
+    }
+    #[cfg(feature = "overlayfs")]
+    if let Some(node) = any.downcast_ref::<overlayfs::OverlayNode>() {
+        return Some(node);
+    }
+    #[cfg(all(feature = "extfs", not(feature = "myfs")))]
+    if let Some(node) = any.downcast_ref::<extfs::ExtNode>() {
+        return Some(node);
+    }
+    #[cfg(feature = "9p")]
+    if let Some(node) = any.downcast_ref::<ninep::NinePNode>() {
+        return Some(node);
+    }
+    None
+}
+
+/// Creates a hard link `dst_path` to `src_path`, both relative to `root`, the
+/// root directory of a filesystem.
+///
+/// Only filesystems in this crate implementing [`LinkOps`] support hard
+/// links, others get [`VfsError::Unsupported`].
+pub(crate) fn hard_link(root: &VfsNodeRef, src_path: &str, dst_path: &str) -> VfsResult {
+    as_linkable(root)
+        .ok_or(VfsError::Unsupported)?
+        .link(src_path, dst_path)
+}
+
+/// Performs the device-specific operation `cmd` on `node`, as `ioctl` in
+/// Linux.
+///
+/// Only device files in `/dev` support it, other nodes get
+/// [`VfsError::Unsupported`].
+///
+/// # Safety
+///
+/// See [`devfs::DeviceOps::ioctl`].
+#[allow(unused_variables)]
+pub(crate) unsafe fn ioctl(node: &VfsNodeRef, cmd: u32, arg: usize) -> VfsResult<usize> {
+    #[cfg(feature = "devfs")]
+    if let Some(dev) = devfs::as_device(node) {
+        return dev.ioctl(cmd, arg);
+    }
+    Err(VfsError::Unsupported)
+}
@@ -0,0 +1,625 @@
+//! 9P2000.L client filesystem, to access a directory shared by the host,
+//! e.g., by QEMU `-virtfs` through a virtio-9p device.
+//!
+//! Nothing is cached, so changes on the host are seen immediately. Each node
+//! holds a fid walked from the root, and opens another one for reading and
+//! writing when needed. Symbolic links are created with a placeholder
+//! target, which is replaced when the link is written, as filesystems in
+//! this crate get the targets.
+
+mod proto;
+
+use alloc::{boxed::Box, string::String, sync::Arc, vec, vec::Vec};
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use axfs_vfs::{VfsDirEntry, VfsError, VfsNodePerm, VfsResult};
+use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType, VfsOps};
+use axsync::Mutex;
+
+use self::proto::*;
+use crate::fs::{FileId, LinkOps, NodeMeta};
+
+/// A transport of 9P messages to a server, e.g., a virtio-9p device.
+pub trait NinePTransport: Send {
+    /// The tag identifying the shared directory.
+    fn mount_tag(&self) -> &str;
+
+    /// The maximum size of a message in bytes, including its header.
+    fn max_message_size(&self) -> usize;
+
+    /// Sends the request `req`, and receives its response into `resp`.
+    /// Returns the size of the response.
+    fn request(&mut self, req: &[u8], resp: &mut [u8]) -> VfsResult<usize>;
+}
+
+#[cfg(feature = "virtio-9p")]
+impl NinePTransport for axdriver::VirtIo9pDev {
+    fn mount_tag(&self) -> &str {
+        axdriver::VirtIo9pDev::mount_tag(self)
+    }
+
+    fn max_message_size(&self) -> usize {
+        axdriver::VirtIo9pDev::max_message_size(self)
+    }
+
+    fn request(&mut self, req: &[u8], resp: &mut [u8]) -> VfsResult<usize> {
+        axdriver::VirtIo9pDev::request(self, req, resp).map_err(|_| VfsError::Io)
+    }
+}
+
+/// Registered transports which are not mounted.
+static TRANSPORTS: Mutex<Vec<Box<dyn NinePTransport>>> = Mutex::new(Vec::new());
+
+/// Registers `transport`, to be mounted by its tag.
+pub(crate) fn register_transport(transport: Box<dyn NinePTransport>) {
+    TRANSPORTS.lock().push(transport);
+}
+
+/// Takes the registered transport with `tag` to mount it. It is registered
+/// again when the filesystem is dropped.
+pub(crate) fn take_transport(tag: &str) -> Option<Box<dyn NinePTransport>> {
+    let mut transports = TRANSPORTS.lock();
+    let pos = transports.iter().position(|t| t.mount_tag() == tag)?;
+    Some(transports.remove(pos))
+}
+
+/// The fid of the root directory.
+const ROOT_FID: u32 = 0;
+/// Permissions of created files and directories.
+const FILE_MODE: u32 = 0o644;
+const DIR_MODE: u32 = 0o755;
+/// The target of a created symbolic link until it is written.
+const SYMLINK_PLACEHOLDER: &str = ".";
+
+struct Channel {
+    /// Always [`Some`] until dropped.
+    transport: Option<Box<dyn NinePTransport>>,
+    buf: Vec<u8>,
+}
+
+impl Channel {
+    fn call<T>(
+        &mut self,
+        req: Request,
+        parse: impl FnOnce(&mut Response) -> VfsResult<T>,
+    ) -> VfsResult<T> {
+        let ty = req.ty();
+        let req = req.finish();
+        let transport = self.transport.as_mut().ok_or(VfsError::BadState)?;
+        let len = transport.request(&req, &mut self.buf)?;
+        parse(&mut Response::new(&self.buf[..len], ty)?)
+    }
+}
+
+impl Drop for Channel {
+    fn drop(&mut self) {
+        if let Some(transport) = self.transport.take() {
+            register_transport(transport);
+        }
+    }
+}
+
+struct Shared {
+    channel: Mutex<Channel>,
+    /// The maximum message size negotiated with the server.
+    msize: usize,
+    next_fid: AtomicU32,
+    /// The parent of the mount point, to look up `..` from the root.
+    parent: Mutex<Option<VfsNodeRef>>,
+}
+
+impl Shared {
+    /// Sends the request `req`, and parses its response by `parse`.
+    fn call<T>(
+        &self,
+        req: Request,
+        parse: impl FnOnce(&mut Response) -> VfsResult<T>,
+    ) -> VfsResult<T> {
+        self.channel.lock().call(req, parse)
+    }
+
+    /// Walks from `fid` through `names` to a new fid. Returns it with the qid
+    /// of the last name.
+    fn walk(&self, fid: u32, names: &[&str]) -> VfsResult<(u32, Option<Qid>)> {
+        let new_fid = self.next_fid.fetch_add(1, Ordering::Relaxed);
+        let mut from = fid;
+        let mut qid = None;
+        // walk at least once to clone `fid` if there are no names
+        for chunk in names
+            .chunks(MAXWELEM)
+            .chain(names.is_empty().then_some(names))
+        {
+            let mut req = Request::new(TWALK)
+                .u32(from)
+                .u32(new_fid)
+                .u16(chunk.len() as u16);
+            for name in chunk {
+                req = req.str(name);
+            }
+            let res = self.call(req, |resp| {
+                let nwqid = resp.u16()? as usize;
+                let mut last = None;
+                for _ in 0..nwqid {
+                    last = Some(resp.qid()?);
+                }
+                Ok((nwqid, last))
+            });
+            match res {
+                Ok((nwqid, last)) if nwqid == chunk.len() => qid = last.or(qid),
+                res => {
+                    // the new fid is created only if all names are walked
+                    if from == new_fid {
+                        self.clunk(new_fid);
+                    }
+                    return Err(res.err().unwrap_or(VfsError::NotFound));
+                }
+            }
+            from = new_fid;
+        }
+        Ok((new_fid, qid))
+    }
+
+    /// Opens the file of `fid` with `flags` by a new fid.
+    fn open(&self, fid: u32, flags: u32) -> VfsResult<u32> {
+        let (fid, _) = self.walk(fid, &[])?;
+        let res = self.call(Request::new(TLOPEN).u32(fid).u32(flags), |_| Ok(()));
+        if let Err(e) = res {
+            self.clunk(fid);
+            return Err(e);
+        }
+        Ok(fid)
+    }
+
+    fn clunk(&self, fid: u32) {
+        if let Err(e) = self.call(Request::new(TCLUNK).u32(fid), |_| Ok(())) {
+            warn!("failed to clunk 9p fid {}: {:?}", fid, e);
+        }
+    }
+
+    fn get_attr(&self, fid: u32) -> VfsResult<Attr> {
+        let req = Request::new(TGETATTR).u32(fid).u64(GETATTR_BASIC);
+        self.call(req, |resp| resp.attr())
+    }
+
+    /// The maximum size of data in a read or write.
+    fn io_size(&self) -> usize {
+        self.msize - IOHDRSZ
+    }
+}
+
+/// A directory shared by a 9P server.
+pub struct NinePFileSystem {
+    root: Arc<NinePNode>,
+}
+
+/// A file or directory in a 9P filesystem.
+pub struct NinePNode {
+    shared: Arc<Shared>,
+    fid: u32,
+    /// The path relative to the root, without leading or trailing `/`.
+    path: String,
+    ty: VfsNodeType,
+    /// A fid opened for reading and writing, and whether it is writable.
+    opened: Mutex<Option<(u32, bool)>>,
+}
+
+impl NinePFileSystem {
+    /// Negotiates the protocol with the server through `transport`, and
+    /// attaches to the root of the shared directory.
+    pub fn new(transport: Box<dyn NinePTransport>) -> VfsResult<Self> {
+        let mut channel = Channel {
+            buf: vec![0; transport.max_message_size()],
+            transport: Some(transport),
+        };
+        let req = Request::new(TVERSION)
+            .u32(channel.buf.len() as u32)
+            .str(VERSION);
+        let (msize, version) =
+            channel.call(req, |resp| Ok((resp.u32()? as usize, resp.string()?)))?;
+        if version != VERSION {
+            warn!("9p server does not support {}: {}", VERSION, version);
+            return Err(VfsError::Unsupported);
+        }
+        if msize <= IOHDRSZ || msize > channel.buf.len() {
+            return Err(VfsError::InvalidData);
+        }
+        let req = Request::new(TATTACH)
+            .u32(ROOT_FID)
+            .u32(NOFID)
+            .str("root")
+            .str("")
+            .u32(0); // n_uname
+        channel.call(req, |resp| resp.qid())?;
+        let shared = Arc::new(Shared {
+            channel: Mutex::new(channel),
+            msize,
+            next_fid: AtomicU32::new(ROOT_FID + 1),
+            parent: Mutex::new(None),
+        });
+        Ok(Self {
+            root: NinePNode::new(&shared, ROOT_FID, String::new(), VfsNodeType::Dir),
+        })
+    }
+}
+
+impl VfsOps for NinePFileSystem {
+    fn mount(&self, _path: &str, mount_point: VfsNodeRef) -> VfsResult {
+        *self.root.shared.parent.lock() = mount_point.parent();
+        Ok(())
+    }
+
+    fn root_dir(&self) -> VfsNodeRef {
+        self.root.clone()
+    }
+}
+
+/// Splits `path` into the parent part and the last component.
+fn split_parent(path: &str) -> (&str, &str) {
+    let path = path.trim_end_matches('/');
+    match path.rfind('/') {
+        Some(pos) => (&path[..pos], &path[pos + 1..]),
+        None => ("", path),
+    }
+}
+
+impl NinePNode {
+    fn new(shared: &Arc<Shared>, fid: u32, path: String, ty: VfsNodeType) -> Arc<Self> {
+        Arc::new(Self {
+            shared: shared.clone(),
+            fid,
+            path,
+            ty,
+            opened: Mutex::new(None),
+        })
+    }
+
+    /// Opens the node at `path` relative to the root.
+    fn open(shared: &Arc<Shared>, path: String) -> VfsResult<Arc<Self>> {
+        let names: Vec<&str> = path.split('/').filter(|name| !name.is_empty()).collect();
+        let (fid, qid) = shared.walk(ROOT_FID, &names)?;
+        let ty = qid.map_or(VfsNodeType::Dir, |qid| qid.node_type());
+        Ok(Self::new(shared, fid, path, ty))
+    }
+
+    /// Resolves `path` relative to this node to the path relative to the
+    /// root. `..` at the root goes to the parent of the mount point, which
+    /// is returned with the rest path.
+    fn resolve<'a>(&self, path: &'a str) -> Result<String, (VfsNodeRef, &'a str)> {
+        let mut names: Vec<&str> = self.path.split('/').filter(|n| !n.is_empty()).collect();
+        let mut rest = path.trim_start_matches('/');
+        while !rest.is_empty() {
+            let (name, next) = rest.split_once('/').unwrap_or((rest, ""));
+            rest = next.trim_start_matches('/');
+            match name {
+                "" | "." => {}
+                ".." => {
+                    if names.pop().is_none() {
+                        if let Some(parent) = self.shared.parent.lock().clone() {
+                            return Err((parent, rest));
+                        }
+                    }
+                }
+                _ => names.push(name),
+            }
+        }
+        Ok(names.join("/"))
+    }
+
+    /// Opens the directory containing `path` in this filesystem, returns it
+    /// with the last component.
+    fn parent_of<'a>(&self, path: &'a str) -> VfsResult<(Arc<Self>, &'a str)> {
+        let (parent, name) = split_parent(path);
+        if name.is_empty() || name == "." || name == ".." {
+            return Err(VfsError::InvalidInput);
+        }
+        match self.resolve(parent) {
+            Ok(path) => Ok((Self::open(&self.shared, path)?, name)),
+            // other filesystems are not accessible
+            Err(_) => Err(VfsError::PermissionDenied),
+        }
+    }
+
+    /// Returns a fid of this node opened for reading, and also for writing
+    /// if `write` is true.
+    fn io_fid(&self, write: bool) -> VfsResult<u32> {
+        let mut opened = self.opened.lock();
+        if let Some((fid, writable)) = *opened {
+            if writable || !write {
+                return Ok(fid);
+            }
+        }
+        let fid = match self.shared.open(self.fid, O_RDWR) {
+            Ok(fid) => (fid, true),
+            // read-only files
+            Err(VfsError::PermissionDenied) if !write => {
+                (self.shared.open(self.fid, O_RDONLY)?, false)
+            }
+            Err(e) => return Err(e),
+        };
+        if let Some((old, _)) = opened.replace(fid) {
+            self.shared.clunk(old);
+        }
+        Ok(fid.0)
+    }
+
+    fn read_link(&self) -> VfsResult<String> {
+        self.shared
+            .call(Request::new(TREADLINK).u32(self.fid), |resp| resp.string())
+    }
+
+    /// Replaces the target of the symbolic link.
+    fn set_link_target(&self, target: &str) -> VfsResult {
+        let (dir, name) = split_parent(&self.path);
+        let dir = Self::open(&self.shared, dir.into())?;
+        let req = Request::new(TUNLINKAT).u32(dir.fid).str(name).u32(0);
+        self.shared.call(req, |_| Ok(()))?;
+        let req = Request::new(TSYMLINK)
+            .u32(dir.fid)
+            .str(name)
+            .str(target)
+            .u32(0); // gid
+        self.shared.call(req, |resp| resp.qid()).map(|_| ())
+    }
+
+    /// Reads all entries of the directory opened by `fid`.
+    fn read_entries(&self, fid: u32) -> VfsResult<Vec<(String, VfsNodeType)>> {
+        let mut entries = Vec::new();
+        let mut offset = 0;
+        loop {
+            let req = Request::new(TREADDIR)
+                .u32(fid)
+                .u64(offset)
+                .u32(self.shared.io_size() as u32);
+            let len = entries.len();
+            self.shared.call(req, |resp| {
+                resp.u32()?; // count
+                while !resp.is_empty() {
+                    resp.qid()?;
+                    offset = resp.u64()?;
+                    let ty = dirent_type(resp.u8()?);
+                    entries.push((resp.string()?, ty));
+                }
+                Ok(())
+            })?;
+            if entries.len() == len {
+                return Ok(entries);
+            }
+        }
+    }
+
+    /// Returns the identity of the file on the server, with the address of
+    /// the filesystem.
+    pub(crate) fn file_id(&self) -> VfsResult<FileId> {
+        let attr = self.shared.get_attr(self.fid)?;
+        Ok(FileId::Inode(Arc::as_ptr(&self.shared) as usize, attr.ino))
+    }
+
+    /// Returns the timestamps and ownership of the file.
+    pub fn meta(&self) -> VfsResult<NodeMeta> {
+        let attr = self.shared.get_attr(self.fid)?;
+        Ok(NodeMeta {
+            atime: attr.atime,
+            mtime: attr.mtime,
+            ctime: attr.ctime,
+            uid: attr.uid,
+            gid: attr.gid,
+        })
+    }
+}
+
+impl LinkOps for NinePNode {
+    fn link(&self, src_path: &str, dst_path: &str) -> VfsResult {
+        let Ok(src_path) = self.resolve(src_path) else {
+            return Err(VfsError::PermissionDenied);
+        };
+        let src = Self::open(&self.shared, src_path)?;
+        let (dir, name) = self.parent_of(dst_path)?;
+        let req = Request::new(TLINK).u32(dir.fid).u32(src.fid).str(name);
+        self.shared.call(req, |_| Ok(()))
+    }
+}
+
+impl VfsNodeOps for NinePNode {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        let attr = self.shared.get_attr(self.fid)?;
+        let perm = VfsNodePerm::from_bits_truncate((attr.mode & 0o777) as u16);
+        Ok(VfsNodeAttr::new(
+            perm,
+            attr.node_type(),
+            attr.size,
+            attr.blocks,
+        ))
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        match self.ty {
+            VfsNodeType::Dir => return Err(VfsError::IsADirectory),
+            VfsNodeType::SymLink => {
+                let target = self.read_link()?;
+                let target = target.as_bytes();
+                let start = target.len().min(offset as usize);
+                let len = buf.len().min(target.len() - start);
+                buf[..len].copy_from_slice(&target[start..start + len]);
+                return Ok(len);
+            }
+            _ => {}
+        }
+        let fid = self.io_fid(false)?;
+        let mut read = 0;
+        while read < buf.len() {
+            let count = (buf.len() - read).min(self.shared.io_size());
+            let req = Request::new(TREAD)
+                .u32(fid)
+                .u64(offset + read as u64)
+                .u32(count as u32);
+            let len = self.shared.call(req, |resp| {
+                let data = resp.data()?;
+                let len = data.len().min(count);
+                buf[read..read + len].copy_from_slice(&data[..len]);
+                Ok(len)
+            })?;
+            read += len;
+            if len < count {
+                break;
+            }
+        }
+        Ok(read)
+    }
+
+    fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        match self.ty {
+            VfsNodeType::Dir => return Err(VfsError::IsADirectory),
+            VfsNodeType::SymLink if offset == 0 => {
+                let target = core::str::from_utf8(buf).map_err(|_| VfsError::InvalidInput)?;
+                self.set_link_target(target)?;
+                return Ok(buf.len());
+            }
+            VfsNodeType::SymLink => return Err(VfsError::InvalidInput),
+            _ => {}
+        }
+        let fid = self.io_fid(true)?;
+        let mut written = 0;
+        while written < buf.len() {
+            let count = (buf.len() - written).min(self.shared.io_size());
+            let req = Request::new(TWRITE)
+                .u32(fid)
+                .u64(offset + written as u64)
+                .data(&buf[written..written + count]);
+            let len = self.shared.call(req, |resp| Ok(resp.u32()? as usize))?;
+            if len == 0 {
+                break;
+            }
+            written += len.min(count);
+        }
+        Ok(written)
+    }
+
+    fn fsync(&self) -> VfsResult {
+        match *self.opened.lock() {
+            Some((fid, _)) => {
+                let req = Request::new(TFSYNC).u32(fid).u32(0); // datasync
+                self.shared.call(req, |_| Ok(()))
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn truncate(&self, size: u64) -> VfsResult {
+        if self.ty == VfsNodeType::Dir {
+            return Err(VfsError::IsADirectory);
+        }
+        let req = Request::new(TSETATTR)
+            .u32(self.fid)
+            .u32(SETATTR_SIZE)
+            .u32(0) // mode
+            .u32(0) // uid
+            .u32(0) // gid
+            .u64(size)
+            .u64(0) // atime
+            .u64(0)
+            .u64(0) // mtime
+            .u64(0);
+        self.shared.call(req, |_| Ok(()))
+    }
+
+    fn parent(&self) -> Option<VfsNodeRef> {
+        match self.resolve("..") {
+            Ok(path) => Some(Self::open(&self.shared, path).ok()?),
+            Err((parent, _)) => Some(parent),
+        }
+    }
+
+    fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
+        debug!("lookup at 9p: {}", path);
+        match self.resolve(path) {
+            Ok(path) if path == self.path => Ok(self),
+            Ok(path) => Ok(Self::open(&self.shared, path)?),
+            Err((parent, rest)) => parent.lookup(rest),
+        }
+    }
+
+    fn create(&self, path: &str, ty: VfsNodeType) -> VfsResult {
+        debug!("create {:?} at 9p: {}", ty, path);
+        let (dir, name) = self.parent_of(path)?;
+        let req = match ty {
+            VfsNodeType::File => {
+                // the new fid is turned into the created file
+                let (fid, _) = self.shared.walk(dir.fid, &[])?;
+                let req = Request::new(TLCREATE)
+                    .u32(fid)
+                    .str(name)
+                    .u32(O_RDWR)
+                    .u32(FILE_MODE)
+                    .u32(0); // gid
+                let res = self.shared.call(req, |resp| resp.qid());
+                self.shared.clunk(fid);
+                return res.map(|_| ());
+            }
+            VfsNodeType::Dir => Request::new(TMKDIR)
+                .u32(dir.fid)
+                .str(name)
+                .u32(DIR_MODE)
+                .u32(0),
+            VfsNodeType::SymLink => Request::new(TSYMLINK)
+                .u32(dir.fid)
+                .str(name)
+                .str(SYMLINK_PLACEHOLDER)
+                .u32(0),
+            _ => return Err(VfsError::Unsupported),
+        };
+        self.shared.call(req, |resp| resp.qid()).map(|_| ())
+    }
+
+    fn remove(&self, path: &str) -> VfsResult {
+        debug!("remove at 9p: {}", path);
+        let (dir, name) = self.parent_of(path)?;
+        let (fid, qid) = self.shared.walk(dir.fid, &[name])?;
+        self.shared.clunk(fid);
+        let flags = match qid.map(|qid| qid.node_type()) {
+            Some(VfsNodeType::Dir) => AT_REMOVEDIR,
+            _ => 0,
+        };
+        let req = Request::new(TUNLINKAT).u32(dir.fid).str(name).u32(flags);
+        self.shared.call(req, |_| Ok(()))
+    }
+
+    fn read_dir(&self, start_idx: usize, dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
+        let fid = self.shared.open(self.fid, O_RDONLY | O_DIRECTORY)?;
+        let entries = self.read_entries(fid);
+        self.shared.clunk(fid);
+        let mut count = 0;
+        for ((name, ty), out) in entries?.iter().skip(start_idx).zip(dirents.iter_mut()) {
+            *out = VfsDirEntry::new(name, *ty);
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    fn rename(&self, src_path: &str, dst_path: &str) -> VfsResult {
+        debug!(
+            "rename at 9p, src_path: {}, dst_path: {}",
+            src_path, dst_path
+        );
+        let (src_dir, src_name) = self.parent_of(src_path)?;
+        let (dst_dir, dst_name) = self.parent_of(dst_path)?;
+        let req = Request::new(TRENAMEAT)
+            .u32(src_dir.fid)
+            .str(src_name)
+            .u32(dst_dir.fid)
+            .str(dst_name);
+        self.shared.call(req, |_| Ok(()))
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+}
+
+impl Drop for NinePNode {
+    fn drop(&mut self) {
+        if let Some((fid, _)) = self.opened.lock().take() {
+            self.shared.clunk(fid);
+        }
+        self.shared.clunk(self.fid);
+    }
+}
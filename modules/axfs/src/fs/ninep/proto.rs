@@ -0,0 +1,306 @@
+//! Messages of the 9P2000.L protocol, see
+//! <https://github.com/chaos/diod/blob/master/protocol.md>.
+//!
+//! All integers are little-endian, and strings are prefixed with their
+//! lengths in 2 bytes.
+
+use alloc::{string::String, vec::Vec};
+use core::time::Duration;
+
+use axfs_vfs::{VfsError, VfsNodeType, VfsResult};
+
+pub const VERSION: &str = "9P2000.L";
+
+/// The tag of `Tversion`, other messages are tagged with 0 as they are sent
+/// one by one.
+pub const NOTAG: u16 = !0;
+/// No fid, e.g., the `afid` of `Tattach` without authentication.
+pub const NOFID: u32 = !0;
+/// The maximum number of names in a `Twalk`.
+pub const MAXWELEM: usize = 16;
+/// The space for the headers of `Twrite` and `Rread` in a message, the rest
+/// is for data.
+pub const IOHDRSZ: usize = 24;
+
+pub const RLERROR: u8 = 7;
+pub const TSYMLINK: u8 = 16;
+pub const TLOPEN: u8 = 12;
+pub const TLCREATE: u8 = 14;
+pub const TREADLINK: u8 = 22;
+pub const TGETATTR: u8 = 24;
+pub const TSETATTR: u8 = 26;
+pub const TREADDIR: u8 = 40;
+pub const TFSYNC: u8 = 50;
+pub const TLINK: u8 = 70;
+pub const TMKDIR: u8 = 72;
+pub const TRENAMEAT: u8 = 74;
+pub const TUNLINKAT: u8 = 76;
+pub const TVERSION: u8 = 100;
+pub const TATTACH: u8 = 104;
+pub const TWALK: u8 = 110;
+pub const TREAD: u8 = 116;
+pub const TWRITE: u8 = 118;
+pub const TCLUNK: u8 = 120;
+
+/// Flags of `Tlopen` and `Tlcreate`, as in Linux.
+pub const O_RDONLY: u32 = 0;
+pub const O_RDWR: u32 = 2;
+pub const O_DIRECTORY: u32 = 0o200000;
+
+/// The `request_mask` of `Tgetattr` for the fields of `stat`.
+pub const GETATTR_BASIC: u64 = 0x7ff;
+/// The `valid` bit of `Tsetattr` to change the size.
+pub const SETATTR_SIZE: u32 = 0x8;
+/// The `flags` of `Tunlinkat` to remove a directory.
+pub const AT_REMOVEDIR: u32 = 0x200;
+
+const QTDIR: u8 = 0x80;
+const QTSYMLINK: u8 = 0x02;
+
+/// The unique identification of a file on the server.
+#[derive(Debug, Clone, Copy)]
+pub struct Qid {
+    pub ty: u8,
+    /// Unique among the files on the server, like an inode number.
+    pub path: u64,
+}
+
+impl Qid {
+    pub const fn node_type(&self) -> VfsNodeType {
+        if self.ty & QTDIR != 0 {
+            VfsNodeType::Dir
+        } else if self.ty & QTSYMLINK != 0 {
+            VfsNodeType::SymLink
+        } else {
+            VfsNodeType::File
+        }
+    }
+}
+
+/// Attributes of a file in `Rgetattr`.
+pub struct Attr {
+    /// The path of the qid.
+    pub ino: u64,
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub size: u64,
+    /// The number of 512-byte blocks allocated.
+    pub blocks: u64,
+    pub atime: Duration,
+    pub mtime: Duration,
+    pub ctime: Duration,
+}
+
+impl Attr {
+    pub const fn node_type(&self) -> VfsNodeType {
+        match self.mode & 0o170000 {
+            0o010000 => VfsNodeType::Fifo,
+            0o020000 => VfsNodeType::CharDevice,
+            0o040000 => VfsNodeType::Dir,
+            0o060000 => VfsNodeType::BlockDevice,
+            0o120000 => VfsNodeType::SymLink,
+            0o140000 => VfsNodeType::Socket,
+            _ => VfsNodeType::File,
+        }
+    }
+}
+
+/// Converts the type of a directory entry in `Rreaddir`, which is `d_type`
+/// of `struct dirent` in Linux.
+pub const fn dirent_type(ty: u8) -> VfsNodeType {
+    match ty {
+        1 => VfsNodeType::Fifo,
+        2 => VfsNodeType::CharDevice,
+        4 => VfsNodeType::Dir,
+        6 => VfsNodeType::BlockDevice,
+        10 => VfsNodeType::SymLink,
+        12 => VfsNodeType::Socket,
+        _ => VfsNodeType::File,
+    }
+}
+
+/// Converts the Linux error number in `Rlerror`.
+pub const fn errno_to_vfs_error(errno: u32) -> VfsError {
+    match errno {
+        1 | 13 | 30 => VfsError::PermissionDenied, // EPERM, EACCES, EROFS
+        2 => VfsError::NotFound,                   // ENOENT
+        11 => VfsError::WouldBlock,                // EAGAIN
+        12 => VfsError::NoMemory,                  // ENOMEM
+        16 => VfsError::ResourceBusy,              // EBUSY
+        17 => VfsError::AlreadyExists,             // EEXIST
+        20 => VfsError::NotADirectory,             // ENOTDIR
+        21 => VfsError::IsADirectory,              // EISDIR
+        22 | 36 | 40 => VfsError::InvalidInput,    // EINVAL, ENAMETOOLONG, ELOOP
+        28 | 122 => VfsError::StorageFull,         // ENOSPC, EDQUOT
+        38 | 95 => VfsError::Unsupported,          // ENOSYS, EOPNOTSUPP
+        39 => VfsError::DirectoryNotEmpty,         // ENOTEMPTY
+        _ => VfsError::Io,
+    }
+}
+
+/// A T-message being built.
+pub struct Request {
+    buf: Vec<u8>,
+}
+
+impl Request {
+    /// Starts a message of `ty`, followed by the fields added by other
+    /// methods.
+    pub fn new(ty: u8) -> Self {
+        let tag = if ty == TVERSION { NOTAG } else { 0 };
+        let mut buf = Vec::with_capacity(64);
+        buf.extend_from_slice(&[0; 4]); // size, filled by `finish`
+        buf.push(ty);
+        buf.extend_from_slice(&tag.to_le_bytes());
+        Self { buf }
+    }
+
+    pub fn ty(&self) -> u8 {
+        self.buf[4]
+    }
+
+    pub fn u16(mut self, value: u16) -> Self {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    pub fn u32(mut self, value: u32) -> Self {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    pub fn u64(mut self, value: u64) -> Self {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    pub fn str(mut self, value: &str) -> Self {
+        self.buf
+            .extend_from_slice(&(value.len() as u16).to_le_bytes());
+        self.buf.extend_from_slice(value.as_bytes());
+        self
+    }
+
+    /// Adds `data` with its length in 4 bytes, as in `Twrite`.
+    pub fn data(mut self, data: &[u8]) -> Self {
+        self.buf
+            .extend_from_slice(&(data.len() as u32).to_le_bytes());
+        self.buf.extend_from_slice(data);
+        self
+    }
+
+    /// Fills the size of the message, and returns it.
+    pub fn finish(mut self) -> Vec<u8> {
+        let size = self.buf.len() as u32;
+        self.buf[..4].copy_from_slice(&size.to_le_bytes());
+        self.buf
+    }
+}
+
+/// An R-message being parsed, starting after the header.
+pub struct Response<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Response<'a> {
+    /// Parses the header of the response `buf` to a request of `ty`, and
+    /// returns the error in it if it is `Rlerror`.
+    pub fn new(buf: &'a [u8], ty: u8) -> VfsResult<Self> {
+        let mut resp = Self { buf };
+        let size = resp.u32()? as usize;
+        if size < 7 || size > buf.len() {
+            return Err(VfsError::InvalidData);
+        }
+        resp.buf = &buf[4..size];
+        let resp_ty = resp.u8()?;
+        resp.u16()?; // tag
+        match resp_ty {
+            RLERROR => Err(errno_to_vfs_error(resp.u32()?)),
+            _ if resp_ty == ty + 1 => Ok(resp),
+            _ => Err(VfsError::InvalidData),
+        }
+    }
+
+    pub fn bytes(&mut self, len: usize) -> VfsResult<&'a [u8]> {
+        if len > self.buf.len() {
+            return Err(VfsError::InvalidData);
+        }
+        let (bytes, rest) = self.buf.split_at(len);
+        self.buf = rest;
+        Ok(bytes)
+    }
+
+    pub fn u8(&mut self) -> VfsResult<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub fn u16(&mut self) -> VfsResult<u16> {
+        Ok(u16::from_le_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    pub fn u32(&mut self) -> VfsResult<u32> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    pub fn u64(&mut self) -> VfsResult<u64> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+
+    pub fn str(&mut self) -> VfsResult<&'a str> {
+        let len = self.u16()? as usize;
+        core::str::from_utf8(self.bytes(len)?).map_err(|_| VfsError::InvalidData)
+    }
+
+    pub fn string(&mut self) -> VfsResult<String> {
+        self.str().map(String::from)
+    }
+
+    /// Reads data with its length in 4 bytes, as in `Rread`.
+    pub fn data(&mut self) -> VfsResult<&'a [u8]> {
+        let len = self.u32()? as usize;
+        self.bytes(len)
+    }
+
+    pub fn qid(&mut self) -> VfsResult<Qid> {
+        let ty = self.u8()?;
+        self.u32()?; // version
+        let path = self.u64()?;
+        Ok(Qid { ty, path })
+    }
+
+    fn time(&mut self) -> VfsResult<Duration> {
+        let sec = self.u64()?;
+        let nsec = self.u64()?;
+        Ok(Duration::new(sec, nsec as u32))
+    }
+
+    /// Reads the body of `Rgetattr`.
+    pub fn attr(&mut self) -> VfsResult<Attr> {
+        self.u64()?; // valid
+        let ino = self.qid()?.path;
+        let mode = self.u32()?;
+        let uid = self.u32()?;
+        let gid = self.u32()?;
+        self.u64()?; // nlink
+        self.u64()?; // rdev
+        let size = self.u64()?;
+        self.u64()?; // blksize
+        let blocks = self.u64()?;
+        Ok(Attr {
+            ino,
+            mode,
+            uid,
+            gid,
+            size,
+            blocks,
+            atime: self.time()?,
+            mtime: self.time()?,
+            ctime: self.time()?,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+}
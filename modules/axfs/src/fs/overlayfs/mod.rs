@@ -0,0 +1,252 @@
+//! Overlay filesystem, which merges a read-only lower filesystem with a
+//! writable upper RAM filesystem.
+//!
+//! The lower filesystem is never modified. Files are copied up to the upper
+//! layer when they are modified, and removed files are hidden by whiteouts.
+//! Like overlayfs in Linux, directories from the lower layer cannot be
+//! renamed.
+
+mod node;
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::{string::String, sync::Arc, vec};
+use axfs_vfs::{VfsDirEntry, VfsError, VfsNodeOps, VfsNodeRef, VfsNodeType, VfsOps, VfsResult};
+use axsync::Mutex;
+
+use super::ramfs::{DirNode as RamDirNode, RamFileSystem};
+use super::LinkOps;
+
+pub use self::node::OverlayNode;
+
+/// An overlay filesystem that implements [`axfs_vfs::VfsOps`].
+pub struct OverlayFileSystem {
+    layers: Arc<Layers>,
+}
+
+struct Layers {
+    lower: Arc<dyn VfsOps>,
+    upper: Arc<RamDirNode>,
+    /// Paths whose nodes in the lower layer are hidden, with all their
+    /// descendants. They are removed, or replaced by nodes in the upper layer.
+    whiteouts: Mutex<BTreeSet<String>>,
+    /// The parent of the mount point, to look up `..` from the root.
+    mount_parent: Mutex<Option<VfsNodeRef>>,
+}
+
+/// Splits `path` into the parent part and the last component.
+fn split_parent(path: &str) -> (&str, &str) {
+    match path.rfind('/') {
+        Some(pos) => (&path[..pos], &path[pos + 1..]),
+        None => ("", path),
+    }
+}
+
+fn join(dir: &str, name: &str) -> String {
+    if dir.is_empty() {
+        name.into()
+    } else {
+        alloc::format!("{}/{}", dir, name)
+    }
+}
+
+fn lookup(root: VfsNodeRef, path: &str) -> VfsResult<VfsNodeRef> {
+    if path.is_empty() {
+        Ok(root)
+    } else {
+        root.lookup(path)
+    }
+}
+
+/// Reads all entries of the directory `dir`, except `.` and `..`.
+fn read_entries(dir: &VfsNodeRef) -> VfsResult<BTreeMap<String, VfsNodeType>> {
+    let mut entries = BTreeMap::new();
+    const EMPTY: VfsDirEntry = VfsDirEntry::default();
+    let mut buf = [EMPTY; 16];
+    let mut start_idx = 0;
+    loop {
+        let n = dir.read_dir(start_idx, &mut buf)?;
+        if n == 0 {
+            return Ok(entries);
+        }
+        start_idx += n;
+        // not every filesystem lists `.` and `..`, e.g., the FAT root
+        for ent in &buf[..n] {
+            let name = ent.name_as_bytes();
+            if name != b"." && name != b".." {
+                let name = String::from_utf8_lossy(name).into_owned();
+                entries.insert(name, ent.entry_type());
+            }
+        }
+    }
+}
+
+impl OverlayFileSystem {
+    /// Creates an overlay on `lower`, with an empty upper layer.
+    pub fn new(lower: Arc<dyn VfsOps>) -> Self {
+        Self {
+            layers: Arc::new(Layers {
+                lower,
+                upper: RamFileSystem::new().root_dir_node(),
+                whiteouts: Mutex::new(BTreeSet::new()),
+                mount_parent: Mutex::new(None),
+            }),
+        }
+    }
+}
+
+impl VfsOps for OverlayFileSystem {
+    fn mount(&self, _path: &str, mount_point: VfsNodeRef) -> VfsResult {
+        *self.layers.mount_parent.lock() = mount_point.parent();
+        Ok(())
+    }
+
+    fn umount(&self) -> VfsResult {
+        *self.layers.mount_parent.lock() = None;
+        Ok(())
+    }
+
+    fn root_dir(&self) -> VfsNodeRef {
+        Arc::new(OverlayNode::new(self.layers.clone(), String::new()))
+    }
+}
+
+impl Layers {
+    /// Whether the node at `path` in the lower layer is not hidden.
+    fn lower_visible(&self, path: &str) -> bool {
+        let whiteouts = self.whiteouts.lock();
+        let hidden = |prefix: &str| whiteouts.contains(prefix);
+        !path.match_indices('/').any(|(i, _)| hidden(&path[..i])) && !hidden(path)
+    }
+
+    fn lookup_upper(&self, path: &str) -> VfsResult<VfsNodeRef> {
+        lookup(self.upper.clone(), path)
+    }
+
+    fn lookup_lower(&self, path: &str) -> VfsResult<VfsNodeRef> {
+        if !path.is_empty() && !self.lower_visible(path) {
+            return Err(VfsError::NotFound);
+        }
+        lookup(self.lower.root_dir(), path)
+    }
+
+    /// Looks up the node at `path`, in the upper layer first.
+    fn resolve(&self, path: &str) -> VfsResult<VfsNodeRef> {
+        match self.lookup_upper(path) {
+            Err(VfsError::NotFound) => self.lookup_lower(path),
+            res => res,
+        }
+    }
+
+    /// Reads the merged entries of the directory at `path`.
+    fn read_dir(&self, path: &str) -> VfsResult<BTreeMap<String, VfsNodeType>> {
+        let mut entries = match self.lookup_upper(path) {
+            Ok(dir) => read_entries(&dir)?,
+            Err(VfsError::NotFound) => BTreeMap::new(),
+            Err(e) => return Err(e),
+        };
+        if let Ok(dir) = self.lookup_lower(path) {
+            if dir.get_attr()?.is_dir() {
+                for (name, ty) in read_entries(&dir)? {
+                    if !entries.contains_key(&name) && self.lower_visible(&join(path, &name)) {
+                        entries.insert(name, ty);
+                    }
+                }
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Copies the node at `path` and its parent directories up to the upper
+    /// layer if they are not there, returns the copy.
+    fn copy_up(&self, path: &str) -> VfsResult<VfsNodeRef> {
+        match self.lookup_upper(path) {
+            Err(VfsError::NotFound) => {}
+            res => return res,
+        }
+        let node = self.lookup_lower(path)?;
+        self.copy_up(split_parent(path).0)?;
+        let ty = node.get_attr()?.file_type();
+        match self.upper.create(path, ty) {
+            // copied up by others
+            Err(VfsError::AlreadyExists) => return self.lookup_upper(path),
+            res => res?,
+        }
+        debug!("overlay: copy up {:?} {}", ty, path);
+        let copy = self.lookup_upper(path)?;
+        if ty != VfsNodeType::Dir {
+            let mut buf = vec![0; 4096];
+            let mut offset = 0;
+            loop {
+                let n = node.read_at(offset, &mut buf)?;
+                if n == 0 {
+                    break;
+                }
+                copy.write_at(offset, &buf[..n])?;
+                offset += n as u64;
+            }
+        }
+        Ok(copy)
+    }
+
+    fn create(&self, path: &str, ty: VfsNodeType) -> VfsResult {
+        match self.resolve(path) {
+            Ok(_) => return Err(VfsError::AlreadyExists),
+            Err(VfsError::NotFound) => {}
+            Err(e) => return Err(e),
+        }
+        let parent = self.resolve(split_parent(path).0)?;
+        if !parent.get_attr()?.is_dir() {
+            return Err(VfsError::NotADirectory);
+        }
+        self.copy_up(split_parent(path).0)?;
+        // if there is a whiteout, it is kept to hide what is in the lower
+        // layer, e.g., entries of the removed directory
+        self.upper.create(path, ty)
+    }
+
+    fn remove(&self, path: &str) -> VfsResult {
+        let node = self.resolve(path)?;
+        if node.get_attr()?.is_dir() && !self.read_dir(path)?.is_empty() {
+            return Err(VfsError::DirectoryNotEmpty);
+        }
+        match self.upper.remove(path) {
+            Ok(()) | Err(VfsError::NotFound) => {}
+            Err(e) => return Err(e),
+        }
+        if self.lookup_lower(path).is_ok() {
+            self.whiteouts.lock().insert(path.into());
+        }
+        Ok(())
+    }
+
+    fn rename(&self, src_path: &str, dst_path: &str) -> VfsResult {
+        let node = self.resolve(src_path)?;
+        match self.resolve(dst_path) {
+            Ok(_) => return Err(VfsError::AlreadyExists),
+            Err(VfsError::NotFound) => {}
+            Err(e) => return Err(e),
+        }
+        let in_lower = self.lookup_lower(src_path).is_ok();
+        if in_lower && node.get_attr()?.is_dir() {
+            return Err(VfsError::Unsupported);
+        }
+        self.copy_up(src_path)?;
+        self.copy_up(split_parent(dst_path).0)?;
+        self.upper.rename(src_path, dst_path)?;
+        if in_lower {
+            self.whiteouts.lock().insert(src_path.into());
+        }
+        Ok(())
+    }
+
+    fn link(&self, src_path: &str, dst_path: &str) -> VfsResult {
+        match self.resolve(dst_path) {
+            Ok(_) => return Err(VfsError::AlreadyExists),
+            Err(VfsError::NotFound) => {}
+            Err(e) => return Err(e),
+        }
+        self.copy_up(src_path)?;
+        self.copy_up(split_parent(dst_path).0)?;
+        self.upper.link(src_path, dst_path)
+    }
+}
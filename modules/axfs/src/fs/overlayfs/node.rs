@@ -0,0 +1,157 @@
+use alloc::{string::String, sync::Arc, vec::Vec};
+use axfs_vfs::{VfsDirEntry, VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType};
+use axfs_vfs::{VfsError, VfsResult};
+
+use super::{split_parent, Layers};
+use crate::fs::LinkOps;
+
+/// A node of the overlay filesystem.
+///
+/// It refers to a path rather than a node of either layer, so it sees the
+/// copy in the upper layer after copied up.
+pub struct OverlayNode {
+    layers: Arc<Layers>,
+    path: String,
+}
+
+impl OverlayNode {
+    pub(super) fn new(layers: Arc<Layers>, path: String) -> Self {
+        Self { layers, path }
+    }
+
+    /// Returns the node in the upper layer, or in the lower layer if it is
+    /// not copied up.
+    pub fn real_node(&self) -> VfsResult<VfsNodeRef> {
+        self.layers.resolve(&self.path)
+    }
+
+    /// Returns the path of `rel_path` relative to this node, or the rest of
+    /// it after going above the root.
+    fn walk<'a>(&self, rel_path: &'a str) -> Result<String, &'a str> {
+        let mut parts: Vec<&str> = self.path.split('/').filter(|s| !s.is_empty()).collect();
+        let mut rest = rel_path;
+        while !rest.is_empty() {
+            let (name, next) = rest.split_once('/').unwrap_or((rest, ""));
+            rest = next;
+            match name {
+                "" | "." => {}
+                ".." => {
+                    if parts.pop().is_none() {
+                        return Err(rest);
+                    }
+                }
+                _ => parts.push(name),
+            }
+        }
+        Ok(parts.join("/"))
+    }
+
+    /// Returns the path of `rel_path` relative to this node, which must not go
+    /// above the root.
+    fn child_path(&self, rel_path: &str) -> VfsResult<String> {
+        match self.walk(rel_path) {
+            Ok(path) if !path.is_empty() => Ok(path),
+            Ok(_) => Err(VfsError::InvalidInput),
+            Err(_) => Err(VfsError::PermissionDenied),
+        }
+    }
+}
+
+impl LinkOps for OverlayNode {
+    fn link(&self, src_path: &str, dst_path: &str) -> VfsResult {
+        let src_path = self.child_path(src_path)?;
+        let dst_path = self.child_path(dst_path)?;
+        self.layers.link(&src_path, &dst_path)
+    }
+}
+
+impl VfsNodeOps for OverlayNode {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        self.real_node()?.get_attr()
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        self.real_node()?.read_at(offset, buf)
+    }
+
+    fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        self.layers.copy_up(&self.path)?.write_at(offset, buf)
+    }
+
+    fn fsync(&self) -> VfsResult {
+        self.real_node()?.fsync()
+    }
+
+    fn truncate(&self, size: u64) -> VfsResult {
+        self.layers.copy_up(&self.path)?.truncate(size)
+    }
+
+    fn parent(&self) -> Option<VfsNodeRef> {
+        if self.path.is_empty() {
+            self.layers.mount_parent.lock().clone()
+        } else {
+            let parent = split_parent(&self.path).0;
+            Some(Arc::new(Self::new(self.layers.clone(), parent.into())))
+        }
+    }
+
+    fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
+        debug!("lookup at overlay: {}/{}", self.path, path);
+        match self.walk(path) {
+            Ok(path) => {
+                self.layers.resolve(&path)?;
+                Ok(Arc::new(Self::new(self.layers.clone(), path)))
+            }
+            Err(rest) => match self.layers.mount_parent.lock().clone() {
+                Some(parent) => parent.lookup(rest),
+                None => Arc::new(Self::new(self.layers.clone(), String::new())).lookup(rest),
+            },
+        }
+    }
+
+    fn read_dir(&self, start_idx: usize, dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
+        if !self.get_attr()?.is_dir() {
+            return Err(VfsError::NotADirectory);
+        }
+        let entries = self.layers.read_dir(&self.path)?;
+        let mut entries = entries.iter().skip(start_idx.max(2) - 2);
+        for (i, ent) in dirents.iter_mut().enumerate() {
+            match i + start_idx {
+                0 => *ent = VfsDirEntry::new(".", VfsNodeType::Dir),
+                1 => *ent = VfsDirEntry::new("..", VfsNodeType::Dir),
+                _ => match entries.next() {
+                    Some((name, ty)) => *ent = VfsDirEntry::new(name, *ty),
+                    None => return Ok(i),
+                },
+            }
+        }
+        Ok(dirents.len())
+    }
+
+    fn create(&self, path: &str, ty: VfsNodeType) -> VfsResult {
+        debug!("create {:?} at overlay: {}/{}", ty, self.path, path);
+        self.layers.create(&self.child_path(path)?, ty)
+    }
+
+    fn remove(&self, path: &str) -> VfsResult {
+        debug!("remove at overlay: {}/{}", self.path, path);
+        self.layers.remove(&self.child_path(path)?)
+    }
+
+    fn rename(&self, src_path: &str, dst_path: &str) -> VfsResult {
+        debug!(
+            "rename at overlay, src_path: {}/{}, dst_path: {}/{}",
+            self.path, src_path, self.path, dst_path
+        );
+        let src_path = self.child_path(src_path)?;
+        let dst_path = self.child_path(dst_path)?;
+        if dst_path.starts_with(&src_path) && dst_path[src_path.len()..].starts_with('/') {
+            return Err(VfsError::InvalidInput); // cannot move a directory into itself
+        }
+        self.layers.rename(&src_path, &dst_path)
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+}
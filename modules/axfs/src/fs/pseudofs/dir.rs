@@ -0,0 +1,203 @@
+use alloc::collections::BTreeMap;
+use alloc::sync::{Arc, Weak};
+use alloc::{string::String, vec::Vec};
+
+use axfs_vfs::{VfsDirEntry, VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeRef, VfsNodeType};
+use axfs_vfs::{VfsError, VfsResult};
+use axsync::Mutex;
+
+use super::file::{FileNode, Generator};
+
+/// Lists the entries of a [`DirNode`] that change over time, e.g., one per
+/// task. It is given the directory to be the parent of new nodes.
+pub type Lister = Arc<dyn Fn(&Arc<DirNode>) -> Vec<(String, VfsNodeRef)> + Send + Sync>;
+
+/// The directory node in the pseudo filesystem.
+pub struct DirNode {
+    this: Weak<DirNode>,
+    parent: Weak<DirNode>,
+    /// The parent of the mount point, to look up `..` from the root.
+    mount_parent: Mutex<Option<VfsNodeRef>>,
+    children: Mutex<BTreeMap<String, VfsNodeRef>>,
+    lister: Mutex<Option<Lister>>,
+}
+
+impl DirNode {
+    /// Creates an empty directory in `parent`, which is not added to it.
+    pub fn new(parent: Option<&Arc<DirNode>>) -> Arc<Self> {
+        Arc::new_cyclic(|this| Self {
+            this: this.clone(),
+            parent: parent.map_or(Weak::new(), Arc::downgrade),
+            mount_parent: Mutex::new(None),
+            children: Mutex::new(BTreeMap::new()),
+            lister: Mutex::new(None),
+        })
+    }
+
+    pub(super) fn set_mount_parent(&self, parent: Option<VfsNodeRef>) {
+        *self.mount_parent.lock() = parent;
+    }
+
+    /// Creates a subdirectory with the given name, or returns the existing
+    /// one.
+    pub fn mkdir(&self, name: &str) -> Arc<Self> {
+        let mut children = self.children.lock();
+        if let Some(dir) = children.get(name).and_then(Self::downcast) {
+            return dir;
+        }
+        let dir = Self::new(Some(&self.this.upgrade().unwrap()));
+        children.insert(name.into(), dir.clone());
+        dir
+    }
+
+    /// Adds a node with the given name, replacing the existing one.
+    pub fn add(&self, name: &str, node: VfsNodeRef) {
+        self.children.lock().insert(name.into(), node);
+    }
+
+    /// Adds a file whose content is generated by `generate` when it is read.
+    pub fn add_file<F>(&self, name: &str, generate: F)
+    where
+        F: Fn() -> VfsResult<String> + Send + Sync + 'static,
+    {
+        self.add(name, new_node(VfsNodeType::File, Arc::new(generate)));
+    }
+
+    /// Adds a symbolic link whose target is generated by `target`.
+    pub fn add_symlink<F>(&self, name: &str, target: F)
+    where
+        F: Fn() -> VfsResult<String> + Send + Sync + 'static,
+    {
+        self.add(name, new_node(VfsNodeType::SymLink, Arc::new(target)));
+    }
+
+    /// Sets the function to list entries in addition to the added ones.
+    pub fn set_lister<F>(&self, lister: F)
+    where
+        F: Fn(&Arc<DirNode>) -> Vec<(String, VfsNodeRef)> + Send + Sync + 'static,
+    {
+        *self.lister.lock() = Some(Arc::new(lister));
+    }
+
+    fn downcast(node: &VfsNodeRef) -> Option<Arc<Self>> {
+        node.as_any()
+            .downcast_ref::<Self>()
+            .and_then(|dir| dir.this.upgrade())
+    }
+
+    /// Returns all entries, the added ones first.
+    fn entries(&self) -> Vec<(String, VfsNodeRef)> {
+        let mut entries: Vec<_> = self
+            .children
+            .lock()
+            .iter()
+            .map(|(name, node)| (name.clone(), node.clone()))
+            .collect();
+        let lister = self.lister.lock().clone();
+        if let Some(lister) = lister {
+            entries.extend(lister(&self.this.upgrade().unwrap()));
+        }
+        entries
+    }
+
+    /// Returns the entry with the given name. A file is reopened, so that it
+    /// has its own content.
+    fn child(&self, name: &str) -> Option<VfsNodeRef> {
+        let node = self.children.lock().get(name).cloned();
+        let node = node.or_else(|| {
+            let lister = self.lister.lock().clone()?;
+            lister(&self.this.upgrade().unwrap())
+                .into_iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, node)| node)
+        })?;
+        match node.as_any().downcast_ref::<FileNode>() {
+            Some(file) => Some(file.reopen()),
+            None => Some(node),
+        }
+    }
+}
+
+fn new_node(ty: VfsNodeType, generate: Generator) -> VfsNodeRef {
+    Arc::new(FileNode::new(ty, generate))
+}
+
+impl VfsNodeOps for DirNode {
+    axfs_vfs::impl_vfs_dir_default! {}
+
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        let perm = VfsNodePerm::from_bits_truncate(0o555);
+        Ok(VfsNodeAttr::new(perm, VfsNodeType::Dir, 0, 0))
+    }
+
+    fn parent(&self) -> Option<VfsNodeRef> {
+        match self.parent.upgrade() {
+            Some(parent) => Some(parent as VfsNodeRef),
+            None => self.mount_parent.lock().clone(),
+        }
+    }
+
+    fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
+        debug!("lookup at pseudofs: {}", path);
+        let mut dir = self;
+        let mut rest = path.trim_start_matches('/');
+        while !rest.is_empty() {
+            let (name, next) = rest.split_once('/').unwrap_or((rest, ""));
+            rest = next.trim_start_matches('/');
+            let node = match name {
+                "" | "." => continue,
+                ".." => {
+                    if let Some(parent) = dir.parent.upgrade() {
+                        dir = parent;
+                    } else if let Some(parent) = dir.mount_parent.lock().clone() {
+                        return parent.lookup(rest);
+                    }
+                    continue;
+                }
+                _ => dir.child(name).ok_or(VfsError::NotFound)?,
+            };
+            if rest.is_empty() {
+                return Ok(node);
+            }
+            dir = Self::downcast(&node).ok_or(VfsError::NotADirectory)?;
+        }
+        Ok(dir)
+    }
+
+    fn create(&self, path: &str, _ty: VfsNodeType) -> VfsResult {
+        // nodes are only added by the kernel
+        match self.this.upgrade().unwrap().lookup(path) {
+            Ok(_) => Err(VfsError::AlreadyExists),
+            Err(VfsError::NotFound) => Err(VfsError::PermissionDenied),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn remove(&self, path: &str) -> VfsResult {
+        self.this.upgrade().unwrap().lookup(path)?;
+        Err(VfsError::PermissionDenied)
+    }
+
+    fn read_dir(&self, start_idx: usize, dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
+        let entries = self.entries();
+        let mut entries = entries.iter().skip(start_idx.max(2) - 2);
+        for (i, ent) in dirents.iter_mut().enumerate() {
+            match i + start_idx {
+                0 => *ent = VfsDirEntry::new(".", VfsNodeType::Dir),
+                1 => *ent = VfsDirEntry::new("..", VfsNodeType::Dir),
+                _ => {
+                    if let Some((name, node)) = entries.next() {
+                        *ent = VfsDirEntry::new(name, node.get_attr()?.file_type());
+                    } else {
+                        return Ok(i);
+                    }
+                }
+            }
+        }
+        Ok(dirents.len())
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+}
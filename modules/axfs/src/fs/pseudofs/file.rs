@@ -0,0 +1,66 @@
+use alloc::{string::String, sync::Arc};
+use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeType, VfsResult};
+use axsync::Mutex;
+
+/// Generates the content of a [`FileNode`].
+pub type Generator = Arc<dyn Fn() -> VfsResult<String> + Send + Sync>;
+
+/// A file whose content is generated when it is read. A symbolic link is a
+/// file whose content is the target.
+pub struct FileNode {
+    ty: VfsNodeType,
+    generate: Generator,
+    /// The content generated by the last read from the beginning, so that a
+    /// file read in pieces is consistent.
+    content: Mutex<Option<String>>,
+}
+
+impl FileNode {
+    pub(super) fn new(ty: VfsNodeType, generate: Generator) -> Self {
+        Self {
+            ty,
+            generate,
+            content: Mutex::new(None),
+        }
+    }
+
+    /// Returns a new node with the same generator, so that each opened file
+    /// has its own content.
+    pub(super) fn reopen(&self) -> Arc<Self> {
+        Arc::new(Self::new(self.ty, self.generate.clone()))
+    }
+}
+
+impl VfsNodeOps for FileNode {
+    axfs_vfs::impl_vfs_non_dir_default! {}
+
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        // The size of a file is unknown until it is read, as in Linux.
+        let (perm, size) = match self.ty {
+            VfsNodeType::SymLink => (0o777, (self.generate)()?.len() as u64),
+            _ => (0o444, 0),
+        };
+        let perm = VfsNodePerm::from_bits_truncate(perm);
+        Ok(VfsNodeAttr::new(perm, self.ty, size, 0))
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        let mut content = self.content.lock();
+        if offset == 0 || content.is_none() {
+            *content = Some((self.generate)()?);
+        }
+        let bytes = content.as_deref().unwrap_or_default().as_bytes();
+        let start = bytes.len().min(offset as usize);
+        let end = bytes.len().min(start + buf.len());
+        buf[..end - start].copy_from_slice(&bytes[start..end]);
+        Ok(end - start)
+    }
+
+    fn fsync(&self) -> VfsResult {
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+}
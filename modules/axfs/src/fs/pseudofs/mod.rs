@@ -0,0 +1,55 @@
+//! Pseudo filesystem, used for `/proc`, `/sys` and `/dev`.
+//!
+//! Its files do not store data, but generate their content when read, e.g.,
+//! from the kernel state. Directories may list entries that change over time,
+//! or hold other nodes such as device files.
+
+mod dir;
+mod file;
+
+use alloc::sync::Arc;
+use axfs_vfs::{VfsNodeRef, VfsOps, VfsResult};
+
+pub use self::dir::DirNode;
+pub use self::file::FileNode;
+
+/// A pseudo filesystem that implements [`axfs_vfs::VfsOps`].
+pub struct PseudoFileSystem {
+    root: Arc<DirNode>,
+}
+
+impl PseudoFileSystem {
+    /// Create a new instance.
+    pub fn new() -> Self {
+        Self {
+            root: DirNode::new(None),
+        }
+    }
+
+    /// Returns the root directory node in [`Arc<DirNode>`](DirNode).
+    pub fn root_dir_node(&self) -> Arc<DirNode> {
+        self.root.clone()
+    }
+}
+
+impl VfsOps for PseudoFileSystem {
+    fn mount(&self, _path: &str, mount_point: VfsNodeRef) -> VfsResult {
+        self.root.set_mount_parent(mount_point.parent());
+        Ok(())
+    }
+
+    fn umount(&self) -> VfsResult {
+        self.root.set_mount_parent(None);
+        Ok(())
+    }
+
+    fn root_dir(&self) -> VfsNodeRef {
+        self.root.clone()
+    }
+}
+
+impl Default for PseudoFileSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
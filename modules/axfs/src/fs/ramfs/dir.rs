@@ -0,0 +1,261 @@
+use alloc::collections::BTreeMap;
+use alloc::sync::{Arc, Weak};
+use alloc::{string::String, vec::Vec};
+
+use axfs_vfs::{VfsDirEntry, VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType};
+use axfs_vfs::{VfsError, VfsResult};
+use axsync::Mutex;
+
+use super::file::FileNode;
+use crate::fs::{current_time, LinkOps, NodeMeta};
+
+/// The directory node in the RAM filesystem.
+pub struct DirNode {
+    this: Weak<DirNode>,
+    parent: Mutex<Weak<DirNode>>,
+    /// The parent of the mount point, to look up `..` from the root.
+    mount_parent: Mutex<Option<VfsNodeRef>>,
+    children: Mutex<BTreeMap<String, VfsNodeRef>>,
+    meta: Mutex<NodeMeta>,
+}
+
+/// Splits `path` into the parent part and the last component.
+fn split_parent(path: &str) -> (&str, &str) {
+    let path = path.trim_end_matches('/');
+    match path.rfind('/') {
+        Some(pos) => (&path[..pos], &path[pos + 1..]),
+        None => ("", path),
+    }
+}
+
+impl DirNode {
+    pub(super) fn new(parent: Option<Weak<DirNode>>) -> Arc<Self> {
+        Arc::new_cyclic(|this| Self {
+            this: this.clone(),
+            parent: Mutex::new(parent.unwrap_or_default()),
+            mount_parent: Mutex::new(None),
+            children: Mutex::new(BTreeMap::new()),
+            meta: Mutex::new(NodeMeta::new(current_time())),
+        })
+    }
+
+    /// Returns the timestamps and ownership of the directory.
+    pub fn meta(&self) -> NodeMeta {
+        *self.meta.lock()
+    }
+
+    /// Updates the modification time, when entries are added or removed.
+    fn touch_modified(&self) {
+        let now = current_time();
+        let mut meta = self.meta.lock();
+        meta.mtime = now;
+        meta.ctime = now;
+    }
+
+    pub(super) fn set_mount_parent(&self, parent: Option<VfsNodeRef>) {
+        *self.mount_parent.lock() = parent;
+    }
+
+    /// Returns a string list of all entries in this directory.
+    pub fn get_entries(&self) -> Vec<String> {
+        self.children.lock().keys().cloned().collect()
+    }
+
+    /// Checks whether a node with the given name exists in this directory.
+    pub fn exist(&self, name: &str) -> bool {
+        self.children.lock().contains_key(name)
+    }
+
+    /// Creates a new node with the given name and type in this directory.
+    pub fn create_node(&self, name: &str, ty: VfsNodeType) -> VfsResult {
+        let mut children = self.children.lock();
+        if children.contains_key(name) {
+            return Err(VfsError::AlreadyExists);
+        }
+        let node: VfsNodeRef = match ty {
+            VfsNodeType::File | VfsNodeType::SymLink => Arc::new(FileNode::new(ty)),
+            VfsNodeType::Dir => Self::new(Some(self.this.clone())),
+            _ => return Err(VfsError::Unsupported),
+        };
+        children.insert(name.into(), node);
+        self.touch_modified();
+        Ok(())
+    }
+
+    /// Removes a node by the given name in this directory.
+    pub fn remove_node(&self, name: &str) -> VfsResult {
+        let mut children = self.children.lock();
+        let node = children.get(name).ok_or(VfsError::NotFound)?;
+        if let Some(dir) = node.as_any().downcast_ref::<DirNode>() {
+            if !dir.children.lock().is_empty() {
+                return Err(VfsError::DirectoryNotEmpty);
+            }
+        }
+        children.remove(name);
+        self.touch_modified();
+        Ok(())
+    }
+
+    /// Looks up `path` relative to this directory. `..` at the root goes to
+    /// the parent of the mount point, which is returned with the rest path.
+    fn walk<'a>(&self, path: &'a str) -> VfsResult<Result<VfsNodeRef, (VfsNodeRef, &'a str)>> {
+        let mut dir = self.this.upgrade().unwrap();
+        let mut rest = path.trim_start_matches('/');
+        while !rest.is_empty() {
+            let (name, next) = rest.split_once('/').unwrap_or((rest, ""));
+            rest = next.trim_start_matches('/');
+            let node = match name {
+                "" | "." => continue,
+                ".." => {
+                    let parent = dir.parent.lock().upgrade();
+                    if let Some(parent) = parent {
+                        dir = parent;
+                    } else if let Some(parent) = dir.mount_parent.lock().clone() {
+                        return Ok(Err((parent, rest)));
+                    }
+                    continue;
+                }
+                _ => dir.children.lock().get(name).cloned(),
+            };
+            let node = node.ok_or(VfsError::NotFound)?;
+            if rest.is_empty() {
+                return Ok(Ok(node));
+            }
+            dir = match node.as_any().downcast_ref::<DirNode>() {
+                Some(child) => child.this.upgrade().unwrap(),
+                None => return Err(VfsError::NotADirectory),
+            };
+        }
+        Ok(Ok(dir))
+    }
+
+    /// Looks up the directory containing `path` in this filesystem, returns
+    /// it and the last component.
+    fn parent_of<'a>(&self, path: &'a str) -> VfsResult<(Arc<DirNode>, &'a str)> {
+        let (parent, name) = split_parent(path);
+        if name.is_empty() || name == "." || name == ".." {
+            return Err(VfsError::InvalidInput);
+        }
+        match self.walk(parent)? {
+            Ok(node) => match node.as_any().downcast_ref::<DirNode>() {
+                Some(dir) => Ok((dir.this.upgrade().unwrap(), name)),
+                None => Err(VfsError::NotADirectory),
+            },
+            // other filesystems are not accessible
+            Err(_) => Err(VfsError::PermissionDenied),
+        }
+    }
+}
+
+impl LinkOps for DirNode {
+    fn link(&self, src_path: &str, dst_path: &str) -> VfsResult {
+        let node = match self.walk(src_path)? {
+            Ok(node) => node,
+            Err(_) => return Err(VfsError::PermissionDenied),
+        };
+        let Some(file) = node.as_any().downcast_ref::<FileNode>() else {
+            return Err(VfsError::PermissionDenied);
+        };
+        let (dir, name) = self.parent_of(dst_path)?;
+        let mut children = dir.children.lock();
+        if children.contains_key(name) {
+            return Err(VfsError::AlreadyExists);
+        }
+        file.touch_changed();
+        children.insert(name.into(), node);
+        dir.touch_modified();
+        Ok(())
+    }
+}
+
+impl VfsNodeOps for DirNode {
+    axfs_vfs::impl_vfs_dir_default! {}
+
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        Ok(VfsNodeAttr::new_dir(4096, 0))
+    }
+
+    fn parent(&self) -> Option<VfsNodeRef> {
+        match self.parent.lock().upgrade() {
+            Some(parent) => Some(parent as VfsNodeRef),
+            None => self.mount_parent.lock().clone(),
+        }
+    }
+
+    fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
+        debug!("lookup at ramfs: {}", path);
+        match self.walk(path)? {
+            Ok(node) => Ok(node),
+            Err((parent, rest)) => parent.lookup(rest),
+        }
+    }
+
+    fn read_dir(&self, start_idx: usize, dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
+        let children = self.children.lock();
+        let mut children = children.iter().skip(start_idx.max(2) - 2);
+        for (i, ent) in dirents.iter_mut().enumerate() {
+            match i + start_idx {
+                0 => *ent = VfsDirEntry::new(".", VfsNodeType::Dir),
+                1 => *ent = VfsDirEntry::new("..", VfsNodeType::Dir),
+                _ => {
+                    if let Some((name, node)) = children.next() {
+                        *ent = VfsDirEntry::new(name, node.get_attr()?.file_type());
+                    } else {
+                        return Ok(i);
+                    }
+                }
+            }
+        }
+        Ok(dirents.len())
+    }
+
+    fn create(&self, path: &str, ty: VfsNodeType) -> VfsResult {
+        debug!("create {:?} at ramfs: {}", ty, path);
+        let (dir, name) = self.parent_of(path)?;
+        dir.create_node(name, ty)
+    }
+
+    fn remove(&self, path: &str) -> VfsResult {
+        debug!("remove at ramfs: {}", path);
+        let (dir, name) = self.parent_of(path)?;
+        dir.remove_node(name)
+    }
+
+    fn rename(&self, src_path: &str, dst_path: &str) -> VfsResult {
+        debug!(
+            "rename at ramfs, src_path: {}, dst_path: {}",
+            src_path, dst_path
+        );
+        let (src_dir, src_name) = self.parent_of(src_path)?;
+        let (dst_dir, dst_name) = self.parent_of(dst_path)?;
+        let node = src_dir
+            .children
+            .lock()
+            .get(src_name)
+            .cloned()
+            .ok_or(VfsError::NotFound)?;
+        if dst_dir.exist(dst_name) {
+            return Err(VfsError::AlreadyExists);
+        }
+        if let Some(moved) = node.as_any().downcast_ref::<DirNode>() {
+            // cannot move a directory into itself
+            let mut dir = Some(dst_dir.clone());
+            while let Some(d) = dir {
+                if Arc::ptr_eq(&d, &moved.this.upgrade().unwrap()) {
+                    return Err(VfsError::InvalidInput);
+                }
+                dir = d.parent.lock().upgrade();
+            }
+            *moved.parent.lock() = Arc::downgrade(&dst_dir);
+        }
+        src_dir.children.lock().remove(src_name);
+        dst_dir.children.lock().insert(dst_name.into(), node);
+        src_dir.touch_modified();
+        dst_dir.touch_modified();
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+}
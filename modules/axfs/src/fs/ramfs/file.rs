@@ -0,0 +1,88 @@
+use alloc::vec::Vec;
+use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeType, VfsResult};
+use axsync::Mutex;
+
+use crate::fs::{current_time, NodeMeta};
+
+/// The file node in the RAM filesystem. A symbolic link is a file node that
+/// stores its target as the content.
+pub struct FileNode {
+    ty: VfsNodeType,
+    content: Mutex<Vec<u8>>,
+    meta: Mutex<NodeMeta>,
+}
+
+impl FileNode {
+    pub(super) fn new(ty: VfsNodeType) -> Self {
+        Self {
+            ty,
+            content: Mutex::new(Vec::new()),
+            meta: Mutex::new(NodeMeta::new(current_time())),
+        }
+    }
+
+    /// Returns the timestamps and ownership of the file.
+    pub fn meta(&self) -> NodeMeta {
+        *self.meta.lock()
+    }
+
+    /// Updates the change time, when a link to the file is added.
+    pub(super) fn touch_changed(&self) {
+        self.meta.lock().ctime = current_time();
+    }
+
+    fn touch_modified(&self) {
+        let now = current_time();
+        let mut meta = self.meta.lock();
+        meta.mtime = now;
+        meta.ctime = now;
+    }
+}
+
+impl VfsNodeOps for FileNode {
+    axfs_vfs::impl_vfs_non_dir_default! {}
+
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        let size = self.content.lock().len() as u64;
+        let perm = match self.ty {
+            VfsNodeType::SymLink => VfsNodePerm::from_bits_truncate(0o777),
+            _ => VfsNodePerm::default_file(),
+        };
+        Ok(VfsNodeAttr::new(perm, self.ty, size, size.div_ceil(512)))
+    }
+
+    fn truncate(&self, size: u64) -> VfsResult {
+        self.content.lock().resize(size as usize, 0);
+        self.touch_modified();
+        Ok(())
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        let content = self.content.lock();
+        let start = content.len().min(offset as usize);
+        let end = content.len().min(offset as usize + buf.len());
+        let src = &content[start..end];
+        buf[..src.len()].copy_from_slice(src);
+        self.meta.lock().atime = current_time();
+        Ok(src.len())
+    }
+
+    fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        let offset = offset as usize;
+        let mut content = self.content.lock();
+        if offset + buf.len() > content.len() {
+            content.resize(offset + buf.len(), 0);
+        }
+        content[offset..offset + buf.len()].copy_from_slice(buf);
+        self.touch_modified();
+        Ok(buf.len())
+    }
+
+    fn fsync(&self) -> VfsResult {
+        Ok(())
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+}
@@ -0,0 +1,54 @@
+//! RAM filesystem, used for `/tmp`.
+//!
+//! Besides files and directories, it supports symbolic links, and hard links
+//! to files.
+
+mod dir;
+mod file;
+
+use alloc::sync::Arc;
+use axfs_vfs::{VfsNodeRef, VfsOps, VfsResult};
+
+pub use self::dir::DirNode;
+pub use self::file::FileNode;
+
+/// A RAM filesystem that implements [`axfs_vfs::VfsOps`].
+pub struct RamFileSystem {
+    root: Arc<DirNode>,
+}
+
+impl RamFileSystem {
+    /// Create a new instance.
+    pub fn new() -> Self {
+        Self {
+            root: DirNode::new(None),
+        }
+    }
+
+    /// Returns the root directory node in [`Arc<DirNode>`](DirNode).
+    pub fn root_dir_node(&self) -> Arc<DirNode> {
+        self.root.clone()
+    }
+}
+
+impl VfsOps for RamFileSystem {
+    fn mount(&self, _path: &str, mount_point: VfsNodeRef) -> VfsResult {
+        self.root.set_mount_parent(mount_point.parent());
+        Ok(())
+    }
+
+    fn umount(&self) -> VfsResult {
+        self.root.set_mount_parent(None);
+        Ok(())
+    }
+
+    fn root_dir(&self) -> VfsNodeRef {
+        self.root.clone()
+    }
+}
+
+impl Default for RamFileSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,170 @@
+//! Initial RAM filesystem, unpacked from a cpio archive in the `newc` format.
+//!
+//! See <https://www.kernel.org/doc/html/latest/driver-api/early-userspace/buffer-format.html>.
+
+use alloc::{collections::BTreeMap, string::String, sync::Arc};
+use axfs_vfs::{VfsError, VfsNodeOps, VfsNodeType, VfsResult};
+
+use crate::fs::{ramfs::DirNode, LinkOps};
+
+const HEADER_SIZE: usize = 110;
+const MAGIC_NEWC: &[u8] = b"070701";
+const MAGIC_NEWC_CRC: &[u8] = b"070702";
+const TRAILER: &str = "TRAILER!!!";
+
+const S_IFMT: u32 = 0o170000;
+const S_IFDIR: u32 = 0o040000;
+const S_IFREG: u32 = 0o100000;
+const S_IFLNK: u32 = 0o120000;
+
+/// An entry of the archive.
+struct Entry<'a> {
+    ino: u32,
+    mode: u32,
+    nlink: u32,
+    dev: (u32, u32),
+    name: &'a str,
+    data: &'a [u8],
+}
+
+/// Parses the hexadecimal field at `index` of the header.
+fn parse_field(header: &[u8], index: usize) -> VfsResult<u32> {
+    let start = 6 + index * 8;
+    let field =
+        core::str::from_utf8(&header[start..start + 8]).map_err(|_| VfsError::InvalidData)?;
+    u32::from_str_radix(field, 16).map_err(|_| VfsError::InvalidData)
+}
+
+/// Reads entries of one or more concatenated archives.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    /// Takes `len` bytes, and skips the padding to 4 bytes after them if
+    /// `aligned`.
+    fn take(&mut self, len: usize, aligned: bool) -> VfsResult<&'a [u8]> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + len)
+            .ok_or(VfsError::InvalidData)?;
+        self.pos += len;
+        if aligned {
+            self.pos = self.pos.next_multiple_of(4);
+        }
+        Ok(bytes)
+    }
+
+    fn next_entry(&mut self) -> VfsResult<Option<Entry<'a>>> {
+        loop {
+            // archives may be padded with zeros
+            while self.data.get(self.pos) == Some(&0) {
+                self.pos += 1;
+            }
+            if self.pos >= self.data.len() {
+                return Ok(None);
+            }
+            let header = self.take(HEADER_SIZE, false)?;
+            if !header.starts_with(MAGIC_NEWC) && !header.starts_with(MAGIC_NEWC_CRC) {
+                warn!("initramfs: not a cpio archive in the newc format");
+                return Err(VfsError::InvalidData);
+            }
+            let name_size = parse_field(header, 11)? as usize;
+            let name = self.take(name_size, true)?;
+            let name = name.strip_suffix(b"\0").ok_or(VfsError::InvalidData)?;
+            let name = core::str::from_utf8(name).map_err(|_| VfsError::InvalidData)?;
+            let data = self.take(parse_field(header, 6)? as usize, true)?;
+            if name == TRAILER {
+                continue; // another archive may follow
+            }
+            return Ok(Some(Entry {
+                ino: parse_field(header, 0)?,
+                mode: parse_field(header, 1)?,
+                nlink: parse_field(header, 4)?,
+                dev: (parse_field(header, 7)?, parse_field(header, 8)?),
+                name,
+                data,
+            }));
+        }
+    }
+}
+
+/// Creates a node at `path`, replacing the existing one if it is not a
+/// directory.
+fn create(root: &Arc<DirNode>, path: &str, ty: VfsNodeType) -> VfsResult {
+    match root.create(path, ty) {
+        Err(VfsError::AlreadyExists) if ty == VfsNodeType::Dir => Ok(()),
+        Err(VfsError::AlreadyExists) => {
+            root.remove(path)?;
+            root.create(path, ty)
+        }
+        res => res,
+    }
+}
+
+/// Creates the missing parent directories of `path`.
+fn create_parents(root: &Arc<DirNode>, path: &str) -> VfsResult {
+    for (i, _) in path.match_indices('/') {
+        create(root, &path[..i], VfsNodeType::Dir)?;
+    }
+    Ok(())
+}
+
+fn write_content(root: &Arc<DirNode>, path: &str, data: &[u8]) -> VfsResult {
+    if !data.is_empty() {
+        root.clone().lookup(path)?.write_at(0, data)?;
+    }
+    Ok(())
+}
+
+/// Unpacks the cpio `archive` into the RAM filesystem at `root`.
+///
+/// Directories, regular files, symbolic links and hard links are unpacked,
+/// while other types of files (e.g., device files) are skipped.
+pub(crate) fn unpack(archive: &[u8], root: &Arc<DirNode>) -> VfsResult {
+    let mut reader = Reader {
+        data: archive,
+        pos: 0,
+    };
+    // the first path of each file with multiple links
+    let mut links = BTreeMap::<(u32, u32, u32), String>::new();
+    while let Some(entry) = reader.next_entry()? {
+        let path = entry.name.trim_start_matches("./").trim_matches('/');
+        if path.is_empty() || path == "." {
+            continue;
+        }
+        debug!("initramfs: unpack {} ({:o})", path, entry.mode);
+        create_parents(root, path)?;
+        match entry.mode & S_IFMT {
+            S_IFDIR => create(root, path, VfsNodeType::Dir)?,
+            S_IFREG if entry.nlink > 1 => {
+                // only the last link has the content
+                let key = (entry.dev.0, entry.dev.1, entry.ino);
+                if let Some(first) = links.get(&key) {
+                    if root.clone().lookup(path).is_ok() {
+                        root.remove(path)?;
+                    }
+                    root.link(first, path)?;
+                } else {
+                    create(root, path, VfsNodeType::File)?;
+                    links.insert(key, path.into());
+                }
+                write_content(root, path, entry.data)?;
+            }
+            S_IFREG => {
+                create(root, path, VfsNodeType::File)?;
+                write_content(root, path, entry.data)?;
+            }
+            S_IFLNK => {
+                create(root, path, VfsNodeType::SymLink)?;
+                write_content(root, path, entry.data)?;
+            }
+            _ => warn!(
+                "initramfs: skip {}, unsupported mode {:o}",
+                path, entry.mode
+            ),
+        }
+    }
+    Ok(())
+}
@@ -6,17 +6,55 @@
 //!
 //! - `fatfs`: Use [FAT] as the main filesystem and mount it on `/`. This feature
 //!    is **enabled** by default.
-//! - `devfs`: Mount [`axfs_devfs::DeviceFileSystem`] on `/dev`. This feature is
-//!    **enabled** by default.
-//! - `ramfs`: Mount [`axfs_ramfs::RamFileSystem`] on `/tmp`. This feature is
-//!    **enabled** by default.
+//! - `extfs`: Use an [ext2/ext4] filesystem as the main filesystem if the root
+//!    device contains one, otherwise fall back to FAT. ext2 volumes can be read
+//!    and written, while ext4 volumes (extents, 64-bit, etc.) are read-only.
+//!    This feature is **disabled** by default.
+//! - `devfs`: Mount device files on `/dev`, e.g., `/dev/null`, `/dev/console`,
+//!    `/dev/urandom`, `/dev/rtc` and `/dev/<name>` for block devices, which
+//!    also support `ioctl` by [`File::ioctl`]. This feature is **enabled** by
+//!    default.
+//! - `ramfs`: Mount a RAM filesystem on `/tmp`, which supports symbolic links
+//!    and hard links. This feature is **enabled** by default.
+//! - `procfs`: Mount a pseudo filesystem on `/proc`, whose files are generated
+//!    from the kernel state when read, e.g., `/proc/meminfo` and `/proc/mounts`.
+//!    This feature is **enabled** by default.
+//! - `sysfs`: Mount a pseudo filesystem on `/sys`, which shows the devices
+//!    probed by [axdriver], block devices and the clock source, e.g.,
+//!    `/sys/bus/pci/devices` and `/sys/class/block/<name>/size`. This feature
+//!    is **enabled** by default.
+//! - `multitask`, `irq`, `net`: Show tasks (`/proc/<tid>/status`), interrupt
+//!    counts (`/proc/interrupts`) and network interfaces (`/proc/net/dev` and
+//!    `/sys/class/net`) in procfs and sysfs. They are enabled along with the
+//!    same features of other modules.
+//! - `display`: Add `/dev/fb0` for the framebuffer of [axdisplay]. It is
+//!    enabled along with the same feature of other modules.
+//! - `initramfs`: Allow to use a RAM filesystem unpacked from an initramfs
+//!    (a cpio archive in the `newc` format) as the main filesystem, by
+//!    [`init_filesystems_with_initramfs`]. The archive can be embedded in the
+//!    kernel image at build time by the `AX_INITRAMFS` environment variable,
+//!    see [`embedded_initramfs`]. This feature is **disabled** by default.
+//! - `overlayfs`: Overlay the main filesystem with a RAM filesystem, so that
+//!    it is never modified, and changes are discarded at reboot. Other
+//!    filesystems can be overlaid by [`api::mount_overlay`]. This feature is
+//!    **disabled** by default.
+//! - `9p`: Allow to mount directories shared by 9P servers by [`api::mount_9p`],
+//!    through the transports registered by [`register_9p_transport`]. This
+//!    feature is **disabled** by default.
+//! - `virtio-9p`: Enable `9p`, and mount the directories shared by virtio-9p
+//!    devices (e.g., QEMU `-virtfs`) on `/mnt/<tag>` by [`init_9p_devices`].
+//!    This feature is **disabled** by default.
 //! - `myfs`: Allow users to define their custom filesystems to override the
 //!    default. In this case, [`MyFileSystemIf`] is required to be implemented
 //!    to create and initialize other filesystems. This feature is **disabled** by
 //!    by default, but it will override other filesystem selection features if
 //!    both are enabled.
 //!
+//! [axdriver]: axdriver::devices
+//! [axdisplay]: https://arceos-org.github.io/arceos/axdisplay/index.html
+//! [`File::ioctl`]: fops::File::ioctl
 //! [FAT]: https://en.wikipedia.org/wiki/File_Allocation_Table
+//! [ext2/ext4]: https://en.wikipedia.org/wiki/Ext4
 //! [`MyFileSystemIf`]: fops::MyFileSystemIf
 
 #![cfg_attr(all(not(test), not(doc)), no_std)]
@@ -26,21 +64,113 @@
 extern crate log;
 extern crate alloc;
 
+mod cache;
 mod dev;
 mod fs;
+#[cfg(feature = "initramfs")]
+mod initramfs;
+mod lock;
 mod mounts;
+mod partition;
 mod root;
+mod watch;
 
 pub mod api;
 pub mod fops;
 
+pub use self::cache::CacheStats;
+pub use self::dev::{block_devices, BlockDevice};
+#[cfg(feature = "9p")]
+pub use self::fs::ninep::NinePTransport;
+pub use self::partition::{Guid, Partition, PartitionType};
+
 use axdriver::{prelude::*, AxDeviceContainer};
 
+/// The device of the root filesystem, set by the `AX_ROOT` environment
+/// variable at build time. It can be a device name (e.g., `vda2`),
+/// `PARTLABEL=<label>`, or `PARTUUID=<uuid>`.
+const ROOT_DEVICE: Option<&str> = option_env!("AX_ROOT");
+
 /// Initializes filesystems by block devices.
-pub fn init_filesystems(mut blk_devs: AxDeviceContainer<AxBlockDevice>) {
+///
+/// All devices and their partitions are registered, and the root filesystem
+/// is mounted on the one specified by `AX_ROOT`. If it is not set, the first
+/// partition of the first device is used, or the whole device if it is not
+/// partitioned.
+pub fn init_filesystems(blk_devs: AxDeviceContainer<AxBlockDevice>) {
+    info!("Initialize filesystems...");
+
+    let default_root = register_devices(blk_devs);
+    let root = match ROOT_DEVICE.filter(|spec| !spec.is_empty()) {
+        Some(spec) => block_devices()
+            .into_iter()
+            .find(|d| d.matches(spec))
+            .unwrap_or_else(|| panic!("Root device {:?} not found!", spec)),
+        None => default_root.expect("No block device found!"),
+    };
+    info!("  use block device {} as root", root.name());
+    self::root::init_rootfs(self::dev::Disk::from_block_device(root));
+}
+
+/// Initializes filesystems with a RAM filesystem unpacked from the initramfs
+/// `archive` as the root filesystem, so that no block device is required.
+///
+/// Block devices and their partitions are still registered, and can be
+/// accessed by device files.
+#[cfg(feature = "initramfs")]
+pub fn init_filesystems_with_initramfs(blk_devs: AxDeviceContainer<AxBlockDevice>, archive: &[u8]) {
     info!("Initialize filesystems...");
 
-    let dev = blk_devs.take_one().expect("No block device found!");
-    info!("  use block device 0: {:?}", dev.device_name());
-    self::root::init_rootfs(self::dev::Disk::new(dev));
+    register_devices(blk_devs);
+    info!("  use initramfs of {} bytes as root", archive.len());
+    self::root::init_rootfs_from_initramfs(archive);
+}
+
+/// Returns the initramfs embedded in the kernel image, which is read from
+/// the path in the `AX_INITRAMFS` environment variable at build time.
+#[cfg(feature = "initramfs")]
+pub fn embedded_initramfs() -> Option<&'static [u8]> {
+    static ARCHIVE: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/initramfs.cpio"));
+    (!ARCHIVE.is_empty()).then_some(ARCHIVE)
+}
+
+/// Registers a transport of 9P messages, whose shared directory can be
+/// mounted by [`api::mount_9p`] with its mount tag.
+#[cfg(feature = "9p")]
+pub fn register_9p_transport(transport: alloc::boxed::Box<dyn NinePTransport>) {
+    info!("  register 9p transport {:?}", transport.mount_tag());
+    self::fs::ninep::register_transport(transport);
+}
+
+/// Registers virtio-9p devices, and mounts their shared directories on
+/// `/mnt/<tag>` by their mount tags. It must be called after the root
+/// filesystem is initialized.
+#[cfg(feature = "virtio-9p")]
+pub fn init_9p_devices(devs: alloc::vec::Vec<axdriver::VirtIo9pDev>) {
+    for dev in devs {
+        let tag = alloc::string::String::from(dev.mount_tag());
+        let path = alloc::format!("/mnt/{}", tag);
+        register_9p_transport(alloc::boxed::Box::new(dev));
+        match api::create_dir_all(&path).and_then(|_| api::mount_9p(&path, &tag)) {
+            Ok(()) => info!("  mount 9p {:?} on {}", tag, path),
+            Err(e) => warn!("failed to mount 9p {:?} on {}: {:?}", tag, path, e),
+        }
+    }
+}
+
+/// Registers all devices and their partitions, returns the default root
+/// device: the first partition of the first device, or the whole device if
+/// it is not partitioned.
+fn register_devices(mut blk_devs: AxDeviceContainer<AxBlockDevice>) -> Option<BlockDevice> {
+    let mut default_root = None;
+    let mut index = 0;
+    while let Some(dev) = blk_devs.take_one() {
+        let registered = self::dev::register_device(dev, index);
+        index += 1;
+        if default_root.is_none() {
+            // the first partition, or the whole device
+            default_root = registered.get(1).or(registered.first()).cloned();
+        }
+    }
+    default_root
 }
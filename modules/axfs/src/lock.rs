@@ -0,0 +1,368 @@
+//! Advisory file locks: whole-file locks like `flock`, and byte-range locks
+//! like POSIX record locks of `fcntl`.
+//!
+//! Locks are kept for each file, which is identified by its inode or node,
+//! so they are shared by its hard links. They are advisory, i.e., reads and
+//! writes are not checked against them. Whole-file locks and byte-range locks
+//! do not conflict with each other, like in Linux.
+
+use alloc::collections::BTreeMap;
+use alloc::{sync::Arc, sync::Weak, vec::Vec};
+use core::ops::Range;
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use axerrno::{ax_err, AxError, AxResult};
+use axfs_vfs::VfsNodeRef;
+use axsync::Mutex;
+use cap_access::Cap;
+
+use crate::fs::FileId;
+
+/// The kind of a file lock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockKind {
+    /// A shared (read) lock, which can be held by multiple owners at once.
+    Shared,
+    /// An exclusive (write) lock.
+    Exclusive,
+}
+
+/// A byte-range lock held on a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileLock {
+    /// The kind of the lock.
+    pub kind: LockKind,
+    /// The locked bytes. It ends at [`u64::MAX`] if the lock extends to the
+    /// end of the file, however the file grows.
+    pub range: Range<u64>,
+    /// The owner of the lock, e.g., the process ID.
+    pub owner: u64,
+}
+
+impl LockKind {
+    const fn conflicts(self, other: Self) -> bool {
+        matches!(self, Self::Exclusive) || matches!(other, Self::Exclusive)
+    }
+}
+
+const fn overlaps(a: &Range<u64>, b: &Range<u64>) -> bool {
+    a.start < b.end && b.start < a.end
+}
+
+#[derive(Default)]
+struct LockState {
+    /// Whole-file locks, with the IDs of their opened files.
+    whole: Vec<(u64, LockKind)>,
+    /// Byte-range locks. Ranges of the same owner never overlap.
+    ranges: Vec<FileLock>,
+}
+
+impl LockState {
+    fn conflict(&self, owner: u64, kind: LockKind, range: &Range<u64>) -> Option<&FileLock> {
+        self.ranges.iter().find(|lock| {
+            lock.owner != owner && overlaps(&lock.range, range) && lock.kind.conflicts(kind)
+        })
+    }
+
+    /// Removes the parts of the locks of `owner` in `range`.
+    fn unlock_range(&mut self, owner: u64, range: &Range<u64>) {
+        let mut rest = Vec::new();
+        self.ranges.retain(|lock| {
+            if lock.owner != owner || !overlaps(&lock.range, range) {
+                return true;
+            }
+            if lock.range.start < range.start {
+                rest.push(FileLock {
+                    range: lock.range.start..range.start,
+                    ..lock.clone()
+                });
+            }
+            if range.end < lock.range.end {
+                rest.push(FileLock {
+                    range: range.end..lock.range.end,
+                    ..lock.clone()
+                });
+            }
+            false
+        });
+        self.ranges.extend(rest);
+    }
+
+    /// Adds a lock, merged with the adjacent locks of the same owner and kind.
+    fn lock_range(&mut self, owner: u64, kind: LockKind, mut range: Range<u64>) {
+        self.unlock_range(owner, &range);
+        self.ranges.retain(|lock| {
+            let adjacent = lock.range.start <= range.end && range.start <= lock.range.end;
+            if lock.owner == owner && lock.kind == kind && adjacent {
+                range = lock.range.start.min(range.start)..lock.range.end.max(range.end);
+                false
+            } else {
+                true
+            }
+        });
+        self.ranges.push(FileLock { kind, range, owner });
+    }
+}
+
+/// Locks of a file, shared by all opened files of it.
+struct FileLocks {
+    id: FileId,
+    /// Keeps the node alive, so that its address in `id` is not reused.
+    _node: VfsNodeRef,
+    state: Mutex<LockState>,
+    /// Increased when locks are released or downgraded, to wake up the tasks
+    /// waiting for them.
+    generation: AtomicUsize,
+    #[cfg(feature = "multitask")]
+    wait_queue: axtask::WaitQueue,
+}
+
+/// Locks of the opened files, by their identities.
+static FILES: Mutex<BTreeMap<FileId, Weak<FileLocks>>> = Mutex::new(BTreeMap::new());
+
+impl FileLocks {
+    /// Returns the locks of the file `id`, whose node is `node`.
+    fn get(id: FileId, node: &VfsNodeRef) -> Arc<Self> {
+        let mut files = FILES.lock();
+        if let Some(locks) = files.get(&id).and_then(Weak::upgrade) {
+            return locks;
+        }
+        let locks = Arc::new(Self {
+            id: id.clone(),
+            _node: node.clone(),
+            state: Mutex::new(LockState::default()),
+            generation: AtomicUsize::new(0),
+            #[cfg(feature = "multitask")]
+            wait_queue: axtask::WaitQueue::new(),
+        });
+        files.insert(id, Arc::downgrade(&locks));
+        locks
+    }
+
+    /// Runs `try_lock` until it succeeds, waits for other locks to be
+    /// released between the tries if `wait` is true.
+    fn acquire(&self, wait: bool, mut try_lock: impl FnMut(&mut LockState) -> bool) -> AxResult {
+        loop {
+            let generation = self.generation.load(Ordering::Acquire);
+            if try_lock(&mut self.state.lock()) {
+                return Ok(());
+            }
+            if !wait {
+                return ax_err!(WouldBlock);
+            }
+            self.wait(generation)?;
+        }
+    }
+
+    #[cfg(feature = "multitask")]
+    fn wait(&self, generation: usize) -> AxResult {
+        self.wait_queue
+            .wait_until(|| self.generation.load(Ordering::Acquire) != generation);
+        Ok(())
+    }
+
+    #[cfg(not(feature = "multitask"))]
+    fn wait(&self, _generation: usize) -> AxResult {
+        // no other tasks can release the lock
+        ax_err!(WouldBlock, "waiting for a file lock forever")
+    }
+
+    /// Wakes up the tasks waiting for locks.
+    fn notify(&self) {
+        self.generation.fetch_add(1, Ordering::Release);
+        #[cfg(feature = "multitask")]
+        self.wait_queue.notify_all(false);
+    }
+
+    /// Applies or converts the whole-file lock of the opened file `id`.
+    ///
+    /// Like `flock` in Linux, the existing lock is released first if it is
+    /// converted and the new one cannot be applied immediately.
+    fn lock(&self, id: u64, kind: LockKind, wait: bool) -> AxResult {
+        self.acquire(wait, |state| {
+            let pos = state.whole.iter().position(|(owner, _)| *owner == id);
+            if let Some(pos) = pos {
+                if state.whole[pos].1 == kind {
+                    return true;
+                }
+            }
+            let conflicted = state
+                .whole
+                .iter()
+                .any(|(owner, held)| *owner != id && held.conflicts(kind));
+            match pos {
+                Some(pos) if conflicted => {
+                    state.whole.remove(pos);
+                    self.notify();
+                }
+                Some(pos) => {
+                    // downgraded to a shared lock, or upgraded
+                    state.whole[pos].1 = kind;
+                    self.notify();
+                }
+                None if !conflicted => state.whole.push((id, kind)),
+                None => {}
+            }
+            !conflicted
+        })
+    }
+
+    /// Releases the whole-file lock of the opened file `id`.
+    fn unlock(&self, id: u64) {
+        let mut state = self.state.lock();
+        if let Some(pos) = state.whole.iter().position(|(owner, _)| *owner == id) {
+            state.whole.remove(pos);
+            self.notify();
+        }
+    }
+
+    /// Applies a lock of `kind` on `range` for `owner`, or releases its locks
+    /// in `range` if `kind` is [`None`]. Its existing locks in `range` are
+    /// replaced.
+    fn lock_range(
+        &self,
+        owner: u64,
+        kind: Option<LockKind>,
+        range: Range<u64>,
+        wait: bool,
+    ) -> AxResult {
+        if range.is_empty() {
+            return ax_err!(InvalidInput);
+        }
+        let Some(kind) = kind else {
+            self.state.lock().unlock_range(owner, &range);
+            self.notify();
+            return Ok(());
+        };
+        self.acquire(wait, |state| {
+            if state.conflict(owner, kind, &range).is_some() {
+                return false;
+            }
+            state.lock_range(owner, kind, range.clone());
+            true
+        })?;
+        // a shared lock may replace an exclusive one
+        self.notify();
+        Ok(())
+    }
+
+    /// Returns a lock of other owners which prevents `owner` from applying a
+    /// lock of `kind` on `range`.
+    fn conflicting_lock(&self, owner: u64, kind: LockKind, range: &Range<u64>) -> Option<FileLock> {
+        let state = self.state.lock();
+        state.conflict(owner, kind, range).cloned()
+    }
+
+    /// Releases all byte-range locks of `owner`.
+    fn unlock_ranges(&self, owner: u64) {
+        let mut state = self.state.lock();
+        let len = state.ranges.len();
+        state.ranges.retain(|lock| lock.owner != owner);
+        if state.ranges.len() != len {
+            self.notify();
+        }
+    }
+}
+
+/// Applies locks on a file on behalf of an opened file of it, which owns the
+/// whole-file lock.
+///
+/// It is shared by the opened file, and can be used without borrowing it, e.g.,
+/// to wait for a lock while others are using the file. The whole-file lock is
+/// released when all of them are dropped.
+pub struct FileLocker {
+    locks: Arc<FileLocks>,
+    id: u64,
+    cap: Cap,
+}
+
+impl FileLocker {
+    /// Creates a locker for the file of `node` at the absolute `path`,
+    /// opened with `cap`.
+    pub(crate) fn new(node: &VfsNodeRef, path: &str, cap: Cap) -> AxResult<Self> {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+        let file_id = crate::fs::file_id(node, path)?;
+        Ok(Self {
+            locks: FileLocks::get(file_id, node),
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            cap,
+        })
+    }
+
+    /// Applies a whole-file lock of `kind`, as `flock` in Linux. The existing
+    /// lock is converted.
+    ///
+    /// If the file is locked by others, it waits for the locks to be released
+    /// if `wait` is true, or returns [`AxError::WouldBlock`] otherwise.
+    pub fn lock(&self, kind: LockKind, wait: bool) -> AxResult {
+        self.locks.lock(self.id, kind, wait)
+    }
+
+    /// Releases the whole-file lock.
+    pub fn unlock(&self) -> AxResult {
+        self.locks.unlock(self.id);
+        Ok(())
+    }
+
+    /// Applies a lock of `kind` on the bytes in `range` for `owner`, or
+    /// releases its locks in `range` if `kind` is [`None`], as POSIX record
+    /// locks of `fcntl`. The existing locks of `owner` in `range` are replaced.
+    ///
+    /// The file must be opened for reading to apply a shared lock, and for
+    /// writing to apply an exclusive one. If others hold conflicting locks,
+    /// it waits for them to be released if `wait` is true, or returns
+    /// [`AxError::WouldBlock`] otherwise.
+    pub fn lock_range(
+        &self,
+        owner: u64,
+        kind: Option<LockKind>,
+        range: Range<u64>,
+        wait: bool,
+    ) -> AxResult {
+        let cap = match kind {
+            Some(LockKind::Shared) => Cap::READ,
+            Some(LockKind::Exclusive) => Cap::WRITE,
+            None => Cap::empty(),
+        };
+        if !self.cap.contains(cap) {
+            return Err(AxError::PermissionDenied);
+        }
+        self.locks.lock_range(owner, kind, range, wait)
+    }
+
+    /// Returns a lock held by others which prevents `owner` from applying a
+    /// lock of `kind` on the bytes in `range`.
+    pub fn conflicting_lock(
+        &self,
+        owner: u64,
+        kind: LockKind,
+        range: Range<u64>,
+    ) -> Option<FileLock> {
+        self.locks.conflicting_lock(owner, kind, &range)
+    }
+
+    /// Releases all locks of `owner` applied by [`lock_range`](Self::lock_range)
+    /// on the file, through any opened file of it.
+    pub fn unlock_ranges(&self, owner: u64) {
+        self.locks.unlock_ranges(owner)
+    }
+}
+
+impl Drop for FileLocker {
+    fn drop(&mut self) {
+        self.locks.unlock(self.id);
+    }
+}
+
+impl Drop for FileLocks {
+    fn drop(&mut self) {
+        let mut files = FILES.lock();
+        // it may have been replaced by the locks of a newly opened file
+        if files
+            .get(&self.id)
+            .is_some_and(|locks| locks.strong_count() == 0)
+        {
+            files.remove(&self.id);
+        }
+    }
+}
@@ -1,18 +1,67 @@
 use alloc::sync::Arc;
-use axfs_vfs::{VfsNodeType, VfsOps, VfsResult};
 
 use crate::fs;
 
+#[cfg(any(feature = "devfs", feature = "sysfs"))]
+use alloc::format;
+#[cfg(any(feature = "sysfs", all(feature = "devfs", feature = "display")))]
+use axdriver::prelude::DeviceType;
+
 #[cfg(feature = "devfs")]
-pub(crate) fn devfs() -> Arc<fs::devfs::DeviceFileSystem> {
-    let null = fs::devfs::NullDev;
-    let zero = fs::devfs::ZeroDev;
-    let bar = fs::devfs::ZeroDev;
-    let devfs = fs::devfs::DeviceFileSystem::new();
-    let foo_dir = devfs.mkdir("foo");
-    devfs.add("null", Arc::new(null));
-    devfs.add("zero", Arc::new(zero));
-    foo_dir.add("bar", Arc::new(bar));
+pub(crate) fn devfs() -> Arc<fs::pseudofs::PseudoFileSystem> {
+    use fs::devfs::*;
+
+    let devfs = fs::pseudofs::PseudoFileSystem::new();
+    let dev_root = devfs.root_dir_node();
+
+    dev_root.add("null", Arc::new(NullDev));
+    dev_root.add("zero", Arc::new(ZeroDev));
+    let random = Arc::new(RandomDev);
+    dev_root.add("random", random.clone());
+    dev_root.add("urandom", random);
+
+    // Create /dev/console and /dev/ttyS0 for the UART
+    let console = Arc::new(ConsoleDev);
+    dev_root.add("console", console.clone());
+    dev_root.add("ttyS0", console);
+
+    // Create /dev/rtc0, and /dev/rtc linked to it
+    dev_root.add("rtc0", Arc::new(RtcDev));
+    dev_root.add_symlink("rtc", || Ok("rtc0".into()));
+
+    // Create /dev/<name> for block devices and partitions, and
+    // /dev/disk/by-partuuid/<uuid> and /dev/disk/by-partlabel/<label> linked
+    // to partitions
+    let block_devices = crate::block_devices();
+    if !block_devices.is_empty() {
+        dev_root.mkdir("disk").mkdir("by-partuuid");
+    }
+    for dev in block_devices {
+        dev_root.add(dev.name(), Arc::new(BlockDev::new(dev.clone())));
+        if let Some(part) = dev.partition_info() {
+            let disk = dev_root.mkdir("disk");
+            let target = format!("../../{}", dev.name());
+            let link = target.clone();
+            disk.mkdir("by-partuuid")
+                .add_symlink(&part.uuid, move || Ok(link.clone()));
+            if !part.label.is_empty() {
+                // escaped as udev does
+                let label = part.label.replace('/', "\\x2f").replace(' ', "\\x20");
+                disk.mkdir("by-partlabel")
+                    .add_symlink(&label, move || Ok(target.clone()));
+            }
+        }
+    }
+
+    // Create /dev/fb0 for the display device used by axdisplay
+    #[cfg(feature = "display")]
+    if let Some(dev) = axdriver::devices()
+        .iter()
+        .find(|dev| dev.device_type == DeviceType::Display)
+    {
+        dev_root.add("fb0", Arc::new(FrameBufferDev::new(dev.name.clone())));
+    }
+
     Arc::new(devfs)
 }
 
@@ -22,59 +71,499 @@ pub(crate) fn ramfs() -> Arc<fs::ramfs::RamFileSystem> {
 }
 
 #[cfg(feature = "procfs")]
-pub(crate) fn procfs() -> VfsResult<Arc<fs::ramfs::RamFileSystem>> {
-    let procfs = fs::ramfs::RamFileSystem::new();
-    let proc_root = procfs.root_dir();
+pub(crate) fn procfs() -> Arc<fs::pseudofs::PseudoFileSystem> {
+    let procfs = fs::pseudofs::PseudoFileSystem::new();
+    let proc_root = procfs.root_dir_node();
+
+    proc_root.add_file("meminfo", proc::meminfo);
+    proc_root.add_file("cpuinfo", proc::cpuinfo);
+    proc_root.add_file("uptime", proc::uptime);
+    proc_root.add_file("interrupts", proc::interrupts);
+    proc_root.add_file("mounts", proc::mounts);
+    proc_root.mkdir("net").add_file("dev", proc::net_dev);
 
     // Create /proc/sys/net/core/somaxconn
-    proc_root.create("sys", VfsNodeType::Dir)?;
-    proc_root.create("sys/net", VfsNodeType::Dir)?;
-    proc_root.create("sys/net/core", VfsNodeType::Dir)?;
-    proc_root.create("sys/net/core/somaxconn", VfsNodeType::File)?;
-    let file_somaxconn = proc_root.clone().lookup("./sys/net/core/somaxconn")?;
-    file_somaxconn.write_at(0, b"4096\n")?;
+    let sys = proc_root.mkdir("sys");
+    let core = sys.mkdir("net").mkdir("core");
+    core.add_file("somaxconn", || Ok("4096\n".into()));
 
     // Create /proc/sys/vm/overcommit_memory
-    proc_root.create("sys/vm", VfsNodeType::Dir)?;
-    proc_root.create("sys/vm/overcommit_memory", VfsNodeType::File)?;
-    let file_over = proc_root.clone().lookup("./sys/vm/overcommit_memory")?;
-    file_over.write_at(0, b"0\n")?;
+    let vm = sys.mkdir("vm");
+    vm.add_file("overcommit_memory", || Ok("0\n".into()));
 
-    // Create /proc/self/stat
-    proc_root.create("self", VfsNodeType::Dir)?;
-    proc_root.create("self/stat", VfsNodeType::File)?;
+    // Create /proc/<tid> for each task, and /proc/self for the current one
+    #[cfg(feature = "multitask")]
+    {
+        proc_root.add_symlink("self", proc::current_tid);
+        proc_root.set_lister(proc::task_dirs);
+    }
 
-    Ok(Arc::new(procfs))
+    Arc::new(procfs)
 }
 
 #[cfg(feature = "sysfs")]
-pub(crate) fn sysfs() -> VfsResult<Arc<fs::ramfs::RamFileSystem>> {
-    let sysfs = fs::ramfs::RamFileSystem::new();
-    let sys_root = sysfs.root_dir();
+pub(crate) fn sysfs() -> Arc<fs::pseudofs::PseudoFileSystem> {
+    let sysfs = fs::pseudofs::PseudoFileSystem::new();
+    let sys_root = sysfs.root_dir_node();
 
     // Create /sys/kernel/mm/transparent_hugepage/enabled
-    sys_root.create("kernel", VfsNodeType::Dir)?;
-    sys_root.create("kernel/mm", VfsNodeType::Dir)?;
-    sys_root.create("kernel/mm/transparent_hugepage", VfsNodeType::Dir)?;
-    sys_root.create("kernel/mm/transparent_hugepage/enabled", VfsNodeType::File)?;
-    let file_hp = sys_root
-        .clone()
-        .lookup("./kernel/mm/transparent_hugepage/enabled")?;
-    file_hp.write_at(0, b"always [madvise] never\n")?;
-
-    // Create /sys/devices/system/clocksource/clocksource0/current_clocksource
-    sys_root.create("devices", VfsNodeType::Dir)?;
-    sys_root.create("devices/system", VfsNodeType::Dir)?;
-    sys_root.create("devices/system/clocksource", VfsNodeType::Dir)?;
-    sys_root.create("devices/system/clocksource/clocksource0", VfsNodeType::Dir)?;
-    sys_root.create(
-        "devices/system/clocksource/clocksource0/current_clocksource",
-        VfsNodeType::File,
-    )?;
-    let file_cc = sys_root
-        .clone()
-        .lookup("devices/system/clocksource/clocksource0/current_clocksource")?;
-    file_cc.write_at(0, b"tsc\n")?;
-
-    Ok(Arc::new(sysfs))
+    let mm = sys_root.mkdir("kernel").mkdir("mm");
+    let hugepage = mm.mkdir("transparent_hugepage");
+    hugepage.add_file("enabled", || Ok("always [madvise] never\n".into()));
+
+    // Create /sys/devices/system/clocksource/clocksource0 and /sys/devices/system/cpu
+    let system = sys_root.mkdir("devices").mkdir("system");
+    let clocksource = system.mkdir("clocksource").mkdir("clocksource0");
+    clocksource.add_file("current_clocksource", sys::clock_source);
+    clocksource.add_file("available_clocksource", sys::clock_source);
+    let cpu = system.mkdir("cpu");
+    cpu.add_file("online", sys::cpu_list);
+    cpu.add_file("possible", sys::cpu_list);
+
+    // Create /sys/devices/<bus>/<device> for devices probed by axdriver, and
+    // /sys/bus/<bus>/devices/<device> linked to them
+    for (index, dev) in axdriver::devices().iter().enumerate() {
+        sys::add_device(&sys_root, index, dev);
+    }
+
+    // Create /sys/class/block/<name> for block devices and partitions, and
+    // /sys/block/<name> for the whole devices
+    sys::add_block_devices(&sys_root);
+
+    // Create /sys/class/graphics/fb<n>
+    let class = sys_root.mkdir("class");
+    let graphics = axdriver::devices()
+        .iter()
+        .filter(|dev| dev.device_type == DeviceType::Display);
+    for (i, _) in graphics.enumerate() {
+        let fb = class.mkdir("graphics").mkdir(&format!("fb{}", i));
+        sys::add_device_link(&fb, DeviceType::Display, i);
+    }
+
+    // Create /sys/class/net/<name>, listed when accessed as interfaces are
+    // created after filesystems
+    #[cfg(feature = "net")]
+    class.mkdir("net").set_lister(sys::net_dirs);
+
+    Arc::new(sysfs)
+}
+
+/// Generators of files in `/proc`, in the format of Linux.
+#[cfg(feature = "procfs")]
+mod proc {
+    use alloc::{format, string::String};
+    use axfs_vfs::VfsResult;
+    use core::fmt::Write;
+
+    #[cfg(feature = "multitask")]
+    use {
+        crate::fs::pseudofs::DirNode,
+        alloc::{string::ToString, sync::Arc, vec::Vec},
+        axfs_vfs::{VfsError, VfsNodeRef},
+    };
+
+    /// Appends a line of `/proc/meminfo`.
+    fn meminfo_line(buf: &mut String, name: &str, bytes: usize) {
+        let name = format!("{}:", name);
+        writeln!(buf, "{:<16}{:>8} kB", name, bytes / 1024).unwrap();
+    }
+
+    pub fn meminfo() -> VfsResult<String> {
+        const PAGE_SIZE: usize = axhal::mem::PAGE_SIZE_4K;
+        let allocator = axalloc::global_allocator();
+        let used_pages = allocator.used_pages();
+        let free_pages = allocator.available_pages();
+        let total = (used_pages + free_pages) * PAGE_SIZE;
+        let free = free_pages * PAGE_SIZE + allocator.available_bytes();
+        let (mut buffers, mut dirty) = (0, 0);
+        for dev in crate::block_devices() {
+            // partitions share the cache of the whole device
+            if dev.partition_info().is_none() {
+                let stats = dev.cache_stats();
+                buffers += stats.cached_blocks * dev.block_size();
+                dirty += stats.dirty_blocks * dev.block_size();
+            }
+        }
+
+        let mut buf = String::new();
+        meminfo_line(&mut buf, "MemTotal", total);
+        meminfo_line(&mut buf, "MemFree", free);
+        meminfo_line(&mut buf, "MemAvailable", free);
+        meminfo_line(&mut buf, "Buffers", buffers);
+        meminfo_line(&mut buf, "Cached", 0);
+        meminfo_line(&mut buf, "Dirty", dirty);
+        meminfo_line(&mut buf, "HeapUsed", allocator.used_bytes());
+        Ok(buf)
+    }
+
+    pub fn cpuinfo() -> VfsResult<String> {
+        let mut buf = String::new();
+        for cpu_id in 0..axconfig::SMP {
+            writeln!(buf, "processor\t: {}", cpu_id).unwrap();
+            writeln!(buf, "arch\t\t: {}", axconfig::ARCH).unwrap();
+            writeln!(buf, "platform\t: {}", axconfig::PLATFORM).unwrap();
+            writeln!(buf).unwrap();
+        }
+        Ok(buf)
+    }
+
+    pub fn uptime() -> VfsResult<String> {
+        let uptime = axhal::time::monotonic_time();
+        // the idle time is not accounted
+        Ok(format!(
+            "{}.{:02} 0.00\n",
+            uptime.as_secs(),
+            uptime.subsec_millis() / 10
+        ))
+    }
+
+    pub fn interrupts() -> VfsResult<String> {
+        let mut buf = String::from("    ");
+        for cpu_id in 0..axconfig::SMP {
+            write!(buf, " {:>10}", format!("CPU{}", cpu_id)).unwrap();
+        }
+        writeln!(buf).unwrap();
+        #[cfg(feature = "irq")]
+        for irq_num in 0..axhal::irq::MAX_IRQ_COUNT {
+            let counts: [usize; axconfig::SMP] =
+                core::array::from_fn(|cpu_id| axhal::irq::irq_count(cpu_id, irq_num));
+            if counts.iter().all(|&count| count == 0) {
+                continue;
+            }
+            write!(buf, "{:>3}:", irq_num).unwrap();
+            for count in counts {
+                write!(buf, " {:>10}", count).unwrap();
+            }
+            writeln!(buf).unwrap();
+        }
+        Ok(buf)
+    }
+
+    pub fn mounts() -> VfsResult<String> {
+        let mut buf = String::new();
+        for mount in crate::root::mounts() {
+            // spaces are escaped as in Linux
+            let path = mount.path.replace(' ', "\\040");
+            writeln!(buf, "{0} {1} {0} rw 0 0", mount.fs_type, path).unwrap();
+        }
+        Ok(buf)
+    }
+
+    pub fn net_dev() -> VfsResult<String> {
+        let mut buf = String::new();
+        buf.push_str(concat!(
+            "Inter-|   Receive                                                |  Transmit\n",
+            " face |bytes    packets errs drop fifo frame compressed multicast",
+            "|bytes    packets errs drop fifo colls carrier compressed\n",
+        ));
+        // only bytes, packets and errors are counted
+        #[cfg(feature = "net")]
+        for iface in axnet::interfaces() {
+            let stats = iface.stats;
+            writeln!(
+                buf,
+                "{:>6}:{:>8} {:>7} {:>4}    0    0     0          0         0 {:>8} {:>7} {:>4}    0    0     0       0          0",
+                iface.name,
+                stats.rx_bytes,
+                stats.rx_packets,
+                stats.rx_errors,
+                stats.tx_bytes,
+                stats.tx_packets,
+                stats.tx_errors,
+            )
+            .unwrap();
+        }
+        Ok(buf)
+    }
+
+    #[cfg(feature = "multitask")]
+    pub fn current_tid() -> VfsResult<String> {
+        Ok(axtask::current().id().as_u64().to_string())
+    }
+
+    /// Lists the directories `/proc/<tid>` of all tasks.
+    #[cfg(feature = "multitask")]
+    pub fn task_dirs(proc_root: &Arc<DirNode>) -> Vec<(String, VfsNodeRef)> {
+        axtask::all_tasks()
+            .iter()
+            .map(|task| (task.id().as_u64().to_string(), task_dir(proc_root, task)))
+            .collect()
+    }
+
+    /// Creates the directory `/proc/<tid>` of `task`. It does not keep the
+    /// task alive, reading its files fails after the task is dropped.
+    #[cfg(feature = "multitask")]
+    fn task_dir(proc_root: &Arc<DirNode>, task: &axtask::AxTaskRef) -> VfsNodeRef {
+        let dir = DirNode::new(Some(proc_root));
+        let weak = Arc::downgrade(task);
+        dir.add_file("status", move || {
+            let task = weak.upgrade().ok_or(VfsError::NotFound)?;
+            Ok(task_status(&task))
+        });
+        let weak = Arc::downgrade(task);
+        dir.add_file("stat", move || {
+            let task = weak.upgrade().ok_or(VfsError::NotFound)?;
+            Ok(task_stat(&task))
+        });
+        dir
+    }
+
+    /// Returns the state of `task` in `/proc/<tid>/status`. Runnable tasks
+    /// are running, as in Linux.
+    #[cfg(feature = "multitask")]
+    fn task_state(task: &axtask::AxTaskRef) -> &'static str {
+        match task.state() {
+            axtask::TaskState::Running | axtask::TaskState::Ready => "R (running)",
+            axtask::TaskState::Blocked => "S (sleeping)",
+            axtask::TaskState::Exited => "Z (zombie)",
+        }
+    }
+
+    /// Formats a CPU set as a list of ranges, e.g., `0-2,4`.
+    #[cfg(feature = "multitask")]
+    fn cpu_list(cpumask: &axtask::AxCpuMask) -> String {
+        let mut ranges: Vec<(usize, usize)> = Vec::new();
+        for cpu_id in (0..axconfig::SMP).filter(|&i| cpumask.get(i)) {
+            match ranges.last_mut() {
+                Some((_, end)) if *end + 1 == cpu_id => *end = cpu_id,
+                _ => ranges.push((cpu_id, cpu_id)),
+            }
+        }
+        let ranges: Vec<_> = ranges
+            .into_iter()
+            .map(|(start, end)| match start == end {
+                true => start.to_string(),
+                false => format!("{}-{}", start, end),
+            })
+            .collect();
+        ranges.join(",")
+    }
+
+    #[cfg(feature = "multitask")]
+    fn task_status(task: &axtask::AxTaskRef) -> String {
+        let tid = task.id().as_u64();
+        let mut buf = String::new();
+        writeln!(buf, "Name:\t{}", task.name()).unwrap();
+        writeln!(buf, "State:\t{}", task_state(task)).unwrap();
+        writeln!(buf, "Tgid:\t{}", tid).unwrap();
+        writeln!(buf, "Pid:\t{}", tid).unwrap();
+        writeln!(buf, "PPid:\t0").unwrap();
+        writeln!(buf, "Uid:\t0\t0\t0\t0").unwrap();
+        writeln!(buf, "Gid:\t0\t0\t0\t0").unwrap();
+        writeln!(buf, "Threads:\t1").unwrap();
+        writeln!(buf, "Cpus_allowed_list:\t{}", cpu_list(&task.cpumask())).unwrap();
+        buf
+    }
+
+    /// Returns `/proc/<tid>/stat` of `task`, where unknown fields are zeros.
+    #[cfg(feature = "multitask")]
+    fn task_stat(task: &axtask::AxTaskRef) -> String {
+        let tid = task.id().as_u64();
+        let state = &task_state(task)[..1];
+        let nice = task.priority();
+        // fields from `ppid` to `num_threads`
+        let mut buf = format!("{tid} ({}) {state} 0 {tid} {tid} 0 -1 0", task.name());
+        buf += " 0 0 0 0 0 0 0 0";
+        write!(buf, " {} {} 1", 20 + nice, nice).unwrap();
+        // fields from `itrealvalue` to `exit_code`
+        buf += &" 0".repeat(32);
+        buf.push('\n');
+        buf
+    }
+}
+
+/// Generators of files in `/sys`, in the format of Linux.
+#[cfg(feature = "sysfs")]
+mod sys {
+    use alloc::{format, string::String, sync::Arc};
+    use axdriver::{prelude::DeviceType, BusInfo, DeviceInfo, PciInfo};
+    use axfs_vfs::VfsResult;
+
+    use crate::fs::pseudofs::DirNode;
+
+    #[cfg(feature = "net")]
+    use {
+        alloc::{string::ToString, vec::Vec},
+        axfs_vfs::{VfsError, VfsNodeRef},
+        axnet::{InterfaceInfo, InterfaceStats},
+    };
+
+    /// The unit of sizes in `/sys/class/block`.
+    const SECTOR_SIZE: u64 = 512;
+
+    /// Returns a generator of a file whose content never changes.
+    fn text(content: String) -> impl Fn() -> VfsResult<String> + Send + Sync + 'static {
+        move || Ok(content.clone())
+    }
+
+    pub fn clock_source() -> VfsResult<String> {
+        Ok(format!("{}\n", axhal::time::CLOCK_SOURCE))
+    }
+
+    pub fn cpu_list() -> VfsResult<String> {
+        Ok(match axconfig::SMP {
+            1 => "0\n".into(),
+            n => format!("0-{}\n", n - 1),
+        })
+    }
+
+    fn pci_slot(pci: &PciInfo) -> String {
+        format!("0000:{:02x}:{:02x}.{:x}", pci.bus, pci.device, pci.function)
+    }
+
+    /// Returns the path of the directory of a device relative to `/sys`, and
+    /// the bus it is on.
+    fn device_path(index: usize, dev: &DeviceInfo) -> (String, &'static str) {
+        match &dev.bus {
+            BusInfo::Pci(pci) => (format!("devices/pci0000:00/{}", pci_slot(pci)), "pci"),
+            BusInfo::Mmio { base, .. } => {
+                let path = format!("devices/platform/{:x}.{}", base, dev.name);
+                (path, "platform")
+            }
+            BusInfo::Platform => {
+                let path = format!("devices/platform/{}.{}", dev.name, index);
+                (path, "platform")
+            }
+        }
+    }
+
+    /// Creates the directory of the device `dev`, which is the `index`-th in
+    /// [`axdriver::devices`].
+    pub fn add_device(sys_root: &Arc<DirNode>, index: usize, dev: &DeviceInfo) {
+        let (path, bus) = device_path(index, dev);
+        let mut dir = sys_root.clone();
+        for name in path.split('/') {
+            dir = dir.mkdir(name);
+        }
+
+        let mut uevent = format!("DRIVER={}\n", dev.name);
+        if let BusInfo::Pci(pci) = &dev.bus {
+            dir.add_file("vendor", text(format!("{:#06x}\n", pci.vendor_id)));
+            dir.add_file("device", text(format!("{:#06x}\n", pci.device_id)));
+            dir.add_file("class", text(format!("{:#08x}\n", pci.class)));
+            dir.add_file("irq", text(format!("{}\n", pci.irq.unwrap_or(0))));
+            uevent += &format!("PCI_CLASS={:X}\n", pci.class);
+            uevent += &format!("PCI_ID={:04X}:{:04X}\n", pci.vendor_id, pci.device_id);
+            uevent += &format!("PCI_SLOT_NAME={}\n", pci_slot(pci));
+        }
+        dir.add_file("uevent", text(uevent));
+
+        let name = path.rsplit('/').next().unwrap();
+        let bus_devices = sys_root.mkdir("bus").mkdir(bus).mkdir("devices");
+        bus_devices.add_symlink(name, text(format!("../../../{}", path)));
+    }
+
+    /// Adds the symbolic link `device` in `dir` of `/sys/class/<class>`, to
+    /// the `nth` device of `device_type`, if it is probed by axdriver.
+    pub fn add_device_link(dir: &DirNode, device_type: DeviceType, nth: usize) {
+        let dev = axdriver::devices()
+            .iter()
+            .enumerate()
+            .filter(|(_, dev)| dev.device_type == device_type)
+            .nth(nth);
+        if let Some((index, dev)) = dev {
+            let (path, _) = device_path(index, dev);
+            dir.add_symlink("device", text(format!("../../../{}", path)));
+        }
+    }
+
+    /// Creates `/sys/class/block/<name>` for all block devices and
+    /// partitions, and `/sys/block/<name>` for the whole devices.
+    pub fn add_block_devices(sys_root: &Arc<DirNode>) {
+        let class_block = sys_root.mkdir("class").mkdir("block");
+        let mut disk: Option<Arc<DirNode>> = None;
+        // partitions are registered right after the whole device
+        for dev in crate::block_devices() {
+            let name = dev.name();
+            let dir = class_block.mkdir(name);
+            let sectors = dev.size() / SECTOR_SIZE;
+            dir.add_file("size", text(format!("{}\n", sectors)));
+            dir.add_file("ro", text("0\n".into()));
+            match dev.partition_info() {
+                Some(part) => {
+                    let start = part.start_block * dev.block_size() as u64 / SECTOR_SIZE;
+                    dir.add_file("partition", text(format!("{}\n", part.number)));
+                    dir.add_file("start", text(format!("{}\n", start)));
+                    if let Some(disk) = &disk {
+                        disk.add_symlink(name, text(format!("../{}", name)));
+                    }
+                }
+                None => {
+                    let block_size = format!("{}\n", dev.block_size());
+                    let queue = dir.mkdir("queue");
+                    queue.add_file("logical_block_size", text(block_size));
+                    if let Some(index) = dev.driver_index() {
+                        add_device_link(&dir, DeviceType::Block, index);
+                    }
+                    let link = text(format!("../class/block/{}", name));
+                    sys_root.mkdir("block").add_symlink(name, link);
+                    disk = Some(dir);
+                }
+            }
+        }
+    }
+
+    /// Files in `/sys/class/net/<name>/statistics`.
+    #[cfg(feature = "net")]
+    const NET_STATS: [&str; 6] = [
+        "rx_bytes",
+        "rx_packets",
+        "rx_errors",
+        "tx_bytes",
+        "tx_packets",
+        "tx_errors",
+    ];
+
+    #[cfg(feature = "net")]
+    fn net_stat(stats: &InterfaceStats, file: &str) -> u64 {
+        match file {
+            "rx_bytes" => stats.rx_bytes,
+            "rx_packets" => stats.rx_packets,
+            "rx_errors" => stats.rx_errors,
+            "tx_bytes" => stats.tx_bytes,
+            "tx_packets" => stats.tx_packets,
+            _ => stats.tx_errors,
+        }
+    }
+
+    #[cfg(feature = "net")]
+    fn interface(name: &str) -> VfsResult<InterfaceInfo> {
+        let ifaces = axnet::interfaces();
+        let iface = ifaces.into_iter().find(|iface| iface.name == name);
+        iface.ok_or(VfsError::NotFound)
+    }
+
+    /// Adds a file of an attribute of the network interface `name`, which is
+    /// read when the file is read.
+    #[cfg(feature = "net")]
+    fn add_net_file<F>(dir: &DirNode, file: &str, name: &'static str, attr: F)
+    where
+        F: Fn(&InterfaceInfo) -> String + Send + Sync + 'static,
+    {
+        dir.add_file(file, move || Ok(attr(&interface(name)?) + "\n"));
+    }
+
+    /// Lists the directories `/sys/class/net/<name>` of all network interfaces.
+    #[cfg(feature = "net")]
+    pub fn net_dirs(parent: &Arc<DirNode>) -> Vec<(String, VfsNodeRef)> {
+        let mut dirs = Vec::new();
+        // interfaces are created from NICs in order
+        for (i, iface) in axnet::interfaces().into_iter().enumerate() {
+            let dir = DirNode::new(Some(parent));
+            add_net_file(&dir, "address", iface.name, |iface| {
+                let mac = iface.mac_address.map(|b| format!("{:02x}", b));
+                mac.join(":")
+            });
+            add_net_file(&dir, "mtu", iface.name, |iface| iface.mtu.to_string());
+            let stats = dir.mkdir("statistics");
+            for file in NET_STATS {
+                add_net_file(&stats, file, iface.name, move |iface| {
+                    net_stat(&iface.stats, file).to_string()
+                });
+            }
+            add_device_link(&dir, DeviceType::Net, i);
+            dirs.push((iface.name.into(), dir as VfsNodeRef));
+        }
+        dirs
+    }
 }
@@ -0,0 +1,358 @@
+//! MBR and GPT partition table parsing.
+
+use alloc::{format, string::String, vec, vec::Vec};
+use axdriver::prelude::DevResult;
+
+const MBR_SIGNATURE: [u8; 2] = [0x55, 0xaa];
+const MBR_ENTRY_OFFSET: usize = 446;
+const MBR_TYPE_GPT_PROTECTIVE: u8 = 0xee;
+const MBR_TYPES_EXTENDED: [u8; 3] = [0x05, 0x0f, 0x85];
+
+const GPT_SIGNATURE: &[u8; 8] = b"EFI PART";
+const GPT_ENTRY_MIN_SIZE: usize = 128;
+const GPT_MAX_ENTRIES: usize = 256;
+
+/// Maximum number of logical partitions followed in an extended partition.
+const MAX_LOGICAL_PARTITIONS: usize = 64;
+
+/// The type of a partition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartitionType {
+    /// The system ID of an MBR partition, e.g., `0x0c` for FAT32 (LBA).
+    Mbr(u8),
+    /// The partition type GUID of a GPT partition.
+    Gpt(Guid),
+}
+
+/// A GUID, as stored in GPT (mixed-endian).
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Guid(pub [u8; 16]);
+
+impl core::fmt::Display for Guid {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        let b = &self.0;
+        write!(
+            f,
+            "{:02x}{:02x}{:02x}{:02x}-{:02x}{:02x}-{:02x}{:02x}-{:02x}{:02x}-",
+            b[3], b[2], b[1], b[0], b[5], b[4], b[7], b[6], b[8], b[9]
+        )?;
+        for byte in &b[10..] {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl core::fmt::Debug for Guid {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        core::fmt::Display::fmt(self, f)
+    }
+}
+
+/// A partition on a block device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Partition {
+    /// The partition number, starting from 1. Logical partitions in an MBR
+    /// extended partition start from 5.
+    pub number: usize,
+    /// The first block of the partition.
+    pub start_block: u64,
+    /// The number of blocks in the partition.
+    pub num_blocks: u64,
+    /// The partition type.
+    pub part_type: PartitionType,
+    /// The partition name of GPT, empty for MBR.
+    pub label: String,
+    /// The unique partition GUID of GPT, or `<disk signature>-<number>` for
+    /// MBR, in lowercase as Linux's `PARTUUID`.
+    pub uuid: String,
+}
+
+fn read_u32(buf: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(buf[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(buf: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(buf[offset..offset + 8].try_into().unwrap())
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xedb8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+/// Whether the block looks like a FAT boot sector, which also ends with the
+/// MBR signature.
+fn is_fat_boot_sector(block: &[u8]) -> bool {
+    matches!(block[0], 0xeb | 0xe9)
+        && (&block[0x36..0x39] == b"FAT" || &block[0x52..0x55] == b"FAT")
+}
+
+/// Parses the partition table of a device with `num_blocks` blocks of
+/// `block_size` bytes, reading blocks by `read_block`.
+///
+/// Block addresses in the partition table are in units of `block_size`, and
+/// the MBR is in the first 512 bytes of block 0, as Linux does for devices
+/// with large logical blocks.
+///
+/// Returns an empty list if the device is not partitioned.
+pub fn parse_partitions<F>(
+    num_blocks: u64,
+    block_size: usize,
+    mut read_block: F,
+) -> DevResult<Vec<Partition>>
+where
+    F: FnMut(u64, &mut [u8]) -> DevResult,
+{
+    let mut mbr = vec![0; block_size];
+    read_block(0, &mut mbr)?;
+    if mbr[510..512] != MBR_SIGNATURE || is_fat_boot_sector(&mbr) {
+        return Ok(Vec::new());
+    }
+    let entries = (0..4)
+        .map(|i| &mbr[MBR_ENTRY_OFFSET + i * 16..MBR_ENTRY_OFFSET + (i + 1) * 16])
+        .collect::<Vec<_>>();
+    if entries.iter().any(|e| e[0] != 0 && e[0] != 0x80) {
+        // boot code, not a partition table
+        return Ok(Vec::new());
+    }
+    if entries.iter().any(|e| e[4] == MBR_TYPE_GPT_PROTECTIVE) {
+        match parse_gpt(num_blocks, block_size, &mut read_block)? {
+            Some(parts) => return Ok(parts),
+            None => warn!("invalid GPT header, fall back to MBR"),
+        }
+    }
+
+    let disk_sig = read_u32(&mbr, 440);
+    let mbr_part = |number: usize, os_type: u8, start_block: u64, size: u64| Partition {
+        number,
+        start_block,
+        num_blocks: size.min(num_blocks.saturating_sub(start_block)),
+        part_type: PartitionType::Mbr(os_type),
+        label: String::new(),
+        uuid: format!("{:08x}-{:02x}", disk_sig, number),
+    };
+    let mut parts = Vec::new();
+    let mut extended = None;
+    for (i, entry) in entries.iter().enumerate() {
+        let os_type = entry[4];
+        let start = read_u32(entry, 8) as u64;
+        let size = read_u32(entry, 12) as u64;
+        if os_type == 0 || size == 0 || start >= num_blocks {
+            continue;
+        }
+        if MBR_TYPES_EXTENDED.contains(&os_type) {
+            extended.get_or_insert(start);
+        } else {
+            parts.push(mbr_part(i + 1, os_type, start, size));
+        }
+    }
+
+    // Follow the chain of extended boot records for logical partitions.
+    if let Some(ext_start) = extended {
+        let mut ebr_block = ext_start;
+        let mut ebr = vec![0; block_size];
+        for number in 5..5 + MAX_LOGICAL_PARTITIONS {
+            read_block(ebr_block, &mut ebr)?;
+            if ebr[510..512] != MBR_SIGNATURE {
+                break;
+            }
+            let (entry, next) = (&ebr[MBR_ENTRY_OFFSET..], &ebr[MBR_ENTRY_OFFSET + 16..]);
+            let start = ebr_block + read_u32(entry, 8) as u64;
+            let size = read_u32(entry, 12) as u64;
+            if entry[4] != 0 && size != 0 && start < num_blocks {
+                parts.push(mbr_part(number, entry[4], start, size));
+            }
+            let next_offset = read_u32(next, 8) as u64;
+            if next[4] == 0 || next_offset == 0 || ext_start + next_offset >= num_blocks {
+                break;
+            }
+            ebr_block = ext_start + next_offset;
+        }
+    }
+    Ok(parts)
+}
+
+/// Parses the GPT, returns [`None`] if the header is invalid.
+fn parse_gpt<F>(
+    num_blocks: u64,
+    block_size: usize,
+    read_block: &mut F,
+) -> DevResult<Option<Vec<Partition>>>
+where
+    F: FnMut(u64, &mut [u8]) -> DevResult,
+{
+    let mut header = vec![0; block_size];
+    read_block(1, &mut header)?;
+    if &header[..8] != GPT_SIGNATURE {
+        return Ok(None);
+    }
+    let header_size = read_u32(&header, 12) as usize;
+    if !(92..=block_size).contains(&header_size) {
+        return Ok(None);
+    }
+    let header_crc = read_u32(&header, 16);
+    let mut buf = header.clone();
+    buf[16..20].fill(0);
+    if crc32(&buf[..header_size]) != header_crc {
+        return Ok(None);
+    }
+
+    let entry_lba = read_u64(&header, 72);
+    let num_entries = read_u32(&header, 80) as usize;
+    let entry_size = read_u32(&header, 84) as usize;
+    let entries_crc = read_u32(&header, 88);
+    if entry_size < GPT_ENTRY_MIN_SIZE
+        || !entry_size.is_power_of_two()
+        || entry_size > block_size
+        || num_entries > GPT_MAX_ENTRIES
+    {
+        return Ok(None);
+    }
+
+    let total = num_entries * entry_size;
+    let mut entries = vec![0; total.next_multiple_of(block_size)];
+    for (i, block) in entries.chunks_exact_mut(block_size).enumerate() {
+        read_block(entry_lba + i as u64, block)?;
+    }
+    if crc32(&entries[..total]) != entries_crc {
+        return Ok(None);
+    }
+
+    let mut parts = Vec::new();
+    for (i, entry) in entries[..total].chunks_exact(entry_size).enumerate() {
+        let type_guid = Guid(entry[..16].try_into().unwrap());
+        if type_guid.0 == [0; 16] {
+            continue;
+        }
+        let first = read_u64(entry, 32);
+        let last = read_u64(entry, 40);
+        if first > last || first >= num_blocks {
+            continue;
+        }
+        let name = entry[56..128]
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .take_while(|&c| c != 0);
+        parts.push(Partition {
+            number: i + 1,
+            start_block: first,
+            num_blocks: (last + 1).min(num_blocks) - first,
+            part_type: PartitionType::Gpt(type_guid),
+            label: char::decode_utf16(name)
+                .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+                .collect(),
+            uuid: format!("{}", Guid(entry[16..32].try_into().unwrap())),
+        });
+    }
+    Ok(Some(parts))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLOCK_SIZE: usize = 4096;
+    const NUM_BLOCKS: u64 = 64;
+
+    fn set_mbr_entry(block: &mut [u8], index: usize, os_type: u8, start: u32, size: u32) {
+        let entry = &mut block[MBR_ENTRY_OFFSET + index * 16..MBR_ENTRY_OFFSET + (index + 1) * 16];
+        entry[4] = os_type;
+        entry[8..12].copy_from_slice(&start.to_le_bytes());
+        entry[12..16].copy_from_slice(&size.to_le_bytes());
+        block[510..512].copy_from_slice(&MBR_SIGNATURE);
+    }
+
+    fn parse(image: &[u8]) -> Vec<Partition> {
+        parse_partitions(NUM_BLOCKS, BLOCK_SIZE, |id, buf| {
+            let start = id as usize * BLOCK_SIZE;
+            buf.copy_from_slice(&image[start..start + BLOCK_SIZE]);
+            Ok(())
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn mbr_with_4k_blocks() {
+        let mut image = vec![0; NUM_BLOCKS as usize * BLOCK_SIZE];
+        image[440..444].copy_from_slice(&0x1234_5678u32.to_le_bytes());
+        set_mbr_entry(&mut image, 0, 0x83, 1, 16);
+        set_mbr_entry(&mut image, 1, 0x05, 20, 40);
+        // logical partitions, relative to their EBRs and the extended one
+        let ebr = &mut image[20 * BLOCK_SIZE..21 * BLOCK_SIZE];
+        set_mbr_entry(ebr, 0, 0x83, 1, 8);
+        set_mbr_entry(ebr, 1, 0x05, 10, 10);
+        let ebr = &mut image[30 * BLOCK_SIZE..31 * BLOCK_SIZE];
+        set_mbr_entry(ebr, 0, 0x0c, 2, 100);
+
+        let parts = parse(&image);
+        let blocks = parts
+            .iter()
+            .map(|p| (p.number, p.start_block, p.num_blocks))
+            .collect::<Vec<_>>();
+        assert_eq!(blocks, [(1, 1, 16), (5, 21, 8), (6, 32, 32)]);
+        assert_eq!(parts[2].part_type, PartitionType::Mbr(0x0c));
+        assert_eq!(parts[0].uuid, "12345678-01");
+    }
+
+    #[test]
+    fn gpt_with_4k_blocks() {
+        const ENTRY_SIZE: usize = 128;
+        const NUM_ENTRIES: usize = 128;
+        const FIRST_USABLE: u64 = 2 + (NUM_ENTRIES * ENTRY_SIZE / BLOCK_SIZE) as u64;
+
+        let mut image = vec![0; NUM_BLOCKS as usize * BLOCK_SIZE];
+        set_mbr_entry(
+            &mut image,
+            0,
+            MBR_TYPE_GPT_PROTECTIVE,
+            1,
+            NUM_BLOCKS as u32 - 1,
+        );
+
+        let mut entries = vec![0; NUM_ENTRIES * ENTRY_SIZE];
+        for (i, (first, last, name)) in [(FIRST_USABLE, 15, "boot"), (16, 99, "data")]
+            .into_iter()
+            .enumerate()
+        {
+            let entry = &mut entries[i * ENTRY_SIZE..(i + 1) * ENTRY_SIZE];
+            entry[..16].fill(0x11 * (i as u8 + 1));
+            entry[16..32].fill(0xaa + i as u8);
+            entry[32..40].copy_from_slice(&first.to_le_bytes());
+            entry[40..48].copy_from_slice(&(last as u64).to_le_bytes());
+            for (j, c) in name.encode_utf16().enumerate() {
+                entry[56 + j * 2..58 + j * 2].copy_from_slice(&c.to_le_bytes());
+            }
+        }
+        image[2 * BLOCK_SIZE..FIRST_USABLE as usize * BLOCK_SIZE].copy_from_slice(&entries);
+
+        let header = &mut image[BLOCK_SIZE..2 * BLOCK_SIZE];
+        header[..8].copy_from_slice(GPT_SIGNATURE);
+        header[12..16].copy_from_slice(&92u32.to_le_bytes());
+        header[72..80].copy_from_slice(&2u64.to_le_bytes());
+        header[80..84].copy_from_slice(&(NUM_ENTRIES as u32).to_le_bytes());
+        header[84..88].copy_from_slice(&(ENTRY_SIZE as u32).to_le_bytes());
+        header[88..92].copy_from_slice(&crc32(&entries).to_le_bytes());
+        let crc = crc32(&header[..92]);
+        header[16..20].copy_from_slice(&crc.to_le_bytes());
+
+        let parts = parse(&image);
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].label, "boot");
+        assert_eq!(
+            (parts[0].start_block, parts[0].num_blocks),
+            (FIRST_USABLE, 10)
+        );
+        assert_eq!(parts[0].part_type, PartitionType::Gpt(Guid([0x11; 16])));
+        assert_eq!(parts[1].label, "data");
+        assert_eq!(parts[1].uuid, "abababab-abab-abab-abab-abababababab");
+        // clamped to the end of the device
+        assert_eq!((parts[1].start_block, parts[1].num_blocks), (16, 48));
+    }
+}
@@ -1,33 +1,72 @@
 //! Root directory of the filesystem
 //!
-//! TODO: it doesn't work very well if the mount points have containment relationships.
+//! Filesystems can be mounted on any directory, including one inside another
+//! mounted filesystem. A path is resolved by the mount point that is its
+//! longest prefix, compared component by component.
+//!
+//! Symbolic links are followed while walking a path, at most
+//! [`MAX_SYMLINKS`] times per lookup.
 
-use alloc::{string::String, sync::Arc, vec::Vec};
+use alloc::collections::VecDeque;
+use alloc::{format, string::String, sync::Arc, vec, vec::Vec};
 use axerrno::{ax_err, AxError, AxResult};
 use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType, VfsOps, VfsResult};
 use axsync::Mutex;
 use lazyinit::LazyInit;
 
-use crate::{api::FileType, fs, mounts};
+use crate::watch::WatchEventKind;
+use crate::{fs, mounts};
+
+/// Maximum number of symbolic links followed in a path lookup.
+const MAX_SYMLINKS: usize = 40;
+
+/// Maximum length of a symbolic link target.
+const MAX_SYMLINK_LEN: u64 = 4096;
 
 static CURRENT_DIR_PATH: Mutex<String> = Mutex::new(String::new());
-static CURRENT_DIR: LazyInit<Mutex<VfsNodeRef>> = LazyInit::new();
 
 struct MountPoint {
-    path: &'static str,
+    /// The canonical absolute path, without the trailing `/`.
+    path: String,
+    fs_type: String,
     fs: Arc<dyn VfsOps>,
 }
 
+/// Information of a mounted filesystem.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MountInfo {
+    /// The absolute path of the mount point.
+    pub path: String,
+    /// The filesystem type, e.g., `"fatfs"`, `"ramfs"`.
+    pub fs_type: String,
+}
+
 struct RootDirectory {
     main_fs: Arc<dyn VfsOps>,
-    mounts: Vec<MountPoint>,
+    main_fs_type: &'static str,
+    mounts: Mutex<Vec<MountPoint>>,
 }
 
 static ROOT_DIR: LazyInit<Arc<RootDirectory>> = LazyInit::new();
 
 impl MountPoint {
-    pub fn new(path: &'static str, fs: Arc<dyn VfsOps>) -> Self {
-        Self { path, fs }
+    pub fn new(path: String, fs_type: &str, fs: Arc<dyn VfsOps>) -> Self {
+        Self {
+            path,
+            fs_type: fs_type.into(),
+            fs,
+        }
+    }
+
+    /// Returns the rest of `path` (canonical, without the leading `/`) if it
+    /// is under this mount point.
+    fn strip<'a>(&self, path: &'a str) -> Option<&'a str> {
+        let rest = path.strip_prefix(&self.path[1..])?;
+        if rest.is_empty() || rest.starts_with('/') {
+            Some(rest.trim_start_matches('/'))
+        } else {
+            None
+        }
     }
 }
 
@@ -37,37 +76,155 @@ impl Drop for MountPoint {
     }
 }
 
+/// Returns the canonical form of an absolute mount path, without the trailing
+/// `/`.
+fn mount_path(path: &str) -> AxResult<String> {
+    if !path.starts_with('/') {
+        return ax_err!(InvalidInput, "mount path must start with '/'");
+    }
+    let path = normalize(path);
+    if path.is_empty() {
+        return ax_err!(InvalidInput, "cannot mount or unmount root filesystem");
+    }
+    Ok(format!("/{}", path))
+}
+
+/// Normalizes `path` relative to the root directory, so that it can be
+/// matched against the mount points. `..` at the root stays at the root, as
+/// in [`resolve_path`].
+fn normalize(path: &str) -> String {
+    let mut parts = Vec::new();
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            _ => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
 impl RootDirectory {
-    pub const fn new(main_fs: Arc<dyn VfsOps>) -> Self {
+    pub const fn new(main_fs: Arc<dyn VfsOps>, main_fs_type: &'static str) -> Self {
         Self {
             main_fs,
-            mounts: Vec::new(),
+            main_fs_type,
+            mounts: Mutex::new(Vec::new()),
         }
     }
 
-    pub fn mount(&mut self, path: &'static str, fs: Arc<dyn VfsOps>) -> AxResult {
-        if path == "/" {
-            return ax_err!(InvalidInput, "cannot mount root filesystem");
-        }
-        if !path.starts_with('/') {
-            return ax_err!(InvalidInput, "mount path must start with '/'");
+    /// Finds the mount point that `path` (canonical, without the leading `/`)
+    /// is under, returns it and the rest of the path.
+    fn find_mount<'a, 'b>(
+        mounts: &'b [MountPoint],
+        path: &'a str,
+    ) -> Option<(&'b MountPoint, &'a str)> {
+        // Find the filesystem that has the longest mounted path match
+        // TODO: more efficient, e.g. trie
+        mounts
+            .iter()
+            .filter_map(|mp| mp.strip(path).map(|rest| (mp, rest)))
+            .max_by_key(|(mp, _)| mp.path.len())
+    }
+
+    /// Finds the filesystem that `path` (canonical, without the leading `/`)
+    /// belongs to, returns it and the rest of the path.
+    fn resolve<'a>(
+        main_fs: &Arc<dyn VfsOps>,
+        mounts: &[MountPoint],
+        path: &'a str,
+    ) -> (Arc<dyn VfsOps>, &'a str) {
+        Self::find_mount(mounts, path)
+            .map_or((main_fs.clone(), path), |(mp, rest)| (mp.fs.clone(), rest))
+    }
+
+    pub fn mount(&self, path: &str, fs: Arc<dyn VfsOps>, fs_type: &str) -> AxResult {
+        let path = mount_path(path)?;
+        let mut mounts = self.mounts.lock();
+        if mounts.iter().any(|mp| mp.path == path) {
+            return ax_err!(AlreadyExists, "mount point already exists");
         }
-        if self.mounts.iter().any(|mp| mp.path == path) {
-            return ax_err!(InvalidInput, "mount point already exists");
+        // create the mount point in the parent filesystem if it does not exist
+        let (parent_fs, rest) = Self::resolve(&self.main_fs, &mounts, &path[1..]);
+        let parent_root = parent_fs.root_dir();
+        let node = match parent_root.clone().lookup(rest) {
+            Ok(node) => node,
+            Err(AxError::NotFound) => {
+                parent_root.create(rest, VfsNodeType::Dir)?;
+                parent_root.lookup(rest)?
+            }
+            Err(e) => return Err(e),
+        };
+        if !node.get_attr()?.is_dir() {
+            return ax_err!(NotADirectory);
         }
-        // create the mount point in the main filesystem if it does not exist
-        self.main_fs.root_dir().create(path, FileType::Dir)?;
-        fs.mount(path, self.main_fs.root_dir().lookup(path)?)?;
-        self.mounts.push(MountPoint::new(path, fs));
+        fs.mount(&path, node)?;
+        mounts.push(MountPoint::new(path, fs_type, fs));
         Ok(())
     }
 
-    pub fn _umount(&mut self, path: &str) {
-        self.mounts.retain(|mp| mp.path != path);
+    pub fn umount(&self, path: &str) -> AxResult {
+        let path = mount_path(path)?;
+        let mut mounts = self.mounts.lock();
+        let Some(idx) = mounts.iter().position(|mp| mp.path == path) else {
+            return ax_err!(InvalidInput, "not a mount point");
+        };
+        if mounts
+            .iter()
+            .any(|mp| mp.path.len() > path.len() && mounts[idx].strip(&mp.path[1..]).is_some())
+        {
+            return ax_err!(ResourceBusy, "other filesystems are mounted under it");
+        }
+        if mounts[idx].strip(&CURRENT_DIR_PATH.lock()[1..]).is_some() {
+            return ax_err!(ResourceBusy, "current directory is under it");
+        }
+        mounts.remove(idx);
+        Ok(())
     }
 
     pub fn contains(&self, path: &str) -> bool {
-        self.mounts.iter().any(|mp| mp.path == path)
+        let path = path.trim_end_matches('/');
+        self.mounts.lock().iter().any(|mp| mp.path == path)
+    }
+
+    pub fn mounts(&self) -> Vec<MountInfo> {
+        let root = MountInfo {
+            path: "/".into(),
+            fs_type: self.main_fs_type.into(),
+        };
+        let mounts = self.mounts.lock();
+        let mut infos = Vec::with_capacity(mounts.len() + 1);
+        infos.push(root);
+        infos.extend(mounts.iter().map(|mp| MountInfo {
+            path: mp.path.clone(),
+            fs_type: mp.fs_type.clone(),
+        }));
+        infos
+    }
+
+    /// Creates a hard link `dst_path` to `src_path`, which must be in the same
+    /// filesystem.
+    pub fn link(&self, src_path: &str, dst_path: &str) -> AxResult {
+        let (src_path, dst_path) = (normalize(src_path), normalize(dst_path));
+        let mounts = self.mounts.lock();
+        let src = Self::find_mount(&mounts, &src_path);
+        let dst = Self::find_mount(&mounts, &dst_path);
+        let (fs, src_rest, dst_rest) = match (src, dst) {
+            (None, None) => (self.main_fs.clone(), src_path.as_str(), dst_path.as_str()),
+            (Some((src_mp, src_rest)), Some((dst_mp, dst_rest)))
+                if core::ptr::eq(src_mp, dst_mp) =>
+            {
+                (src_mp.fs.clone(), src_rest, dst_rest)
+            }
+            _ => return ax_err!(Unsupported, "cannot link across filesystems"),
+        };
+        drop(mounts);
+        if src_rest.is_empty() || dst_rest.is_empty() {
+            return ax_err!(PermissionDenied); // cannot link mount points
+        }
+        fs::hard_link(&fs.root_dir(), src_rest, dst_rest)
     }
 
     fn lookup_mounted_fs<F, T>(&self, path: &str, f: F) -> AxResult<T>
@@ -75,29 +232,9 @@ impl RootDirectory {
         F: FnOnce(Arc<dyn VfsOps>, &str) -> AxResult<T>,
     {
         debug!("lookup at root: {}", path);
-        let path = path.trim_matches('/');
-        if let Some(rest) = path.strip_prefix("./") {
-            return self.lookup_mounted_fs(rest, f);
-        }
-
-        let mut idx = 0;
-        let mut max_len = 0;
-
-        // Find the filesystem that has the longest mounted path match
-        // TODO: more efficient, e.g. trie
-        for (i, mp) in self.mounts.iter().enumerate() {
-            // skip the first '/'
-            if path.starts_with(&mp.path[1..]) && mp.path.len() - 1 > max_len {
-                max_len = mp.path.len() - 1;
-                idx = i;
-            }
-        }
-
-        if max_len == 0 {
-            f(self.main_fs.clone(), path) // not matched any mount point
-        } else {
-            f(self.mounts[idx].fs.clone(), &path[max_len..]) // matched at `idx`
-        }
+        let path = normalize(path);
+        let (fs, rest) = Self::resolve(&self.main_fs, &self.mounts.lock(), &path);
+        f(fs, rest)
     }
 }
 
@@ -133,62 +270,273 @@ impl VfsNodeOps for RootDirectory {
     }
 
     fn rename(&self, src_path: &str, dst_path: &str) -> VfsResult {
+        let (dst_fs, dst_rest) =
+            self.lookup_mounted_fs(dst_path, |fs, rest_path| Ok((fs, String::from(rest_path))))?;
         self.lookup_mounted_fs(src_path, |fs, rest_path| {
-            if rest_path.is_empty() {
+            if rest_path.is_empty() || dst_rest.is_empty() {
                 ax_err!(PermissionDenied) // cannot rename mount points
+            } else if !Arc::ptr_eq(&fs, &dst_fs) {
+                ax_err!(Unsupported, "cannot rename across filesystems")
             } else {
-                fs.root_dir().rename(rest_path, dst_path)
+                fs.root_dir().rename(rest_path, &dst_rest)
             }
         })
     }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+}
+
+/// Creates the main filesystem by the format of the disk.
+#[cfg(not(feature = "myfs"))]
+fn new_main_fs(disk: crate::dev::Disk) -> (Arc<dyn VfsOps>, &'static str) {
+    #[cfg(feature = "extfs")]
+    let mut disk = disk;
+    #[cfg(feature = "extfs")]
+    if fs::extfs::ExtFileSystem::probe(&mut disk) {
+        let ext_fs = fs::extfs::ExtFileSystem::new(disk).expect("failed to open ext filesystem");
+        let fs_type = ext_fs.fs_type();
+        return (Arc::new(ext_fs), fs_type);
+    }
+
+    #[cfg(feature = "fatfs")]
+    {
+        static FAT_FS: LazyInit<Arc<fs::fatfs::FatFileSystem>> = LazyInit::new();
+        FAT_FS.init_once(Arc::new(fs::fatfs::FatFileSystem::new(disk)));
+        FAT_FS.init();
+        return (FAT_FS.clone(), "fatfs");
+    }
+
+    #[allow(unreachable_code)]
+    {
+        panic!("no supported filesystem found on the disk")
+    }
 }
 
 pub(crate) fn init_rootfs(disk: crate::dev::Disk) {
     cfg_if::cfg_if! {
         if #[cfg(feature = "myfs")] { // override the default filesystem
-            let main_fs = fs::myfs::new_myfs(disk);
-        } else if #[cfg(feature = "fatfs")] {
-            static FAT_FS: LazyInit<Arc<fs::fatfs::FatFileSystem>> = LazyInit::new();
-            FAT_FS.init_once(Arc::new(fs::fatfs::FatFileSystem::new(disk)));
-            FAT_FS.init();
-            let main_fs = FAT_FS.clone();
+            let main_fs = Arc::new(fs::ForeignFileSystem::new(fs::myfs::new_myfs(disk)));
+            let main_fs_type = "myfs";
+        } else {
+            let (main_fs, main_fs_type) = new_main_fs(disk);
         }
     }
+    init_root_dir(main_fs, main_fs_type);
+}
+
+/// Initializes the root directory with a RAM filesystem unpacked from the
+/// initramfs `archive`.
+#[cfg(feature = "initramfs")]
+pub(crate) fn init_rootfs_from_initramfs(archive: &[u8]) {
+    let ram_fs = fs::ramfs::RamFileSystem::new();
+    crate::initramfs::unpack(archive, &ram_fs.root_dir_node()).expect("failed to unpack initramfs");
+    init_root_dir(Arc::new(ram_fs), "ramfs");
+}
 
-    let mut root_dir = RootDirectory::new(main_fs);
+/// Initializes the root directory on `main_fs`, and mounts other
+/// filesystems on it.
+fn init_root_dir(main_fs: Arc<dyn VfsOps>, main_fs_type: &'static str) {
+    #[cfg(feature = "overlayfs")]
+    let (main_fs, main_fs_type): (Arc<dyn VfsOps>, _) = {
+        info!("  overlay the {} root with a RAM filesystem", main_fs_type);
+        let overlay = fs::overlayfs::OverlayFileSystem::new(main_fs);
+        (Arc::new(overlay), "overlay")
+    };
+
+    let root_dir = RootDirectory::new(main_fs, main_fs_type);
 
     #[cfg(feature = "devfs")]
     root_dir
-        .mount("/dev", mounts::devfs())
+        .mount("/dev", mounts::devfs(), "devfs")
         .expect("failed to mount devfs at /dev");
 
     #[cfg(feature = "ramfs")]
     root_dir
-        .mount("/tmp", mounts::ramfs())
+        .mount("/tmp", mounts::ramfs(), "ramfs")
         .expect("failed to mount ramfs at /tmp");
 
-    // Mount another ramfs as procfs
     #[cfg(feature = "procfs")]
-    root_dir // should not fail
-        .mount("/proc", mounts::procfs().unwrap())
-        .expect("fail to mount procfs at /proc");
+    root_dir
+        .mount("/proc", mounts::procfs(), "procfs")
+        .expect("failed to mount procfs at /proc");
 
-    // Mount another ramfs as sysfs
     #[cfg(feature = "sysfs")]
-    root_dir // should not fail
-        .mount("/sys", mounts::sysfs().unwrap())
-        .expect("fail to mount sysfs at /sys");
+    root_dir
+        .mount("/sys", mounts::sysfs(), "sysfs")
+        .expect("failed to mount sysfs at /sys");
 
     ROOT_DIR.init_once(Arc::new(root_dir));
-    CURRENT_DIR.init_once(Mutex::new(ROOT_DIR.clone()));
     *CURRENT_DIR_PATH.lock() = "/".into();
 }
 
-fn parent_node_of(dir: Option<&VfsNodeRef>, path: &str) -> VfsNodeRef {
-    if path.starts_with('/') {
-        ROOT_DIR.clone()
+/// A directory that paths can be relative to, instead of the current
+/// directory.
+#[derive(Clone, Copy)]
+pub(crate) struct BaseDir<'a> {
+    pub node: &'a VfsNodeRef,
+    /// The absolute path of the directory, with symbolic links resolved.
+    pub path: &'a str,
+}
+
+/// A path with symbolic links resolved.
+struct ResolvedPath {
+    /// The directory that `path` is relative to, or the root directory if
+    /// [`None`], where `path` is absolute.
+    base: Option<VfsNodeRef>,
+    /// The absolute path of `base`.
+    base_path: String,
+    path: String,
+    /// The node at `path`, if it has been looked up while resolving.
+    node: Option<VfsNodeRef>,
+}
+
+impl ResolvedPath {
+    fn base(&self) -> VfsNodeRef {
+        self.base.clone().unwrap_or_else(|| ROOT_DIR.clone())
+    }
+
+    fn lookup(&self) -> AxResult<VfsNodeRef> {
+        match &self.node {
+            Some(node) => Ok(node.clone()),
+            None => self.base().lookup(&self.path),
+        }
+    }
+
+    /// Returns the absolute path, even if it is relative to `base`.
+    fn absolute_path(&self) -> String {
+        match self.base {
+            Some(_) => axfs_vfs::path::canonicalize(&format!("{}/{}", self.base_path, self.path)),
+            None => self.path.clone(),
+        }
+    }
+
+    /// Notifies the watchers of an event of `kind` on the path.
+    fn notify(&self, kind: WatchEventKind, is_dir: bool) {
+        crate::watch::notify(&self.absolute_path(), kind, is_dir);
+    }
+
+    /// Looks up the node like [`lookup`](Self::lookup), and checks it is a
+    /// directory if the original path ends with `/`.
+    fn lookup_as(&self, orig_path: &str) -> AxResult<VfsNodeRef> {
+        let node = self.lookup()?;
+        if orig_path.ends_with('/') && !node.get_attr()?.is_dir() {
+            ax_err!(NotADirectory)
+        } else {
+            Ok(node)
+        }
+    }
+}
+
+/// Reads the target of the symbolic link `node`.
+fn read_link_target(node: &VfsNodeRef) -> AxResult<String> {
+    let size = node.get_attr()?.size();
+    if size > MAX_SYMLINK_LEN {
+        return ax_err!(InvalidData, "symbolic link target too long");
+    }
+    let mut buf = vec![0; size as usize];
+    let len = node.read_at(0, &mut buf)?;
+    buf.truncate(len);
+    String::from_utf8(buf).map_err(|_| AxError::InvalidData)
+}
+
+/// Resolves the symbolic links in `path`, which is relative to `dir`, or the
+/// current directory if `dir` is [`None`].
+///
+/// A symbolic link at the last component is followed only if `follow` is
+/// true, or the path ends with `/`. The last component may not exist, so that
+/// it can be created.
+fn resolve_path(dir: Option<BaseDir>, path: &str, follow: bool) -> AxResult<ResolvedPath> {
+    if path.is_empty() {
+        return ax_err!(NotFound);
+    }
+    let dir = dir.filter(|_| !path.starts_with('/'));
+    let base_path = dir.map_or_else(String::new, |dir| dir.path.into());
+    let mut base = dir.map(|dir| dir.node.clone());
+    let mut parts: Vec<String> = Vec::new();
+    if base.is_none() && !path.starts_with('/') {
+        let cwd = CURRENT_DIR_PATH.lock();
+        parts.extend(cwd.split('/').filter(|p| !p.is_empty()).map(String::from));
+    }
+    let follow = follow || matches!(path.rsplit('/').next(), Some("" | "." | ".."));
+    let mut pending: VecDeque<String> = path
+        .split('/')
+        .filter(|p| !p.is_empty() && *p != ".")
+        .map(String::from)
+        .collect();
+
+    let mut node = None;
+    let mut links = 0;
+    while let Some(part) = pending.pop_front() {
+        node = None;
+        if part == ".." {
+            match parts.last() {
+                // the resolved components have no symbolic links
+                Some(last) if base.is_none() || last != ".." => {
+                    parts.pop();
+                }
+                None if base.is_none() => return ax_err!(NotFound),
+                _ => parts.push(part),
+            }
+            continue;
+        }
+
+        let is_last = pending.is_empty();
+        parts.push(part);
+        if is_last && !follow {
+            break;
+        }
+        let resolved = ResolvedPath {
+            path: join_path(base.is_none(), &parts),
+            base: base.clone(),
+            base_path: String::new(),
+            node: None,
+        };
+        let found = match resolved.lookup() {
+            Ok(found) => found,
+            Err(AxError::NotFound) if is_last => break,
+            Err(e) => return Err(e),
+        };
+        let attr = found.get_attr()?;
+        if attr.file_type() == VfsNodeType::SymLink {
+            links += 1;
+            if links > MAX_SYMLINKS {
+                return ax_err!(InvalidData, "too many levels of symbolic links");
+            }
+            let target = read_link_target(&found)?;
+            if target.is_empty() {
+                return ax_err!(NotFound);
+            }
+            parts.pop();
+            if target.starts_with('/') {
+                base = None;
+                parts.clear();
+            }
+            for part in target.rsplit('/').filter(|p| !p.is_empty() && *p != ".") {
+                pending.push_front(part.into());
+            }
+        } else if !is_last && !attr.is_dir() {
+            return ax_err!(NotADirectory);
+        } else if is_last {
+            node = Some(found);
+        }
+    }
+    Ok(ResolvedPath {
+        path: join_path(base.is_none(), &parts),
+        base,
+        base_path,
+        node,
+    })
+}
+
+fn join_path(absolute: bool, parts: &[String]) -> String {
+    if absolute {
+        String::from("/") + &parts.join("/")
+    } else if parts.is_empty() {
+        ".".into()
     } else {
-        dir.cloned().unwrap_or_else(|| CURRENT_DIR.lock().clone())
+        parts.join("/")
     }
 }
 
@@ -201,50 +549,62 @@ pub(crate) fn absolute_path(path: &str) -> AxResult<String> {
     }
 }
 
-pub(crate) fn lookup(dir: Option<&VfsNodeRef>, path: &str) -> AxResult<VfsNodeRef> {
-    if path.is_empty() {
-        return ax_err!(NotFound);
-    }
-    let node = parent_node_of(dir, path).lookup(path)?;
-    if path.ends_with('/') && !node.get_attr()?.is_dir() {
-        ax_err!(NotADirectory)
-    } else {
-        Ok(node)
-    }
+pub(crate) fn lookup(dir: Option<BaseDir>, path: &str) -> AxResult<VfsNodeRef> {
+    resolve_path(dir, path, true)?.lookup_as(path)
 }
 
-pub(crate) fn create_file(dir: Option<&VfsNodeRef>, path: &str) -> AxResult<VfsNodeRef> {
+/// Looks up `path` like [`lookup`], but does not follow the symbolic link at
+/// the last component.
+pub(crate) fn lookup_no_follow(dir: Option<BaseDir>, path: &str) -> AxResult<VfsNodeRef> {
+    resolve_path(dir, path, false)?.lookup_as(path)
+}
+
+/// Returns the absolute path of `path` relative to `dir`, with symbolic links
+/// resolved.
+pub(crate) fn real_path(dir: Option<BaseDir>, path: &str) -> AxResult<String> {
+    Ok(resolve_path(dir, path, true)?.absolute_path())
+}
+
+pub(crate) fn create_file(dir: Option<BaseDir>, path: &str) -> AxResult<VfsNodeRef> {
     if path.is_empty() {
         return ax_err!(NotFound);
     } else if path.ends_with('/') {
         return ax_err!(NotADirectory);
     }
-    let parent = parent_node_of(dir, path);
-    parent.create(path, VfsNodeType::File)?;
-    parent.lookup(path)
+    let resolved = resolve_path(dir, path, true)?;
+    let parent = resolved.base();
+    parent.create(&resolved.path, VfsNodeType::File)?;
+    resolved.notify(WatchEventKind::Create, false);
+    parent.lookup(&resolved.path)
 }
 
-pub(crate) fn create_dir(dir: Option<&VfsNodeRef>, path: &str) -> AxResult {
-    match lookup(dir, path) {
-        Ok(_) => ax_err!(AlreadyExists),
-        Err(AxError::NotFound) => parent_node_of(dir, path).create(path, VfsNodeType::Dir),
-        Err(e) => Err(e),
+pub(crate) fn create_dir(dir: Option<BaseDir>, path: &str) -> AxResult {
+    let resolved = resolve_path(dir, path, false)?;
+    match resolved.lookup_as(path) {
+        Ok(_) => return ax_err!(AlreadyExists),
+        Err(AxError::NotFound) => {}
+        Err(e) => return Err(e),
     }
+    resolved.base().create(&resolved.path, VfsNodeType::Dir)?;
+    resolved.notify(WatchEventKind::Create, true);
+    Ok(())
 }
 
-pub(crate) fn remove_file(dir: Option<&VfsNodeRef>, path: &str) -> AxResult {
-    let node = lookup(dir, path)?;
-    let attr = node.get_attr()?;
+pub(crate) fn remove_file(dir: Option<BaseDir>, path: &str) -> AxResult {
+    let resolved = resolve_path(dir, path, false)?;
+    let attr = resolved.lookup_as(path)?.get_attr()?;
     if attr.is_dir() {
         ax_err!(IsADirectory)
     } else if !attr.perm().owner_writable() {
         ax_err!(PermissionDenied)
     } else {
-        parent_node_of(dir, path).remove(path)
+        resolved.base().remove(&resolved.path)?;
+        resolved.notify(WatchEventKind::Delete, false);
+        Ok(())
     }
 }
 
-pub(crate) fn remove_dir(dir: Option<&VfsNodeRef>, path: &str) -> AxResult {
+pub(crate) fn remove_dir(dir: Option<BaseDir>, path: &str) -> AxResult {
     if path.is_empty() {
         return ax_err!(NotFound);
     }
@@ -258,18 +618,20 @@ pub(crate) fn remove_dir(dir: Option<&VfsNodeRef>, path: &str) -> AxResult {
     {
         return ax_err!(InvalidInput);
     }
-    if ROOT_DIR.contains(&absolute_path(path)?) {
+    let resolved = resolve_path(dir, path, false)?;
+    if ROOT_DIR.contains(&resolved.absolute_path()) {
         return ax_err!(PermissionDenied);
     }
 
-    let node = lookup(dir, path)?;
-    let attr = node.get_attr()?;
+    let attr = resolved.lookup_as(path)?.get_attr()?;
     if !attr.is_dir() {
         ax_err!(NotADirectory)
     } else if !attr.perm().owner_writable() {
         ax_err!(PermissionDenied)
     } else {
-        parent_node_of(dir, path).remove(path)
+        resolved.base().remove(&resolved.path)?;
+        resolved.notify(WatchEventKind::Delete, true);
+        Ok(())
     }
 }
 
@@ -278,33 +640,115 @@ pub(crate) fn current_dir() -> AxResult<String> {
 }
 
 pub(crate) fn set_current_dir(path: &str) -> AxResult {
-    let mut abs_path = absolute_path(path)?;
-    if !abs_path.ends_with('/') {
-        abs_path += "/";
-    }
-    if abs_path == "/" {
-        *CURRENT_DIR.lock() = ROOT_DIR.clone();
-        *CURRENT_DIR_PATH.lock() = "/".into();
-        return Ok(());
-    }
-
-    let node = lookup(None, &abs_path)?;
-    let attr = node.get_attr()?;
+    let resolved = resolve_path(None, path, true)?;
+    let attr = resolved.lookup_as(path)?.get_attr()?;
     if !attr.is_dir() {
         ax_err!(NotADirectory)
     } else if !attr.perm().owner_executable() {
         ax_err!(PermissionDenied)
     } else {
-        *CURRENT_DIR.lock() = node;
+        let mut abs_path = resolved.path;
+        if !abs_path.ends_with('/') {
+            abs_path += "/";
+        }
         *CURRENT_DIR_PATH.lock() = abs_path;
         Ok(())
     }
 }
 
 pub(crate) fn rename(old: &str, new: &str) -> AxResult {
-    if parent_node_of(None, new).lookup(new).is_ok() {
+    let old = resolve_path(None, old, false)?;
+    let new = resolve_path(None, new, false)?;
+    if new.lookup().is_ok() {
         warn!("dst file already exist, now remove it");
-        remove_file(None, new)?;
+        remove_file(None, &new.path)?;
+    }
+    let is_dir = old.lookup()?.get_attr()?.is_dir();
+    ROOT_DIR.rename(&old.path, &new.path)?;
+    crate::watch::notify_rename(&old.path, &new.path, is_dir);
+    Ok(())
+}
+
+/// Creates a symbolic link at `path` that points to `target`.
+pub(crate) fn symlink(dir: Option<BaseDir>, target: &str, path: &str) -> AxResult {
+    if target.is_empty() {
+        return ax_err!(NotFound);
+    }
+    let resolved = resolve_path(dir, path, false)?;
+    match resolved.lookup() {
+        Ok(_) => return ax_err!(AlreadyExists),
+        Err(AxError::NotFound) => {}
+        Err(e) => return Err(e),
+    }
+    let parent = resolved.base();
+    parent.create(&resolved.path, VfsNodeType::SymLink)?;
+    let res = parent
+        .clone()
+        .lookup(&resolved.path)
+        .and_then(|node| node.write_at(0, target.as_bytes()));
+    if res.is_err() {
+        parent.remove(&resolved.path).ok();
+    } else {
+        resolved.notify(WatchEventKind::Create, false);
     }
-    parent_node_of(None, old).rename(old, new)
+    res.map(|_| ())
+}
+
+/// Returns the target of the symbolic link at `path`.
+pub(crate) fn read_link(dir: Option<BaseDir>, path: &str) -> AxResult<String> {
+    let node = lookup_no_follow(dir, path)?;
+    if node.get_attr()?.file_type() != VfsNodeType::SymLink {
+        return ax_err!(InvalidInput, "not a symbolic link");
+    }
+    read_link_target(&node)
+}
+
+/// Creates a hard link `new` to the file `old`.
+pub(crate) fn hard_link(old: &str, new: &str) -> AxResult {
+    let old = resolve_path(None, old, false)?;
+    if old.lookup()?.get_attr()?.is_dir() {
+        return ax_err!(PermissionDenied, "cannot link directories");
+    }
+    let new = resolve_path(None, new, false)?;
+    if new.lookup().is_ok() {
+        return ax_err!(AlreadyExists);
+    }
+    ROOT_DIR.link(&old.path, &new.path)?;
+    new.notify(WatchEventKind::Create, false);
+    Ok(())
+}
+
+/// Mounts `fs`, which is defined outside this crate, on `path`.
+pub(crate) fn mount(path: &str, fs: Arc<dyn VfsOps>, fs_type: &str) -> AxResult {
+    let fs = Arc::new(fs::ForeignFileSystem::new(fs));
+    ROOT_DIR.mount(&absolute_path(path)?, fs, fs_type)
+}
+
+/// Mounts an overlay on `path`, which merges `lower`, which is defined
+/// outside this crate, with a RAM filesystem.
+#[cfg(feature = "overlayfs")]
+pub(crate) fn mount_overlay(path: &str, lower: Arc<dyn VfsOps>) -> AxResult {
+    let lower = Arc::new(fs::ForeignFileSystem::new(lower));
+    let fs = Arc::new(fs::overlayfs::OverlayFileSystem::new(lower));
+    ROOT_DIR.mount(&absolute_path(path)?, fs, "overlay")
+}
+
+/// Mounts the directory shared by the registered 9P transport with `tag` on
+/// `path`.
+#[cfg(feature = "9p")]
+pub(crate) fn mount_9p(path: &str, tag: &str) -> AxResult {
+    let path = absolute_path(path)?;
+    let Some(transport) = fs::ninep::take_transport(tag) else {
+        return ax_err!(NotFound, "no 9p transport with the mount tag");
+    };
+    let fs = Arc::new(fs::ninep::NinePFileSystem::new(transport)?);
+    ROOT_DIR.mount(&path, fs, "9p")
+}
+
+pub(crate) fn umount(path: &str) -> AxResult {
+    ROOT_DIR.umount(&absolute_path(path)?)
+}
+
+pub(crate) fn mounts() -> Vec<MountInfo> {
+    ROOT_DIR.mounts()
 }
@@ -0,0 +1,280 @@
+//! File change notifications, like `inotify` in Linux.
+//!
+//! Watches are kept by the paths of files or directories, with symbolic links
+//! resolved when added, so a file created again at a watched path is still
+//! watched. Events are generated by the operations of this crate on paths
+//! relative to the root or the current directory.
+//!
+//! At most [`MAX_EVENTS`] events are queued for each watcher, the events
+//! after them are dropped and reported by an [`Overflow`] event.
+//!
+//! [`Overflow`]: WatchEventKind::Overflow
+
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::{string::String, sync::Arc, vec::Vec};
+use core::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+use axerrno::{ax_err, AxResult};
+use axio::PollState;
+use axsync::Mutex;
+
+/// The kind of a file change event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchEventKind {
+    /// A file or directory is created.
+    Create,
+    /// A file is written or truncated.
+    Modify,
+    /// A file or directory is removed.
+    Delete,
+    /// A file or directory is renamed from the path.
+    MovedFrom,
+    /// A file or directory is renamed to the path.
+    MovedTo,
+    /// Events are dropped because the queue is full, like `IN_Q_OVERFLOW`
+    /// in Linux. Its watch descriptor is 0.
+    Overflow,
+}
+
+/// A file change event, read from a [`Watcher`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchEvent {
+    /// The watch descriptor returned by [`Watcher::add`].
+    pub wd: usize,
+    /// The kind of the event.
+    pub kind: WatchEventKind,
+    /// The name of the changed entry in the watched directory, or [`None`]
+    /// if the watched path itself is changed.
+    pub name: Option<String>,
+    /// Whether the changed entry is a directory.
+    pub is_dir: bool,
+    /// A non-zero value shared by the [`MovedFrom`] and [`MovedTo`] events of
+    /// the same rename, or 0 for other events.
+    ///
+    /// [`MovedFrom`]: WatchEventKind::MovedFrom
+    /// [`MovedTo`]: WatchEventKind::MovedTo
+    pub cookie: u32,
+}
+
+/// The maximum number of queued events of a watcher, including the
+/// [`Overflow`](WatchEventKind::Overflow) event, like the default
+/// `max_queued_events` of Linux.
+const MAX_EVENTS: usize = 16384;
+
+#[derive(Default)]
+struct WatchState {
+    /// Watched paths, by their watch descriptors.
+    watches: BTreeMap<usize, String>,
+    next_wd: usize,
+    events: VecDeque<WatchEvent>,
+}
+
+/// Queues `event` in `events`, or an overflow event if it is full. Returns
+/// whether an event is queued.
+fn push_event(events: &mut VecDeque<WatchEvent>, event: WatchEvent) -> bool {
+    // merge with the same unread event, e.g., of consecutive writes
+    if events.back() == Some(&event) {
+        return false;
+    }
+    let len = events.len();
+    if len + 1 < MAX_EVENTS {
+        events.push_back(event);
+    } else if len + 1 == MAX_EVENTS {
+        events.push_back(WatchEvent {
+            wd: 0,
+            kind: WatchEventKind::Overflow,
+            name: None,
+            is_dir: false,
+            cookie: 0,
+        });
+    } else {
+        // overflowed
+        return false;
+    }
+    true
+}
+
+struct WatchQueue {
+    state: Mutex<WatchState>,
+    /// The number of queued events, to be checked without locking `state`.
+    pending: AtomicUsize,
+    #[cfg(feature = "multitask")]
+    wait_queue: axtask::WaitQueue,
+}
+
+/// Queues of the active watchers.
+static WATCHERS: Mutex<Vec<Arc<WatchQueue>>> = Mutex::new(Vec::new());
+
+/// The number of active watchers, to skip locking [`WATCHERS`] without them.
+static NUM_WATCHERS: AtomicUsize = AtomicUsize::new(0);
+
+impl WatchQueue {
+    /// Queues the events of `kind` on `path` for the watches of it, or of its
+    /// parent directory.
+    fn push(&self, path: &str, kind: WatchEventKind, is_dir: bool, cookie: u32) {
+        let (parent, name) = match path.rsplit_once('/') {
+            Some(("", name)) => ("/", name),
+            Some(split) => split,
+            None => return,
+        };
+        let mut state = self.state.lock();
+        let state = &mut *state;
+        let mut pushed = false;
+        for (&wd, watched) in state.watches.iter() {
+            let name = if watched == path {
+                None
+            } else if watched == parent {
+                Some(name.into())
+            } else {
+                continue;
+            };
+            let event = WatchEvent {
+                wd,
+                kind,
+                name,
+                is_dir,
+                cookie,
+            };
+            pushed |= push_event(&mut state.events, event);
+        }
+        if pushed {
+            self.pending.store(state.events.len(), Ordering::Release);
+            #[cfg(feature = "multitask")]
+            self.wait_queue.notify_all(false);
+        }
+    }
+
+    #[cfg(feature = "multitask")]
+    fn wait(&self) -> AxResult {
+        self.wait_queue
+            .wait_until(|| self.pending.load(Ordering::Acquire) > 0);
+        Ok(())
+    }
+
+    #[cfg(not(feature = "multitask"))]
+    fn wait(&self) -> AxResult {
+        // no other tasks can change files
+        ax_err!(WouldBlock, "waiting for a file change forever")
+    }
+}
+
+/// Notifies the watchers of an event of `kind` on the absolute `path`.
+pub(crate) fn notify(path: &str, kind: WatchEventKind, is_dir: bool) {
+    if NUM_WATCHERS.load(Ordering::Acquire) == 0 {
+        return;
+    }
+    for queue in WATCHERS.lock().iter() {
+        queue.push(path, kind, is_dir, 0);
+    }
+}
+
+/// Notifies the watchers that `old` is renamed to `new`, which are absolute.
+pub(crate) fn notify_rename(old: &str, new: &str, is_dir: bool) {
+    static NEXT_COOKIE: AtomicU32 = AtomicU32::new(1);
+    if NUM_WATCHERS.load(Ordering::Acquire) == 0 {
+        return;
+    }
+    let watchers = WATCHERS.lock();
+    let cookie = NEXT_COOKIE.fetch_add(1, Ordering::Relaxed);
+    for queue in watchers.iter() {
+        queue.push(old, WatchEventKind::MovedFrom, is_dir, cookie);
+        queue.push(new, WatchEventKind::MovedTo, is_dir, cookie);
+    }
+}
+
+/// A handle to watch files and directories for changes, and to read the
+/// events of them.
+///
+/// A watch on a file reports the changes of itself, and a watch on a
+/// directory also reports the changes of the entries in it, but not in their
+/// subdirectories.
+pub struct Watcher {
+    queue: Arc<WatchQueue>,
+}
+
+impl Watcher {
+    /// Creates a watcher without any watches.
+    pub fn new() -> Self {
+        let queue = Arc::new(WatchQueue {
+            state: Mutex::new(WatchState {
+                next_wd: 1,
+                ..Default::default()
+            }),
+            pending: AtomicUsize::new(0),
+            #[cfg(feature = "multitask")]
+            wait_queue: axtask::WaitQueue::new(),
+        });
+        let mut watchers = WATCHERS.lock();
+        watchers.push(queue.clone());
+        NUM_WATCHERS.store(watchers.len(), Ordering::Release);
+        Self { queue }
+    }
+
+    /// Watches the file or directory at `path`, which must exist. Returns
+    /// the watch descriptor, which is the existing one if it is already
+    /// watched.
+    pub fn add(&self, path: &str) -> AxResult<usize> {
+        crate::root::lookup(None, path)?;
+        let path = crate::root::real_path(None, path)?;
+        let mut state = self.queue.state.lock();
+        if let Some((&wd, _)) = state.watches.iter().find(|(_, p)| **p == path) {
+            return Ok(wd);
+        }
+        let wd = state.next_wd;
+        state.next_wd += 1;
+        state.watches.insert(wd, path);
+        Ok(wd)
+    }
+
+    /// Removes the watch `wd`. The queued events of it are kept.
+    pub fn remove(&self, wd: usize) -> AxResult {
+        match self.queue.state.lock().watches.remove(&wd) {
+            Some(_) => Ok(()),
+            None => ax_err!(InvalidInput, "no such watch"),
+        }
+    }
+
+    /// Reads the next event.
+    ///
+    /// If there are no events, it waits for one if `wait` is true, or returns
+    /// [`AxError::WouldBlock`](axerrno::AxError::WouldBlock) otherwise.
+    pub fn next_event(&self, wait: bool) -> AxResult<WatchEvent> {
+        loop {
+            {
+                let mut state = self.queue.state.lock();
+                if let Some(event) = state.events.pop_front() {
+                    self.queue
+                        .pending
+                        .store(state.events.len(), Ordering::Release);
+                    return Ok(event);
+                }
+            }
+            if !wait {
+                return ax_err!(WouldBlock);
+            }
+            self.queue.wait()?;
+        }
+    }
+
+    /// Returns whether there are events to read.
+    pub fn poll(&self) -> AxResult<PollState> {
+        Ok(PollState {
+            readable: self.queue.pending.load(Ordering::Acquire) > 0,
+            writable: false,
+        })
+    }
+}
+
+impl Default for Watcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        let mut watchers = WATCHERS.lock();
+        watchers.retain(|queue| !Arc::ptr_eq(queue, &self.queue));
+        NUM_WATCHERS.store(watchers.len(), Ordering::Release);
+    }
+}
@@ -0,0 +1,403 @@
+#![cfg(all(feature = "9p", not(feature = "myfs")))]
+
+use std::collections::HashMap;
+use std::fs::{self as host, OpenOptions};
+use std::os::unix::fs::{FileExt, MetadataExt};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use axdriver::AxDeviceContainer;
+use axdriver_block::ramdisk::RamDisk;
+use axfs::api as fs;
+use axfs::NinePTransport;
+use axfs_vfs::VfsResult;
+use axio::Error;
+
+const IMG_PATH: &str = "resources/fat16.img";
+
+fn make_disk() -> std::io::Result<RamDisk> {
+    let path = std::env::current_dir()?.join(IMG_PATH);
+    let data = std::fs::read(path)?;
+    Ok(RamDisk::from(&data))
+}
+
+/// A 9P2000.L server sharing a host directory, answering just enough for
+/// the client.
+struct HostServer {
+    root: PathBuf,
+    fids: HashMap<u32, (PathBuf, Option<host::File>)>,
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn take(&mut self, len: usize) -> &[u8] {
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        bytes
+    }
+
+    fn u16(&mut self) -> u16 {
+        u16::from_le_bytes(self.take(2).try_into().unwrap())
+    }
+
+    fn u32(&mut self) -> u32 {
+        u32::from_le_bytes(self.take(4).try_into().unwrap())
+    }
+
+    fn u64(&mut self) -> u64 {
+        u64::from_le_bytes(self.take(8).try_into().unwrap())
+    }
+
+    fn str(&mut self) -> String {
+        let len = self.u16() as usize;
+        String::from_utf8(self.take(len).to_vec()).unwrap()
+    }
+}
+
+fn put_str(out: &mut Vec<u8>, s: &str) {
+    out.extend_from_slice(&(s.len() as u16).to_le_bytes());
+    out.extend_from_slice(s.as_bytes());
+}
+
+fn put_qid(out: &mut Vec<u8>, path: &Path) -> std::io::Result<()> {
+    let meta = host::symlink_metadata(path)?;
+    out.push(if meta.is_dir() {
+        0x80
+    } else if meta.is_symlink() {
+        0x02
+    } else {
+        0
+    });
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(&meta.ino().to_le_bytes());
+    Ok(())
+}
+
+impl HostServer {
+    fn path(&self, fid: u32) -> PathBuf {
+        self.fids[&fid].0.clone()
+    }
+
+    fn handle(&mut self, ty: u8, req: &mut Reader) -> std::io::Result<Vec<u8>> {
+        let mut out = Vec::new();
+        match ty {
+            100 => {
+                // Tversion
+                out.extend_from_slice(&req.u32().to_le_bytes());
+                put_str(&mut out, &req.str());
+                self.fids.clear();
+            }
+            104 => {
+                // Tattach
+                self.fids.insert(req.u32(), (self.root.clone(), None));
+                put_qid(&mut out, &self.root)?;
+            }
+            110 => {
+                // Twalk
+                let (fid, new_fid, nwname) = (req.u32(), req.u32(), req.u16());
+                let mut path = self.path(fid);
+                let mut qids = Vec::new();
+                for i in 0..nwname {
+                    path.push(req.str());
+                    if let Err(e) = put_qid(&mut qids, &path) {
+                        if i == 0 {
+                            return Err(e);
+                        }
+                        out.extend_from_slice(&i.to_le_bytes());
+                        out.extend_from_slice(&qids);
+                        return Ok(out);
+                    }
+                }
+                out.extend_from_slice(&nwname.to_le_bytes());
+                out.extend_from_slice(&qids);
+                self.fids.insert(new_fid, (path, None));
+            }
+            12 => {
+                // Tlopen
+                let (fid, flags) = (req.u32(), req.u32());
+                let path = self.path(fid);
+                if !path.is_dir() {
+                    let file = OpenOptions::new()
+                        .read(true)
+                        .write(flags & 3 != 0)
+                        .open(&path)?;
+                    self.fids.get_mut(&fid).unwrap().1 = Some(file);
+                }
+                put_qid(&mut out, &path)?;
+                out.extend_from_slice(&0u32.to_le_bytes());
+            }
+            14 => {
+                // Tlcreate
+                let (fid, name) = (req.u32(), req.str());
+                let path = self.path(fid).join(name);
+                let file = OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .create_new(true)
+                    .open(&path)?;
+                put_qid(&mut out, &path)?;
+                out.extend_from_slice(&0u32.to_le_bytes());
+                self.fids.insert(fid, (path, Some(file)));
+            }
+            116 => {
+                // Tread
+                let (fid, offset, count) = (req.u32(), req.u64(), req.u32());
+                let mut buf = vec![0; count as usize];
+                let len = self.fids[&fid]
+                    .1
+                    .as_ref()
+                    .unwrap()
+                    .read_at(&mut buf, offset)?;
+                out.extend_from_slice(&(len as u32).to_le_bytes());
+                out.extend_from_slice(&buf[..len]);
+            }
+            118 => {
+                // Twrite
+                let (fid, offset, count) = (req.u32(), req.u64(), req.u32());
+                let data = req.take(count as usize);
+                let len = self.fids[&fid].1.as_ref().unwrap().write_at(data, offset)?;
+                out.extend_from_slice(&(len as u32).to_le_bytes());
+            }
+            120 => {
+                // Tclunk
+                self.fids.remove(&req.u32());
+            }
+            24 => {
+                // Tgetattr
+                let path = self.path(req.u32());
+                let meta = host::symlink_metadata(&path)?;
+                out.extend_from_slice(&0x7ffu64.to_le_bytes());
+                put_qid(&mut out, &path)?;
+                for value in [meta.mode(), meta.uid(), meta.gid()] {
+                    out.extend_from_slice(&value.to_le_bytes());
+                }
+                for value in [
+                    meta.nlink(),
+                    meta.rdev(),
+                    meta.size(),
+                    meta.blksize(),
+                    meta.blocks(),
+                    meta.atime() as u64,
+                    meta.atime_nsec() as u64,
+                    meta.mtime() as u64,
+                    meta.mtime_nsec() as u64,
+                    meta.ctime() as u64,
+                    meta.ctime_nsec() as u64,
+                    0,
+                    0,
+                    0,
+                    0,
+                ] {
+                    out.extend_from_slice(&value.to_le_bytes());
+                }
+            }
+            26 => {
+                // Tsetattr
+                let (fid, valid) = (req.u32(), req.u32());
+                req.take(12);
+                let size = req.u64();
+                if valid & 0x8 != 0 {
+                    OpenOptions::new()
+                        .write(true)
+                        .open(self.path(fid))?
+                        .set_len(size)?;
+                }
+            }
+            40 => {
+                // Treaddir
+                let (fid, offset, count) = (req.u32(), req.u64(), req.u32());
+                let dir = self.path(fid);
+                let mut names = vec![".".to_string(), "..".to_string()];
+                for entry in host::read_dir(&dir)? {
+                    names.push(entry?.file_name().into_string().unwrap());
+                }
+                names.sort();
+                let mut data = Vec::new();
+                for (i, name) in names.iter().enumerate().skip(offset as usize) {
+                    let mut entry = Vec::new();
+                    put_qid(&mut entry, &dir.join(name))?;
+                    entry.extend_from_slice(&(i as u64 + 1).to_le_bytes());
+                    entry.push(match entry[0] {
+                        0x80 => 4,
+                        0x02 => 10,
+                        _ => 8,
+                    });
+                    put_str(&mut entry, name);
+                    if data.len() + entry.len() > count as usize {
+                        break;
+                    }
+                    data.extend_from_slice(&entry);
+                }
+                out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+                out.extend_from_slice(&data);
+            }
+            72 => {
+                // Tmkdir
+                let path = self.path(req.u32()).join(req.str());
+                host::create_dir(&path)?;
+                put_qid(&mut out, &path)?;
+            }
+            76 => {
+                // Tunlinkat
+                let path = self.path(req.u32()).join(req.str());
+                match req.u32() {
+                    0x200 => host::remove_dir(path)?,
+                    _ => host::remove_file(path)?,
+                }
+            }
+            74 => {
+                // Trenameat
+                let old = self.path(req.u32()).join(req.str());
+                let new = self.path(req.u32()).join(req.str());
+                host::rename(old, new)?;
+            }
+            16 => {
+                // Tsymlink
+                let path = self.path(req.u32()).join(req.str());
+                std::os::unix::fs::symlink(req.str(), &path)?;
+                put_qid(&mut out, &path)?;
+            }
+            22 => {
+                // Treadlink
+                let target = host::read_link(self.path(req.u32()))?;
+                put_str(&mut out, target.to_str().unwrap());
+            }
+            70 => {
+                // Tlink
+                let dir = self.path(req.u32());
+                let src = self.path(req.u32());
+                host::hard_link(src, dir.join(req.str()))?;
+            }
+            50 => {
+                // Tfsync
+            }
+            // ENOSYS
+            _ => return Err(std::io::Error::from_raw_os_error(38)),
+        }
+        Ok(out)
+    }
+}
+
+impl NinePTransport for HostServer {
+    fn mount_tag(&self) -> &str {
+        "host"
+    }
+
+    fn max_message_size(&self) -> usize {
+        // small enough to split large reads and writes
+        4096
+    }
+
+    fn request(&mut self, req: &[u8], resp: &mut [u8]) -> VfsResult<usize> {
+        let ty = req[4];
+        let (resp_ty, body) = match self.handle(ty, &mut Reader(&req[7..])) {
+            Ok(body) => (ty + 1, body),
+            Err(e) => (7, e.raw_os_error().unwrap_or(5).to_le_bytes().to_vec()),
+        };
+        let size = 7 + body.len();
+        resp[..4].copy_from_slice(&(size as u32).to_le_bytes());
+        resp[4] = resp_ty;
+        resp[5..7].copy_from_slice(&req[5..7]);
+        resp[7..size].copy_from_slice(&body);
+        Ok(size)
+    }
+}
+
+fn read_dir(path: &str) -> Vec<String> {
+    let mut names: Vec<_> = fs::read_dir(path)
+        .unwrap()
+        .map(|e| e.unwrap().file_name())
+        .filter(|name| name != "." && name != "..")
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn test_9p() {
+    println!("Testing 9p ...");
+
+    let shared = std::env::temp_dir().join(format!("axfs-9p-{}", std::process::id()));
+    host::create_dir_all(shared.join("sub")).unwrap();
+    host::write(shared.join("hello.txt"), "Hello, host!\n").unwrap();
+    host::write(shared.join("sub/nested.txt"), "nested").unwrap();
+
+    let disk = make_disk().expect("failed to load disk image");
+    axtask::init_scheduler(); // call this to use `axsync::Mutex`.
+    axfs::init_filesystems(AxDeviceContainer::from_one(disk));
+    axfs::register_9p_transport(Box::new(HostServer {
+        root: shared.clone(),
+        fids: HashMap::new(),
+    }));
+    assert_eq!(fs::mount_9p("/host", "other").err(), Some(Error::NotFound));
+    fs::mount_9p("/host", "host").unwrap();
+    assert_eq!(fs::mounts().last().unwrap().fs_type, "9p");
+
+    // changes on the host are seen immediately
+    assert_eq!(
+        fs::read_to_string("/host/hello.txt").unwrap(),
+        "Hello, host!\n"
+    );
+    host::write(shared.join("hello.txt"), "Changed on host\n").unwrap();
+    assert_eq!(
+        fs::read_to_string("/host/hello.txt").unwrap(),
+        "Changed on host\n"
+    );
+    assert_eq!(read_dir("/host"), ["hello.txt", "sub"]);
+    let mtime = host::metadata(shared.join("hello.txt")).unwrap().mtime();
+    let modified = fs::metadata("/host/hello.txt").unwrap().modified().unwrap();
+    assert_eq!(modified.as_secs(), mtime as u64);
+
+    // and vice versa, with data larger than a message
+    let data: Vec<u8> = (0..20000).map(|i| i as u8).collect();
+    fs::write("/host/sub/large.bin", &data).unwrap();
+    assert_eq!(host::read(shared.join("sub/large.bin")).unwrap(), data);
+    assert_eq!(fs::read("/host/sub/large.bin").unwrap(), data);
+    assert_eq!(fs::metadata("/host/sub/large.bin").unwrap().len(), 20000);
+    fs::write("/host/sub/large.bin", "small").unwrap();
+    assert_eq!(host::read(shared.join("sub/large.bin")).unwrap(), b"small");
+
+    // directories, renames and links
+    fs::create_dir("/host/sub/dir").unwrap();
+    fs::rename("/host/sub/nested.txt", "/host/sub/dir/moved.txt").unwrap();
+    assert_eq!(
+        host::read_to_string(shared.join("sub/dir/moved.txt")).unwrap(),
+        "nested"
+    );
+    assert_eq!(
+        fs::remove_dir("/host/sub/dir").err(),
+        Some(Error::DirectoryNotEmpty)
+    );
+    fs::symlink("dir/moved.txt", "/host/sub/link").unwrap();
+    assert_eq!(fs::read_link("/host/sub/link").unwrap(), "dir/moved.txt");
+    assert_eq!(fs::read_to_string("/host/sub/link").unwrap(), "nested");
+    fs::hard_link("/host/sub/dir/moved.txt", "/host/sub/hard.txt").unwrap();
+    assert_eq!(
+        host::metadata(shared.join("sub/hard.txt")).unwrap().nlink(),
+        2
+    );
+    assert_eq!(
+        read_dir("/host/sub"),
+        ["dir", "hard.txt", "large.bin", "link"]
+    );
+
+    // `..` leaves the shared directory
+    fs::set_current_dir("/host/sub/dir").unwrap();
+    assert_eq!(fs::read_to_string("../hard.txt").unwrap(), "nested");
+    assert!(fs::metadata("../../../long.txt").unwrap().is_file());
+    fs::set_current_dir("/").unwrap();
+
+    fs::remove_file("/host/sub/dir/moved.txt").unwrap();
+    fs::remove_dir("/host/sub/dir").unwrap();
+    assert!(!shared.join("sub/dir").exists());
+
+    // mounted again after unmounted
+    fs::umount("/host").unwrap();
+    assert_eq!(fs::metadata("/host/hello.txt").err(), Some(Error::NotFound));
+    fs::mount_9p("/host", "host").unwrap();
+    assert_eq!(fs::read_to_string("/host/sub/hard.txt").unwrap(), "nested");
+
+    host::remove_dir_all(&shared).unwrap();
+    println!("9p test passed!");
+}
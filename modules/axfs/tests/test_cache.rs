@@ -0,0 +1,53 @@
+#![cfg(not(any(feature = "myfs", feature = "overlayfs")))]
+
+use axdriver::AxDeviceContainer;
+use axdriver_block::ramdisk::RamDisk;
+use axfs::api as fs;
+use axio::Write;
+
+const IMG_PATH: &str = "resources/fat16.img";
+
+fn make_disk() -> std::io::Result<RamDisk> {
+    let path = std::env::current_dir()?.join(IMG_PATH);
+    println!("Loading disk image from {:?} ...", path);
+    let data = std::fs::read(path)?;
+    Ok(RamDisk::from(&data))
+}
+
+#[test]
+fn test_cache() {
+    println!("Testing block cache ...");
+
+    let disk = make_disk().expect("failed to load disk image");
+    axtask::init_scheduler(); // call this to use `axsync::Mutex`.
+    axfs::init_filesystems(AxDeviceContainer::from_one(disk));
+    let dev = &axfs::block_devices()[0];
+
+    // sequential reads are served by read-ahead
+    let before = dev.cache_stats();
+    let contents = fs::read_to_string("/long.txt").unwrap();
+    assert_eq!(contents, "Rust is cool!\n".repeat(1000));
+    let stats = dev.cache_stats();
+    let blocks = (contents.len() / 512) as u64;
+    println!("cache stats after reading: {:?}", stats);
+    assert!(stats.misses - before.misses < blocks / 4);
+
+    // reading again does not touch the device
+    fs::read_to_string("/long.txt").unwrap();
+    assert_eq!(dev.cache_stats().misses, stats.misses);
+
+    // writes stay in the cache until synced
+    let mut file = fs::File::create("/cached.txt").unwrap();
+    file.write_all(&[b'x'; 4096]).unwrap();
+    assert!(dev.cache_stats().dirty_blocks > 0);
+    file.sync_all().unwrap();
+    assert_eq!(dev.cache_stats().dirty_blocks, 0);
+
+    fs::write("/cached.txt", "again").unwrap();
+    assert!(dev.cache_stats().dirty_blocks > 0);
+    fs::sync().unwrap();
+    assert_eq!(dev.cache_stats().dirty_blocks, 0);
+    assert_eq!(fs::read_to_string("/cached.txt").unwrap(), "again");
+
+    println!("test_cache() OK!");
+}
@@ -1,4 +1,7 @@
+use std::sync::Arc;
+
 use axfs::api as fs;
+use axfs_ramfs::RamFileSystem;
 use axio as io;
 
 use fs::{File, FileType, OpenOptions};
@@ -208,8 +211,8 @@ fn test_devfs_ramfs() -> Result<()> {
     assert!(!md.is_file());
     assert!(md.is_dir());
 
-    // stat /dev/foo/bar
-    let fname = ".//.///././/./dev///.///./foo//././bar";
+    // stat /dev/console
+    let fname = ".//.///././/./dev///.///./disk//..//console";
     let file = File::open(fname)?;
     let md = file.metadata()?;
     println!("metadata of {:?}: {:?}", fname, md);
@@ -233,7 +236,7 @@ fn test_devfs_ramfs() -> Result<()> {
     assert_eq!(fs::write(".///dev//..//233//.///test.txt", "test"), Ok(()));
     assert_err!(fs::remove_file("./dev//../..//233//.///test.txt"), NotFound);
     assert_eq!(fs::remove_file("./dev//..//233//../233/./test.txt"), Ok(()));
-    assert_eq!(fs::remove_dir("dev//foo/../foo/../.././/233"), Ok(()));
+    assert_eq!(fs::remove_dir("dev//disk/../disk/../.././/233"), Ok(()));
     assert_err!(fs::remove_dir("very/../dev//"), PermissionDenied);
 
     // tests in /tmp
@@ -242,7 +245,13 @@ fn test_devfs_ramfs() -> Result<()> {
     assert_eq!(fs::read_dir("tmp").unwrap().count(), 1);
     assert_eq!(fs::write(".///tmp///dir//.///test.txt", "test"), Ok(()));
     assert_eq!(fs::read("tmp//././/dir//.///test.txt"), Ok("test".into()));
-    // assert_err!(fs::remove_dir("dev/../tmp//dir"), DirectoryNotEmpty); // TODO
+    let mut file = File::options().write(true).open("/tmp/dir/test.txt")?;
+    assert_eq!(file.write(b"sync")?, 4);
+    assert_eq!(file.sync_all(), Ok(()));
+    assert_eq!(file.flush(), Ok(()));
+    drop(file);
+    assert_eq!(File::open("/dev/null")?.sync_all(), Ok(()));
+    assert_err!(fs::remove_dir("dev/../tmp//dir"), DirectoryNotEmpty);
     assert_err!(fs::remove_dir("/tmp/dir/../dir"), DirectoryNotEmpty);
     assert_eq!(fs::remove_file("./tmp//dir//test.txt"), Ok(()));
     assert_eq!(fs::remove_dir("tmp/dir/.././dir///"), Ok(()));
@@ -252,6 +261,239 @@ fn test_devfs_ramfs() -> Result<()> {
     Ok(())
 }
 
+fn test_mount() -> Result<()> {
+    println!("test mount and umount:");
+    let ramfs = || Arc::new(RamFileSystem::new());
+    let mounts = fs::mounts();
+    println!("mounts = {:?}", mounts);
+    assert_eq!(mounts[0].path, "/");
+    assert!(mounts
+        .iter()
+        .any(|m| m.path == "/tmp" && m.fs_type == "ramfs"));
+
+    // nested mount points
+    fs::mount("/tmp/scratch", ramfs(), "ramfs")?;
+    fs::mount("/../tmp/../../tmp//scratch/inner/", ramfs(), "ramfs")?;
+    fs::write("/tmp/scratch/outer.txt", "outer")?;
+    fs::write("/tmp/scratch/inner/inner.txt", "inner")?;
+    assert_eq!(
+        fs::read_to_string("tmp/./scratch//inner/inner.txt")?,
+        "inner"
+    );
+    assert_eq!(
+        fs::read_to_string("/tmp/scratch/inner/../outer.txt")?,
+        "outer"
+    );
+    assert_err!(fs::metadata("/tmp/scratch/inner/outer.txt"), NotFound);
+    assert!(fs::mounts().iter().any(|m| m.path == "/tmp/scratch/inner"));
+
+    // error cases
+    assert_err!(fs::mount("/", ramfs(), "ramfs"), InvalidInput);
+    assert_err!(fs::mount("/tmp/scratch/", ramfs(), "ramfs"), AlreadyExists);
+    assert_err!(
+        fs::mount("/tmp/scratch/outer.txt", ramfs(), "ramfs"),
+        NotADirectory
+    );
+    assert_err!(fs::umount("/tmp/scratch"), ResourceBusy);
+    assert_err!(fs::umount("/tmp/scratch/outer.txt"), InvalidInput);
+    assert_err!(fs::umount("/.."), InvalidInput);
+    assert_err!(fs::remove_dir("/tmp/scratch/inner"), PermissionDenied);
+
+    // unmount in reverse order
+    fs::umount("/tmp/scratch/inner")?;
+    assert_err!(fs::metadata("/tmp/scratch/inner/inner.txt"), NotFound);
+    assert!(fs::metadata("/tmp/scratch/inner")?.is_dir());
+    fs::umount("/tmp/scratch")?;
+    assert_err!(fs::metadata("/tmp/scratch/outer.txt"), NotFound);
+    assert!(!fs::mounts()
+        .iter()
+        .any(|m| m.path.starts_with("/tmp/scratch")));
+    assert_eq!(fs::remove_dir("/tmp/scratch"), Ok(()));
+    assert_eq!(fs::read_dir("tmp").unwrap().count(), 0);
+
+    println!("test_mount() OK!");
+    Ok(())
+}
+
+fn test_links() -> Result<()> {
+    println!("test symbolic and hard links:");
+    fs::create_dir("/tmp/links")?;
+    fs::write("/tmp/links/file.txt", "linked")?;
+
+    // symbolic links, relative and absolute
+    fs::symlink("file.txt", "/tmp/links/rel")?;
+    fs::symlink("/tmp/links", "/tmp/abs")?;
+    fs::symlink("../short.txt", "/tmp/up")?;
+    assert_eq!(fs::read_to_string("/tmp/links/rel")?, "linked");
+    assert_eq!(fs::read_to_string("tmp/abs/./rel")?, "linked");
+    assert_eq!(fs::read_to_string("/tmp/up")?, "Rust is cool!\n");
+    assert_eq!(fs::read_link("/tmp/links/rel")?, "file.txt");
+    assert!(fs::metadata("/tmp/abs")?.is_dir());
+    assert!(fs::symlink_metadata("/tmp/abs")?.is_symlink());
+    assert!(fs::symlink_metadata("/tmp/abs/")?.is_dir());
+
+    // dangling links and loops
+    fs::symlink("missing.txt", "/tmp/links/dangling")?;
+    fs::symlink("loop2", "/tmp/links/loop1")?;
+    fs::symlink("loop1", "/tmp/links/loop2")?;
+    assert_err!(fs::metadata("/tmp/links/dangling"), NotFound);
+    assert_err!(fs::metadata("/tmp/links/loop1"), InvalidData);
+    fs::write("/tmp/links/dangling", "created")?;
+    assert_eq!(fs::read_to_string("/tmp/links/missing.txt")?, "created");
+
+    // error cases
+    assert_err!(fs::symlink("file.txt", "/tmp/links/rel"), AlreadyExists);
+    assert_err!(fs::read_link("/tmp/links/file.txt"), InvalidInput);
+    assert_err!(fs::hard_link("/tmp/links", "/tmp/links2"), PermissionDenied);
+    assert_err!(
+        fs::hard_link("/tmp/links/file.txt", "/short2.txt"),
+        Unsupported
+    );
+
+    // hard links share the contents
+    fs::hard_link("/tmp/links/file.txt", "/tmp/hard.txt")?;
+    fs::write("/tmp/hard.txt", "changed")?;
+    assert_eq!(fs::read_to_string("/tmp/links/file.txt")?, "changed");
+    fs::remove_file("/tmp/links/file.txt")?;
+    assert_eq!(fs::read_to_string("/tmp/hard.txt")?, "changed");
+    assert_err!(fs::metadata("/tmp/abs/rel"), NotFound);
+
+    // removing a link keeps its target
+    fs::remove_file("/tmp/abs")?;
+    assert!(fs::metadata("/tmp/links")?.is_dir());
+    for name in ["rel", "dangling", "missing.txt", "loop1", "loop2"] {
+        fs::remove_file(&format!("/tmp/links/{}", name))?;
+    }
+    fs::remove_dir("/tmp/links")?;
+    fs::remove_file("/tmp/up")?;
+    fs::remove_file("/tmp/hard.txt")?;
+    assert_eq!(fs::read_dir("tmp").unwrap().count(), 0);
+
+    println!("test_links() OK!");
+    Ok(())
+}
+
+fn test_locks() -> Result<()> {
+    println!("test advisory file locks:");
+    let fname = "/tmp/lock.txt";
+    fs::write(fname, "locked")?;
+    let a = File::open(fname)?;
+    let b = File::open("/tmp/../tmp/./lock.txt")?;
+
+    // shared locks are compatible, exclusive ones are not
+    a.try_lock_shared()?;
+    b.try_lock_shared()?;
+    assert_err!(a.try_lock(), WouldBlock);
+    b.unlock()?;
+    a.try_lock()?;
+    assert_err!(b.try_lock_shared(), WouldBlock);
+
+    // released when the file is closed
+    drop(a);
+    b.try_lock()?;
+    b.unlock()?;
+
+    // shared by hard links, and kept after renamed
+    fs::hard_link(fname, "/tmp/lock-link.txt")?;
+    let c = File::open("/tmp/lock-link.txt")?;
+    b.try_lock()?;
+    assert_err!(c.try_lock_shared(), WouldBlock);
+    fs::rename(fname, "/tmp/lock-renamed.txt")?;
+    let d = File::open("/tmp/lock-renamed.txt")?;
+    assert_err!(d.try_lock_shared(), WouldBlock);
+    drop(b);
+    c.try_lock_shared()?;
+    d.try_lock_shared()?;
+    drop((c, d));
+
+    fs::remove_file("/tmp/lock-link.txt")?;
+    fs::remove_file("/tmp/lock-renamed.txt")?;
+    println!("test_locks() OK!");
+    Ok(())
+}
+
+fn test_watch() -> Result<()> {
+    println!("test file change notifications:");
+    use fs::{WatchEvent, WatchEventKind, Watcher};
+    let event = |wd, kind, name: &str| WatchEvent {
+        wd,
+        kind,
+        name: Some(name.into()),
+        is_dir: false,
+        cookie: 0,
+    };
+
+    fs::create_dir("/tmp/watched")?;
+    let watcher = Watcher::new();
+    let wd = watcher.add("/tmp/watched")?;
+    assert_err!(watcher.next_event(false), WouldBlock);
+
+    fs::write("/tmp/watched/config", "old")?;
+    fs::write("/tmp/watched/config", "new")?;
+    assert!(watcher.poll()?.readable);
+    let create = watcher.next_event(false)?;
+    assert_eq!(create, event(wd, WatchEventKind::Create, "config"));
+    let modify = watcher.next_event(false)?;
+    assert_eq!(modify, event(wd, WatchEventKind::Modify, "config"));
+    assert!(!watcher.poll()?.readable);
+
+    fs::rename("/tmp/watched/config", "/tmp/watched/config.bak")?;
+    let from = watcher.next_event(false)?;
+    let to = watcher.next_event(false)?;
+    assert_eq!(
+        (from.kind, to.kind),
+        (WatchEventKind::MovedFrom, WatchEventKind::MovedTo)
+    );
+    assert_eq!(to.name.as_deref(), Some("config.bak"));
+    assert!(from.cookie != 0 && from.cookie == to.cookie);
+
+    fs::remove_file("/tmp/watched/config.bak")?;
+    let delete = watcher.next_event(false)?;
+    assert_eq!(delete, event(wd, WatchEventKind::Delete, "config.bak"));
+
+    // changes through directory handles are watched too
+    let mut opts = axfs::fops::OpenOptions::new();
+    opts.read(true);
+    let dir = axfs::fops::Directory::open_dir("/tmp", &opts)?;
+    dir.create_file("watched/relative")?;
+    opts.write(true);
+    dir.open_file_at("watched/./relative", &opts)?
+        .write(b"data")?;
+    dir.remove_file("watched/relative")?;
+    for kind in [
+        WatchEventKind::Create,
+        WatchEventKind::Modify,
+        WatchEventKind::Delete,
+    ] {
+        assert_eq!(watcher.next_event(false)?, event(wd, kind, "relative"));
+    }
+
+    // events are dropped when too many are queued
+    let mut a = File::create("/tmp/watched/a")?;
+    let mut b = File::create("/tmp/watched/b")?;
+    for _ in 0..10000 {
+        a.write_all(b"a")?;
+        b.write_all(b"b")?;
+    }
+    let mut events = Vec::new();
+    while let Ok(event) = watcher.next_event(false) {
+        events.push(event);
+    }
+    assert_eq!(events.len(), 16384);
+    let overflow = events.pop().unwrap();
+    assert_eq!((overflow.wd, overflow.kind), (0, WatchEventKind::Overflow));
+    assert!(events.iter().all(|e| e.kind != WatchEventKind::Overflow));
+
+    watcher.remove(wd)?;
+    assert_err!(watcher.remove(wd), InvalidInput);
+    fs::remove_file("/tmp/watched/a")?;
+    fs::remove_file("/tmp/watched/b")?;
+    fs::remove_dir("/tmp/watched")?;
+    assert_err!(watcher.next_event(false), WouldBlock);
+    println!("test_watch() OK!");
+    Ok(())
+}
+
 pub fn test_all() {
     test_read_write_file().expect("test_read_write_file() failed");
     test_read_dir().expect("test_read_dir() failed");
@@ -259,4 +501,8 @@ pub fn test_all() {
     test_create_file_dir().expect("test_create_file_dir() failed");
     test_remove_file_dir().expect("test_remove_file_dir() failed");
     test_devfs_ramfs().expect("test_devfs_ramfs() failed");
+    test_mount().expect("test_mount() failed");
+    test_links().expect("test_links() failed");
+    test_locks().expect("test_locks() failed");
+    test_watch().expect("test_watch() failed");
 }
@@ -0,0 +1,83 @@
+#![cfg(all(feature = "devfs", not(feature = "myfs")))]
+
+use axdriver::AxDeviceContainer;
+use axdriver_block::ramdisk::RamDisk;
+use axfs::api::{self as fs, FileType};
+use axfs::fops::{File, OpenOptions};
+use axio::Error;
+
+const IMG_PATH: &str = "resources/fat16.img";
+
+/// Returns the size in bytes.
+const BLKGETSIZE64: u32 = 0x8008_1272;
+/// Returns the window size of a terminal.
+const TIOCGWINSZ: u32 = 0x5413;
+/// Returns the entropy count of the random pool.
+const RNDGETENTCNT: u32 = 0x8004_5200;
+
+fn make_disk() -> std::io::Result<RamDisk> {
+    let path = std::env::current_dir()?.join(IMG_PATH);
+    let data = std::fs::read(path)?;
+    Ok(RamDisk::from(&data))
+}
+
+fn open(path: &str) -> File {
+    let mut opts = OpenOptions::new();
+    opts.read(true);
+    File::open(path, &opts).unwrap()
+}
+
+#[test]
+fn test_devfs() {
+    println!("Testing devfs ...");
+
+    let disk = make_disk().expect("failed to load disk image");
+    let image = std::fs::read(IMG_PATH).unwrap();
+    axtask::init_scheduler(); // call this to use `axsync::Mutex`.
+    axfs::init_filesystems(AxDeviceContainer::from_one(disk));
+
+    // the ramdisk with 2560000 bytes
+    let md = fs::metadata("/dev/sda").unwrap();
+    assert_eq!(md.file_type(), FileType::BlockDevice);
+    assert_eq!(md.len(), 2560000);
+    let mut sda = open("/dev/sda");
+    let mut buf = [0; 1000];
+    assert_eq!(sda.read(&mut buf).unwrap(), 1000);
+    assert_eq!(buf, image[..1000]);
+    let mut size = 0u64;
+    let ret = unsafe { sda.ioctl(BLKGETSIZE64, &mut size as *mut _ as usize) };
+    assert_eq!(ret, Ok(0));
+    assert_eq!(size, 2560000);
+
+    let console = open("/dev/console");
+    let mut winsize = [0u16; 4];
+    let ret = unsafe { console.ioctl(TIOCGWINSZ, winsize.as_mut_ptr() as usize) };
+    assert_eq!(ret, Ok(0));
+    assert_eq!(winsize[..2], [24, 80]);
+    let ret = unsafe { open("/dev/null").ioctl(TIOCGWINSZ, 0) };
+    assert_eq!(ret, Err(Error::Unsupported));
+
+    let mut random = [0; 64];
+    assert_eq!(open("/dev/urandom").read(&mut random).unwrap(), 64);
+    assert!(random.iter().any(|&b| b != 0));
+    let mut count = 0i32;
+    let ret = unsafe { open("/dev/random").ioctl(RNDGETENTCNT, &mut count as *mut _ as usize) };
+    assert_eq!(ret, Err(Error::Unsupported));
+    assert_eq!(fs::read_link("/dev/rtc").unwrap(), "rtc0");
+    assert_eq!(
+        fs::metadata("/dev/rtc").unwrap().file_type(),
+        FileType::CharDevice
+    );
+
+    // read-only
+    assert_eq!(
+        fs::create_dir("/dev/foo").err(),
+        Some(Error::PermissionDenied)
+    );
+    assert_eq!(
+        fs::remove_file("/dev/sda").err(),
+        Some(Error::PermissionDenied)
+    );
+
+    println!("devfs test passed!");
+}
@@ -0,0 +1,78 @@
+#![cfg(all(feature = "extfs", not(any(feature = "myfs", feature = "overlayfs"))))]
+
+use axdriver::AxDeviceContainer;
+use axdriver_block::ramdisk::RamDisk;
+use axfs::api as fs;
+use axio::Error;
+
+const IMG_PATH: &str = "resources/ext4.img";
+
+fn make_disk() -> std::io::Result<RamDisk> {
+    let path = std::env::current_dir()?.join(IMG_PATH);
+    println!("Loading disk image from {:?} ...", path);
+    let data = std::fs::read(path)?;
+    println!("size = {} bytes", data.len());
+    Ok(RamDisk::from(&data))
+}
+
+#[test]
+fn test_ext4() {
+    println!("Testing ext4 with ramdisk ...");
+
+    let disk = make_disk().expect("failed to load disk image");
+    axtask::init_scheduler(); // call this to use `axsync::Mutex`.
+    axfs::init_filesystems(AxDeviceContainer::from_one(disk));
+
+    assert_eq!(fs::mounts()[0].fs_type, "ext4");
+
+    // files mapped by extents
+    let contents = fs::read_to_string("/long.txt").unwrap();
+    assert_eq!(contents, "Rust is cool!\n".repeat(1000));
+    assert_eq!(
+        fs::read_to_string("/very/long/path/test.txt").unwrap(),
+        "Rust is cool!\n"
+    );
+    let sparse = fs::read("/sparse.bin").unwrap();
+    assert_eq!(sparse.len(), 100004);
+    assert_eq!(&sparse[..4], b"head");
+    assert!(sparse[4..100000].iter().all(|&b| b == 0));
+    assert_eq!(&sparse[100000..], b"tail");
+
+    // hash-indexed directory
+    assert_eq!(fs::read_dir("/many").unwrap().count(), 300);
+    for i in [1, 150, 300] {
+        let path = format!("/many/file-with-a-long-name-{}.txt", i);
+        assert_eq!(fs::read_to_string(&path).unwrap(), format!("{}\n", i));
+    }
+    assert_eq!(
+        fs::metadata("/many/file-with-a-long-name-301.txt").err(),
+        Some(Error::NotFound)
+    );
+
+    // the volume has features that are not writable
+    assert_eq!(
+        fs::write("/new.txt", "Rust is cool!\n").err(),
+        Some(Error::PermissionDenied)
+    );
+    assert_eq!(
+        fs::write("/short.txt", "Rust is cool!\n").err(),
+        Some(Error::PermissionDenied)
+    );
+    assert_eq!(fs::create_dir("/new").err(), Some(Error::PermissionDenied));
+    assert_eq!(
+        fs::remove_file("/short.txt").err(),
+        Some(Error::PermissionDenied)
+    );
+    assert_eq!(
+        fs::rename("/short.txt", "/very/short.txt").err(),
+        Some(Error::PermissionDenied)
+    );
+    assert_eq!(fs::read_to_string("/short.txt").unwrap(), "Rust is cool!\n");
+
+    // other filesystems are still mounted on it
+    fs::write("/tmp/test.txt", "Rust is cool!\n").unwrap();
+    assert_eq!(
+        fs::read_to_string("/tmp/test.txt").unwrap(),
+        "Rust is cool!\n"
+    );
+}
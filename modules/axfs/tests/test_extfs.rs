@@ -0,0 +1,60 @@
+#![cfg(all(feature = "extfs", not(any(feature = "myfs", feature = "overlayfs"))))]
+
+mod test_common;
+
+use std::time::Duration;
+
+use axdriver::AxDeviceContainer;
+use axdriver_block::ramdisk::RamDisk;
+use axfs::api as fs;
+
+const IMG_PATH: &str = "resources/ext2.img";
+
+fn make_disk() -> std::io::Result<RamDisk> {
+    let path = std::env::current_dir()?.join(IMG_PATH);
+    println!("Loading disk image from {:?} ...", path);
+    let data = std::fs::read(path)?;
+    println!("size = {} bytes", data.len());
+    Ok(RamDisk::from(&data))
+}
+
+#[test]
+fn test_extfs() {
+    println!("Testing extfs with ramdisk ...");
+
+    let disk = make_disk().expect("failed to load disk image");
+    axtask::init_scheduler(); // call this to use `axsync::Mutex`.
+    axfs::init_filesystems(AxDeviceContainer::from_one(disk));
+
+    assert_eq!(fs::mounts()[0].fs_type, "ext2");
+
+    // timestamps from the inode
+    let meta = fs::metadata("/long.txt").unwrap();
+    assert_eq!(meta.modified().unwrap(), Duration::from_secs(0x6ad52d5b));
+    assert_eq!(meta.accessed().unwrap(), Duration::from_secs(0x6ad52d5b));
+
+    test_common::test_all();
+
+    // renamed within the root filesystem
+    fs::rename("/short.txt", "/very/short.txt").unwrap();
+    assert!(fs::metadata("/very/short.txt").unwrap().is_file());
+    fs::rename("/very/short.txt", "/short.txt").unwrap();
+
+    // links on the root filesystem
+    fs::symlink("short.txt", "/short-link").unwrap();
+    assert_eq!(fs::read_link("/short-link").unwrap(), "short.txt");
+    assert!(fs::symlink_metadata("/short-link").unwrap().is_symlink());
+    fs::hard_link("/short.txt", "/short-hard.txt").unwrap();
+    fs::remove_file("/short.txt").unwrap();
+    assert_eq!(
+        fs::read_to_string("/short-hard.txt").unwrap(),
+        "Rust is cool!\n"
+    );
+    assert!(fs::metadata("/short-link").is_err());
+
+    // the clock is not set in tests
+    fs::write("/long.txt", "Rust is cool!\n").unwrap();
+    let meta = fs::metadata("/long.txt").unwrap();
+    assert_eq!(meta.modified().unwrap(), Duration::ZERO);
+    assert_eq!(meta.accessed().unwrap(), Duration::from_secs(0x6ad52d5b));
+}
@@ -1,9 +1,12 @@
-#![cfg(not(feature = "myfs"))]
+#![cfg(not(any(feature = "myfs", feature = "overlayfs")))]
 
 mod test_common;
 
+use std::time::Duration;
+
 use axdriver::AxDeviceContainer;
 use axdriver_block::ramdisk::RamDisk;
+use axfs::api as fs;
 
 const IMG_PATH: &str = "resources/fat16.img";
 
@@ -23,5 +26,14 @@ fn test_fatfs() {
     axtask::init_scheduler(); // call this to use `axsync::Mutex`.
     axfs::init_filesystems(AxDeviceContainer::from_one(disk));
 
+    // 2023-04-06 17:32:14, from the directory entry
+    let modified = fs::metadata("/long.txt").unwrap().modified().unwrap();
+    assert_eq!(modified, Duration::from_secs(1680802334));
+
     test_common::test_all();
+
+    // the clock is not set in tests, which is clamped to 1980-01-01 by FAT
+    fs::write("/long.txt", "Rust is cool!\n").unwrap();
+    let modified = fs::metadata("/long.txt").unwrap().modified().unwrap();
+    assert_eq!(modified, Duration::from_secs(315532800));
 }
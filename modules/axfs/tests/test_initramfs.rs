@@ -0,0 +1,124 @@
+#![cfg(all(feature = "initramfs", not(feature = "overlayfs")))]
+
+mod test_common;
+
+use axdriver::AxDeviceContainer;
+use axdriver_block::ramdisk::RamDisk;
+use axfs::api::{self as fs, FileType};
+
+const S_IFDIR: u32 = 0o040000;
+const S_IFREG: u32 = 0o100000;
+const S_IFLNK: u32 = 0o120000;
+const S_IFCHR: u32 = 0o020000;
+
+/// Builds a cpio archive in the `newc` format.
+#[derive(Default)]
+struct Archive(Vec<u8>);
+
+impl Archive {
+    fn add(&mut self, ino: u32, mode: u32, nlink: u32, name: &str, data: &[u8]) -> &mut Self {
+        let fields = [
+            ino,
+            mode,
+            0, // uid
+            0, // gid
+            nlink,
+            0, // mtime
+            data.len() as u32,
+            0, // devmajor
+            0, // devminor
+            0, // rdevmajor
+            0, // rdevminor
+            name.len() as u32 + 1,
+            0, // check
+        ];
+        self.0.extend_from_slice(b"070701");
+        for field in fields {
+            self.0
+                .extend_from_slice(format!("{:08X}", field).as_bytes());
+        }
+        self.0.extend_from_slice(name.as_bytes());
+        self.0.push(0);
+        self.pad();
+        self.0.extend_from_slice(data);
+        self.pad();
+        self
+    }
+
+    fn pad(&mut self) {
+        self.0.resize(self.0.len().next_multiple_of(4), 0);
+    }
+
+    fn finish(&mut self) -> Vec<u8> {
+        self.add(0, 0, 1, "TRAILER!!!", b"");
+        // padded to 512 bytes, like the output of `cpio`
+        self.0.resize(self.0.len().next_multiple_of(512), 0);
+        core::mem::take(&mut self.0)
+    }
+}
+
+fn make_archive() -> Vec<u8> {
+    let mut archive = Archive::default();
+    archive
+        .add(1, S_IFDIR | 0o755, 2, ".", b"")
+        .add(2, S_IFREG | 0o644, 1, "short.txt", b"Rust is cool!\n")
+        .add(
+            3,
+            S_IFREG | 0o644,
+            1,
+            "long.txt",
+            "Rust is cool!\n".repeat(100).as_bytes(),
+        )
+        .add(4, S_IFDIR | 0o755, 2, "very-long-dir-name", b"")
+        .add(
+            5,
+            S_IFREG | 0o644,
+            1,
+            "very-long-dir-name/very-long-file-name.txt",
+            b"Rust is cool!\n",
+        )
+        // parent directories are not in the archive
+        .add(
+            6,
+            S_IFREG | 0o644,
+            1,
+            "very/long/path/test.txt",
+            b"Rust is cool!\n",
+        )
+        .add(7, S_IFDIR | 0o755, 2, "etc", b"")
+        .add(8, S_IFLNK | 0o777, 1, "etc/link", b"../short.txt")
+        // only the last hard link has the content
+        .add(9, S_IFREG | 0o644, 2, "etc/a.conf", b"")
+        .add(9, S_IFREG | 0o644, 2, "etc/b.conf", b"linked")
+        // skipped
+        .add(10, S_IFCHR | 0o600, 1, "etc/console", b"");
+    archive.finish()
+}
+
+#[test]
+fn test_initramfs() {
+    println!("Testing initramfs ...");
+
+    let archive = make_archive();
+    axtask::init_scheduler(); // call this to use `axsync::Mutex`.
+    axfs::init_filesystems_with_initramfs(
+        AxDeviceContainer::from_one(RamDisk::default()), // not used as root
+        &archive,
+    );
+
+    assert!(fs::metadata("/very/long").unwrap().is_dir());
+    assert_eq!(fs::read_link("/etc/link").unwrap(), "../short.txt");
+    assert_eq!(fs::read_to_string("/etc/link").unwrap(), "Rust is cool!\n");
+    assert_eq!(fs::read_to_string("/etc/a.conf").unwrap(), "linked");
+    fs::write("/etc/b.conf", "changed").unwrap();
+    assert_eq!(fs::read_to_string("/etc/a.conf").unwrap(), "changed");
+    assert_eq!(
+        fs::metadata("/etc/console").err(),
+        Some(axio::Error::NotFound)
+    );
+    let md = fs::symlink_metadata("/etc/link").unwrap();
+    assert_eq!(md.file_type(), FileType::SymLink);
+    assert_eq!(fs::mounts()[0].fs_type, "ramfs");
+
+    test_common::test_all();
+}
@@ -0,0 +1,113 @@
+#![cfg(all(feature = "overlayfs", not(feature = "myfs")))]
+
+mod test_common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axdriver::AxDeviceContainer;
+use axdriver_block::ramdisk::RamDisk;
+use axfs::api as fs;
+use axfs_ramfs::RamFileSystem;
+use axfs_vfs::{VfsNodeType, VfsOps};
+use axio::{Error, Read, Write};
+
+const IMG_PATH: &str = "resources/fat16.img";
+
+fn make_disk() -> std::io::Result<RamDisk> {
+    let path = std::env::current_dir()?.join(IMG_PATH);
+    let data = std::fs::read(path)?;
+    Ok(RamDisk::from(&data))
+}
+
+fn read_dir(path: &str) -> Vec<String> {
+    let mut names: Vec<_> = fs::read_dir(path)
+        .unwrap()
+        .map(|e| e.unwrap().file_name())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn test_overlayfs() {
+    println!("Testing overlayfs ...");
+
+    let disk = make_disk().expect("failed to load disk image");
+    let image = std::fs::read(IMG_PATH).unwrap();
+    axtask::init_scheduler(); // call this to use `axsync::Mutex`.
+    axfs::init_filesystems(AxDeviceContainer::from_one(disk));
+    assert_eq!(fs::mounts()[0].fs_type, "overlay");
+
+    // timestamps from the lower layer, until copied up
+    let modified = fs::metadata("/long.txt").unwrap().modified().unwrap();
+    assert_eq!(modified, Duration::from_secs(1680802334));
+
+    // all entries of the lower root are listed
+    let names = read_dir("/");
+    assert!(names.contains(&"long.txt".into()));
+    assert!(names.contains(&"short.txt".into()));
+
+    test_common::test_all();
+
+    // copy-up keeps the rest of the contents
+    let mut file = fs::File::options().write(true).open("/long.txt").unwrap();
+    file.write_all(b"Java").unwrap();
+    drop(file);
+    let contents = fs::read_to_string("/long.txt").unwrap();
+    assert_eq!(contents.len(), 14000);
+    assert!(contents.starts_with("Java is cool!\nRust is cool!\n"));
+
+    // whiteouts hide removed files and directories in the lower layer
+    fs::remove_file("/very/long/path/test.txt").unwrap();
+    fs::remove_dir("/very/long/path").unwrap();
+    assert_eq!(
+        fs::metadata("/very/long/path/test.txt").err(),
+        Some(Error::NotFound)
+    );
+    assert!(!read_dir("/very/long").contains(&"path".into()));
+    fs::create_dir("/very/long/path").unwrap();
+    assert!(read_dir("/very/long/path").is_empty());
+
+    // directories in the lower layer cannot be renamed
+    assert_eq!(
+        fs::rename("/very-long-dir-name", "/renamed").err(),
+        Some(Error::Unsupported)
+    );
+    fs::rename("/short.txt", "/very/short.txt").unwrap();
+    assert_eq!(fs::metadata("/short.txt").err(), Some(Error::NotFound));
+    assert_eq!(
+        fs::read_to_string("/very/short.txt").unwrap(),
+        "Rust is cool!\n"
+    );
+    fs::hard_link("/very/short.txt", "/short.txt").unwrap();
+    assert_eq!(fs::read_to_string("/short.txt").unwrap(), "Rust is cool!\n");
+
+    // the disk is never written
+    let mut sda = fs::File::open("/dev/sda").unwrap();
+    let mut data = Vec::new();
+    sda.read_to_end(&mut data).unwrap();
+    assert!(data == image);
+
+    // overlay other filesystems
+    let lower = Arc::new(RamFileSystem::new());
+    lower
+        .root_dir()
+        .create("base.txt", VfsNodeType::File)
+        .unwrap();
+    let base = lower.root_dir().lookup("base.txt").unwrap();
+    base.write_at(0, b"base").unwrap();
+    fs::mount_overlay("/mnt", lower.clone()).unwrap();
+    fs::write("/mnt/base.txt", "changed").unwrap();
+    fs::write("/mnt/new.txt", "new").unwrap();
+    assert_eq!(read_dir("/mnt"), ["base.txt", "new.txt"]);
+    fs::umount("/mnt").unwrap();
+    let mut buf = [0; 16];
+    assert_eq!(base.read_at(0, &mut buf).unwrap(), 4);
+    assert_eq!(
+        lower.root_dir().lookup("new.txt").err(),
+        Some(Error::NotFound)
+    );
+
+    println!("overlayfs test passed!");
+}
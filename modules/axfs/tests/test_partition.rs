@@ -0,0 +1,117 @@
+#![cfg(not(feature = "myfs"))]
+
+use axdriver::AxDeviceContainer;
+use axdriver_block::ramdisk::RamDisk;
+use axfs::api as fs;
+use axfs::PartitionType;
+
+const IMG_PATH: &str = "resources/fat16.img";
+const BLOCK_SIZE: usize = 512;
+const ENTRY_SIZE: usize = 128;
+const NUM_ENTRIES: usize = 128;
+const FIRST_USABLE: usize = 2 + NUM_ENTRIES * ENTRY_SIZE / BLOCK_SIZE;
+const DATA_BLOCKS: usize = 64;
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xedb8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+fn gpt_entry(type_guid: u8, uuid: u8, first: usize, last: usize, name: &str) -> Vec<u8> {
+    let mut entry = vec![0; ENTRY_SIZE];
+    entry[..16].fill(type_guid);
+    entry[16..32].fill(uuid);
+    entry[32..40].copy_from_slice(&(first as u64).to_le_bytes());
+    entry[40..48].copy_from_slice(&(last as u64).to_le_bytes());
+    for (i, c) in name.encode_utf16().enumerate() {
+        entry[56 + i * 2..58 + i * 2].copy_from_slice(&c.to_le_bytes());
+    }
+    entry
+}
+
+/// Makes a GPT disk with a FAT16 partition "boot" and an empty partition
+/// "data".
+fn make_disk() -> std::io::Result<RamDisk> {
+    let path = std::env::current_dir()?.join(IMG_PATH);
+    println!("Loading disk image from {:?} ...", path);
+    let fat = std::fs::read(path)?;
+    let fat_blocks = fat.len() / BLOCK_SIZE;
+    let num_blocks = FIRST_USABLE + fat_blocks + DATA_BLOCKS;
+    let mut data = vec![0; num_blocks * BLOCK_SIZE];
+
+    // protective MBR
+    data[446 + 4] = 0xee;
+    data[446 + 8..446 + 12].copy_from_slice(&1u32.to_le_bytes());
+    data[446 + 12..446 + 16].copy_from_slice(&(num_blocks as u32 - 1).to_le_bytes());
+    data[510..512].copy_from_slice(&[0x55, 0xaa]);
+
+    // partition entries
+    let boot_end = FIRST_USABLE + fat_blocks - 1;
+    let mut entries = gpt_entry(0x11, 0xaa, FIRST_USABLE, boot_end, "boot");
+    entries.extend(gpt_entry(0x22, 0xbb, boot_end + 1, num_blocks - 1, "data"));
+    entries.resize(NUM_ENTRIES * ENTRY_SIZE, 0);
+    data[2 * BLOCK_SIZE..FIRST_USABLE * BLOCK_SIZE].copy_from_slice(&entries);
+
+    // GPT header
+    let header = &mut data[BLOCK_SIZE..2 * BLOCK_SIZE];
+    header[..8].copy_from_slice(b"EFI PART");
+    header[8..12].copy_from_slice(&0x10000u32.to_le_bytes());
+    header[12..16].copy_from_slice(&92u32.to_le_bytes());
+    header[24..32].copy_from_slice(&1u64.to_le_bytes());
+    header[40..48].copy_from_slice(&(FIRST_USABLE as u64).to_le_bytes());
+    header[48..56].copy_from_slice(&(num_blocks as u64 - 1).to_le_bytes());
+    header[72..80].copy_from_slice(&2u64.to_le_bytes());
+    header[80..84].copy_from_slice(&(NUM_ENTRIES as u32).to_le_bytes());
+    header[84..88].copy_from_slice(&(ENTRY_SIZE as u32).to_le_bytes());
+    header[88..92].copy_from_slice(&crc32(&entries).to_le_bytes());
+    let crc = crc32(&header[..92]);
+    header[16..20].copy_from_slice(&crc.to_le_bytes());
+
+    let fat_start = FIRST_USABLE * BLOCK_SIZE;
+    data[fat_start..fat_start + fat.len()].copy_from_slice(&fat);
+    Ok(RamDisk::from(&data))
+}
+
+#[test]
+fn test_partition() {
+    println!("Testing GPT partitions ...");
+
+    let disk = make_disk().expect("failed to make disk image");
+    axtask::init_scheduler(); // call this to use `axsync::Mutex`.
+    axfs::init_filesystems(AxDeviceContainer::from_one(disk));
+
+    let devs = axfs::block_devices();
+    let names = devs.iter().map(|d| d.name()).collect::<Vec<_>>();
+    assert_eq!(names, ["sda", "sda1", "sda2"]);
+    assert!(devs[0].partition_info().is_none());
+
+    let boot = devs[1].partition_info().unwrap();
+    assert_eq!(boot.number, 1);
+    assert_eq!(boot.label, "boot");
+    assert_eq!(boot.start_block, FIRST_USABLE as u64);
+    assert_eq!(boot.part_type, PartitionType::Gpt(axfs::Guid([0x11; 16])));
+    assert_eq!(boot.uuid, "aaaaaaaa-aaaa-aaaa-aaaa-aaaaaaaaaaaa");
+
+    let data = &devs[2];
+    assert_eq!(data.num_blocks(), DATA_BLOCKS as u64);
+    assert!(data.matches("PARTLABEL=data"));
+    assert!(data.matches("PARTUUID=BBBBBBBB-BBBB-BBBB-BBBB-BBBBBBBBBBBB"));
+    assert!(data.matches("/dev/sda2"));
+    assert!(!data.matches("PARTLABEL=boot"));
+
+    // blocks out of the partition are not accessible
+    let mut buf = [0; BLOCK_SIZE];
+    assert!(data.read_block(DATA_BLOCKS as u64 - 1, &mut buf).is_ok());
+    assert!(data.read_block(DATA_BLOCKS as u64, &mut buf).is_err());
+
+    // the root filesystem is on the first partition
+    assert_eq!(fs::read_to_string("/short.txt").unwrap(), "Rust is cool!\n");
+
+    println!("test_partition() OK!");
+}
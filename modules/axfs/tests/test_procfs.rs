@@ -0,0 +1,87 @@
+#![cfg(all(feature = "procfs", not(any(feature = "myfs", feature = "overlayfs"))))]
+
+use std::sync::Arc;
+
+use axdriver::AxDeviceContainer;
+use axdriver_block::ramdisk::RamDisk;
+use axfs::api as fs;
+use axio::Error;
+
+const IMG_PATH: &str = "resources/fat16.img";
+
+fn make_disk() -> std::io::Result<RamDisk> {
+    let path = std::env::current_dir()?.join(IMG_PATH);
+    let data = std::fs::read(path)?;
+    Ok(RamDisk::from(&data))
+}
+
+#[test]
+fn test_procfs() {
+    println!("Testing procfs ...");
+
+    let disk = make_disk().expect("failed to load disk image");
+    axtask::init_scheduler(); // call this to use `axsync::Mutex`.
+    axfs::init_filesystems(AxDeviceContainer::from_one(disk));
+
+    let names: Vec<_> = fs::read_dir("/proc")
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    for name in ["cpuinfo", "meminfo", "mounts", "net", "uptime"] {
+        assert!(names.iter().any(|n| n == name), "/proc/{} not found", name);
+    }
+
+    // generated when read, but the size is unknown
+    assert_eq!(fs::metadata("/proc/mounts").unwrap().len(), 0);
+    let mounts = fs::read_to_string("/proc/mounts").unwrap();
+    print!("{}", mounts);
+    assert!(mounts.starts_with("fatfs / fatfs rw 0 0\n"));
+    assert!(mounts.contains("procfs /proc procfs rw 0 0\n"));
+
+    fs::create_dir("/mnt").unwrap();
+    fs::mount("/mnt", Arc::new(axfs_ramfs::RamFileSystem::new()), "ramfs").unwrap();
+    let mounts = fs::read_to_string("/proc/mounts").unwrap();
+    assert!(mounts.ends_with("ramfs /mnt ramfs rw 0 0\n"));
+    fs::umount("/mnt").unwrap();
+    fs::remove_dir("/mnt").unwrap();
+    assert!(!fs::read_to_string("/proc/mounts").unwrap().contains("/mnt"));
+
+    let meminfo = fs::read_to_string("/proc/meminfo").unwrap();
+    print!("{}", meminfo);
+    assert!(meminfo.starts_with("MemTotal:"));
+    assert!(meminfo.lines().all(|line| line.ends_with(" kB")));
+
+    let cpuinfo = fs::read_to_string("/proc/cpuinfo").unwrap();
+    assert!(cpuinfo.starts_with("processor\t: 0\n"));
+    let uptime = fs::read_to_string("/proc/uptime").unwrap();
+    assert_eq!(uptime.split_whitespace().count(), 2);
+    let net_dev = fs::read_to_string("/proc/net/dev").unwrap();
+    assert_eq!(net_dev.lines().count(), 2);
+    assert_eq!(
+        fs::read_to_string("/proc/sys/net/core/somaxconn").unwrap(),
+        "4096\n"
+    );
+
+    // read-only
+    assert_eq!(
+        fs::write("/proc/meminfo", "0").err(),
+        Some(Error::PermissionDenied)
+    );
+    assert!(fs::write("/proc/foo", "0").is_err());
+    assert!(fs::remove_file("/proc/uptime").is_err());
+
+    #[cfg(feature = "multitask")]
+    {
+        let tid = axtask::current().id().as_u64().to_string();
+        assert_eq!(fs::read_link("/proc/self").unwrap(), tid);
+        let status = fs::read_to_string("/proc/self/status").unwrap();
+        print!("{}", status);
+        assert!(status.starts_with("Name:\tmain\nState:\tR (running)\n"));
+        assert!(status.contains(&format!("\nPid:\t{}\n", tid)));
+        let stat = fs::read_to_string(&format!("/proc/{}/stat", tid)).unwrap();
+        assert!(stat.starts_with(&format!("{} (main) R ", tid)));
+        assert_eq!(stat.split_whitespace().count(), 52);
+    }
+
+    println!("procfs test passed!");
+}
@@ -0,0 +1,58 @@
+#![cfg(all(feature = "sysfs", not(feature = "myfs")))]
+
+use axdriver::AxDeviceContainer;
+use axdriver_block::ramdisk::RamDisk;
+use axfs::api as fs;
+use axio::Error;
+
+const IMG_PATH: &str = "resources/fat16.img";
+
+fn make_disk() -> std::io::Result<RamDisk> {
+    let path = std::env::current_dir()?.join(IMG_PATH);
+    let data = std::fs::read(path)?;
+    Ok(RamDisk::from(&data))
+}
+
+#[test]
+fn test_sysfs() {
+    println!("Testing sysfs ...");
+
+    let disk = make_disk().expect("failed to load disk image");
+    axtask::init_scheduler(); // call this to use `axsync::Mutex`.
+    axfs::init_filesystems(AxDeviceContainer::from_one(disk));
+
+    let clocksource = "/sys/devices/system/clocksource/clocksource0/current_clocksource";
+    assert_eq!(
+        fs::read_to_string(clocksource).unwrap(),
+        format!("{}\n", axhal::time::CLOCK_SOURCE)
+    );
+    assert!(fs::read_to_string("/sys/devices/system/cpu/online")
+        .unwrap()
+        .starts_with('0'));
+
+    // the ramdisk with 2560000 bytes, not probed by axdriver
+    let names: Vec<_> = fs::read_dir("/sys/class/block")
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    assert_eq!(names, ["sda"]);
+    assert_eq!(
+        fs::read_link("/sys/block/sda").unwrap(),
+        "../class/block/sda"
+    );
+    assert_eq!(fs::read_to_string("/sys/block/sda/size").unwrap(), "5000\n");
+    assert_eq!(
+        fs::read_to_string("/sys/block/sda/queue/logical_block_size").unwrap(),
+        "512\n"
+    );
+    assert!(fs::metadata("/sys/block/sda/device").is_err());
+
+    // read-only
+    assert_eq!(
+        fs::write("/sys/block/sda/size", "0").err(),
+        Some(Error::PermissionDenied)
+    );
+    assert!(fs::create_dir("/sys/foo").is_err());
+
+    println!("sysfs test passed!");
+}
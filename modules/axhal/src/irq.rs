@@ -1,17 +1,65 @@
 //! Interrupt management.
 
+use core::sync::atomic::{AtomicUsize, Ordering};
+
 use handler_table::HandlerTable;
 
-use crate::platform::irq::{dispatch_irq, MAX_IRQ_COUNT};
+use crate::platform::irq::dispatch_irq;
 use crate::trap::{register_trap_handler, IRQ};
 
-pub use crate::platform::irq::{register_handler, set_enable};
+pub use crate::platform::irq::{register_handler, set_enable, MAX_IRQ_COUNT};
 
 /// The type if an IRQ handler.
 pub type IrqHandler = handler_table::Handler;
 
 static IRQ_HANDLER_TABLE: HandlerTable<MAX_IRQ_COUNT> = HandlerTable::new();
 
+/// The number of times each IRQ has been handled on each CPU.
+static IRQ_COUNTS: [[AtomicUsize; MAX_IRQ_COUNT]; axconfig::SMP] =
+    [const { [const { AtomicUsize::new(0) }; MAX_IRQ_COUNT] }; axconfig::SMP];
+
+/// The nesting level of IRQ handlers on the current CPU.
+#[percpu::def_percpu]
+static IRQ_NESTING: usize = 0;
+
+/// The hook called on entry (`true`) and exit (`false`) of IRQ handlers.
+static IRQ_HOOK: AtomicUsize = AtomicUsize::new(0);
+
+/// The type of a hook called on entry and exit of IRQ handlers, with the IRQ
+/// number and whether it is the entry.
+pub type IrqHook = fn(irq_num: usize, entry: bool);
+
+/// Sets the hook called on entry and exit of every IRQ handler, e.g., for
+/// tracing, or removes it if `None` is given.
+pub fn set_irq_hook(hook: Option<IrqHook>) {
+    IRQ_HOOK.store(hook.map_or(0, |f| f as usize), Ordering::Release);
+}
+
+#[inline]
+fn call_irq_hook(irq_num: usize, entry: bool) {
+    let hook = IRQ_HOOK.load(Ordering::Acquire);
+    if hook != 0 {
+        // Safety: it is set by `set_irq_hook` from an `IrqHook`.
+        let hook: IrqHook = unsafe { core::mem::transmute(hook) };
+        hook(irq_num, entry);
+    }
+}
+
+/// Returns whether the current CPU is running an IRQ handler.
+#[inline]
+pub fn in_irq() -> bool {
+    IRQ_NESTING.read_current() != 0
+}
+
+/// Returns the number of times the IRQ `irq_num` has been handled on the CPU
+/// `cpu_id`.
+pub fn irq_count(cpu_id: usize, irq_num: usize) -> usize {
+    IRQ_COUNTS
+        .get(cpu_id)
+        .and_then(|counts| counts.get(irq_num))
+        .map_or(0, |count| count.load(Ordering::Relaxed))
+}
+
 /// Platform-independent IRQ dispatching.
 #[allow(dead_code)]
 pub(crate) fn dispatch_irq_common(irq_num: usize) {
@@ -38,7 +86,14 @@ pub(crate) fn register_handler_common(irq_num: usize, handler: IrqHandler) -> bo
 #[register_trap_handler(IRQ)]
 fn handler_irq(irq_num: usize) -> bool {
     let guard = kernel_guard::NoPreempt::new();
+    unsafe { IRQ_NESTING.write_current_raw(IRQ_NESTING.read_current_raw() + 1) };
+    if let Some(count) = IRQ_COUNTS[crate::cpu::this_cpu_id()].get(irq_num) {
+        count.fetch_add(1, Ordering::Relaxed);
+    }
+    call_irq_hook(irq_num, true);
     dispatch_irq(irq_num);
+    call_irq_hook(irq_num, false);
+    unsafe { IRQ_NESTING.write_current_raw(IRQ_NESTING.read_current_raw() - 1) };
     drop(guard); // rescheduling may occur when preemption is re-enabled.
     true
 }
@@ -46,6 +46,7 @@ pub mod trap;
 pub mod arch;
 pub mod cpu;
 pub mod mem;
+pub mod misc;
 pub mod time;
 
 #[cfg(feature = "tls")]
@@ -62,11 +63,6 @@ pub mod console {
     pub use super::platform::console::*;
 }
 
-/// Miscellaneous operation, e.g. terminate the system.
-pub mod misc {
-    pub use super::platform::misc::*;
-}
-
 /// Multi-core operations.
 #[cfg(feature = "smp")]
 pub mod mp {
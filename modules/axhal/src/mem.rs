@@ -1,6 +1,7 @@
 //! Physical memory management.
 
 use core::fmt;
+use core::sync::atomic::{AtomicUsize, Ordering};
 
 #[doc(no_inline)]
 pub use memory_addr::{MemoryAddr, PhysAddr, VirtAddr, PAGE_SIZE_4K};
@@ -44,6 +45,33 @@ pub struct MemRegion {
     pub name: &'static str,
 }
 
+/// The physical address range `[start, end)` of the initial RAM disk.
+static INITRD: [AtomicUsize; 2] = [AtomicUsize::new(0), AtomicUsize::new(0)];
+
+/// Records the initial RAM disk loaded by the bootloader at `[start, end)`,
+/// so that its memory is reserved.
+#[allow(dead_code)]
+pub(crate) fn set_initrd(start: PhysAddr, end: PhysAddr) {
+    INITRD[0].store(start.as_usize(), Ordering::Relaxed);
+    INITRD[1].store(end.as_usize(), Ordering::Relaxed);
+}
+
+fn initrd_range() -> Option<(usize, usize)> {
+    let start = INITRD[0].load(Ordering::Relaxed);
+    let end = INITRD[1].load(Ordering::Relaxed);
+    (start < end).then_some((start, end))
+}
+
+/// Returns the initial RAM disk loaded by the bootloader (e.g., by QEMU
+/// `-initrd`), if any.
+///
+/// Its memory is reserved and never freed.
+pub fn initrd() -> Option<&'static [u8]> {
+    let (start, end) = initrd_range()?;
+    let ptr = phys_to_virt(start.into()).as_ptr();
+    Some(unsafe { core::slice::from_raw_parts(ptr, end - start) })
+}
+
 /// Converts a virtual address to a physical address.
 ///
 /// It assumes that there is a linear mapping with the offset
@@ -72,7 +100,21 @@ pub const fn phys_to_virt(paddr: PhysAddr) -> VirtAddr {
 
 /// Returns an iterator over all physical memory regions.
 pub fn memory_regions() -> impl Iterator<Item = MemRegion> {
-    kernel_image_regions().chain(crate::platform::mem::platform_regions())
+    kernel_image_regions()
+        .chain(initrd_region())
+        .chain(crate::platform::mem::platform_regions())
+}
+
+/// Returns the memory region of the initial RAM disk, if any.
+fn initrd_region() -> Option<MemRegion> {
+    let (start, end) = initrd_range()?;
+    let start = start.align_down_4k();
+    Some(MemRegion {
+        paddr: start.into(),
+        size: end.align_up_4k() - start,
+        flags: MemRegionFlags::RESERVED | MemRegionFlags::READ,
+        name: "initrd",
+    })
 }
 
 /// Returns the memory regions of the kernel image (code and data sections).
@@ -126,17 +168,30 @@ pub(crate) fn default_mmio_regions() -> impl Iterator<Item = MemRegion> {
     })
 }
 
-/// Returns the default free memory regions (kernel image end to physical memory end).
+/// Returns the default free memory regions (kernel image end to physical
+/// memory end), excluding the initial RAM disk.
 #[allow(dead_code)]
 pub(crate) fn default_free_regions() -> impl Iterator<Item = MemRegion> {
-    let start = virt_to_phys((_ekernel as usize).into()).align_up_4k();
-    let end = pa!(axconfig::PHYS_MEMORY_END).align_down_4k();
-    core::iter::once(MemRegion {
-        paddr: start,
-        size: end.as_usize() - start.as_usize(),
-        flags: MemRegionFlags::FREE | MemRegionFlags::READ | MemRegionFlags::WRITE,
-        name: "free memory",
-    })
+    let start = virt_to_phys((_ekernel as usize).into())
+        .align_up_4k()
+        .as_usize();
+    let end = axconfig::PHYS_MEMORY_END.align_down_4k();
+    let (hole_start, hole_end) = match initrd_region() {
+        Some(r) if r.paddr.as_usize() < end && r.paddr.as_usize() + r.size > start => (
+            r.paddr.as_usize().max(start),
+            (r.paddr.as_usize() + r.size).min(end),
+        ),
+        _ => (end, end),
+    };
+    [(start, hole_start), (hole_end, end)]
+        .into_iter()
+        .filter(|(start, end)| start < end)
+        .map(|(start, end)| MemRegion {
+            paddr: start.into(),
+            size: end - start,
+            flags: MemRegionFlags::FREE | MemRegionFlags::READ | MemRegionFlags::WRITE,
+            name: "free memory",
+        })
 }
 
 /// Fills the `.bss` section with zeros.
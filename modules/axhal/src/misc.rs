@@ -0,0 +1,46 @@
+//! Miscellaneous operation, e.g. terminate the system.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use handler_table::HandlerTable;
+
+// other platform-specific operations, e.g., resets on some boards
+#[allow(unused_imports)]
+pub use crate::platform::misc::*;
+
+/// The maximum number of shutdown hooks.
+const MAX_SHUTDOWN_HOOKS: usize = 8;
+
+static SHUTDOWN_HOOKS: HandlerTable<MAX_SHUTDOWN_HOOKS> = HandlerTable::new();
+
+/// Registers a function to be called before the system is shut down by
+/// [`terminate`], e.g., to write cached data back to disks.
+///
+/// Returns `false` if too many hooks are registered.
+pub fn register_shutdown_hook(hook: fn()) -> bool {
+    (0..MAX_SHUTDOWN_HOOKS).any(|idx| SHUTDOWN_HOOKS.register_handler(idx, hook))
+}
+
+/// Calls the registered shutdown hooks in order. They are only called the
+/// first time, e.g., not again if one of them terminates the system.
+pub fn run_shutdown_hooks() {
+    static DONE: AtomicBool = AtomicBool::new(false);
+    if !DONE.swap(true, Ordering::AcqRel) {
+        for idx in 0..MAX_SHUTDOWN_HOOKS {
+            SHUTDOWN_HOOKS.handle(idx);
+        }
+    }
+}
+
+/// Shutdown the whole system, including all CPUs, after calling the
+/// shutdown hooks.
+pub fn terminate() -> ! {
+    run_shutdown_hooks();
+    crate::platform::misc::terminate()
+}
+
+/// Shutdown the whole system, including all CPUs, without calling the
+/// shutdown hooks, e.g., on panic.
+pub fn abort() -> ! {
+    crate::platform::misc::terminate()
+}
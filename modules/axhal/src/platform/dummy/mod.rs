@@ -35,6 +35,9 @@ pub mod mem {
 }
 
 pub mod time {
+    /// The name of the clock source, as `current_clocksource` in Linux.
+    pub const CLOCK_SOURCE: &str = "jiffies";
+
     /// Returns the current clock time in hardware ticks.
     pub fn current_ticks() -> u64 {
         0
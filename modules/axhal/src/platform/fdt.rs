@@ -0,0 +1,90 @@
+//! A minimal reader of the flattened device tree (FDT), to find boot
+//! parameters before the memory allocator is ready.
+
+const FDT_MAGIC: u32 = 0xd00d_feed;
+const FDT_BEGIN_NODE: u32 = 1;
+const FDT_END_NODE: u32 = 2;
+const FDT_PROP: u32 = 3;
+const FDT_NOP: u32 = 4;
+
+fn read_be32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_be_bytes(bytes.try_into().unwrap()))
+}
+
+/// Reads a property value of one or two cells.
+fn read_cells(value: &[u8]) -> Option<usize> {
+    match value.len() {
+        4 => Some(u32::from_be_bytes(value.try_into().unwrap()) as usize),
+        8 => Some(u64::from_be_bytes(value.try_into().unwrap()) as usize),
+        _ => None,
+    }
+}
+
+/// Reads a NUL-terminated string at `offset`.
+fn read_str(data: &[u8], offset: usize) -> Option<&[u8]> {
+    let bytes = data.get(offset..)?;
+    Some(&bytes[..bytes.iter().position(|&b| b == 0)?])
+}
+
+/// Returns the physical address range `[start, end)` of the initial RAM disk
+/// given by `linux,initrd-start` and `linux,initrd-end` in `/chosen`.
+fn find_initrd(data: &[u8]) -> Option<(usize, usize)> {
+    let off_struct = read_be32(data, 8)? as usize;
+    let off_strings = read_be32(data, 12)? as usize;
+    let (mut start, mut end) = (None, None);
+    let mut pos = off_struct;
+    let mut depth = 0;
+    let mut chosen_depth = None;
+    loop {
+        let token = read_be32(data, pos)?;
+        pos += 4;
+        match token {
+            FDT_BEGIN_NODE => {
+                let name = read_str(data, pos)?;
+                pos = (pos + name.len() + 1 + 3) & !3;
+                depth += 1;
+                // the root node is at depth 1
+                if depth == 2 && name == b"chosen" {
+                    chosen_depth = Some(depth);
+                }
+            }
+            FDT_END_NODE if chosen_depth == Some(depth) => break,
+            FDT_END_NODE => depth -= 1,
+            FDT_PROP => {
+                let len = read_be32(data, pos)? as usize;
+                let name_off = read_be32(data, pos + 4)? as usize;
+                let value = data.get(pos + 8..pos + 8 + len)?;
+                pos = (pos + 8 + len + 3) & !3;
+                if chosen_depth == Some(depth) {
+                    match read_str(data, off_strings + name_off)? {
+                        b"linux,initrd-start" => start = read_cells(value),
+                        b"linux,initrd-end" => end = read_cells(value),
+                        _ => {}
+                    }
+                }
+            }
+            FDT_NOP => {}
+            _ => break, // `FDT_END` or malformed
+        }
+    }
+    Some((start?, end?))
+}
+
+/// Records the initial RAM disk given in the device tree at the physical
+/// address `dtb`, if any.
+pub(super) unsafe fn init_initrd(dtb: usize) {
+    if dtb == 0 {
+        return;
+    }
+    let base = crate::mem::phys_to_virt(dtb.into()).as_ptr();
+    let header = core::slice::from_raw_parts(base, 8);
+    if read_be32(header, 0) != Some(FDT_MAGIC) {
+        return;
+    }
+    let total_size = read_be32(header, 4).unwrap() as usize;
+    let data = core::slice::from_raw_parts(base, total_size);
+    if let Some((start, end)) = find_initrd(data) {
+        crate::mem::set_initrd(start.into(), end.into());
+    }
+}
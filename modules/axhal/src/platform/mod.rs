@@ -6,6 +6,12 @@ cfg_if::cfg_if! {
     }
 }
 
+#[cfg(any(
+    all(target_arch = "riscv64", platform_family = "riscv64-qemu-virt"),
+    all(target_arch = "aarch64", platform_family = "aarch64-qemu-virt"),
+))]
+mod fdt;
+
 cfg_if::cfg_if! {
     if #[cfg(all(target_arch = "x86_64", platform_family = "x86-pc"))] {
         mod x86_pc;
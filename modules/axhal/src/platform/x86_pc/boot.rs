@@ -16,6 +16,10 @@ const MULTIBOOT_HEADER_MAGIC: usize = 0x1BADB002;
 /// This should be in EAX.
 pub(super) const MULTIBOOT_BOOTLOADER_MAGIC: usize = 0x2BADB002;
 
+/// Set in the ’flags’ member of the multiboot information if the boot
+/// modules are loaded (`mods_count` and `mods_addr` are valid).
+const MULTIBOOT_INFO_MODS: u32 = 1 << 3;
+
 const CR0: u64 = Cr0Flags::PROTECTED_MODE_ENABLE.bits()
     | Cr0Flags::MONITOR_COPROCESSOR.bits()
     | Cr0Flags::NUMERIC_ERROR.bits()
@@ -50,3 +54,18 @@ global_asm!(
     efer_msr = const x86::msr::IA32_EFER,
     efer = const EFER,
 );
+
+/// Records the first boot module in the multiboot information at the
+/// physical address `mbi` as the initial RAM disk (e.g., loaded by QEMU
+/// `-initrd`).
+pub(super) unsafe fn init_initrd(mbi: usize) {
+    let info = crate::mem::phys_to_virt(mbi.into()).as_ptr() as *const u32;
+    let (flags, mods_count, mods_addr) = (info.read(), info.add(5).read(), info.add(6).read());
+    if flags & MULTIBOOT_INFO_MODS == 0 || mods_count == 0 {
+        return;
+    }
+    // each module is `mod_start`, `mod_end`, `string` and `reserved`
+    let module = crate::mem::phys_to_virt((mods_addr as usize).into()).as_ptr() as *const u32;
+    let (start, end) = (module.read() as usize, module.add(1).read() as usize);
+    crate::mem::set_initrd(start.into(), end.into());
+}
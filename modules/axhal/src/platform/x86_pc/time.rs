@@ -15,6 +15,9 @@ static mut CPU_FREQ_MHZ: u64 = axconfig::TIMER_FREQUENCY as u64 / 1_000_000;
 /// RTC wall time offset in nanoseconds at monotonic time base.
 static mut RTC_EPOCHOFFSET_NANOS: u64 = 0;
 
+/// The name of the clock source, as `current_clocksource` in Linux.
+pub const CLOCK_SOURCE: &str = "tsc";
+
 /// Returns the current clock time in hardware ticks.
 pub fn current_ticks() -> u64 {
     unsafe { core::arch::x86_64::_rdtsc() - INIT_TICK }
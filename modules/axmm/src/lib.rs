@@ -13,7 +13,7 @@ pub use self::aspace::AddrSpace;
 use axerrno::{AxError, AxResult};
 use axhal::mem::phys_to_virt;
 use axhal::paging::PagingError;
-use kspin::SpinNoIrq;
+use axsync::spin::SpinNoIrq;
 use lazyinit::LazyInit;
 use memory_addr::{va, PhysAddr};
 
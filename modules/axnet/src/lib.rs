@@ -35,6 +35,7 @@ pub use self::net_impl::TcpSocket;
 pub use self::net_impl::UdpSocket;
 pub use self::net_impl::{bench_receive, bench_transmit};
 pub use self::net_impl::{dns_query, poll_interfaces};
+pub use self::net_impl::{interfaces, InterfaceInfo, InterfaceStats};
 
 use axdriver::{prelude::*, AxDeviceContainer};
 
@@ -5,8 +5,8 @@ mod listen_table;
 mod tcp;
 mod udp;
 
-use alloc::vec;
-use core::cell::RefCell;
+use alloc::{vec, vec::Vec};
+use core::cell::{Cell, RefCell};
 use core::ops::DerefMut;
 
 use axdriver::prelude::*;
@@ -58,6 +58,37 @@ struct SocketSetWrapper<'a>(Mutex<SocketSet<'a>>);
 
 struct DeviceWrapper {
     inner: RefCell<AxNetDevice>, // use `RefCell` is enough since it's wrapped in `Mutex` in `InterfaceWrapper`.
+    stats: Cell<InterfaceStats>,
+}
+
+/// Information of a network interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InterfaceInfo {
+    /// The interface name, e.g., `eth0`.
+    pub name: &'static str,
+    /// The MAC address.
+    pub mac_address: [u8; 6],
+    /// The maximum transmission unit, excluding the Ethernet header.
+    pub mtu: usize,
+    /// The traffic statistics.
+    pub stats: InterfaceStats,
+}
+
+/// Traffic statistics of a network interface.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct InterfaceStats {
+    /// The number of bytes received.
+    pub rx_bytes: u64,
+    /// The number of packets received.
+    pub rx_packets: u64,
+    /// The number of errors when receiving.
+    pub rx_errors: u64,
+    /// The number of bytes transmitted.
+    pub tx_bytes: u64,
+    /// The number of packets transmitted.
+    pub tx_packets: u64,
+    /// The number of errors when transmitting.
+    pub tx_errors: u64,
 }
 
 struct InterfaceWrapper {
@@ -183,8 +214,15 @@ impl DeviceWrapper {
     fn new(inner: AxNetDevice) -> Self {
         Self {
             inner: RefCell::new(inner),
+            stats: Cell::new(InterfaceStats::default()),
         }
     }
+
+    fn update_stats(&self, f: impl FnOnce(&mut InterfaceStats)) {
+        let mut stats = self.stats.get();
+        f(&mut stats);
+        self.stats.set(stats);
+    }
 }
 
 impl Device for DeviceWrapper {
@@ -201,6 +239,7 @@ impl Device for DeviceWrapper {
         let mut dev = self.inner.borrow_mut();
         if let Err(e) = dev.recycle_tx_buffers() {
             warn!("recycle_tx_buffers failed: {:?}", e);
+            self.update_stats(|s| s.tx_errors += 1);
             return None;
         }
 
@@ -212,21 +251,23 @@ impl Device for DeviceWrapper {
             Err(err) => {
                 if !matches!(err, DevError::Again) {
                     warn!("receive failed: {:?}", err);
+                    self.update_stats(|s| s.rx_errors += 1);
                 }
                 return None;
             }
         };
-        Some((AxNetRxToken(&self.inner, rx_buf), AxNetTxToken(&self.inner)))
+        Some((AxNetRxToken(self, rx_buf), AxNetTxToken(self)))
     }
 
     fn transmit(&mut self, _timestamp: Instant) -> Option<Self::TxToken<'_>> {
         let mut dev = self.inner.borrow_mut();
         if let Err(e) = dev.recycle_tx_buffers() {
             warn!("recycle_tx_buffers failed: {:?}", e);
+            self.update_stats(|s| s.tx_errors += 1);
             return None;
         }
         if dev.can_transmit() {
-            Some(AxNetTxToken(&self.inner))
+            Some(AxNetTxToken(self))
         } else {
             None
         }
@@ -241,8 +282,8 @@ impl Device for DeviceWrapper {
     }
 }
 
-struct AxNetRxToken<'a>(&'a RefCell<AxNetDevice>, NetBufPtr);
-struct AxNetTxToken<'a>(&'a RefCell<AxNetDevice>);
+struct AxNetRxToken<'a>(&'a DeviceWrapper, NetBufPtr);
+struct AxNetTxToken<'a>(&'a DeviceWrapper);
 
 impl<'a> RxToken for AxNetRxToken<'a> {
     fn preprocess(&self, sockets: &mut SocketSet<'_>) {
@@ -259,8 +300,12 @@ impl<'a> RxToken for AxNetRxToken<'a> {
             rx_buf.packet_len(),
             rx_buf.packet()
         );
+        self.0.update_stats(|s| {
+            s.rx_bytes += rx_buf.packet_len() as u64;
+            s.rx_packets += 1;
+        });
         let result = f(rx_buf.packet_mut());
-        self.0.borrow_mut().recycle_rx_buffer(rx_buf).unwrap();
+        self.0.inner.borrow_mut().recycle_rx_buffer(rx_buf).unwrap();
         result
     }
 }
@@ -270,11 +315,15 @@ impl<'a> TxToken for AxNetTxToken<'a> {
     where
         F: FnOnce(&mut [u8]) -> R,
     {
-        let mut dev = self.0.borrow_mut();
+        let mut dev = self.0.inner.borrow_mut();
         let mut tx_buf = dev.alloc_tx_buffer(len).unwrap();
         let ret = f(tx_buf.packet_mut());
         trace!("SEND {} bytes: {:02X?}", len, tx_buf.packet());
         dev.transmit(tx_buf).unwrap();
+        self.0.update_stats(|s| {
+            s.tx_bytes += len as u64;
+            s.tx_packets += 1;
+        });
         ret
     }
 }
@@ -306,6 +355,19 @@ pub fn poll_interfaces() {
     SOCKET_SET.poll_interfaces();
 }
 
+/// Returns the information of all network interfaces.
+pub fn interfaces() -> Vec<InterfaceInfo> {
+    if !ETH0.is_inited() {
+        return Vec::new();
+    }
+    vec![InterfaceInfo {
+        name: ETH0.name,
+        mac_address: ETH0.ether_addr.0,
+        mtu: STANDARD_MTU,
+        stats: ETH0.dev.lock().stats.get(),
+    }]
+}
+
 /// Benchmark raw socket transmit bandwidth.
 pub fn bench_transmit() {
     ETH0.dev.lock().bench_transmit_bandwidth();
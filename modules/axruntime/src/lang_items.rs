@@ -3,5 +3,5 @@ use core::panic::PanicInfo;
 #[panic_handler]
 fn panic(info: &PanicInfo) -> ! {
     error!("{}", info);
-    axhal::misc::terminate()
+    axhal::misc::abort()
 }
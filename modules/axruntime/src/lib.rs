@@ -11,6 +11,8 @@
 //! - `multitask`: Enable multi-threading support.
 //! - `smp`: Enable SMP (symmetric multiprocessing) support.
 //! - `fs`: Enable filesystem support.
+//! - `initramfs`: Use an initramfs as the root filesystem if it is loaded by
+//!    the bootloader or embedded in the kernel image, instead of a block device.
 //! - `net`: Enable networking support.
 //! - `display`: Enable graphics support.
 //!
@@ -159,7 +161,13 @@ pub extern "C" fn rust_main(cpu_id: usize, dtb: usize) -> ! {
         let all_devices = axdriver::init_drivers();
 
         #[cfg(feature = "fs")]
-        axfs::init_filesystems(all_devices.block);
+        init_filesystems(all_devices.block);
+
+        #[cfg(feature = "virtio-9p")]
+        axfs::init_9p_devices(all_devices.ninep);
+
+        #[cfg(all(feature = "fs", feature = "multitask", feature = "irq"))]
+        spawn_writeback_task();
 
         #[cfg(feature = "net")]
         axnet::init_network(all_devices.net);
@@ -201,6 +209,33 @@ pub extern "C" fn rust_main(cpu_id: usize, dtb: usize) -> ! {
     }
 }
 
+/// Initializes filesystems, on the initramfs loaded by the bootloader (e.g.,
+/// QEMU `-initrd`) or embedded in the kernel image if there is one.
+#[cfg(feature = "fs")]
+fn init_filesystems(blk_devs: axdriver::AxDeviceContainer<axdriver::prelude::AxBlockDevice>) {
+    #[cfg(feature = "initramfs")]
+    if let Some(archive) = axhal::mem::initrd().or_else(axfs::embedded_initramfs) {
+        return axfs::init_filesystems_with_initramfs(blk_devs, archive);
+    }
+    axfs::init_filesystems(blk_devs);
+}
+
+/// Spawns a task to write cached filesystem data back periodically.
+#[cfg(all(feature = "fs", feature = "multitask", feature = "irq"))]
+fn spawn_writeback_task() {
+    const WRITEBACK_INTERVAL: core::time::Duration = core::time::Duration::from_secs(5);
+    axtask::spawn_raw(
+        || loop {
+            axtask::sleep(WRITEBACK_INTERVAL);
+            if let Err(e) = axfs::api::sync() {
+                warn!("failed to write back filesystem data: {:?}", e);
+            }
+        },
+        "fs-writeback".into(),
+        axconfig::TASK_STACK_SIZE,
+    );
+}
+
 #[cfg(feature = "alloc")]
 fn init_allocator() {
     use axhal::mem::{memory_regions, phys_to_virt, MemRegionFlags};
@@ -0,0 +1,116 @@
+//! A barrier to synchronize a group of tasks.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use axtask::WaitQueue;
+use kspin::SpinNoIrq;
+
+/// A barrier enables multiple tasks to synchronize the beginning of some
+/// computation, similar to
+/// [`std::sync::Barrier`](https://doc.rust-lang.org/std/sync/struct.Barrier.html).
+pub struct Barrier {
+    wq: WaitQueue,
+    /// The number of tasks arrived in the current generation.
+    count: SpinNoIrq<usize>,
+    /// Incremented when all tasks of a generation have arrived.
+    generation: AtomicUsize,
+    num_tasks: usize,
+}
+
+/// A `BarrierWaitResult` is returned by [`Barrier::wait()`] when all tasks in
+/// the [`Barrier`] have rendezvoused.
+#[derive(Debug)]
+pub struct BarrierWaitResult(bool);
+
+impl BarrierWaitResult {
+    /// Returns `true` if this task is the "leader task" for the call to
+    /// [`Barrier::wait()`].
+    ///
+    /// Only one task will have `true` returned from their result, all other
+    /// tasks will have `false` returned.
+    pub fn is_leader(&self) -> bool {
+        self.0
+    }
+}
+
+impl Barrier {
+    /// Creates a new barrier that can block a given number of tasks.
+    ///
+    /// A barrier will block `n`-1 tasks which call [`wait()`](Barrier::wait)
+    /// and then wake up all tasks at once when the `n`th task calls
+    /// [`wait()`](Barrier::wait).
+    pub const fn new(n: usize) -> Self {
+        Self {
+            wq: WaitQueue::new(),
+            count: SpinNoIrq::new(0),
+            generation: AtomicUsize::new(0),
+            num_tasks: n,
+        }
+    }
+
+    /// Blocks the current task until all tasks have rendezvoused here.
+    ///
+    /// Barriers are re-usable after all tasks have rendezvoused once, and can
+    /// be used continuously.
+    pub fn wait(&self) -> BarrierWaitResult {
+        let mut count = self.count.lock();
+        let generation = self.generation.load(Ordering::Acquire);
+        *count += 1;
+        if *count < self.num_tasks {
+            drop(count);
+            self.wq
+                .wait_until(|| self.generation.load(Ordering::Acquire) != generation);
+            BarrierWaitResult(false)
+        } else {
+            *count = 0;
+            self.generation.fetch_add(1, Ordering::Release);
+            drop(count);
+            self.wq.notify_all(true);
+            BarrierWaitResult(true)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Barrier;
+    use axtask as thread;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Once;
+
+    static INIT: Once = Once::new();
+
+    #[test]
+    fn rendezvous() {
+        INIT.call_once(thread::init_scheduler);
+
+        const NUM_TASKS: usize = 10;
+        const NUM_ROUNDS: usize = 10;
+        static BARRIER: Barrier = Barrier::new(NUM_TASKS + 1);
+        static ARRIVED: AtomicUsize = AtomicUsize::new(0);
+        static LEADERS: AtomicUsize = AtomicUsize::new(0);
+
+        for _ in 0..NUM_TASKS {
+            thread::spawn(|| {
+                for _ in 0..NUM_ROUNDS {
+                    ARRIVED.fetch_add(1, Ordering::Relaxed);
+                    if BARRIER.wait().is_leader() {
+                        LEADERS.fetch_add(1, Ordering::Relaxed);
+                    }
+                    BARRIER.wait();
+                }
+            });
+        }
+
+        for round in 1..=NUM_ROUNDS {
+            if BARRIER.wait().is_leader() {
+                LEADERS.fetch_add(1, Ordering::Relaxed);
+            }
+            // All tasks have arrived in this round.
+            assert_eq!(ARRIVED.load(Ordering::Relaxed), round * NUM_TASKS);
+            BARRIER.wait();
+        }
+        assert_eq!(LEADERS.load(Ordering::Relaxed), NUM_ROUNDS);
+        println!("Barrier test OK");
+    }
+}
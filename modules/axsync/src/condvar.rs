@@ -0,0 +1,188 @@
+//! A condition variable.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+#[cfg(feature = "irq")]
+use core::time::Duration;
+
+use axtask::WaitQueue;
+
+use crate::MutexGuard;
+
+/// A type indicating whether a timed wait on a condition variable returned
+/// due to a time out or not.
+///
+/// It is returned by the [`wait_timeout`](Condvar::wait_timeout) method.
+#[cfg(feature = "irq")]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct WaitTimeoutResult(bool);
+
+#[cfg(feature = "irq")]
+impl WaitTimeoutResult {
+    /// Returns `true` if the wait was known to have timed out.
+    pub fn timed_out(&self) -> bool {
+        self.0
+    }
+}
+
+/// A condition variable, similar to
+/// [`std::sync::Condvar`](https://doc.rust-lang.org/std/sync/struct.Condvar.html).
+///
+/// Condition variables represent the ability to block a task such that it
+/// consumes no CPU time while waiting for an event to occur. It is used
+/// together with a [`Mutex`](crate::Mutex) that protects the shared state.
+///
+/// Like in std, spurious wakeups are possible, the condition should always be
+/// re-checked after waking up (or use [`wait_while`](Condvar::wait_while)).
+pub struct Condvar {
+    wq: WaitQueue,
+    /// Incremented on every notification, so that a waiter can tell whether
+    /// it has been notified after releasing the mutex.
+    seq: AtomicU32,
+}
+
+impl Condvar {
+    /// Creates a new condition variable which is ready to be waited on and
+    /// notified.
+    pub const fn new() -> Self {
+        Self {
+            wq: WaitQueue::new(),
+            seq: AtomicU32::new(0),
+        }
+    }
+
+    /// Blocks the current task until this condition variable receives a
+    /// notification.
+    ///
+    /// It atomically unlocks the mutex specified (represented by `guard`) and
+    /// blocks the current task. When this function returns, the lock will have
+    /// been re-acquired.
+    pub fn wait<'a, T: ?Sized>(&self, guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+        let mutex = guard.lock;
+        self.wait_unlocking(|| drop(guard));
+        mutex.lock()
+    }
+
+    /// Blocks the current task until this condition variable receives a
+    /// notification, after `unlock` releases the lock that protects the
+    /// condition.
+    ///
+    /// It is [`wait`](Condvar::wait) for locks other than [`Mutex`](crate::Mutex),
+    /// the caller should re-acquire the lock after it returns.
+    pub fn wait_unlocking(&self, unlock: impl FnOnce()) {
+        let seq = self.seq.load(Ordering::Acquire);
+        unlock();
+        self.wq
+            .wait_until(|| self.seq.load(Ordering::Acquire) != seq);
+    }
+
+    /// Blocks the current task until the provided condition becomes false.
+    ///
+    /// `condition` is checked immediately; if not met (returns `true`), this
+    /// will [`wait`](Condvar::wait) for the next notification then check again.
+    /// This repeats until `condition` returns `false`, in which case this
+    /// function returns.
+    pub fn wait_while<'a, T: ?Sized, F>(
+        &self,
+        mut guard: MutexGuard<'a, T>,
+        mut condition: F,
+    ) -> MutexGuard<'a, T>
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        while condition(&mut *guard) {
+            guard = self.wait(guard);
+        }
+        guard
+    }
+
+    /// Waits on this condition variable for a notification, timing out after
+    /// the specified duration.
+    ///
+    /// The returned [`WaitTimeoutResult`] value indicates if the timeout is
+    /// known to have elapsed.
+    #[cfg(feature = "irq")]
+    pub fn wait_timeout<'a, T: ?Sized>(
+        &self,
+        guard: MutexGuard<'a, T>,
+        dur: Duration,
+    ) -> (MutexGuard<'a, T>, WaitTimeoutResult) {
+        let mutex = guard.lock;
+        let result = self.wait_timeout_unlocking(dur, || drop(guard));
+        (mutex.lock(), result)
+    }
+
+    /// Waits on this condition variable for a notification, timing out after
+    /// the specified duration, after `unlock` releases the lock that protects
+    /// the condition.
+    ///
+    /// It is [`wait_timeout`](Condvar::wait_timeout) for locks other than
+    /// [`Mutex`](crate::Mutex), the caller should re-acquire the lock after it
+    /// returns.
+    #[cfg(feature = "irq")]
+    pub fn wait_timeout_unlocking(
+        &self,
+        dur: Duration,
+        unlock: impl FnOnce(),
+    ) -> WaitTimeoutResult {
+        let seq = self.seq.load(Ordering::Acquire);
+        unlock();
+        let timed_out = self
+            .wq
+            .wait_timeout_until(dur, || self.seq.load(Ordering::Acquire) != seq);
+        WaitTimeoutResult(timed_out)
+    }
+
+    /// Wakes up one blocked task on this condition variable.
+    pub fn notify_one(&self) {
+        self.seq.fetch_add(1, Ordering::Release);
+        self.wq.notify_one(true);
+    }
+
+    /// Wakes up all blocked tasks on this condition variable.
+    pub fn notify_all(&self) {
+        self.seq.fetch_add(1, Ordering::Release);
+        self.wq.notify_all(true);
+    }
+}
+
+impl Default for Condvar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Condvar, Mutex};
+    use axtask as thread;
+    use std::sync::Once;
+
+    static INIT: Once = Once::new();
+
+    #[test]
+    fn producer_consumer() {
+        INIT.call_once(thread::init_scheduler);
+
+        const NUM_ITEMS: usize = 1_000;
+        static QUEUE: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+        static NOT_EMPTY: Condvar = Condvar::new();
+
+        thread::spawn(|| {
+            for i in 0..NUM_ITEMS {
+                QUEUE.lock().push(i);
+                NOT_EMPTY.notify_one();
+                if rand::random::<u32>() % 3 == 0 {
+                    thread::yield_now();
+                }
+            }
+        });
+
+        let mut sum = 0;
+        for _ in 0..NUM_ITEMS {
+            let mut queue = NOT_EMPTY.wait_while(QUEUE.lock(), |q| q.is_empty());
+            sum += queue.remove(0);
+        }
+        assert_eq!(sum, NUM_ITEMS * (NUM_ITEMS - 1) / 2);
+        println!("Condvar test OK");
+    }
+}
@@ -3,26 +3,93 @@
 //! Currently supported primitives:
 //!
 //! - [`Mutex`]: A mutual exclusion primitive.
+//! - [`PiMutex`]: A mutual exclusion primitive with priority inheritance.
+//! - [`RwLock`]: A writer-preferring readers-writer lock.
+//! - [`Condvar`]: A condition variable used together with [`Mutex`].
+//! - [`Semaphore`]: A counting semaphore.
+//! - [`Barrier`]: A barrier to synchronize a group of tasks.
+//! - mod [`mpsc`]: Bounded and unbounded channels.
 //! - mod [`spin`]: spinlocks imported from the [`kspin`] crate.
+//! - mod [`lockdep`]: a lock dependency validator for the locks above.
 //!
 //! # Cargo Features
 //!
 //! - `multitask`: For use in the multi-threaded environments. If the feature is
-//!   not enabled, [`Mutex`] will be an alias of [`spin::SpinNoIrq`]. This
-//!   feature is enabled by default.
+//!   not enabled, [`Mutex`] and [`PiMutex`] will be aliases of
+//!   [`spin::SpinNoIrq`], and other blocking primitives are not available.
+//!   This feature is enabled by default.
+//! - `irq`: Interrupts are enabled. If this feature is enabled, timed waits
+//!   such as [`Condvar::wait_timeout`] and [`Semaphore::acquire_timeout`] can
+//!   be used.
+//! - `lockdep`: Enables the lock dependency validator, which reports possible
+//!   deadlocks (e.g., two locks acquired in different orders) and IRQ-unsafe
+//!   lock usages the first time they are seen. It implies `multitask`, and
+//!   [`spin`] provides checked wrappers of the [`kspin`] spinlocks. Locks used
+//!   through the [`kspin`] crate directly are not checked, which are the ones
+//!   in the crates axsync depends on: `axhal`, `axalloc`, `axlog` and
+//!   `axtask` (run queues, timers, wait queues, etc.).
 
 #![cfg_attr(not(test), no_std)]
 #![feature(doc_cfg)]
+#![cfg_attr(feature = "lockdep", feature(const_caller_location))]
 
+#[cfg(feature = "multitask")]
+extern crate alloc;
+
+#[cfg(feature = "lockdep")]
+#[macro_use]
+extern crate log;
+
+#[cfg(not(feature = "lockdep"))]
 pub use kspin as spin;
 
+#[cfg(feature = "lockdep")]
+#[doc(cfg(feature = "lockdep"))]
+pub mod lockdep;
+#[cfg(feature = "lockdep")]
+pub mod spin;
+
+#[cfg(feature = "multitask")]
+mod barrier;
+#[cfg(feature = "multitask")]
+mod condvar;
+#[cfg(feature = "multitask")]
+#[doc(cfg(feature = "multitask"))]
+pub mod mpsc;
 #[cfg(feature = "multitask")]
 mod mutex;
+#[cfg(feature = "multitask")]
+mod pi_mutex;
+#[cfg(feature = "multitask")]
+mod rwlock;
+#[cfg(feature = "multitask")]
+mod semaphore;
 
+#[cfg(feature = "multitask")]
+#[doc(cfg(feature = "multitask"))]
+pub use self::barrier::{Barrier, BarrierWaitResult};
+#[cfg(feature = "multitask")]
+#[doc(cfg(feature = "multitask"))]
+pub use self::condvar::Condvar;
+#[cfg(all(feature = "multitask", feature = "irq"))]
+#[doc(cfg(all(feature = "multitask", feature = "irq")))]
+pub use self::condvar::WaitTimeoutResult;
 #[cfg(feature = "multitask")]
 #[doc(cfg(feature = "multitask"))]
 pub use self::mutex::{Mutex, MutexGuard};
+#[cfg(feature = "multitask")]
+#[doc(cfg(feature = "multitask"))]
+pub use self::pi_mutex::{PiMutex, PiMutexGuard};
+#[cfg(feature = "multitask")]
+#[doc(cfg(feature = "multitask"))]
+pub use self::rwlock::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+#[cfg(feature = "multitask")]
+#[doc(cfg(feature = "multitask"))]
+pub use self::semaphore::{Semaphore, SemaphoreGuard};
 
 #[cfg(not(feature = "multitask"))]
 #[doc(cfg(not(feature = "multitask")))]
 pub use kspin::{SpinNoIrq as Mutex, SpinNoIrqGuard as MutexGuard};
+#[cfg(not(feature = "multitask"))]
+#[doc(cfg(not(feature = "multitask")))]
+pub use kspin::{SpinNoIrq as PiMutex, SpinNoIrqGuard as PiMutexGuard};
@@ -0,0 +1,451 @@
+//! Lock dependency validator (lockdep).
+//!
+//! Every lock belongs to a *lock class*, which is the source location where
+//! the lock was constructed (e.g., the `Mutex::new` call of a static). Locks
+//! constructed without a known location (e.g., zero-initialized by C code)
+//! share one class per kind of lock (sleeping or spinning), so that a program
+//! with many such locks does not exhaust the class table. Dependencies among
+//! the locks in such a class are not checked.
+//!
+//! On each acquisition, the classes of the locks already held by the current
+//! context (task, IRQ handler, or a CPU before the scheduler starts) are
+//! recorded as "acquired before" the new class. If the new edge closes a
+//! cycle in this graph, two code paths take the same locks in different
+//! orders (e.g., ABBA), which can deadlock even if it has not happened yet.
+//!
+//! It also records whether a class is used in IRQ context and whether it is
+//! acquired with IRQs enabled. A lock used in both ways, or an IRQ-unsafe
+//! lock acquired while holding a lock used in IRQ context, can deadlock when
+//! the IRQ arrives on the same CPU. Acquiring a sleeping lock in IRQ context
+//! is reported as well.
+//!
+//! Each problem is reported once, when it is first seen. The validator turns
+//! itself off when its fixed-size tables are exhausted.
+
+use core::fmt;
+use core::panic::Location;
+use core::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+
+use kspin::SpinNoIrq;
+
+/// Maximum number of lock classes.
+const MAX_CLASSES: usize = 256;
+/// Maximum number of contexts holding locks at the same time.
+const MAX_CONTEXTS: usize = 64;
+/// Maximum number of locks held by a context at the same time.
+const MAX_HELD: usize = 32;
+
+const BITMAP_WORDS: usize = MAX_CLASSES / 64;
+
+/// The class is acquired in IRQ context.
+const USED_IN_IRQ: u8 = 1 << 0;
+/// The class is acquired in task context with IRQs enabled.
+const IRQS_ENABLED: u8 = 1 << 1;
+/// The class is a sleeping lock.
+const SLEEPING: u8 = 1 << 2;
+/// Inconsistent IRQ usage of the class has been reported.
+const REPORTED_IRQ: u8 = 1 << 3;
+/// Sleeping in IRQ context has been reported for the class.
+const REPORTED_SLEEP: u8 = 1 << 4;
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+static REPORTS: AtomicUsize = AtomicUsize::new(0);
+static GRAPH: SpinNoIrq<Graph> = SpinNoIrq::new(Graph::new());
+
+/// Returns whether the validator is still running.
+///
+/// It is turned off once its internal tables are exhausted.
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Returns the number of problems reported so far.
+pub fn report_count() -> usize {
+    REPORTS.load(Ordering::Relaxed)
+}
+
+/// The kind of a lock, i.e., whether it may sleep while waiting.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LockKind {
+    Sleep,
+    Spin,
+}
+
+/// The lock class of a lock, embedded in the lock itself.
+pub(crate) struct LockClass {
+    site: Option<&'static Location<'static>>,
+    /// Index in the class table plus one, or zero if not registered yet.
+    id: AtomicU32,
+}
+
+impl LockClass {
+    /// Creates the lock class of the caller's location.
+    #[track_caller]
+    #[inline(always)]
+    pub const fn new() -> Self {
+        Self {
+            site: Some(Location::caller()),
+            id: AtomicU32::new(0),
+        }
+    }
+
+    fn key(&self, kind: LockKind) -> ClassKey {
+        match self.site {
+            Some(loc) => ClassKey::Site(loc),
+            None => ClassKey::NoSite(kind),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ClassKey {
+    Site(&'static Location<'static>),
+    /// Locks of the kind constructed without a known location.
+    NoSite(LockKind),
+}
+
+impl fmt::Display for ClassKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Site(loc) => write!(f, "{}", loc),
+            Self::NoSite(LockKind::Sleep) => write!(f, "<sleeping lock of unknown site>"),
+            Self::NoSite(LockKind::Spin) => write!(f, "<spinlock of unknown site>"),
+        }
+    }
+}
+
+/// Locks held by a context, in acquisition order.
+#[derive(Clone, Copy)]
+struct HeldLocks {
+    /// Context ID, or zero if the slot is free.
+    ctx: u64,
+    depth: usize,
+    held: [u16; MAX_HELD],
+}
+
+struct Graph {
+    classes: [Option<ClassKey>; MAX_CLASSES],
+    num_classes: usize,
+    usage: [u8; MAX_CLASSES],
+    /// `deps[a]` has bit `b` set if class `a` has been held while acquiring
+    /// class `b`.
+    deps: [[u64; BITMAP_WORDS]; MAX_CLASSES],
+    contexts: [HeldLocks; MAX_CONTEXTS],
+}
+
+impl Graph {
+    const fn new() -> Self {
+        Self {
+            classes: [None; MAX_CLASSES],
+            num_classes: 0,
+            usage: [0; MAX_CLASSES],
+            deps: [[0; BITMAP_WORDS]; MAX_CLASSES],
+            contexts: [HeldLocks {
+                ctx: 0,
+                depth: 0,
+                held: [0; MAX_HELD],
+            }; MAX_CONTEXTS],
+        }
+    }
+
+    fn class_id(&mut self, class: &LockClass, kind: LockKind) -> Option<usize> {
+        let id = class.id.load(Ordering::Relaxed) as usize;
+        if id != 0 {
+            return Some(id - 1);
+        }
+        let key = class.key(kind);
+        let id = match self.classes[..self.num_classes]
+            .iter()
+            .position(|k| *k == Some(key))
+        {
+            Some(id) => id,
+            None if self.num_classes < MAX_CLASSES => {
+                self.classes[self.num_classes] = Some(key);
+                self.num_classes += 1;
+                self.num_classes - 1
+            }
+            None => return None,
+        };
+        class.id.store(id as u32 + 1, Ordering::Relaxed);
+        Some(id)
+    }
+
+    fn name(&self, id: usize) -> ClassKey {
+        self.classes[id].unwrap()
+    }
+
+    fn has_dep(&self, from: usize, to: usize) -> bool {
+        self.deps[from][to / 64] & (1 << (to % 64)) != 0
+    }
+
+    fn add_dep(&mut self, from: usize, to: usize) {
+        self.deps[from][to / 64] |= 1 << (to % 64);
+    }
+
+    /// Finds a dependency path from `from` to `to`, and returns the number of
+    /// classes on it with the classes stored in `path` (`from` first).
+    fn find_path(&self, from: usize, to: usize, path: &mut [u16; MAX_CLASSES]) -> Option<usize> {
+        let mut parent = [u16::MAX; MAX_CLASSES];
+        let mut queue = [0u16; MAX_CLASSES];
+        let (mut head, mut tail) = (0, 1);
+        queue[0] = from as u16;
+        parent[from] = from as u16;
+        while head < tail {
+            let curr = queue[head] as usize;
+            head += 1;
+            if curr == to {
+                let mut len = 0;
+                let mut node = to;
+                loop {
+                    path[len] = node as u16;
+                    len += 1;
+                    if node == from {
+                        break;
+                    }
+                    node = parent[node] as usize;
+                }
+                path[..len].reverse();
+                return Some(len);
+            }
+            for next in 0..self.num_classes {
+                if parent[next] == u16::MAX && self.has_dep(curr, next) {
+                    parent[next] = curr as u16;
+                    queue[tail] = next as u16;
+                    tail += 1;
+                }
+            }
+        }
+        None
+    }
+
+    fn held_locks(&mut self, ctx: u64, alloc: bool) -> Option<&mut HeldLocks> {
+        let idx = match self.contexts.iter().position(|h| h.ctx == ctx) {
+            Some(idx) => idx,
+            None if alloc => {
+                let idx = self.contexts.iter().position(|h| h.ctx == 0)?;
+                self.contexts[idx].ctx = ctx;
+                self.contexts[idx].depth = 0;
+                idx
+            }
+            None => return None,
+        };
+        Some(&mut self.contexts[idx])
+    }
+}
+
+/// Returns an ID of the current context to track held locks.
+fn context_id() -> (u64, bool) {
+    let cpu_id = axhal::cpu::this_cpu_id() as u64;
+    #[cfg(feature = "irq")]
+    if axhal::irq::in_irq() {
+        return (u64::MAX - cpu_id * 2, true);
+    }
+    match axtask::current_may_uninit() {
+        Some(curr) => (curr.id().as_u64(), false),
+        None => (u64::MAX - cpu_id * 2 - 1, false),
+    }
+}
+
+fn turn_off(reason: &str) {
+    if ENABLED.swap(false, Ordering::Relaxed) {
+        warn!("lockdep: {}, turning off the lock validator", reason);
+    }
+}
+
+fn report_header(what: &str) {
+    REPORTS.fetch_add(1, Ordering::Relaxed);
+    error!("================================================================");
+    error!("lockdep: WARNING: {}", what);
+    match axtask::current_may_uninit() {
+        Some(curr) => error!(
+            "on CPU {}, task {}:",
+            axhal::cpu::this_cpu_id(),
+            curr.id_name()
+        ),
+        None => error!("on CPU {}:", axhal::cpu::this_cpu_id()),
+    }
+}
+
+fn report_held(graph: &Graph, held: &HeldLocks) {
+    error!("locks held by the current context:");
+    for (i, &id) in held.held[..held.depth].iter().enumerate() {
+        error!("  #{}: {}", i, graph.name(id as usize));
+    }
+}
+
+/// Records that the current context is about to acquire a lock of `class`,
+/// and checks the new dependencies.
+///
+/// `trylock` should be `true` if the lock has been acquired without waiting,
+/// no dependencies are recorded in this case as it can not deadlock.
+pub(crate) fn acquire(class: &LockClass, kind: LockKind, trylock: bool) {
+    if !is_enabled() {
+        return;
+    }
+    let (ctx, in_irq) = context_id();
+    let irqs_enabled = axhal::arch::irqs_enabled();
+    let mut graph = GRAPH.lock();
+    let graph = &mut *graph;
+    let Some(id) = graph.class_id(class, kind) else {
+        return turn_off("too many lock classes");
+    };
+    let Some(held) = graph.held_locks(ctx, true).copied() else {
+        return turn_off("too many contexts holding locks");
+    };
+    if held.depth == MAX_HELD {
+        return turn_off("too many locks held");
+    }
+
+    // Update and check the IRQ usage.
+    let mut usage = graph.usage[id];
+    if kind == LockKind::Sleep {
+        usage |= SLEEPING;
+    }
+    if in_irq {
+        usage |= USED_IN_IRQ;
+    } else if irqs_enabled {
+        usage |= IRQS_ENABLED;
+    }
+    if in_irq && usage & SLEEPING != 0 && usage & REPORTED_SLEEP == 0 {
+        usage |= REPORTED_SLEEP;
+        report_header("sleeping lock acquired in IRQ context");
+        error!("  {}", graph.name(id));
+        report_held(graph, &held);
+    }
+    if usage & (USED_IN_IRQ | IRQS_ENABLED) == USED_IN_IRQ | IRQS_ENABLED
+        && usage & REPORTED_IRQ == 0
+    {
+        usage |= REPORTED_IRQ;
+        report_header("inconsistent IRQ usage");
+        error!(
+            "  {} is acquired both in IRQ context and with IRQs enabled",
+            graph.name(id)
+        );
+        report_held(graph, &held);
+    }
+    graph.usage[id] = usage;
+
+    // Check and record the dependencies on the held locks.
+    let held_before = if trylock {
+        &[][..]
+    } else {
+        &held.held[..held.depth]
+    };
+    for &prev in held_before {
+        let prev = prev as usize;
+        if prev == id || graph.has_dep(prev, id) {
+            continue;
+        }
+        if graph.usage[prev] & USED_IN_IRQ != 0 && usage & IRQS_ENABLED != 0 {
+            report_header("IRQ-safe -> IRQ-unsafe lock order detected");
+            error!(
+                "  {} (used in IRQ context) is held while acquiring",
+                graph.name(prev)
+            );
+            error!("  {} (acquired with IRQs enabled)", graph.name(id));
+            report_held(graph, &held);
+        }
+        let mut path = [0; MAX_CLASSES];
+        if let Some(len) = graph.find_path(id, prev, &mut path) {
+            report_header("possible circular locking dependency detected");
+            error!("  trying to acquire {}", graph.name(id));
+            error!("  while holding {}", graph.name(prev));
+            error!("existing dependency chain (in reverse order):");
+            for w in path[..len].windows(2) {
+                error!(
+                    "  {} -> {}",
+                    graph.name(w[0] as usize),
+                    graph.name(w[1] as usize)
+                );
+            }
+            report_held(graph, &held);
+        }
+        graph.add_dep(prev, id);
+    }
+
+    let held = graph.held_locks(ctx, false).unwrap();
+    held.held[held.depth] = id as u16;
+    held.depth += 1;
+}
+
+/// Records that the current context has released a lock of `class`.
+pub(crate) fn release(class: &LockClass) {
+    if !is_enabled() {
+        return;
+    }
+    let id = class.id.load(Ordering::Relaxed);
+    if id == 0 {
+        return;
+    }
+    let id = (id - 1) as u16;
+    let (ctx, _) = context_id();
+    let mut graph = GRAPH.lock();
+    // The lock may be released by another context than the one acquired it
+    // (e.g., `force_unlock`), ignore it in that case.
+    if let Some(held) = graph.held_locks(ctx, false) {
+        if let Some(pos) = held.held[..held.depth].iter().rposition(|&h| h == id) {
+            held.held.copy_within(pos + 1..held.depth, pos);
+            held.depth -= 1;
+            if held.depth == 0 {
+                held.ctx = 0;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{lockdep, Mutex};
+    use axtask as thread;
+    use core::sync::atomic::{AtomicU32, Ordering::Relaxed};
+    use std::sync::Once;
+
+    static INIT: Once = Once::new();
+
+    #[test]
+    fn abba() {
+        INIT.call_once(thread::init_scheduler);
+
+        static A: Mutex<()> = Mutex::new(());
+        static B: Mutex<()> = Mutex::new(());
+
+        let reports = lockdep::report_count();
+        let a = A.lock();
+        let b = B.lock();
+        drop(b);
+        drop(a);
+        assert_eq!(lockdep::report_count(), reports);
+
+        for _ in 0..2 {
+            let b = B.lock();
+            let a = A.lock();
+            drop(a);
+            drop(b);
+        }
+        // Reported only once.
+        assert_eq!(lockdep::report_count(), reports + 1);
+        assert!(lockdep::is_enabled());
+        println!("lockdep test OK");
+    }
+
+    #[test]
+    fn no_site() {
+        INIT.call_once(thread::init_scheduler);
+
+        // e.g., zero-initialized by C code
+        let classes: Vec<_> = (0..lockdep::MAX_CLASSES * 2)
+            .map(|_| lockdep::LockClass {
+                site: None,
+                id: AtomicU32::new(0),
+            })
+            .collect();
+        for class in classes.iter() {
+            lockdep::acquire(class, lockdep::LockKind::Sleep, false);
+            lockdep::release(class);
+        }
+        assert!(classes
+            .windows(2)
+            .all(|w| w[0].id.load(Relaxed) == w[1].id.load(Relaxed)));
+        assert!(lockdep::is_enabled());
+    }
+}
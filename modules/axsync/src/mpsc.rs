@@ -0,0 +1,642 @@
+//! Multi-producer FIFO queue communication primitives, similar to
+//! [`std::sync::mpsc`](https://doc.rust-lang.org/std/sync/mpsc/index.html).
+//!
+//! A channel is created by [`channel`] (unbounded, sending never blocks) or
+//! [`sync_channel`] (bounded, sending blocks while the buffer is full). Unlike
+//! std, the [`Receiver`] can be cloned as well, and each message is received
+//! by exactly one of the receivers.
+//!
+//! A channel is disconnected when all senders or all receivers have been
+//! dropped. Receiving from a disconnected channel still returns the buffered
+//! messages first, sending to a channel without receivers returns the message
+//! back in the error.
+
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use core::fmt;
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+#[cfg(feature = "irq")]
+use core::time::Duration;
+
+use axtask::WaitQueue;
+use kspin::SpinNoIrq;
+
+/// An error returned from [`Sender::send`] or [`SyncSender::send`] if all
+/// receivers have been dropped. It contains the message that failed to send.
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub struct SendError<T>(pub T);
+
+/// An error returned from [`Receiver::recv`] if all senders have been
+/// dropped and the channel is empty.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct RecvError;
+
+/// Possible reasons that [`Receiver::try_recv`] could not return a message.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum TryRecvError {
+    /// The channel is currently empty, but senders are still connected.
+    Empty,
+    /// All senders have been dropped and the channel is empty.
+    Disconnected,
+}
+
+/// Possible reasons that [`Receiver::recv_timeout`] could not return a
+/// message.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum RecvTimeoutError {
+    /// No message arrived before the timeout, but senders are still connected.
+    Timeout,
+    /// All senders have been dropped and the channel is empty.
+    Disconnected,
+}
+
+/// Possible reasons that [`SyncSender::try_send`] could not send a message.
+/// It contains the message that failed to send.
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum TrySendError<T> {
+    /// The buffer of the channel is full.
+    Full(T),
+    /// All receivers have been dropped.
+    Disconnected(T),
+}
+
+/// Possible reasons that [`SyncSender::send_timeout`] could not send a
+/// message. It contains the message that failed to send.
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum SendTimeoutError<T> {
+    /// The buffer was still full when the timeout elapsed.
+    Timeout(T),
+    /// All receivers have been dropped.
+    Disconnected(T),
+}
+
+struct Queue<T> {
+    buf: VecDeque<T>,
+    /// Number of messages sent so far.
+    sent: u64,
+}
+
+/// The state shared by both halves of a channel.
+struct Channel<T> {
+    queue: SpinNoIrq<Queue<T>>,
+    /// Maximum number of buffered messages, `None` if unbounded.
+    bound: Option<usize>,
+    /// Number of buffered messages, to check without locking the queue.
+    len: AtomicUsize,
+    /// Number of messages received so far.
+    received: AtomicU64,
+    senders: AtomicUsize,
+    receivers: AtomicUsize,
+    send_wq: WaitQueue,
+    recv_wq: WaitQueue,
+}
+
+impl<T> Channel<T> {
+    fn new(bound: Option<usize>) -> Self {
+        Self {
+            queue: SpinNoIrq::new(Queue {
+                buf: VecDeque::new(),
+                sent: 0,
+            }),
+            bound,
+            len: AtomicUsize::new(0),
+            received: AtomicU64::new(0),
+            senders: AtomicUsize::new(1),
+            receivers: AtomicUsize::new(1),
+            send_wq: WaitQueue::new(),
+            recv_wq: WaitQueue::new(),
+        }
+    }
+
+    fn is_disconnected_to_send(&self) -> bool {
+        self.receivers.load(Ordering::Acquire) == 0
+    }
+
+    fn is_disconnected_to_recv(&self) -> bool {
+        self.senders.load(Ordering::Acquire) == 0
+    }
+
+    /// Whether there is room for a new message. A rendezvous channel (bound
+    /// 0) buffers one message at a time, whose sender waits until it is
+    /// received.
+    fn can_send(&self) -> bool {
+        match self.bound {
+            Some(bound) => self.len.load(Ordering::Acquire) < bound.max(1),
+            None => true,
+        }
+    }
+
+    fn can_recv(&self) -> bool {
+        self.len.load(Ordering::Acquire) > 0
+    }
+
+    /// Buffers the message, and returns its sequence number.
+    fn try_send(&self, msg: T) -> Result<u64, TrySendError<T>> {
+        if self.is_disconnected_to_send() {
+            return Err(TrySendError::Disconnected(msg));
+        }
+        let mut queue = self.queue.lock();
+        if !self.can_send() {
+            return Err(TrySendError::Full(msg));
+        }
+        queue.buf.push_back(msg);
+        queue.sent += 1;
+        self.len.store(queue.buf.len(), Ordering::Release);
+        let seq = queue.sent;
+        drop(queue);
+        self.recv_wq.notify_one(true);
+        Ok(seq)
+    }
+
+    /// Waits until the message `seq` is received if the channel is a
+    /// rendezvous channel. Takes the message back if all receivers are
+    /// dropped before that.
+    fn wait_rendezvous(&self, seq: u64) -> Result<(), T> {
+        if self.bound != Some(0) {
+            return Ok(());
+        }
+        self.send_wq.wait_until(|| {
+            self.received.load(Ordering::Acquire) >= seq || self.is_disconnected_to_send()
+        });
+        let mut queue = self.queue.lock();
+        if self.received.load(Ordering::Acquire) < seq {
+            // Only one message is buffered at a time, so it must be ours.
+            let msg = queue.buf.pop_back().unwrap();
+            self.len.store(queue.buf.len(), Ordering::Release);
+            return Err(msg);
+        }
+        Ok(())
+    }
+
+    fn send(&self, mut msg: T) -> Result<(), SendError<T>> {
+        loop {
+            match self.try_send(msg) {
+                Ok(seq) => return self.wait_rendezvous(seq).map_err(SendError),
+                Err(TrySendError::Full(m)) => msg = m,
+                Err(TrySendError::Disconnected(m)) => return Err(SendError(m)),
+            }
+            self.send_wq
+                .wait_until(|| self.can_send() || self.is_disconnected_to_send());
+        }
+    }
+
+    #[cfg(feature = "irq")]
+    fn send_timeout(&self, mut msg: T, dur: Duration) -> Result<(), SendTimeoutError<T>> {
+        let deadline = axhal::time::wall_time() + dur;
+        loop {
+            match self.try_send(msg) {
+                Ok(seq) => {
+                    return self
+                        .wait_rendezvous(seq)
+                        .map_err(SendTimeoutError::Disconnected)
+                }
+                Err(TrySendError::Full(m)) => msg = m,
+                Err(TrySendError::Disconnected(m)) => {
+                    return Err(SendTimeoutError::Disconnected(m))
+                }
+            }
+            let now = axhal::time::wall_time();
+            if now >= deadline {
+                return Err(SendTimeoutError::Timeout(msg));
+            }
+            self.send_wq.wait_timeout_until(deadline - now, || {
+                self.can_send() || self.is_disconnected_to_send()
+            });
+        }
+    }
+
+    fn try_recv(&self) -> Result<T, TryRecvError> {
+        let mut queue = self.queue.lock();
+        match queue.buf.pop_front() {
+            Some(msg) => {
+                self.len.store(queue.buf.len(), Ordering::Release);
+                self.received.fetch_add(1, Ordering::Release);
+                drop(queue);
+                // Wake up both the senders waiting for room and the
+                // rendezvous sender waiting for this message.
+                self.send_wq.notify_all(true);
+                Ok(msg)
+            }
+            None if self.is_disconnected_to_recv() => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    fn recv(&self) -> Result<T, RecvError> {
+        loop {
+            match self.try_recv() {
+                Ok(msg) => return Ok(msg),
+                Err(TryRecvError::Disconnected) => return Err(RecvError),
+                Err(TryRecvError::Empty) => {}
+            }
+            self.recv_wq
+                .wait_until(|| self.can_recv() || self.is_disconnected_to_recv());
+        }
+    }
+
+    #[cfg(feature = "irq")]
+    fn recv_timeout(&self, dur: Duration) -> Result<T, RecvTimeoutError> {
+        let deadline = axhal::time::wall_time() + dur;
+        loop {
+            match self.try_recv() {
+                Ok(msg) => return Ok(msg),
+                Err(TryRecvError::Disconnected) => return Err(RecvTimeoutError::Disconnected),
+                Err(TryRecvError::Empty) => {}
+            }
+            let now = axhal::time::wall_time();
+            if now >= deadline {
+                return Err(RecvTimeoutError::Timeout);
+            }
+            self.recv_wq.wait_timeout_until(deadline - now, || {
+                self.can_recv() || self.is_disconnected_to_recv()
+            });
+        }
+    }
+
+    fn acquire_sender(&self) {
+        self.senders.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn release_sender(&self) {
+        if self.senders.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.recv_wq.notify_all(true);
+        }
+    }
+}
+
+/// Creates a new unbounded channel, returning the sender/receiver halves.
+///
+/// Sending never blocks, all messages are buffered until received.
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let chan = Arc::new(Channel::new(None));
+    (Sender { chan: chan.clone() }, Receiver { chan })
+}
+
+/// Creates a new bounded channel, returning the sender/receiver halves.
+///
+/// At most `bound` messages are buffered, sending blocks while the buffer is
+/// full. If `bound` is 0, the channel is a "rendezvous" channel, where each
+/// send blocks until the message is received.
+pub fn sync_channel<T>(bound: usize) -> (SyncSender<T>, Receiver<T>) {
+    let chan = Arc::new(Channel::new(Some(bound)));
+    (SyncSender { chan: chan.clone() }, Receiver { chan })
+}
+
+/// The sending half of an unbounded channel created by [`channel`].
+///
+/// It can be cloned to send to the same channel from multiple tasks.
+pub struct Sender<T> {
+    chan: Arc<Channel<T>>,
+}
+
+/// The sending half of a bounded channel created by [`sync_channel`].
+///
+/// It can be cloned to send to the same channel from multiple tasks.
+pub struct SyncSender<T> {
+    chan: Arc<Channel<T>>,
+}
+
+/// The receiving half of a channel created by [`channel`] or
+/// [`sync_channel`].
+///
+/// It can be cloned to receive from the same channel in multiple tasks.
+pub struct Receiver<T> {
+    chan: Arc<Channel<T>>,
+}
+
+impl<T> Sender<T> {
+    /// Sends a message on this channel without blocking.
+    ///
+    /// Returns the message back if all receivers have been dropped. A
+    /// successful send does not mean that the message will be received.
+    pub fn send(&self, msg: T) -> Result<(), SendError<T>> {
+        self.chan.send(msg)
+    }
+}
+
+impl<T> SyncSender<T> {
+    /// Sends a message on this channel, blocking the current task while the
+    /// buffer is full (or until the message is received, for a rendezvous
+    /// channel).
+    ///
+    /// Returns the message back if all receivers have been dropped.
+    pub fn send(&self, msg: T) -> Result<(), SendError<T>> {
+        self.chan.send(msg)
+    }
+
+    /// Attempts to send a message on this channel without blocking.
+    ///
+    /// For a rendezvous channel, it succeeds only if no other message is
+    /// pending, and does not wait for the message to be received.
+    pub fn try_send(&self, msg: T) -> Result<(), TrySendError<T>> {
+        self.chan.try_send(msg).map(|_| ())
+    }
+
+    /// Sends a message on this channel, blocking the current task while the
+    /// buffer is full, for at most the given duration.
+    ///
+    /// For a rendezvous channel, the timeout only applies to waiting for the
+    /// pending message of other senders.
+    #[cfg(feature = "irq")]
+    pub fn send_timeout(&self, msg: T, dur: Duration) -> Result<(), SendTimeoutError<T>> {
+        self.chan.send_timeout(msg, dur)
+    }
+}
+
+impl<T> Receiver<T> {
+    /// Receives a message, blocking the current task until one is available.
+    ///
+    /// Returns an error if all senders have been dropped and the channel is
+    /// empty.
+    pub fn recv(&self) -> Result<T, RecvError> {
+        self.chan.recv()
+    }
+
+    /// Attempts to receive a message without blocking.
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        self.chan.try_recv()
+    }
+
+    /// Receives a message, blocking the current task until one is available
+    /// or the given duration has elapsed.
+    #[cfg(feature = "irq")]
+    pub fn recv_timeout(&self, dur: Duration) -> Result<T, RecvTimeoutError> {
+        self.chan.recv_timeout(dur)
+    }
+
+    /// Returns an iterator that blocks waiting for messages, until all
+    /// senders have been dropped.
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { rx: self }
+    }
+
+    /// Returns an iterator that yields the messages currently available
+    /// without blocking.
+    pub fn try_iter(&self) -> TryIter<'_, T> {
+        TryIter { rx: self }
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.chan.acquire_sender();
+        Self {
+            chan: self.chan.clone(),
+        }
+    }
+}
+
+impl<T> Clone for SyncSender<T> {
+    fn clone(&self) -> Self {
+        self.chan.acquire_sender();
+        Self {
+            chan: self.chan.clone(),
+        }
+    }
+}
+
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Self {
+        self.chan.receivers.fetch_add(1, Ordering::Relaxed);
+        Self {
+            chan: self.chan.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        self.chan.release_sender();
+    }
+}
+
+impl<T> Drop for SyncSender<T> {
+    fn drop(&mut self) {
+        self.chan.release_sender();
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        if self.chan.receivers.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.chan.send_wq.notify_all(true);
+        }
+    }
+}
+
+impl<T> fmt::Debug for Sender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Sender { .. }")
+    }
+}
+
+impl<T> fmt::Debug for SyncSender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("SyncSender { .. }")
+    }
+}
+
+impl<T> fmt::Debug for Receiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Receiver { .. }")
+    }
+}
+
+/// An iterator over messages on a [`Receiver`], created by
+/// [`Receiver::iter`].
+pub struct Iter<'a, T: 'a> {
+    rx: &'a Receiver<T>,
+}
+
+/// An iterator that attempts to yield all pending messages on a
+/// [`Receiver`], created by [`Receiver::try_iter`].
+pub struct TryIter<'a, T: 'a> {
+    rx: &'a Receiver<T>,
+}
+
+/// An owning iterator over messages on a [`Receiver`], created by
+/// `into_iter`.
+pub struct IntoIter<T> {
+    rx: Receiver<T>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        self.rx.recv().ok()
+    }
+}
+
+impl<'a, T> Iterator for TryIter<'a, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        self.rx.try_recv().ok()
+    }
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        self.rx.recv().ok()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Receiver<T> {
+    type Item = T;
+    type IntoIter = Iter<'a, T>;
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<T> IntoIterator for Receiver<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter { rx: self }
+    }
+}
+
+impl<T> fmt::Debug for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("SendError { .. }")
+    }
+}
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("sending on a closed channel")
+    }
+}
+
+impl fmt::Display for RecvError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("receiving on a closed channel")
+    }
+}
+
+impl fmt::Display for TryRecvError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Empty => f.write_str("receiving on an empty channel"),
+            Self::Disconnected => f.write_str("receiving on a closed channel"),
+        }
+    }
+}
+
+impl fmt::Display for RecvTimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Timeout => f.write_str("timed out waiting on channel"),
+            Self::Disconnected => f.write_str("channel is empty and sending half is closed"),
+        }
+    }
+}
+
+impl<T> fmt::Debug for TrySendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Full(..) => f.write_str("Full(..)"),
+            Self::Disconnected(..) => f.write_str("Disconnected(..)"),
+        }
+    }
+}
+
+impl<T> fmt::Display for TrySendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Full(..) => f.write_str("sending on a full channel"),
+            Self::Disconnected(..) => f.write_str("sending on a closed channel"),
+        }
+    }
+}
+
+impl<T> fmt::Debug for SendTimeoutError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Timeout(..) => f.write_str("Timeout(..)"),
+            Self::Disconnected(..) => f.write_str("Disconnected(..)"),
+        }
+    }
+}
+
+impl<T> fmt::Display for SendTimeoutError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Timeout(..) => f.write_str("timed out waiting on send operation"),
+            Self::Disconnected(..) => f.write_str("sending on a closed channel"),
+        }
+    }
+}
+
+impl<T> From<SendError<T>> for TrySendError<T> {
+    fn from(err: SendError<T>) -> Self {
+        Self::Disconnected(err.0)
+    }
+}
+
+impl From<RecvError> for TryRecvError {
+    fn from(_: RecvError) -> Self {
+        Self::Disconnected
+    }
+}
+
+impl From<RecvError> for RecvTimeoutError {
+    fn from(_: RecvError) -> Self {
+        Self::Disconnected
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mpsc::{channel, sync_channel, SendError, TryRecvError};
+    use axtask as thread;
+    use std::sync::Once;
+
+    static INIT: Once = Once::new();
+
+    #[test]
+    fn multi_producers() {
+        INIT.call_once(thread::init_scheduler);
+
+        const NUM_TASKS: usize = 10;
+        const NUM_ITEMS: usize = 1_000;
+
+        let (tx, rx) = sync_channel(4);
+        for _ in 0..NUM_TASKS {
+            let tx = tx.clone();
+            thread::spawn(move || {
+                for i in 0..NUM_ITEMS {
+                    tx.send(i).unwrap();
+                }
+            });
+        }
+        drop(tx);
+
+        // Ends when all senders have been dropped.
+        let sum: usize = rx.iter().sum();
+        assert_eq!(sum, NUM_TASKS * NUM_ITEMS * (NUM_ITEMS - 1) / 2);
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+        println!("mpsc multi_producers test OK");
+    }
+
+    #[test]
+    fn disconnect() {
+        INIT.call_once(thread::init_scheduler);
+
+        let (tx, rx) = channel();
+        tx.send(1).unwrap();
+        assert_eq!(rx.try_recv(), Ok(1));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        drop(rx);
+        assert_eq!(tx.send(2), Err(SendError(2)));
+
+        // A rendezvous send completes only when the message is received.
+        let (tx, rx) = sync_channel(0);
+        thread::spawn(move || {
+            assert_eq!(rx.recv(), Ok(3));
+        });
+        tx.send(3).unwrap();
+        println!("mpsc disconnect test OK");
+    }
+}
@@ -0,0 +1,319 @@
+//! A sleeping mutex with priority inheritance.
+
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use axtask::{current, AxTaskRef, WaitQueue};
+use kspin::SpinNoIrq;
+
+#[cfg(feature = "lockdep")]
+use crate::lockdep::{self, LockClass, LockKind};
+
+/// The ownership state of a [`PiMutex`].
+struct PiState {
+    /// The task holding the lock.
+    owner: Option<AxTaskRef>,
+    /// Tasks waiting for the lock, in arrival order.
+    waiters: Vec<AxTaskRef>,
+}
+
+/// A mutual exclusion primitive with the priority inheritance protocol.
+///
+/// It behaves like [`Mutex`](crate::Mutex), but while a task with a higher
+/// priority is waiting for the lock, the owner runs with the priority of that
+/// task, so that a task with a medium priority can not keep the owner (and
+/// thus the waiter) from running. When the lock is released, the owner drops
+/// the priority inherited from this lock, keeping its own priority (including
+/// changes made while holding the lock) and those inherited from other locks
+/// it still holds, and the waiter with the highest priority is woken up first.
+///
+/// Priorities follow [`axtask::set_priority`]: a smaller number means a higher
+/// priority. If the scheduler does not support priorities (e.g., FIFO and
+/// round-robin), it works the same as a plain [`Mutex`](crate::Mutex).
+///
+/// The boost is not propagated transitively, i.e., if the owner is itself
+/// blocked on another [`PiMutex`], the owner of that mutex is not boosted.
+pub struct PiMutex<T: ?Sized> {
+    wq: WaitQueue,
+    owner_id: AtomicU64,
+    state: SpinNoIrq<PiState>,
+    #[cfg(feature = "lockdep")]
+    class: LockClass,
+    data: UnsafeCell<T>,
+}
+
+/// A guard that provides mutable data access.
+///
+/// When the guard falls out of scope it will release the lock.
+pub struct PiMutexGuard<'a, T: ?Sized + 'a> {
+    lock: &'a PiMutex<T>,
+    data: *mut T,
+}
+
+// Same unsafe impls as `std::sync::Mutex`
+unsafe impl<T: ?Sized + Send> Sync for PiMutex<T> {}
+unsafe impl<T: ?Sized + Send> Send for PiMutex<T> {}
+
+impl<T> PiMutex<T> {
+    /// Creates a new [`PiMutex`] wrapping the supplied data.
+    #[inline(always)]
+    #[cfg_attr(feature = "lockdep", track_caller)]
+    pub const fn new(data: T) -> Self {
+        Self {
+            wq: WaitQueue::new(),
+            owner_id: AtomicU64::new(0),
+            state: SpinNoIrq::new(PiState {
+                owner: None,
+                waiters: Vec::new(),
+            }),
+            #[cfg(feature = "lockdep")]
+            class: LockClass::new(),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    /// Consumes this [`PiMutex`] and unwraps the underlying data.
+    #[inline(always)]
+    pub fn into_inner(self) -> T {
+        // We know statically that there are no outstanding references to
+        // `self` so there's no need to lock.
+        let PiMutex { data, .. } = self;
+        data.into_inner()
+    }
+}
+
+impl<T: ?Sized> PiMutex<T> {
+    /// Returns `true` if the lock is currently held.
+    ///
+    /// # Safety
+    ///
+    /// This function provides no synchronization guarantees and so its result should be considered 'out of date'
+    /// the instant it is called. Do not use it for synchronization purposes. However, it may be useful as a heuristic.
+    #[inline(always)]
+    pub fn is_locked(&self) -> bool {
+        self.owner_id.load(Ordering::Relaxed) != 0
+    }
+
+    /// Locks the [`PiMutex`] and returns a guard that permits access to the inner data.
+    ///
+    /// If the lock is held by a task with a lower priority, the owner inherits
+    /// the priority of the current task until it releases the lock.
+    pub fn lock(&self) -> PiMutexGuard<T> {
+        let curr = current();
+        let curr_task = curr.as_task_ref();
+        #[cfg(feature = "lockdep")]
+        lockdep::acquire(&self.class, LockKind::Sleep, false);
+        loop {
+            let mut state = self.state.lock();
+            match &state.owner {
+                None => {
+                    self.acquire(&mut state, curr_task);
+                    break;
+                }
+                Some(owner) => {
+                    assert!(
+                        !Arc::ptr_eq(owner, curr_task),
+                        "{} tried to acquire mutex it already owns.",
+                        curr.id_name()
+                    );
+                    let owner = owner.clone();
+                    if !state.waiters.iter().any(|t| Arc::ptr_eq(t, curr_task)) {
+                        state.waiters.push(curr_task.clone());
+                    }
+                    let prio = state.waiters.iter().map(|t| t.priority()).min();
+                    axtask::set_inherited_priority(&owner, self.key(), prio);
+                }
+            }
+            drop(state);
+            // Wait until the lock looks unlocked before retrying
+            self.wq.wait_until(|| !self.is_locked());
+        }
+        PiMutexGuard {
+            lock: self,
+            data: unsafe { &mut *self.data.get() },
+        }
+    }
+
+    /// Try to lock this [`PiMutex`], returning a lock guard if successful.
+    #[inline(always)]
+    pub fn try_lock(&self) -> Option<PiMutexGuard<T>> {
+        let curr = current();
+        let mut state = self.state.lock();
+        if state.owner.is_some() {
+            return None;
+        }
+        self.acquire(&mut state, curr.as_task_ref());
+        #[cfg(feature = "lockdep")]
+        lockdep::acquire(&self.class, LockKind::Sleep, true);
+        Some(PiMutexGuard {
+            lock: self,
+            data: unsafe { &mut *self.data.get() },
+        })
+    }
+
+    /// Force unlock the [`PiMutex`].
+    ///
+    /// # Safety
+    ///
+    /// This is *extremely* unsafe if the lock is not held by the current
+    /// thread. However, this can be useful in some instances for exposing
+    /// the lock to FFI that doesn’t know how to deal with RAII.
+    pub unsafe fn force_unlock(&self) {
+        #[cfg(feature = "lockdep")]
+        lockdep::release(&self.class);
+        let curr = current();
+        let mut state = self.state.lock();
+        let owner = state.owner.take();
+        assert!(
+            owner.is_some_and(|owner| Arc::ptr_eq(&owner, curr.as_task_ref())),
+            "{} tried to release mutex it doesn't own",
+            curr.id_name()
+        );
+        // Drop the priority inherited from this lock.
+        axtask::set_inherited_priority(curr.as_task_ref(), self.key(), None);
+        self.owner_id.store(0, Ordering::Release);
+        let next = state.waiters.iter().min_by_key(|t| t.priority()).cloned();
+        drop(state);
+
+        // Wake up the waiter with the highest priority. If it has not been
+        // blocked yet, it will see the lock released before blocking.
+        if let Some(task) = next {
+            self.wq.notify_task(true, &task);
+        }
+    }
+
+    /// Returns a mutable reference to the underlying data.
+    ///
+    /// Since this call borrows the [`PiMutex`] mutably, and a mutable reference is guaranteed to be exclusive in
+    /// Rust, no actual locking needs to take place -- the mutable borrow statically guarantees no locks exist. As
+    /// such, this is a 'zero-cost' operation.
+    #[inline(always)]
+    pub fn get_mut(&mut self) -> &mut T {
+        // We know statically that there are no other references to `self`, so
+        // there's no need to lock the inner mutex.
+        unsafe { &mut *self.data.get() }
+    }
+
+    /// Makes `task` the owner, and lets it inherit the highest priority of the
+    /// remaining waiters.
+    fn acquire(&self, state: &mut PiState, task: &AxTaskRef) {
+        self.owner_id.store(task.id().as_u64(), Ordering::Relaxed);
+        state.waiters.retain(|t| !Arc::ptr_eq(t, task));
+        state.owner = Some(task.clone());
+        if let Some(prio) = state.waiters.iter().map(|t| t.priority()).min() {
+            axtask::set_inherited_priority(task, self.key(), Some(prio));
+        }
+    }
+
+    /// The key of the lock for the priorities inherited from it.
+    fn key(&self) -> usize {
+        self as *const Self as *const () as usize
+    }
+}
+
+impl<T: ?Sized + Default> Default for PiMutex<T> {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for PiMutex<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.try_lock() {
+            Some(guard) => write!(f, "PiMutex {{ data: ")
+                .and_then(|()| (*guard).fmt(f))
+                .and_then(|()| write!(f, "}}")),
+            None => write!(f, "PiMutex {{ <locked> }}"),
+        }
+    }
+}
+
+impl<'a, T: ?Sized> Deref for PiMutexGuard<'a, T> {
+    type Target = T;
+    #[inline(always)]
+    fn deref(&self) -> &T {
+        // We know statically that only we are referencing data
+        unsafe { &*self.data }
+    }
+}
+
+impl<'a, T: ?Sized> DerefMut for PiMutexGuard<'a, T> {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut T {
+        // We know statically that only we are referencing data
+        unsafe { &mut *self.data }
+    }
+}
+
+impl<'a, T: ?Sized + fmt::Debug> fmt::Debug for PiMutexGuard<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<'a, T: ?Sized> Drop for PiMutexGuard<'a, T> {
+    /// The dropping of the [`PiMutexGuard`] will release the lock it was created from.
+    fn drop(&mut self) {
+        unsafe { self.lock.force_unlock() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::PiMutex;
+    use axtask as thread;
+    use std::sync::Once;
+
+    static INIT: Once = Once::new();
+
+    fn may_interrupt() {
+        // simulate interrupts
+        if rand::random::<u32>() % 3 == 0 {
+            thread::yield_now();
+        }
+    }
+
+    #[test]
+    fn lots_and_lots() {
+        INIT.call_once(thread::init_scheduler);
+
+        const NUM_TASKS: u32 = 10;
+        const NUM_ITERS: u32 = 10_000;
+        static M: PiMutex<u32> = PiMutex::new(0);
+
+        fn inc(delta: u32) {
+            for _ in 0..NUM_ITERS {
+                let mut val = M.lock();
+                *val += delta;
+                may_interrupt();
+                drop(val);
+                may_interrupt();
+            }
+        }
+
+        for _ in 0..NUM_TASKS {
+            thread::spawn(|| inc(1));
+            thread::spawn(|| inc(2));
+        }
+
+        println!("spawn OK");
+        loop {
+            let val = M.lock();
+            if *val == NUM_ITERS * NUM_TASKS * 3 {
+                break;
+            }
+            may_interrupt();
+            drop(val);
+            may_interrupt();
+        }
+
+        assert_eq!(*M.lock(), NUM_ITERS * NUM_TASKS * 3);
+        assert!(M.try_lock().is_some());
+        println!("PiMutex test OK");
+    }
+}
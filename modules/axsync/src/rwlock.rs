@@ -0,0 +1,386 @@
+//! A sleeping readers-writer lock.
+
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use axtask::WaitQueue;
+
+#[cfg(feature = "lockdep")]
+use crate::lockdep::{self, LockClass, LockKind};
+
+/// The bit in the lock state indicating that a writer holds the lock. The
+/// other bits count the readers.
+const WRITER: usize = 1 << (usize::BITS - 1);
+
+/// A readers-writer lock, similar to
+/// [`std::sync::RwLock`](https://doc.rust-lang.org/std/sync/struct.RwLock.html).
+///
+/// It allows a number of readers or at most one writer at any point in time.
+/// Tasks that can not acquire the lock will block and be put into a wait
+/// queue.
+///
+/// The lock is writer-preferring: once a writer is waiting, new readers will
+/// block until all waiting writers have acquired and released the lock, so
+/// that writers are not starved by a continuous stream of readers.
+pub struct RwLock<T: ?Sized> {
+    state: AtomicUsize,
+    writers_waiting: AtomicUsize,
+    read_wq: WaitQueue,
+    write_wq: WaitQueue,
+    #[cfg(feature = "lockdep")]
+    class: LockClass,
+    data: UnsafeCell<T>,
+}
+
+/// A guard that provides immutable data access.
+///
+/// When the guard falls out of scope it will decrement the read count,
+/// potentially releasing the lock.
+pub struct RwLockReadGuard<'a, T: ?Sized + 'a> {
+    lock: &'a RwLock<T>,
+    data: *const T,
+}
+
+/// A guard that provides mutable data access.
+///
+/// When the guard falls out of scope it will release the lock.
+pub struct RwLockWriteGuard<'a, T: ?Sized + 'a> {
+    lock: &'a RwLock<T>,
+    data: *mut T,
+}
+
+// Same unsafe impls as `std::sync::RwLock`
+unsafe impl<T: ?Sized + Send> Send for RwLock<T> {}
+unsafe impl<T: ?Sized + Send + Sync> Sync for RwLock<T> {}
+
+impl<T> RwLock<T> {
+    /// Creates a new [`RwLock`] wrapping the supplied data.
+    #[inline(always)]
+    #[cfg_attr(feature = "lockdep", track_caller)]
+    pub const fn new(data: T) -> Self {
+        Self {
+            state: AtomicUsize::new(0),
+            writers_waiting: AtomicUsize::new(0),
+            read_wq: WaitQueue::new(),
+            write_wq: WaitQueue::new(),
+            #[cfg(feature = "lockdep")]
+            class: LockClass::new(),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    /// Consumes this [`RwLock`] and unwraps the underlying data.
+    #[inline(always)]
+    pub fn into_inner(self) -> T {
+        // We know statically that there are no outstanding references to
+        // `self` so there's no need to lock.
+        let RwLock { data, .. } = self;
+        data.into_inner()
+    }
+}
+
+impl<T: ?Sized> RwLock<T> {
+    /// Returns `true` if a writer holds the lock.
+    ///
+    /// # Safety
+    ///
+    /// This function provides no synchronization guarantees and so its result should be considered 'out of date'
+    /// the instant it is called. Do not use it for synchronization purposes. However, it may be useful as a heuristic.
+    #[inline(always)]
+    pub fn is_write_locked(&self) -> bool {
+        self.state.load(Ordering::Relaxed) & WRITER != 0
+    }
+
+    /// Returns the number of readers that currently hold the lock.
+    ///
+    /// # Safety
+    ///
+    /// This function provides no synchronization guarantees and so its result should be considered 'out of date'
+    /// the instant it is called. Do not use it for synchronization purposes. However, it may be useful as a heuristic.
+    #[inline(always)]
+    pub fn reader_count(&self) -> usize {
+        self.state.load(Ordering::Relaxed) & !WRITER
+    }
+
+    /// Locks this [`RwLock`] with shared read access, blocking the current
+    /// task until it can be acquired.
+    ///
+    /// It blocks while a writer holds the lock or is waiting for it.
+    pub fn read(&self) -> RwLockReadGuard<T> {
+        // Readers are tracked like writers, as a reader blocks on a waiting
+        // writer.
+        #[cfg(feature = "lockdep")]
+        lockdep::acquire(&self.class, LockKind::Sleep, false);
+        loop {
+            if let Some(guard) = self.try_read_inner() {
+                return guard;
+            }
+            self.read_wq.wait_until(|| self.can_read());
+        }
+    }
+
+    /// Attempts to acquire this [`RwLock`] with shared read access, returning
+    /// a guard if successful.
+    #[inline]
+    pub fn try_read(&self) -> Option<RwLockReadGuard<T>> {
+        let guard = self.try_read_inner();
+        #[cfg(feature = "lockdep")]
+        if guard.is_some() {
+            lockdep::acquire(&self.class, LockKind::Sleep, true);
+        }
+        guard
+    }
+
+    #[inline]
+    fn try_read_inner(&self) -> Option<RwLockReadGuard<T>> {
+        let mut state = self.state.load(Ordering::SeqCst);
+        loop {
+            if state & WRITER != 0 || self.writers_waiting.load(Ordering::SeqCst) > 0 {
+                return None;
+            }
+            match self.state.compare_exchange_weak(
+                state,
+                state + 1,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            ) {
+                Ok(_) => {
+                    return Some(RwLockReadGuard {
+                        lock: self,
+                        data: self.data.get(),
+                    })
+                }
+                Err(s) => state = s,
+            }
+        }
+    }
+
+    /// Locks this [`RwLock`] with exclusive write access, blocking the current
+    /// task until it can be acquired.
+    pub fn write(&self) -> RwLockWriteGuard<T> {
+        #[cfg(feature = "lockdep")]
+        lockdep::acquire(&self.class, LockKind::Sleep, false);
+        if let Some(guard) = self.try_write_inner() {
+            return guard;
+        }
+        // Block new readers until we get the lock.
+        self.writers_waiting.fetch_add(1, Ordering::SeqCst);
+        loop {
+            if self
+                .state
+                .compare_exchange(0, WRITER, Ordering::SeqCst, Ordering::SeqCst)
+                .is_ok()
+            {
+                break;
+            }
+            self.write_wq
+                .wait_until(|| self.state.load(Ordering::SeqCst) == 0);
+        }
+        self.writers_waiting.fetch_sub(1, Ordering::SeqCst);
+        RwLockWriteGuard {
+            lock: self,
+            data: self.data.get(),
+        }
+    }
+
+    /// Attempts to lock this [`RwLock`] with exclusive write access, returning
+    /// a guard if successful.
+    #[inline]
+    pub fn try_write(&self) -> Option<RwLockWriteGuard<T>> {
+        let guard = self.try_write_inner();
+        #[cfg(feature = "lockdep")]
+        if guard.is_some() {
+            lockdep::acquire(&self.class, LockKind::Sleep, true);
+        }
+        guard
+    }
+
+    #[inline]
+    fn try_write_inner(&self) -> Option<RwLockWriteGuard<T>> {
+        if self
+            .state
+            .compare_exchange(0, WRITER, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+        {
+            Some(RwLockWriteGuard {
+                lock: self,
+                data: self.data.get(),
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Returns a mutable reference to the underlying data.
+    ///
+    /// Since this call borrows the [`RwLock`] mutably, and a mutable reference is guaranteed to be exclusive in
+    /// Rust, no actual locking needs to take place -- the mutable borrow statically guarantees no locks exist. As
+    /// such, this is a 'zero-cost' operation.
+    #[inline(always)]
+    pub fn get_mut(&mut self) -> &mut T {
+        // We know statically that there are no other references to `self`, so
+        // there's no need to lock the inner lock.
+        unsafe { &mut *self.data.get() }
+    }
+
+    /// Whether a new reader may acquire the lock now.
+    #[inline(always)]
+    fn can_read(&self) -> bool {
+        self.state.load(Ordering::SeqCst) & WRITER == 0
+            && self.writers_waiting.load(Ordering::SeqCst) == 0
+    }
+
+    fn read_unlock(&self) {
+        #[cfg(feature = "lockdep")]
+        lockdep::release(&self.class);
+        let state = self.state.fetch_sub(1, Ordering::SeqCst);
+        debug_assert!(state & !WRITER > 0);
+        // The last reader hands the lock over to a waiting writer.
+        if state == 1 && self.writers_waiting.load(Ordering::SeqCst) > 0 {
+            self.write_wq.notify_one(true);
+        }
+    }
+
+    fn write_unlock(&self) {
+        #[cfg(feature = "lockdep")]
+        lockdep::release(&self.class);
+        self.state.fetch_and(!WRITER, Ordering::SeqCst);
+        // Prefer waiting writers, otherwise let all readers in.
+        if self.writers_waiting.load(Ordering::SeqCst) > 0 {
+            self.write_wq.notify_one(true);
+        } else {
+            self.read_wq.notify_all(true);
+        }
+    }
+}
+
+impl<T: ?Sized + Default> Default for RwLock<T> {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for RwLock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.try_read() {
+            Some(guard) => write!(f, "RwLock {{ data: ")
+                .and_then(|()| (*guard).fmt(f))
+                .and_then(|()| write!(f, "}}")),
+            None => write!(f, "RwLock {{ <locked> }}"),
+        }
+    }
+}
+
+impl<'a, T: ?Sized> Deref for RwLockReadGuard<'a, T> {
+    type Target = T;
+    #[inline(always)]
+    fn deref(&self) -> &T {
+        // We know statically that only readers are referencing data
+        unsafe { &*self.data }
+    }
+}
+
+impl<'a, T: ?Sized> Deref for RwLockWriteGuard<'a, T> {
+    type Target = T;
+    #[inline(always)]
+    fn deref(&self) -> &T {
+        // We know statically that only we are referencing data
+        unsafe { &*self.data }
+    }
+}
+
+impl<'a, T: ?Sized> DerefMut for RwLockWriteGuard<'a, T> {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut T {
+        // We know statically that only we are referencing data
+        unsafe { &mut *self.data }
+    }
+}
+
+impl<'a, T: ?Sized + fmt::Debug> fmt::Debug for RwLockReadGuard<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<'a, T: ?Sized + fmt::Debug> fmt::Debug for RwLockWriteGuard<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<'a, T: ?Sized> Drop for RwLockReadGuard<'a, T> {
+    /// The dropping of the [`RwLockReadGuard`] will decrement the read count.
+    fn drop(&mut self) {
+        self.lock.read_unlock()
+    }
+}
+
+impl<'a, T: ?Sized> Drop for RwLockWriteGuard<'a, T> {
+    /// The dropping of the [`RwLockWriteGuard`] will release the lock it was created from.
+    fn drop(&mut self) {
+        self.lock.write_unlock()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::RwLock;
+    use axtask as thread;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Once;
+
+    static INIT: Once = Once::new();
+
+    fn may_interrupt() {
+        // simulate interrupts
+        if rand::random::<u32>() % 3 == 0 {
+            thread::yield_now();
+        }
+    }
+
+    #[test]
+    fn readers_and_writers() {
+        INIT.call_once(thread::init_scheduler);
+
+        const NUM_TASKS: usize = 10;
+        const NUM_ITERS: usize = 1_000;
+        static LOCK: RwLock<(usize, usize)> = RwLock::new((0, 0));
+        static FINISHED: AtomicUsize = AtomicUsize::new(0);
+
+        for _ in 0..NUM_TASKS {
+            thread::spawn(|| {
+                for _ in 0..NUM_ITERS {
+                    let mut val = LOCK.write();
+                    val.0 += 1;
+                    may_interrupt();
+                    val.1 += 1;
+                    drop(val);
+                    may_interrupt();
+                }
+                FINISHED.fetch_add(1, Ordering::Relaxed);
+            });
+            thread::spawn(|| {
+                for _ in 0..NUM_ITERS {
+                    let val = LOCK.read();
+                    may_interrupt();
+                    // Writers never run concurrently with readers.
+                    assert_eq!(val.0, val.1);
+                    drop(val);
+                    may_interrupt();
+                }
+                FINISHED.fetch_add(1, Ordering::Relaxed);
+            });
+        }
+
+        while FINISHED.load(Ordering::Relaxed) < NUM_TASKS * 2 {
+            thread::yield_now();
+        }
+        assert_eq!(*LOCK.read(), (NUM_TASKS * NUM_ITERS, NUM_TASKS * NUM_ITERS));
+        assert!(LOCK.try_write().is_some());
+        println!("RwLock test OK");
+    }
+}
@@ -0,0 +1,202 @@
+//! Spinlocks checked by the lock validator.
+//!
+//! They have the same interface as the ones in the [`kspin`] crate, which are
+//! used instead when the `lockdep` feature is not enabled.
+
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+
+use kernel_guard::{BaseGuard, NoOp, NoPreempt, NoPreemptIrqSave};
+
+use crate::lockdep::{self, LockClass, LockKind};
+
+pub use kernel_guard;
+
+/// A spin lock that disables kernel preemption while trying to lock, and
+/// re-enables it after unlocking.
+///
+/// It must be used in the local IRQ-disabled context, or never be used in
+/// interrupt handlers.
+pub type SpinNoPreempt<T> = BaseSpinLock<NoPreempt, T>;
+
+/// A guard that provides mutable data access for [`SpinNoPreempt`].
+pub type SpinNoPreemptGuard<'a, T> = BaseSpinLockGuard<'a, NoPreempt, T>;
+
+/// A spin lock that disables kernel preemption and local IRQs while trying to
+/// lock, and re-enables it after unlocking.
+///
+/// It can be used in the IRQ-enabled context.
+pub type SpinNoIrq<T> = BaseSpinLock<NoPreemptIrqSave, T>;
+
+/// A guard that provides mutable data access for [`SpinNoIrq`].
+pub type SpinNoIrqGuard<'a, T> = BaseSpinLockGuard<'a, NoPreemptIrqSave, T>;
+
+/// A raw spin lock that does nothing while trying to lock.
+///
+/// It must be used in the preemption-disabled and local IRQ-disabled context,
+/// or never be used in interrupt handlers.
+pub type SpinRaw<T> = BaseSpinLock<NoOp, T>;
+
+/// A guard that provides mutable data access for [`SpinRaw`].
+pub type SpinRawGuard<'a, T> = BaseSpinLockGuard<'a, NoOp, T>;
+
+/// A spin lock providing mutually exclusive access to data, with the critical
+/// section protected by the guard `G`.
+///
+/// Acquisitions and releases are reported to the lock validator.
+pub struct BaseSpinLock<G: BaseGuard, T: ?Sized> {
+    class: LockClass,
+    _phantom: core::marker::PhantomData<G>,
+    inner: kspin::SpinRaw<T>,
+}
+
+/// A guard that provides mutable data access.
+///
+/// When the guard falls out of scope it will release the lock.
+pub struct BaseSpinLockGuard<'a, G: BaseGuard, T: ?Sized + 'a> {
+    class: &'a LockClass,
+    irq_state: G::State,
+    inner: Option<kspin::SpinRawGuard<'a, T>>,
+}
+
+unsafe impl<G: BaseGuard, T: ?Sized + Send> Sync for BaseSpinLock<G, T> {}
+unsafe impl<G: BaseGuard, T: ?Sized + Send> Send for BaseSpinLock<G, T> {}
+
+impl<G: BaseGuard, T> BaseSpinLock<G, T> {
+    /// Creates a new [`BaseSpinLock`] wrapping the supplied data.
+    #[track_caller]
+    #[inline(always)]
+    pub const fn new(data: T) -> Self {
+        Self {
+            class: LockClass::new(),
+            _phantom: core::marker::PhantomData,
+            inner: kspin::SpinRaw::new(data),
+        }
+    }
+
+    /// Consumes this [`BaseSpinLock`] and unwraps the underlying data.
+    #[inline(always)]
+    pub fn into_inner(self) -> T {
+        self.inner.into_inner()
+    }
+}
+
+impl<G: BaseGuard, T: ?Sized> BaseSpinLock<G, T> {
+    /// Locks the [`BaseSpinLock`] and returns a guard that permits access to the inner data.
+    ///
+    /// The returned value may be dereferenced for data access
+    /// and the lock will be dropped when the guard falls out of scope.
+    #[inline(always)]
+    pub fn lock(&self) -> BaseSpinLockGuard<G, T> {
+        let irq_state = G::acquire();
+        lockdep::acquire(&self.class, LockKind::Spin, false);
+        BaseSpinLockGuard {
+            class: &self.class,
+            irq_state,
+            inner: Some(self.inner.lock()),
+        }
+    }
+
+    /// Returns `true` if the lock is currently held.
+    ///
+    /// # Safety
+    ///
+    /// This function provides no synchronization guarantees and so its result should be considered 'out of date'
+    /// the instant it is called. Do not use it for synchronization purposes. However, it may be useful as a heuristic.
+    #[inline(always)]
+    pub fn is_locked(&self) -> bool {
+        self.inner.is_locked()
+    }
+
+    /// Try to lock this [`BaseSpinLock`], returning a lock guard if successful.
+    #[inline(always)]
+    pub fn try_lock(&self) -> Option<BaseSpinLockGuard<G, T>> {
+        let irq_state = G::acquire();
+        match self.inner.try_lock() {
+            Some(inner) => {
+                lockdep::acquire(&self.class, LockKind::Spin, true);
+                Some(BaseSpinLockGuard {
+                    class: &self.class,
+                    irq_state,
+                    inner: Some(inner),
+                })
+            }
+            None => {
+                G::release(irq_state);
+                None
+            }
+        }
+    }
+
+    /// Force unlock this [`BaseSpinLock`].
+    ///
+    /// # Safety
+    ///
+    /// This is *extremely* unsafe if the lock is not held by the current
+    /// thread. However, this can be useful in some instances for exposing the
+    /// lock to FFI that doesn't know how to deal with RAII.
+    #[inline(always)]
+    pub unsafe fn force_unlock(&self) {
+        lockdep::release(&self.class);
+        self.inner.force_unlock()
+    }
+
+    /// Returns a mutable reference to the underlying data.
+    ///
+    /// Since this call borrows the [`BaseSpinLock`] mutably, and a mutable reference is guaranteed to be exclusive in
+    /// Rust, no actual locking needs to take place -- the mutable borrow statically guarantees no locks exist. As
+    /// such, this is a 'zero-cost' operation.
+    #[inline(always)]
+    pub fn get_mut(&mut self) -> &mut T {
+        self.inner.get_mut()
+    }
+}
+
+impl<G: BaseGuard, T: ?Sized + Default> Default for BaseSpinLock<G, T> {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+impl<G: BaseGuard, T: ?Sized + fmt::Debug> fmt::Debug for BaseSpinLock<G, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.try_lock() {
+            Some(guard) => write!(f, "SpinLock {{ data: ")
+                .and_then(|()| (*guard).fmt(f))
+                .and_then(|()| write!(f, "}}")),
+            None => write!(f, "SpinLock {{ <locked> }}"),
+        }
+    }
+}
+
+impl<'a, G: BaseGuard, T: ?Sized> Deref for BaseSpinLockGuard<'a, G, T> {
+    type Target = T;
+    #[inline(always)]
+    fn deref(&self) -> &T {
+        self.inner.as_ref().unwrap()
+    }
+}
+
+impl<'a, G: BaseGuard, T: ?Sized> DerefMut for BaseSpinLockGuard<'a, G, T> {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut T {
+        self.inner.as_mut().unwrap()
+    }
+}
+
+impl<'a, G: BaseGuard, T: ?Sized + fmt::Debug> fmt::Debug for BaseSpinLockGuard<'a, G, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<'a, G: BaseGuard, T: ?Sized> Drop for BaseSpinLockGuard<'a, G, T> {
+    /// The dropping of the [`BaseSpinLockGuard`] will release the lock it was created from.
+    #[inline(always)]
+    fn drop(&mut self) {
+        self.inner.take();
+        lockdep::release(self.class);
+        G::release(self.irq_state);
+    }
+}
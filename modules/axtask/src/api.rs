@@ -1,13 +1,13 @@
 //! Task APIs for multi-task configuration.
 
-use alloc::{string::String, sync::Arc};
+use alloc::{string::String, sync::Arc, vec::Vec};
 
 use kernel_guard::NoPreemptIrqSave;
 
 pub(crate) use crate::run_queue::{current_run_queue, select_run_queue};
 
 #[doc(cfg(feature = "multitask"))]
-pub use crate::task::{CurrentTask, TaskId, TaskInner};
+pub use crate::task::{CurrentTask, TaskId, TaskInner, TaskState};
 #[doc(cfg(feature = "multitask"))]
 pub use crate::task_ext::{TaskExtMut, TaskExtRef};
 #[doc(cfg(feature = "multitask"))]
@@ -68,6 +68,13 @@ pub fn current() -> CurrentTask {
     CurrentTask::get()
 }
 
+/// Returns all tasks that have not been dropped, in the order of creation,
+/// including the idle tasks and exited tasks not yet joined.
+#[cfg(feature = "task_list")]
+pub fn all_tasks() -> Vec<AxTaskRef> {
+    crate::task::all_tasks()
+}
+
 /// Initializes the task scheduler (for the primary CPU).
 pub fn init_scheduler() {
     info!("Initialize scheduling...");
@@ -75,6 +82,8 @@ pub fn init_scheduler() {
     crate::run_queue::init();
     #[cfg(feature = "irq")]
     crate::timers::init();
+    #[cfg(feature = "sched_trace")]
+    crate::trace::init();
 
     info!("  use {} scheduler.", Scheduler::scheduler_name());
 }
@@ -93,7 +102,13 @@ pub fn init_scheduler_secondary() {
 #[doc(cfg(feature = "irq"))]
 pub fn on_timer_tick() {
     use kernel_guard::NoOp;
+    #[cfg(feature = "watchdog")]
+    crate::watchdog::on_timer_tick();
     crate::timers::check_events();
+    #[cfg(feature = "task_group")]
+    if axhal::cpu::this_cpu_is_bsp() {
+        crate::group::on_timer_tick();
+    }
     // Since irq and preemption are both disabled here,
     // we can get current run queue with the default `kernel_guard::NoOp`.
     current_run_queue::<NoOp>().scheduler_timer_tick();
@@ -102,6 +117,11 @@ pub fn on_timer_tick() {
 /// Adds the given task to the run queue, returns the task reference.
 pub fn spawn_task(task: TaskInner) -> AxTaskRef {
     let task_ref = task.into_arc();
+    // A task spawned by a group member joins the same group.
+    #[cfg(feature = "task_group")]
+    if let Some(group) = current_may_uninit().and_then(|curr| curr.group()) {
+        group.add_task(&task_ref);
+    }
     select_run_queue::<NoPreemptIrqSave>(&task_ref).add_task(task_ref.clone());
     task_ref
 }
@@ -142,6 +162,34 @@ pub fn set_priority(prio: isize) -> bool {
     current_run_queue::<NoPreemptIrqSave>().set_current_priority(prio)
 }
 
+/// Set the priority for the given task.
+///
+/// It has the same semantics as [`set_priority`], but the target task can be
+/// any task.
+///
+/// Returns `true` if the priority is set successfully.
+pub fn set_task_priority(task: &AxTaskRef, prio: isize) -> bool {
+    current_run_queue::<NoPreemptIrqSave>().set_task_priority(task, prio)
+}
+
+/// Set the priority `task` inherits from the lock at address `lock`, e.g.,
+/// the priority of the highest waiter when `task` owns the lock, or drop it
+/// if `prio` is `None`.
+///
+/// The task runs with the highest of its base priority (set by
+/// [`set_task_priority`]) and the priorities inherited from all the locks it
+/// holds, so the locks may be released in any order.
+pub fn set_inherited_priority(task: &AxTaskRef, lock: usize, prio: Option<isize>) {
+    current_run_queue::<NoPreemptIrqSave>().set_inherited_priority(task, lock, prio)
+}
+
+/// Sets the percentage of its stack a task may use without a warning when it
+/// exits.
+#[cfg(feature = "stack_usage")]
+pub fn set_stack_usage_warn_percent(percent: usize) {
+    crate::task::STACK_USAGE_WARN_PERCENT.store(percent, core::sync::atomic::Ordering::Relaxed);
+}
+
 /// Set the affinity for the current task.
 /// [`AxCpuMask`] is used to specify the CPU affinity.
 /// Returns `true` if the affinity is set successfully.
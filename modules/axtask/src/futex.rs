@@ -0,0 +1,164 @@
+//! Address-keyed wait queues, i.e., futexes ("fast userspace mutexes").
+//!
+//! A futex is a 32-bit integer in memory. Tasks block on its address only if
+//! it still holds an expected value, so that locks built on it can be
+//! lock-free in the uncontended case and enter the kernel only to wait or
+//! wake up waiters.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+use core::time::Duration;
+
+use crate::wait_queue::{unblock_one_task, WaitQueueGuard};
+use crate::WaitQueue;
+
+/// Number of wait queues in the futex table, tasks waiting on different
+/// addresses may share a wait queue.
+const FUTEX_BUCKETS: usize = 64;
+
+static FUTEX_TABLE: [WaitQueue; FUTEX_BUCKETS] = [const { WaitQueue::new() }; FUTEX_BUCKETS];
+
+/// The error type of futex operations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FutexError {
+    /// The futex does not hold the expected value (`EAGAIN`).
+    WouldBlock,
+    /// The timeout has elapsed before the task is woken up (`ETIMEDOUT`).
+    TimedOut,
+    /// A timeout is given, but timed waits are not supported without the
+    /// `irq` feature.
+    Unsupported,
+}
+
+fn futex_key(futex: &AtomicU32) -> usize {
+    futex.as_ptr() as usize
+}
+
+fn futex_bucket(key: usize) -> &'static WaitQueue {
+    let hash = (key >> 2) ^ (key >> 12);
+    &FUTEX_TABLE[hash % FUTEX_BUCKETS]
+}
+
+/// Wakes up at most `count` tasks waiting on `key` in the locked bucket.
+fn wake_locked(wq: &mut WaitQueueGuard, key: usize, count: usize) -> usize {
+    let mut woken = 0;
+    let mut i = 0;
+    while i < wq.len() && woken < count {
+        if wq[i].futex_key() == key {
+            unblock_one_task(wq.remove(i).unwrap(), true);
+            woken += 1;
+        } else {
+            i += 1;
+        }
+    }
+    woken
+}
+
+/// Blocks the current task on `futex` if it holds the `expected` value,
+/// until woken up by [`futex_wake`] or [`futex_requeue`], or the optional
+/// `timeout` has elapsed.
+///
+/// The value is checked atomically with respect to the wakers. Spurious
+/// wakeups are possible, so the caller should re-check its condition.
+///
+/// The timeout requires the `irq` feature, otherwise [`FutexError::Unsupported`]
+/// is returned without waiting if it is given.
+pub fn futex_wait(
+    futex: &AtomicU32,
+    expected: u32,
+    timeout: Option<Duration>,
+) -> Result<(), FutexError> {
+    let key = futex_key(futex);
+    #[cfg(feature = "irq")]
+    let deadline = timeout.map(|dur| axhal::time::wall_time() + dur);
+    #[cfg(not(feature = "irq"))]
+    let deadline = match timeout {
+        Some(_) => return Err(FutexError::Unsupported),
+        None => None,
+    };
+
+    let timed_out = futex_bucket(key)
+        .wait_if(
+            |curr| {
+                if futex.load(Ordering::Acquire) != expected {
+                    return false;
+                }
+                curr.set_futex_key(key);
+                true
+            },
+            deadline,
+        )
+        .ok_or(FutexError::WouldBlock)?;
+
+    if timed_out {
+        // The task may have been requeued to another bucket, or woken up
+        // right after the timeout.
+        let curr = crate::current();
+        while curr.in_wait_queue() {
+            if futex_bucket(curr.futex_key()).cancel_wait(&curr) {
+                return Err(FutexError::TimedOut);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Wakes up at most `count` tasks waiting on `futex`.
+///
+/// Returns the number of tasks woken up.
+pub fn futex_wake(futex: &AtomicU32, count: usize) -> usize {
+    let key = futex_key(futex);
+    wake_locked(&mut futex_bucket(key).lock(), key, count)
+}
+
+/// Wakes up at most `wake_count` tasks waiting on `futex`, and moves at most
+/// `requeue_count` of the remaining ones to wait on `target` without waking
+/// them up.
+///
+/// If `expected` is given, nothing is done unless `futex` still holds that
+/// value, which is checked atomically with respect to the waiters.
+///
+/// Returns the total number of tasks woken up and moved.
+pub fn futex_requeue(
+    futex: &AtomicU32,
+    expected: Option<u32>,
+    wake_count: usize,
+    target: &AtomicU32,
+    requeue_count: usize,
+) -> Result<usize, FutexError> {
+    let (key, new_key) = (futex_key(futex), futex_key(target));
+    let (src_wq, dst_wq) = (futex_bucket(key), futex_bucket(new_key));
+
+    // Always lock the bucket at the lower address first.
+    let mut src;
+    let mut dst = None;
+    if core::ptr::eq(src_wq, dst_wq) {
+        src = src_wq.lock();
+    } else if (src_wq as *const WaitQueue) < (dst_wq as *const WaitQueue) {
+        src = src_wq.lock();
+        dst = Some(dst_wq.lock());
+    } else {
+        dst = Some(dst_wq.lock());
+        src = src_wq.lock();
+    }
+
+    if expected.is_some_and(|val| futex.load(Ordering::Acquire) != val) {
+        return Err(FutexError::WouldBlock);
+    }
+
+    let woken = wake_locked(&mut src, key, wake_count);
+    let mut moved = 0;
+    let mut i = 0;
+    while i < src.len() && moved < requeue_count {
+        if src[i].futex_key() == key {
+            src[i].set_futex_key(new_key);
+            match dst.as_mut() {
+                Some(dst) => dst.push_back(src.remove(i).unwrap()),
+                None => i += 1,
+            }
+            moved += 1;
+        } else {
+            i += 1;
+        }
+    }
+    Ok(woken + moved)
+}
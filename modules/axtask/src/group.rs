@@ -0,0 +1,325 @@
+//! Task groups with CPU bandwidth limits.
+//!
+//! Tasks in a [`TaskGroup`] share:
+//!
+//! - A *CPU quota* per period, the total CPU time the members may run in each
+//!   period across all CPUs. When it is used up, the group is throttled: its
+//!   members are not picked to run until the next period. E.g., a quota of
+//!   30ms per 100ms caps the group at 30% of one CPU.
+//! - A *weight*, divided evenly among the members, each of which gets the
+//!   nice value closest to its share (a weight of 1024 is nice 0). It only
+//!   takes effect with the `sched_cfs` scheduler.
+//!
+//! A task spawned by a member joins the same group, and a task removed from
+//! the group gets back the priority it had before joining. The running
+//! members are charged on each timer tick and context switch, and a
+//! throttled one is switched out on the next tick with the `preempt`
+//! feature, or when it yields or blocks otherwise.
+
+use alloc::{collections::VecDeque, string::String, sync::Arc, sync::Weak, vec::Vec};
+use core::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use core::time::Duration;
+
+use axhal::time::monotonic_time_nanos;
+use kernel_guard::NoPreemptIrqSave;
+use kspin::SpinNoIrq;
+
+use crate::task::TaskState;
+use crate::{select_run_queue, AxTask, AxTaskRef};
+
+/// The weight of a task with nice value 0.
+pub const DEFAULT_WEIGHT: usize = 1024;
+
+/// The default bandwidth period.
+pub const DEFAULT_PERIOD: Duration = Duration::from_millis(100);
+
+/// Weights of nice values from -20 to 19, as in Linux.
+const NICE_TO_WEIGHT: [usize; 40] = [
+    88761, 71755, 56483, 46273, 36291, 29154, 23254, 18705, 14949, 11916, 9548, 7620, 6100, 4904,
+    3906, 3121, 2501, 1991, 1586, 1277, 1024, 820, 655, 526, 423, 335, 272, 215, 172, 137, 110, 87,
+    70, 56, 45, 36, 29, 23, 18, 15,
+];
+
+/// All groups, to start their new periods.
+static GROUPS: SpinNoIrq<Vec<Weak<TaskGroup>>> = SpinNoIrq::new(Vec::new());
+
+struct GroupState {
+    /// The members, with their base priorities before joining the group.
+    members: Vec<(Weak<AxTask>, isize)>,
+    /// When the current period started, in nanoseconds.
+    period_start: u64,
+    /// CPU time used in the current period, in nanoseconds.
+    used: u64,
+    /// Ready members held back while the group is throttled.
+    parked: VecDeque<AxTaskRef>,
+}
+
+/// A group of tasks sharing a CPU quota and a weight.
+pub struct TaskGroup {
+    name: String,
+    /// CPU time allowed in each period in nanoseconds, zero if unlimited.
+    quota: AtomicU64,
+    /// The period in nanoseconds.
+    period: AtomicU64,
+    weight: AtomicUsize,
+    /// Total CPU time used in nanoseconds.
+    usage: AtomicU64,
+    throttled: AtomicBool,
+    state: SpinNoIrq<GroupState>,
+}
+
+impl TaskGroup {
+    /// Creates a new empty group, with no CPU quota and the default weight.
+    pub fn new(name: &str) -> Arc<Self> {
+        let group = Arc::new(Self {
+            name: name.into(),
+            quota: AtomicU64::new(0),
+            period: AtomicU64::new(DEFAULT_PERIOD.as_nanos() as u64),
+            weight: AtomicUsize::new(DEFAULT_WEIGHT),
+            usage: AtomicU64::new(0),
+            throttled: AtomicBool::new(false),
+            state: SpinNoIrq::new(GroupState {
+                members: Vec::new(),
+                period_start: monotonic_time_nanos(),
+                used: 0,
+                parked: VecDeque::new(),
+            }),
+        });
+        GROUPS.lock().push(Arc::downgrade(&group));
+        group
+    }
+
+    /// Returns the name of the group.
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    /// Limits the CPU time of the group to `quota` per `period`, or removes
+    /// the limit if `quota` is [`None`]. It takes effect from a new period.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `period` is zero.
+    pub fn set_bandwidth(&self, quota: Option<Duration>, period: Duration) {
+        assert!(!period.is_zero(), "zero bandwidth period");
+        let quota = quota.map_or(0, |dur| (dur.as_nanos() as u64).max(1));
+        self.quota.store(quota, Ordering::Release);
+        self.period
+            .store(period.as_nanos() as u64, Ordering::Release);
+        self.start_period(monotonic_time_nanos());
+    }
+
+    /// Returns the CPU quota per period ([`None`] if unlimited) and the
+    /// period.
+    pub fn bandwidth(&self) -> (Option<Duration>, Duration) {
+        let quota = self.quota.load(Ordering::Acquire);
+        let period = self.period.load(Ordering::Acquire);
+        (
+            (quota != 0).then(|| Duration::from_nanos(quota)),
+            Duration::from_nanos(period),
+        )
+    }
+
+    /// Sets the weight shared by the members, and updates their priorities.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `weight` is zero.
+    pub fn set_weight(&self, weight: usize) {
+        assert!(weight != 0, "zero group weight");
+        self.weight.store(weight, Ordering::Release);
+        self.update_priorities();
+    }
+
+    /// Returns the weight shared by the members.
+    pub fn weight(&self) -> usize {
+        self.weight.load(Ordering::Acquire)
+    }
+
+    /// Returns the total CPU time used by the members while in the group.
+    pub fn usage(&self) -> Duration {
+        Duration::from_nanos(self.usage.load(Ordering::Acquire))
+    }
+
+    /// Whether the group has used up its quota in the current period.
+    pub fn is_throttled(&self) -> bool {
+        self.throttled.load(Ordering::Acquire)
+    }
+
+    /// Returns the members that have not exited.
+    pub fn members(&self) -> Vec<AxTaskRef> {
+        let mut state = self.state.lock();
+        state.members.retain(|(task, _)| task.strong_count() > 0);
+        state
+            .members
+            .iter()
+            .filter_map(|(task, _)| task.upgrade())
+            .filter(|task| task.state() != TaskState::Exited)
+            .collect()
+    }
+
+    /// Moves `task` into this group, from the group it was in.
+    pub fn add_task(self: &Arc<Self>, task: &AxTaskRef) {
+        let old = task.group_ref().lock().replace(self.clone());
+        let prio = match old {
+            Some(old) if Arc::ptr_eq(&old, self) => return,
+            Some(old) => old.remove_member(task),
+            None => None,
+        };
+        let prio = prio.unwrap_or_else(|| task.base_priority());
+        self.state.lock().members.push((Arc::downgrade(task), prio));
+        self.update_priorities();
+    }
+
+    /// Removes `task` from the group, returns `false` if it is not a member.
+    ///
+    /// The task gets back the priority it had before joining the group.
+    pub fn remove_task(self: &Arc<Self>, task: &AxTaskRef) -> bool {
+        {
+            let mut group = task.group_ref().lock();
+            if !group.as_ref().is_some_and(|g| Arc::ptr_eq(g, self)) {
+                return false;
+            }
+            *group = None;
+        }
+        if let Some(prio) = self.remove_member(task) {
+            crate::set_task_priority(task, prio);
+        }
+        true
+    }
+
+    /// Removes `task` from the members, returns its priority before joining.
+    fn remove_member(&self, task: &AxTaskRef) -> Option<isize> {
+        let (prio, unparked) = {
+            let mut state = self.state.lock();
+            let prio = state
+                .members
+                .iter()
+                .find(|(t, _)| core::ptr::eq(t.as_ptr(), Arc::as_ptr(task)))
+                .map(|&(_, prio)| prio);
+            state.members.retain(|(t, _)| {
+                t.strong_count() > 0 && !core::ptr::eq(t.as_ptr(), Arc::as_ptr(task))
+            });
+            let pos = state.parked.iter().position(|t| Arc::ptr_eq(t, task));
+            (prio, pos.and_then(|i| state.parked.remove(i)))
+        };
+        if let Some(task) = unparked {
+            select_run_queue::<NoPreemptIrqSave>(&task).add_task(task);
+        }
+        self.update_priorities();
+        prio
+    }
+
+    /// Gives each member the nice value closest to its share of the weight.
+    fn update_priorities(&self) {
+        let members = self.members();
+        if members.is_empty() {
+            return;
+        }
+        let share = self.weight() / members.len();
+        let nice = NICE_TO_WEIGHT
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, &w)| w.abs_diff(share))
+            .map(|(i, _)| i as isize - 20)
+            .unwrap();
+        for task in members.iter() {
+            crate::set_task_priority(task, nice);
+        }
+    }
+
+    /// Starts a new period, and puts the parked members back to run queues.
+    fn start_period(&self, now: u64) {
+        let parked = {
+            let mut state = self.state.lock();
+            state.period_start = now;
+            state.used = 0;
+            self.throttled.store(false, Ordering::Release);
+            core::mem::take(&mut state.parked)
+        };
+        for task in parked {
+            select_run_queue::<NoPreemptIrqSave>(&task).add_task(task);
+        }
+    }
+
+    /// Charges `delta` nanoseconds of CPU time, returns whether the group is
+    /// throttled.
+    pub(crate) fn charge(&self, delta: u64) -> bool {
+        self.usage.fetch_add(delta, Ordering::Relaxed);
+        let quota = self.quota.load(Ordering::Acquire);
+        if quota == 0 {
+            return false;
+        }
+        let mut state = self.state.lock();
+        state.used += delta;
+        if state.used >= quota {
+            self.throttled.store(true, Ordering::Release);
+        }
+        self.is_throttled()
+    }
+}
+
+/// Charges the CPU time of `task` since it last started running or was last
+/// charged, returns whether its group is throttled.
+///
+/// IRQs must be disabled.
+fn charge_task(task: &AxTaskRef, now: u64) -> bool {
+    let delta = now.saturating_sub(task.exec_start().swap(now, Ordering::Relaxed));
+    match task.group_ref().lock().as_ref() {
+        Some(group) => group.charge(delta),
+        None => false,
+    }
+}
+
+/// Called when switching from `prev` to `next` on the current CPU.
+pub(crate) fn on_switch(prev: &AxTaskRef, next: &AxTaskRef) {
+    let now = monotonic_time_nanos();
+    charge_task(prev, now);
+    next.exec_start().store(now, Ordering::Relaxed);
+}
+
+/// Called on each timer tick for the running task, returns whether it should
+/// be switched out as its group is throttled.
+pub(crate) fn on_task_tick(curr: &AxTaskRef) -> bool {
+    !curr.is_idle() && charge_task(curr, monotonic_time_nanos())
+}
+
+/// Holds back `task` picked by the scheduler if its group is throttled.
+///
+/// Returns the task back if it can run.
+pub(crate) fn park_if_throttled(task: AxTaskRef) -> Option<AxTaskRef> {
+    let group = task.group_ref().lock().clone();
+    let Some(group) = group else {
+        return Some(task);
+    };
+    if !group.is_throttled() {
+        return Some(task);
+    }
+    let mut state = group.state.lock();
+    // Check again, a new period may have started.
+    if group.is_throttled() {
+        state.parked.push_back(task);
+        None
+    } else {
+        Some(task)
+    }
+}
+
+/// Called on each timer tick of the bootstrap CPU, to start new periods.
+pub(crate) fn on_timer_tick() {
+    let now = monotonic_time_nanos();
+    let mut expired = Vec::new();
+    GROUPS.lock().retain(|group| {
+        let Some(group) = group.upgrade() else {
+            return false;
+        };
+        let period = group.period.load(Ordering::Acquire);
+        if now.saturating_sub(group.state.lock().period_start) >= period {
+            expired.push(group);
+        }
+        true
+    });
+    for group in expired {
+        group.start_period(now);
+    }
+}
@@ -10,9 +10,20 @@
 //!   management and scheduling is used, as well as more task-related APIs.
 //!   Otherwise, only a few APIs with naive implementation is available.
 //! - `irq`: Interrupts are enabled. If this feature is enabled, timer-based
-//!    APIs can be used, such as [`sleep`], [`sleep_until`], and
-//!    [`WaitQueue::wait_timeout`].
+//!    APIs can be used, such as [`sleep`], [`sleep_until`],
+//!    [`WaitQueue::wait_timeout`], and kernel [`Timer`]s.
 //! - `preempt`: Enable preemptive scheduling.
+//! - `watchdog`: Enable the soft-lockup and hung-task detector, see
+//!   [`watchdog`] for details. It also enables `multitask` and `irq`.
+//! - `stack_usage`: Paint new task stacks to measure their deepest usage by
+//!   [`TaskInner::kernel_stack_max_usage`], and warn when a task exits having
+//!   used most of its stack (see [`set_stack_usage_warn_percent`]).
+//! - `task_group`: Enable task groups with CPU bandwidth limits, see
+//!   [`TaskGroup`]. It also enables `multitask` and `irq`.
+//! - `task_list`: Keep a list of all live tasks, see [`all_tasks`]. It is
+//!   enabled by `watchdog` and the `procfs` feature of axfs.
+//! - `sched_trace`: Record scheduler events to be dumped in the Chrome trace
+//!   format, see [`trace`] for details.
 //! - `sched_fifo`: Use the [FIFO cooperative scheduler][1]. It also enables the
 //!   `multitask` feature if it is enabled. This feature is enabled by default,
 //!   and it can be overriden by other scheduler features.
@@ -47,13 +58,27 @@ cfg_if::cfg_if! {
         mod task;
         mod task_ext;
         mod api;
+        mod futex;
         mod wait_queue;
 
         #[cfg(feature = "irq")]
         mod timers;
+        #[cfg(feature = "watchdog")]
+        pub mod watchdog;
+        #[cfg(feature = "sched_trace")]
+        pub mod trace;
+        #[cfg(feature = "task_group")]
+        pub mod group;
 
         #[doc(cfg(feature = "multitask"))]
         pub use self::api::*;
+        #[doc(cfg(feature = "multitask"))]
+        pub use self::futex::{futex_requeue, futex_wait, futex_wake, FutexError};
+        #[cfg(feature = "irq")]
+        #[doc(cfg(all(feature = "multitask", feature = "irq")))]
+        pub use self::timers::Timer;
+        #[cfg(feature = "task_group")]
+        pub use self::group::TaskGroup;
         pub use self::api::{sleep, sleep_until, yield_now};
     } else {
         mod api_s;
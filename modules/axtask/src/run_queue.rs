@@ -229,6 +229,8 @@ impl<'a, G: BaseGuard> AxRunQueueRef<'a, G> {
     /// Unblock one task by inserting it into the run queue.
     pub fn unblock_task(&mut self, task: AxTaskRef, resched: bool) {
         let task_id_name = task.id_name();
+        #[cfg(feature = "sched_trace")]
+        let task_id = task.id().as_u64();
         // Try to change the state of the task from `Blocked` to `Ready`,
         // if successful, the task will be put into this run queue,
         // otherwise, the task is already unblocked by other cores.
@@ -239,6 +241,8 @@ impl<'a, G: BaseGuard> AxRunQueueRef<'a, G> {
             // Since now, the task to be unblocked is in the `Ready` state.
             let cpu_id = self.inner.cpu_id;
             debug!("task unblock: {} on run_queue {}", task_id_name, cpu_id);
+            #[cfg(feature = "sched_trace")]
+            crate::trace::on_wakeup(task_id, cpu_id);
             // Note: when the task is unblocked on another CPU's run queue,
             // we just ingiore the `resched` flag.
             if resched && cpu_id == this_cpu_id() {
@@ -258,6 +262,11 @@ impl<'a, G: BaseGuard> CurrentRunQueueRef<'a, G> {
             #[cfg(feature = "preempt")]
             curr.set_preempt_pending(true);
         }
+        #[cfg(feature = "task_group")]
+        if crate::group::on_task_tick(curr.as_task_ref()) {
+            #[cfg(feature = "preempt")]
+            curr.set_preempt_pending(true);
+        }
     }
 
     /// Yield the current task and reschedule.
@@ -340,7 +349,25 @@ impl<'a, G: BaseGuard> CurrentRunQueueRef<'a, G> {
             }
             axhal::misc::terminate();
         } else {
+            #[cfg(feature = "stack_usage")]
+            if let (Some(used), Some(size)) =
+                (curr.kernel_stack_max_usage(), curr.kernel_stack_size())
+            {
+                let percent = crate::task::STACK_USAGE_WARN_PERCENT
+                    .load(core::sync::atomic::Ordering::Relaxed);
+                if used * 100 > size * percent {
+                    warn!(
+                        "task {} used {} of {} bytes ({}%) of its stack",
+                        curr.id_name(),
+                        used,
+                        size,
+                        used * 100 / size
+                    );
+                }
+            }
             curr.set_state(TaskState::Exited);
+            #[cfg(feature = "sched_trace")]
+            crate::trace::on_exit(curr.as_task_ref(), exit_code);
 
             // Notify the joiner task.
             curr.notify_exit(exit_code);
@@ -381,6 +408,8 @@ impl<'a, G: BaseGuard> CurrentRunQueueRef<'a, G> {
         // while holding the lock of the wait queue.
         curr.set_state(TaskState::Blocked);
         curr.set_in_wait_queue(true);
+        #[cfg(feature = "watchdog")]
+        curr.set_blocked_since(axhal::time::monotonic_time_nanos());
 
         wq_guard.push_back(curr.clone());
         // Drop the lock of wait queue explictly.
@@ -410,10 +439,56 @@ impl<'a, G: BaseGuard> CurrentRunQueueRef<'a, G> {
     }
 
     pub fn set_current_priority(&mut self, prio: isize) -> bool {
-        self.inner
-            .scheduler
-            .lock()
-            .set_priority(self.current_task.as_task_ref(), prio)
+        let curr = self.current_task.clone();
+        self.set_task_priority(&curr, prio)
+    }
+
+    /// Set the priority of the given task, which may be running or blocked on
+    /// another CPU.
+    ///
+    /// The supported schedulers keep the priority in the task entity itself,
+    /// so it does not matter which run queue's scheduler does the update.
+    ///
+    /// It sets the base priority of the task, and the task keeps running with
+    /// a higher priority inherited from the locks it holds, if any.
+    pub fn set_task_priority(&mut self, task: &AxTaskRef, prio: isize) -> bool {
+        let inherited = task.inherited_prios().lock();
+        let effective = inherited.iter().map(|&(_, p)| p).fold(prio, isize::min);
+        if self.apply_priority(task, effective) {
+            task.set_base_priority(prio);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Set the priority `task` inherits from the lock at address `lock`, or
+    /// drop it if `prio` is `None`.
+    ///
+    /// The task runs with the highest of its base priority and the inherited
+    /// priorities.
+    pub fn set_inherited_priority(&mut self, task: &AxTaskRef, lock: usize, prio: Option<isize>) {
+        let mut inherited = task.inherited_prios().lock();
+        inherited.retain(|&(l, _)| l != lock);
+        if let Some(prio) = prio {
+            inherited.push((lock, prio));
+        }
+        let effective = inherited
+            .iter()
+            .map(|&(_, p)| p)
+            .fold(task.base_priority(), isize::min);
+        if effective != task.priority() {
+            self.apply_priority(task, effective);
+        }
+    }
+
+    fn apply_priority(&mut self, task: &AxTaskRef, prio: isize) -> bool {
+        if self.inner.scheduler.lock().set_priority(task, prio) {
+            task.update_priority(prio);
+            true
+        } else {
+            false
+        }
     }
 }
 
@@ -457,17 +532,29 @@ impl AxRunQueue {
         }
     }
 
+    /// Picks the next task to run from the scheduler, skipping the ones
+    /// whose task groups are throttled.
+    fn pick_next_task(&mut self) -> Option<AxTaskRef> {
+        #[cfg(feature = "task_group")]
+        loop {
+            let task = self.scheduler.lock().pick_next_task()?;
+            if let Some(task) = crate::group::park_if_throttled(task) {
+                return Some(task);
+            }
+        }
+        #[cfg(not(feature = "task_group"))]
+        self.scheduler.lock().pick_next_task()
+    }
+
     /// Core reschedule subroutine.
     /// Pick the next task to run and switch to it.
     fn resched(&mut self) {
-        let next = self
-            .scheduler
-            .lock()
-            .pick_next_task()
-            .unwrap_or_else(|| unsafe {
-                // Safety: IRQs must be disabled at this time.
-                IDLE_TASK.current_ref_raw().get_unchecked().clone()
-            });
+        let next = self.pick_next_task().unwrap_or_else(|| unsafe {
+            // Safety: IRQs must be disabled at this time.
+            IDLE_TASK.current_ref_raw().get_unchecked().clone()
+        });
+        #[cfg(feature = "watchdog")]
+        crate::watchdog::on_resched(&next);
         assert!(
             next.is_ready(),
             "next {} is not ready: {:?}",
@@ -495,6 +582,10 @@ impl AxRunQueue {
         if prev_task.ptr_eq(&next_task) {
             return;
         }
+        #[cfg(feature = "sched_trace")]
+        crate::trace::on_switch(prev_task.as_task_ref(), &next_task);
+        #[cfg(feature = "task_group")]
+        crate::group::on_switch(prev_task.as_task_ref(), &next_task);
 
         // Task must be scheduled atomically, wait for next task's scheduling process to complete.
         // If the owning (remote) CPU is still in the middle of schedule() with
@@ -1,12 +1,13 @@
 use alloc::{boxed::Box, string::String, sync::Arc};
 use core::ops::Deref;
-use core::sync::atomic::{AtomicBool, AtomicI32, AtomicU64, AtomicU8, Ordering};
+use core::sync::atomic::{
+    AtomicBool, AtomicI32, AtomicIsize, AtomicU64, AtomicU8, AtomicUsize, Ordering,
+};
 use core::{alloc::Layout, cell::UnsafeCell, fmt, ptr::NonNull};
 
-#[cfg(feature = "smp")]
-use alloc::sync::Weak;
-#[cfg(feature = "preempt")]
-use core::sync::atomic::AtomicUsize;
+#[cfg(feature = "task_list")]
+use alloc::collections::BTreeMap;
+use alloc::{sync::Weak, vec::Vec};
 
 use kspin::SpinNoIrq;
 use memory_addr::{align_up_4k, VirtAddr};
@@ -18,6 +19,11 @@ use axhal::tls::TlsArea;
 use crate::task_ext::AxTaskExt;
 use crate::{AxCpuMask, AxTask, AxTaskRef, WaitQueue};
 
+/// All tasks that have not been dropped, by their IDs in the order of
+/// creation. A task removes itself when it is dropped.
+#[cfg(feature = "task_list")]
+static ALL_TASKS: SpinNoIrq<BTreeMap<u64, Weak<AxTask>>> = SpinNoIrq::new(BTreeMap::new());
+
 /// A unique identifier for a thread.
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub struct TaskId(u64);
@@ -25,7 +31,7 @@ pub struct TaskId(u64);
 /// The possible states of a task.
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
-pub(crate) enum TaskState {
+pub enum TaskState {
     /// Task is running on some CPU.
     Running = 1,
     /// Task is ready to run on some scheduler's ready queue.
@@ -50,8 +56,30 @@ pub struct TaskInner {
     /// CPU affinity mask.
     cpumask: SpinNoIrq<AxCpuMask>,
 
+    /// The effective priority last accepted by the scheduler.
+    prio: AtomicIsize,
+    /// The priority set for the task, without inheritance.
+    base_prio: AtomicIsize,
+    /// The priorities inherited from the locks held by the task, keyed by
+    /// the addresses of the locks.
+    inherited_prios: SpinNoIrq<Vec<(usize, isize)>>,
+
     /// Mark whether the task is in the wait queue.
     in_wait_queue: AtomicBool,
+    /// The address the task is waiting on in a futex wait queue.
+    futex_key: AtomicUsize,
+    /// The monotonic time (in nanoseconds) when the task was last blocked in
+    /// a wait queue.
+    #[cfg(feature = "watchdog")]
+    blocked_since: AtomicU64,
+
+    /// The task group the task belongs to.
+    #[cfg(feature = "task_group")]
+    group: SpinNoIrq<Option<Arc<crate::group::TaskGroup>>>,
+    /// The monotonic time (in nanoseconds) when the task was last charged
+    /// to its group.
+    #[cfg(feature = "task_group")]
+    exec_start: AtomicU64,
 
     /// Used to indicate whether the task is running on a CPU.
     #[cfg(feature = "smp")]
@@ -198,6 +226,23 @@ impl TaskInner {
         }
     }
 
+    /// Returns the size of the kernel stack, or `None` if the task runs on
+    /// the boot stack.
+    #[inline]
+    pub fn kernel_stack_size(&self) -> Option<usize> {
+        self.kstack.as_ref().map(|s| s.layout.size())
+    }
+
+    /// Returns the deepest usage of the kernel stack so far, in bytes.
+    ///
+    /// It is measured by checking how much of the stack has been overwritten
+    /// since it was painted on allocation. Returns `None` if the task runs on
+    /// the boot stack.
+    #[cfg(feature = "stack_usage")]
+    pub fn kernel_stack_max_usage(&self) -> Option<usize> {
+        self.kstack.as_ref().map(|s| s.max_usage())
+    }
+
     /// Gets the cpu affinity mask of the task.
     ///
     /// Returns the cpu affinity mask of the task in type [`AxCpuMask`].
@@ -214,6 +259,35 @@ impl TaskInner {
     pub fn set_cpumask(&self, cpumask: AxCpuMask) {
         *self.cpumask.lock() = cpumask
     }
+
+    /// Gets the current state of the task.
+    #[inline]
+    pub fn state(&self) -> TaskState {
+        self.state.load(Ordering::Acquire).into()
+    }
+
+    /// Gets the effective priority of the task.
+    ///
+    /// It is the last priority accepted by the scheduler, which is `0` by
+    /// default. As with the nice value, a smaller number means a higher
+    /// priority. It may be temporarily raised by priority inheritance.
+    #[inline]
+    pub fn priority(&self) -> isize {
+        self.prio.load(Ordering::Acquire)
+    }
+
+    /// Gets the base priority of the task, i.e., the last priority set for
+    /// it, without the priorities inherited from the locks it holds.
+    #[inline]
+    pub fn base_priority(&self) -> isize {
+        self.base_prio.load(Ordering::Acquire)
+    }
+
+    /// Gets the task group the task belongs to.
+    #[cfg(feature = "task_group")]
+    pub fn group(&self) -> Option<Arc<crate::group::TaskGroup>> {
+        self.group.lock().clone()
+    }
 }
 
 // private methods
@@ -228,7 +302,17 @@ impl TaskInner {
             state: AtomicU8::new(TaskState::Ready as u8),
             // By default, the task is allowed to run on all CPUs.
             cpumask: SpinNoIrq::new(AxCpuMask::full()),
+            prio: AtomicIsize::new(0),
+            base_prio: AtomicIsize::new(0),
+            inherited_prios: SpinNoIrq::new(Vec::new()),
             in_wait_queue: AtomicBool::new(false),
+            futex_key: AtomicUsize::new(0),
+            #[cfg(feature = "watchdog")]
+            blocked_since: AtomicU64::new(0),
+            #[cfg(feature = "task_group")]
+            group: SpinNoIrq::new(None),
+            #[cfg(feature = "task_group")]
+            exec_start: AtomicU64::new(0),
             #[cfg(feature = "irq")]
             timer_ticket_id: AtomicU64::new(0),
             #[cfg(feature = "smp")]
@@ -269,12 +353,14 @@ impl TaskInner {
     }
 
     pub(crate) fn into_arc(self) -> AxTaskRef {
-        Arc::new(AxTask::new(self))
-    }
-
-    #[inline]
-    pub(crate) fn state(&self) -> TaskState {
-        self.state.load(Ordering::Acquire).into()
+        let task = Arc::new(AxTask::new(self));
+        #[cfg(feature = "task_list")]
+        ALL_TASKS
+            .lock()
+            .insert(task.id().as_u64(), Arc::downgrade(&task));
+        #[cfg(feature = "sched_trace")]
+        crate::trace::on_spawn(&task);
+        task
     }
 
     #[inline]
@@ -317,6 +403,21 @@ impl TaskInner {
         self.is_idle
     }
 
+    #[inline]
+    pub(crate) fn update_priority(&self, prio: isize) {
+        self.prio.store(prio, Ordering::Release)
+    }
+
+    #[inline]
+    pub(crate) fn set_base_priority(&self, prio: isize) {
+        self.base_prio.store(prio, Ordering::Release)
+    }
+
+    #[inline]
+    pub(crate) fn inherited_prios(&self) -> &SpinNoIrq<Vec<(usize, isize)>> {
+        &self.inherited_prios
+    }
+
     #[inline]
     pub(crate) fn in_wait_queue(&self) -> bool {
         self.in_wait_queue.load(Ordering::Acquire)
@@ -327,6 +428,50 @@ impl TaskInner {
         self.in_wait_queue.store(in_wait_queue, Ordering::Release);
     }
 
+    #[inline]
+    pub(crate) fn futex_key(&self) -> usize {
+        self.futex_key.load(Ordering::Acquire)
+    }
+
+    #[inline]
+    pub(crate) fn set_futex_key(&self, key: usize) {
+        self.futex_key.store(key, Ordering::Release);
+    }
+
+    #[inline]
+    #[cfg(feature = "watchdog")]
+    pub(crate) fn blocked_since(&self) -> u64 {
+        self.blocked_since.load(Ordering::Acquire)
+    }
+
+    #[inline]
+    #[cfg(feature = "watchdog")]
+    pub(crate) fn set_blocked_since(&self, nanos: u64) {
+        self.blocked_since.store(nanos, Ordering::Release);
+    }
+
+    #[inline]
+    #[cfg(feature = "task_group")]
+    pub(crate) fn group_ref(&self) -> &SpinNoIrq<Option<Arc<crate::group::TaskGroup>>> {
+        &self.group
+    }
+
+    #[inline]
+    #[cfg(feature = "task_group")]
+    pub(crate) fn exec_start(&self) -> &AtomicU64 {
+        &self.exec_start
+    }
+
+    /// Returns the address range of the kernel stack, if the task has its
+    /// own stack.
+    #[inline]
+    #[cfg(feature = "watchdog")]
+    pub(crate) fn kernel_stack_range(&self) -> Option<core::ops::Range<usize>> {
+        self.kstack
+            .as_ref()
+            .map(|s| s.bottom().as_usize()..s.top().as_usize())
+    }
+
     /// Returns task's current timer ticket ID.
     #[inline]
     #[cfg(feature = "irq")]
@@ -469,9 +614,47 @@ impl fmt::Debug for TaskInner {
 impl Drop for TaskInner {
     fn drop(&mut self) {
         debug!("task drop: {}", self.id_name());
+        #[cfg(feature = "task_list")]
+        ALL_TASKS.lock().remove(&self.id.as_u64());
     }
 }
 
+/// Returns all tasks that have not been dropped, in the order of creation.
+///
+/// The list is copied under the lock, so that the caller can inspect the
+/// tasks without blocking others from spawning or dropping tasks.
+#[cfg(feature = "task_list")]
+pub(crate) fn all_tasks() -> Vec<AxTaskRef> {
+    ALL_TASKS
+        .lock()
+        .values()
+        .filter_map(Weak::upgrade)
+        .collect()
+}
+
+/// Calls `f` on each task that has not been dropped, in the order of
+/// creation, with the task list locked.
+///
+/// Unlike [`all_tasks`], it neither allocates nor takes references to the
+/// tasks, so that no task can be freed by the caller, e.g., an IRQ handler.
+#[cfg(feature = "watchdog")]
+pub(crate) fn for_each_task(mut f: impl FnMut(&AxTask)) {
+    for task in ALL_TASKS.lock().values() {
+        // Safety: a task being dropped removes itself from the list under the
+        // lock before its fields are dropped, and the weak reference keeps it
+        // allocated.
+        f(unsafe { &*task.as_ptr() });
+    }
+}
+
+/// The pattern to paint new task stacks with, to measure their usage.
+#[cfg(feature = "stack_usage")]
+const STACK_PAINT: usize = 0x5a5a_5a5a_5a5a_5a5a_u64 as usize;
+
+/// Warn when a task exits having used more than this percentage of its stack.
+#[cfg(feature = "stack_usage")]
+pub(crate) static STACK_USAGE_WARN_PERCENT: AtomicUsize = AtomicUsize::new(90);
+
 struct TaskStack {
     ptr: NonNull<u8>,
     layout: Layout,
@@ -480,12 +663,42 @@ struct TaskStack {
 impl TaskStack {
     pub fn alloc(size: usize) -> Self {
         let layout = Layout::from_size_align(size, 16).unwrap();
-        Self {
+        let stack = Self {
             ptr: NonNull::new(unsafe { alloc::alloc::alloc(layout) }).unwrap(),
             layout,
+        };
+        #[cfg(feature = "stack_usage")]
+        stack.paint();
+        stack
+    }
+
+    /// Fills the whole stack with [`STACK_PAINT`].
+    #[cfg(feature = "stack_usage")]
+    fn paint(&self) {
+        let words = self.layout.size() / core::mem::size_of::<usize>();
+        let ptr = self.ptr.as_ptr() as *mut usize;
+        for i in 0..words {
+            unsafe { ptr.add(i).write_volatile(STACK_PAINT) };
         }
     }
 
+    /// Returns the number of bytes below the top that have been overwritten
+    /// since the stack was painted.
+    #[cfg(feature = "stack_usage")]
+    pub fn max_usage(&self) -> usize {
+        let words = self.layout.size() / core::mem::size_of::<usize>();
+        let ptr = self.ptr.as_ptr() as *const usize;
+        // The stack grows downwards, find the lowest overwritten word.
+        let untouched = (0..words)
+            .take_while(|&i| unsafe { ptr.add(i).read_volatile() } == STACK_PAINT)
+            .count();
+        (words - untouched) * core::mem::size_of::<usize>()
+    }
+
+    pub fn bottom(&self) -> VirtAddr {
+        VirtAddr::from(self.ptr.as_ptr() as usize)
+    }
+
     pub const fn top(&self) -> VirtAddr {
         unsafe { core::mem::transmute(self.ptr.as_ptr().add(self.layout.size())) }
     }
@@ -1,7 +1,7 @@
-use core::sync::atomic::{AtomicUsize, Ordering};
+use core::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
 use std::sync::{Mutex, Once};
 
-use crate::{api as axtask, current, WaitQueue};
+use crate::{api as axtask, current, FutexError, WaitQueue};
 
 static INIT: Once = Once::new();
 static SERIAL: Mutex<()> = Mutex::new(());
@@ -128,3 +128,293 @@ fn test_task_join() {
         assert_eq!(tasks[i].join(), Some(i as _));
     }
 }
+
+#[test]
+fn test_futex() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    const NUM_TASKS: usize = 10;
+
+    static FUTEX1: AtomicU32 = AtomicU32::new(0);
+    static FUTEX2: AtomicU32 = AtomicU32::new(0);
+    static FINISHED_TASKS: AtomicUsize = AtomicUsize::new(0);
+
+    assert_eq!(
+        crate::futex_wait(&FUTEX1, 1, None),
+        Err(FutexError::WouldBlock)
+    );
+    // timed waits never block forever without `irq`
+    #[cfg(not(feature = "irq"))]
+    assert_eq!(
+        crate::futex_wait(&FUTEX1, 0, Some(core::time::Duration::from_millis(1))),
+        Err(FutexError::Unsupported)
+    );
+
+    for _ in 0..NUM_TASKS {
+        axtask::spawn(|| {
+            while FUTEX1.load(Ordering::Acquire) == 0 {
+                let _ = crate::futex_wait(&FUTEX1, 0, None);
+            }
+            FINISHED_TASKS.fetch_add(1, Ordering::Relaxed);
+        });
+    }
+    axtask::yield_now(); // let all tasks block on FUTEX1
+
+    // Move all waiters to FUTEX2, then wake them up from there.
+    assert_eq!(
+        crate::futex_requeue(&FUTEX1, Some(1), 0, &FUTEX2, usize::MAX),
+        Err(FutexError::WouldBlock)
+    );
+    assert_eq!(
+        crate::futex_requeue(&FUTEX1, Some(0), 0, &FUTEX2, usize::MAX),
+        Ok(NUM_TASKS)
+    );
+    assert_eq!(crate::futex_wake(&FUTEX1, usize::MAX), 0);
+    FUTEX1.store(1, Ordering::Release);
+    assert_eq!(crate::futex_wake(&FUTEX2, usize::MAX), NUM_TASKS);
+
+    while FINISHED_TASKS.load(Ordering::Relaxed) < NUM_TASKS {
+        axtask::yield_now();
+    }
+}
+
+#[test]
+#[cfg(feature = "stack_usage")]
+fn test_stack_usage() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    const DEPTH: usize = 32;
+    const FRAME_SIZE: usize = 256;
+    static SHALLOW: AtomicUsize = AtomicUsize::new(0);
+    static DEEP: AtomicUsize = AtomicUsize::new(0);
+
+    #[inline(never)]
+    fn recurse(depth: usize) -> usize {
+        let frame = core::hint::black_box([depth as u8; FRAME_SIZE]);
+        match depth {
+            0 => frame[0] as usize,
+            _ => recurse(depth - 1) + frame[FRAME_SIZE - 1] as usize,
+        }
+    }
+
+    let task = axtask::spawn_raw(
+        || {
+            let usage = || current().kernel_stack_max_usage().unwrap();
+            SHALLOW.store(usage(), Ordering::Relaxed);
+            assert_eq!(recurse(DEPTH), (1..=DEPTH).sum::<usize>());
+            DEEP.store(usage(), Ordering::Relaxed);
+        },
+        "stack_usage".into(),
+        0x10000,
+    );
+    task.join();
+
+    // The high-water mark grows with the recursion, and is kept after it
+    // returns.
+    let shallow = SHALLOW.load(Ordering::Relaxed);
+    let deep = DEEP.load(Ordering::Relaxed);
+    assert!(shallow > 0);
+    assert!(deep >= shallow + DEPTH * FRAME_SIZE);
+    assert!(deep <= 0x10000);
+    assert!(task.kernel_stack_max_usage().unwrap() >= deep);
+}
+
+#[test]
+#[cfg(feature = "watchdog")]
+fn test_hung_task() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    const THRESHOLD: u64 = 1_000_000; // 1ms
+    static WQ: WaitQueue = WaitQueue::new();
+
+    let task = axtask::spawn(|| WQ.wait());
+    axtask::yield_now(); // let the task block on WQ
+    assert_eq!(task.state(), crate::TaskState::Blocked);
+    assert!(task.in_wait_queue());
+
+    // A reported task has its blocked time reset, to be reported again after
+    // another threshold period.
+    let since = task.blocked_since();
+    crate::watchdog::check_hung_tasks(since + THRESHOLD - 1, THRESHOLD);
+    assert_eq!(task.blocked_since(), since);
+    crate::watchdog::check_hung_tasks(since + THRESHOLD, THRESHOLD);
+    assert_eq!(task.blocked_since(), since + THRESHOLD);
+    crate::watchdog::check_hung_tasks(since + THRESHOLD * 3 / 2, THRESHOLD);
+    assert_eq!(task.blocked_since(), since + THRESHOLD);
+
+    WQ.notify_one(true);
+    task.join();
+}
+
+#[test]
+#[cfg(feature = "sched_trace")]
+fn test_sched_trace() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    assert!(!crate::trace::is_enabled()); // off until started
+    crate::trace::clear();
+    crate::trace::start();
+    let task = axtask::spawn_raw(axtask::yield_now, "traced".into(), 0x1000);
+    task.join();
+    crate::trace::stop();
+
+    let mut dump = String::new();
+    crate::trace::dump_chrome_trace(&mut dump).unwrap();
+    crate::trace::clear();
+    let json: serde_json::Value = serde_json::from_str(&dump).unwrap();
+    let events = json["traceEvents"].as_array().unwrap();
+    let task_name = format!("Task({}, traced)", task.id().as_u64());
+    let has_event = |ph: &str, name: &str| {
+        events
+            .iter()
+            .any(|event| event["ph"] == ph && event["name"] == name)
+    };
+    assert!(has_event("i", &format!("spawn {}", task_name)));
+    assert!(has_event("X", &task_name)); // switched in and out
+    assert!(has_event("i", &format!("exit {}", task_name)));
+}
+
+#[test]
+#[cfg(feature = "task_group")]
+fn test_task_group() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    use core::sync::atomic::AtomicBool;
+    use core::time::Duration;
+
+    const QUOTA: Duration = Duration::from_millis(10);
+    static RUNS: AtomicUsize = AtomicUsize::new(0);
+    static STOP: AtomicBool = AtomicBool::new(false);
+
+    let group = crate::TaskGroup::new("test");
+    group.set_bandwidth(Some(QUOTA), crate::group::DEFAULT_PERIOD);
+    let task = axtask::spawn(|| {
+        while !STOP.load(Ordering::Acquire) {
+            RUNS.fetch_add(1, Ordering::Relaxed);
+            axtask::yield_now();
+        }
+    });
+    axtask::set_task_priority(&task, 5);
+    let base_prio = task.base_priority();
+    group.add_task(&task);
+    assert_eq!(group.members().len(), 1);
+    axtask::yield_now();
+    let runs = RUNS.load(Ordering::Relaxed);
+    assert!(runs > 0);
+
+    // A throttled member is parked until a new period starts.
+    assert!(!group.charge(QUOTA.as_nanos() as u64 - 1));
+    assert!(group.charge(1));
+    for _ in 0..10 {
+        axtask::yield_now();
+    }
+    assert_eq!(RUNS.load(Ordering::Relaxed), runs);
+    group.set_bandwidth(Some(QUOTA), crate::group::DEFAULT_PERIOD);
+    assert!(!group.is_throttled());
+    axtask::yield_now();
+    assert!(RUNS.load(Ordering::Relaxed) > runs);
+
+    // The weight overrides the priorities of the members, and a member that
+    // leaves the group gets back its priority before joining.
+    #[cfg(feature = "sched_cfs")]
+    {
+        assert_eq!(base_prio, 5);
+        assert_eq!(task.priority(), 0); // the default weight
+        group.set_weight(110);
+        assert_eq!(task.priority(), 10);
+    }
+    assert!(group.remove_task(&task));
+    assert!(!group.remove_task(&task));
+    assert!(group.members().is_empty());
+    assert_eq!(task.priority(), base_prio);
+    assert_eq!(task.base_priority(), base_prio);
+
+    STOP.store(true, Ordering::Release);
+    task.join();
+}
+
+#[test]
+#[cfg(feature = "irq")]
+fn test_timer() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    use core::time::Duration;
+    use kernel_guard::NoPreemptIrqSave;
+
+    /// Expires the timer events at `now`, and lets the timer task run the
+    /// callbacks.
+    fn fire_at(now_ms: u64) {
+        {
+            let _guard = NoPreemptIrqSave::new();
+            while crate::timers::expire_one(Duration::from_millis(now_ms)) {}
+        }
+        axtask::yield_now();
+    }
+
+    static ONESHOT: AtomicUsize = AtomicUsize::new(0);
+    static PERIODIC: AtomicUsize = AtomicUsize::new(0);
+    static CANCELLED: AtomicUsize = AtomicUsize::new(0);
+    static DROPPED: AtomicUsize = AtomicUsize::new(0);
+    let count = |calls: &AtomicUsize| calls.load(Ordering::Acquire);
+
+    let oneshot = crate::Timer::new(|| {
+        ONESHOT.fetch_add(1, Ordering::AcqRel);
+    });
+    let periodic = crate::Timer::new(|| {
+        PERIODIC.fetch_add(1, Ordering::AcqRel);
+    });
+    let cancelled = crate::Timer::new(|| {
+        CANCELLED.fetch_add(1, Ordering::AcqRel);
+    });
+    let dropped = crate::Timer::new(|| {
+        DROPPED.fetch_add(1, Ordering::AcqRel);
+    });
+
+    // All timers are based on the fake time passed to `fire_at`.
+    oneshot.arm_oneshot(Duration::from_millis(10));
+    periodic.arm(Duration::from_millis(10), Some(Duration::from_millis(10)));
+    cancelled.arm_oneshot(Duration::from_millis(10));
+    dropped.arm(Duration::from_millis(10), Some(Duration::from_millis(10)));
+    assert_eq!(periodic.interval(), Some(Duration::from_millis(10)));
+
+    // Cancelled before firing.
+    assert!(cancelled.cancel());
+    assert!(!cancelled.cancel());
+    assert!(!cancelled.is_armed());
+
+    fire_at(9);
+    assert_eq!((count(&ONESHOT), count(&PERIODIC)), (0, 0));
+
+    fire_at(10);
+    assert_eq!((count(&ONESHOT), count(&PERIODIC)), (1, 1));
+    assert!(!oneshot.is_armed());
+    assert_eq!(periodic.deadline(), Some(Duration::from_millis(20)));
+
+    // One-shot timers fire only once, and periodic ones skip the periods
+    // missed.
+    fire_at(35);
+    assert_eq!((count(&ONESHOT), count(&PERIODIC)), (1, 2));
+    assert_eq!(periodic.deadline(), Some(Duration::from_millis(40)));
+    fire_at(40);
+    assert_eq!((count(&ONESHOT), count(&PERIODIC)), (1, 3));
+    assert_eq!(count(&CANCELLED), 0);
+
+    // Dropping (e.g., `timer_delete`) a timer whose callback is queued runs
+    // the callback once, and stops the timer.
+    {
+        let _guard = NoPreemptIrqSave::new();
+        while crate::timers::expire_one(Duration::from_millis(50)) {}
+    }
+    drop(dropped);
+    axtask::yield_now();
+    assert_eq!(count(&DROPPED), 4);
+    fire_at(60);
+    assert_eq!(count(&DROPPED), 4);
+    assert_eq!(count(&PERIODIC), 5);
+}
@@ -1,17 +1,34 @@
-use core::sync::atomic::{AtomicU64, Ordering};
+use alloc::{boxed::Box, collections::VecDeque, sync::Arc};
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use core::time::Duration;
 
-use kernel_guard::NoOp;
+use kernel_guard::{NoOp, NoPreemptIrqSave};
+use kspin::SpinNoIrq;
 use lazyinit::LazyInit;
 use timer_list::{TimeValue, TimerEvent, TimerList};
 
 use axhal::time::wall_time;
 
-use crate::{select_run_queue, AxTaskRef};
+use crate::{select_run_queue, AxTaskRef, WaitQueue};
 
 static TIMER_TICKET_ID: AtomicU64 = AtomicU64::new(1);
 
 percpu_static! {
-    TIMER_LIST: LazyInit<TimerList<TaskWakeupEvent>> = LazyInit::new(),
+    TIMER_LIST: LazyInit<TimerList<AxTimerEvent>> = LazyInit::new(),
+}
+
+enum AxTimerEvent {
+    Wakeup(TaskWakeupEvent),
+    Timer(TimerFireEvent),
+}
+
+impl TimerEvent for AxTimerEvent {
+    fn callback(self, now: TimeValue) {
+        match self {
+            Self::Wakeup(event) => event.callback(now),
+            Self::Timer(event) => event.callback(now),
+        }
+    }
 }
 
 struct TaskWakeupEvent {
@@ -35,27 +52,45 @@ impl TimerEvent for TaskWakeupEvent {
     }
 }
 
+/// Adds an event to the timer list of the current CPU.
+///
+/// IRQs must be disabled.
+fn set_event(deadline: TimeValue, event: AxTimerEvent) {
+    // Safety: IRQs are disabled, so the timer list is not accessed by
+    // `check_events()` at the same time.
+    unsafe { TIMER_LIST.current_ref_mut_raw() }.set(deadline, event);
+}
+
 pub fn set_alarm_wakeup(deadline: TimeValue, task: AxTaskRef) {
     TIMER_LIST.with_current(|timer_list| {
         let ticket_id = TIMER_TICKET_ID.fetch_add(1, Ordering::AcqRel);
         task.set_timer_ticket(ticket_id);
-        timer_list.set(deadline, TaskWakeupEvent { ticket_id, task });
+        timer_list.set(
+            deadline,
+            AxTimerEvent::Wakeup(TaskWakeupEvent { ticket_id, task }),
+        );
     })
 }
 
 pub fn check_events() {
-    loop {
-        let now = wall_time();
-        let event = unsafe {
-            // Safety: IRQs are disabled at this time.
-            TIMER_LIST.current_ref_mut_raw()
-        }
-        .expire_one(now);
-        if let Some((_deadline, event)) = event {
-            event.callback(now);
-        } else {
-            break;
-        }
+    while expire_one(wall_time()) {}
+}
+
+/// Runs the callback of an event of the current CPU expired at `now`, returns
+/// `false` if there is none.
+///
+/// IRQs must be disabled.
+pub(crate) fn expire_one(now: TimeValue) -> bool {
+    let event = unsafe {
+        // Safety: IRQs are disabled at this time.
+        TIMER_LIST.current_ref_mut_raw()
+    }
+    .expire_one(now);
+    if let Some((_deadline, event)) = event {
+        event.callback(now);
+        true
+    } else {
+        false
     }
 }
 
@@ -64,3 +99,207 @@ pub fn init() {
         timer_list.init_once(TimerList::new());
     });
 }
+
+/// Timers expired but whose callbacks have not run yet.
+static PENDING_TIMERS: SpinNoIrq<VecDeque<Arc<TimerShared>>> = SpinNoIrq::new(VecDeque::new());
+static TIMER_TASK_WQ: WaitQueue = WaitQueue::new();
+static TIMER_TASK_STARTED: AtomicBool = AtomicBool::new(false);
+
+/// The state of a [`Timer`] shared with its pending timer events.
+struct TimerShared {
+    callback: Box<dyn Fn() + Send + Sync>,
+    /// The ticket ID of the current arming, zero if not armed. Events with a
+    /// different ticket ID are stale and ignored.
+    ticket_id: AtomicU64,
+    /// The next expiration time in nanoseconds, valid if armed.
+    deadline_ns: AtomicU64,
+    /// The period in nanoseconds, zero for one-shot timers.
+    interval_ns: AtomicU64,
+    /// Whether the timer is in `PENDING_TIMERS`.
+    pending: AtomicBool,
+}
+
+struct TimerFireEvent {
+    ticket_id: u64,
+    deadline: TimeValue,
+    interval: Option<Duration>,
+    timer: Arc<TimerShared>,
+}
+
+impl TimerEvent for TimerFireEvent {
+    fn callback(self, now: TimeValue) {
+        let timer = self.timer;
+        match self.interval {
+            Some(interval) => {
+                if timer.ticket_id.load(Ordering::Acquire) != self.ticket_id {
+                    // Cancelled or re-armed.
+                    return;
+                }
+                // Skip the periods missed, e.g., when IRQs were disabled for
+                // too long.
+                let mut next = self.deadline + interval;
+                while next <= now {
+                    next += interval;
+                }
+                timer
+                    .deadline_ns
+                    .store(next.as_nanos() as u64, Ordering::Release);
+                set_event(
+                    next,
+                    AxTimerEvent::Timer(TimerFireEvent {
+                        ticket_id: self.ticket_id,
+                        deadline: next,
+                        interval: self.interval,
+                        timer: timer.clone(),
+                    }),
+                );
+            }
+            None => {
+                if timer
+                    .ticket_id
+                    .compare_exchange(self.ticket_id, 0, Ordering::AcqRel, Ordering::Acquire)
+                    .is_err()
+                {
+                    return;
+                }
+            }
+        }
+
+        // Expirations are merged if the previous callback has not run yet.
+        if !timer.pending.swap(true, Ordering::AcqRel) {
+            PENDING_TIMERS.lock().push_back(timer);
+            TIMER_TASK_WQ.notify_one(true);
+        }
+    }
+}
+
+/// The entry of the timer task, which runs the callbacks of expired timers.
+fn timer_task_entry() {
+    loop {
+        TIMER_TASK_WQ.wait_until(|| !PENDING_TIMERS.lock().is_empty());
+        loop {
+            let Some(timer) = PENDING_TIMERS.lock().pop_front() else {
+                break;
+            };
+            timer.pending.store(false, Ordering::Release);
+            (timer.callback)();
+        }
+    }
+}
+
+/// A kernel timer that runs a callback when it expires, once or
+/// periodically.
+///
+/// Callbacks run in a dedicated timer task (named `"timer"`), which is
+/// spawned when the first timer is created. So they can block, but a slow
+/// callback delays the others. If a timer expires again before its callback
+/// has run, the expirations are merged into one call.
+///
+/// Dropping the timer cancels it.
+pub struct Timer {
+    shared: Arc<TimerShared>,
+}
+
+impl Timer {
+    /// Creates a new disarmed timer with the given callback.
+    pub fn new<F>(callback: F) -> Self
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        if !TIMER_TASK_STARTED.swap(true, Ordering::AcqRel) {
+            crate::spawn_raw(timer_task_entry, "timer".into(), axconfig::TASK_STACK_SIZE);
+        }
+        Self {
+            shared: Arc::new(TimerShared {
+                callback: Box::new(callback),
+                ticket_id: AtomicU64::new(0),
+                deadline_ns: AtomicU64::new(0),
+                interval_ns: AtomicU64::new(0),
+                pending: AtomicBool::new(false),
+            }),
+        }
+    }
+
+    /// Arms the timer to expire at `deadline` (in wall time, see
+    /// [`axhal::time::wall_time`]), then every `interval` if given.
+    ///
+    /// A previous arming is cancelled. If `deadline` has passed, the timer
+    /// expires on the next timer tick.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `interval` is zero.
+    pub fn arm(&self, deadline: TimeValue, interval: Option<Duration>) {
+        assert!(
+            interval.map_or(true, |dur| !dur.is_zero()),
+            "zero timer interval"
+        );
+        let _guard = NoPreemptIrqSave::new();
+        let ticket_id = TIMER_TICKET_ID.fetch_add(1, Ordering::AcqRel);
+        let shared = &self.shared;
+        shared
+            .deadline_ns
+            .store(deadline.as_nanos() as u64, Ordering::Release);
+        shared.interval_ns.store(
+            interval.map_or(0, |dur| dur.as_nanos() as u64),
+            Ordering::Release,
+        );
+        shared.ticket_id.store(ticket_id, Ordering::Release);
+        set_event(
+            deadline,
+            AxTimerEvent::Timer(TimerFireEvent {
+                ticket_id,
+                deadline,
+                interval,
+                timer: shared.clone(),
+            }),
+        );
+    }
+
+    /// Arms the timer to expire once at `deadline`.
+    pub fn arm_oneshot(&self, deadline: TimeValue) {
+        self.arm(deadline, None)
+    }
+
+    /// Arms the timer to expire every `interval`, starting from one interval
+    /// later.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `interval` is zero.
+    pub fn arm_periodic(&self, interval: Duration) {
+        self.arm(wall_time() + interval, Some(interval))
+    }
+
+    /// Disarms the timer.
+    ///
+    /// Returns `true` if the timer was armed. A callback already running or
+    /// about to run in the timer task is not affected.
+    pub fn cancel(&self) -> bool {
+        self.shared.ticket_id.swap(0, Ordering::AcqRel) != 0
+    }
+
+    /// Whether the timer is armed.
+    pub fn is_armed(&self) -> bool {
+        self.shared.ticket_id.load(Ordering::Acquire) != 0
+    }
+
+    /// Returns the next expiration time, or [`None`] if not armed.
+    pub fn deadline(&self) -> Option<TimeValue> {
+        let deadline = self.shared.deadline_ns.load(Ordering::Acquire);
+        self.is_armed().then(|| Duration::from_nanos(deadline))
+    }
+
+    /// Returns the period of the timer, or [`None`] if it is one-shot or not
+    /// armed.
+    pub fn interval(&self) -> Option<Duration> {
+        let interval = self.shared.interval_ns.load(Ordering::Acquire);
+        (self.is_armed() && interval != 0).then(|| Duration::from_nanos(interval))
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
@@ -0,0 +1,360 @@
+//! Scheduler event tracing.
+//!
+//! Context switches, wakeups, IRQ entries and exits, and task spawns and
+//! exits are recorded into per-CPU ring buffers, which can be dumped in the
+//! [Chrome trace event format][1] to be viewed in `chrome://tracing` or
+//! [Perfetto](https://ui.perfetto.dev). Each CPU is shown as a thread, whose
+//! slices are the tasks running on it.
+//!
+//! Recording is off at boot, so that it costs nothing but a flag check until
+//! [`start`]ed, and can be paused by [`stop`]. Each ring buffer
+//! keeps the latest [`EVENTS_PER_CPU`] events, and is written only by its own
+//! CPU with IRQs disabled, so no locks are taken.
+//!
+//! [1]: https://docs.google.com/document/d/1CvAClvFfyA5R-PhYUmn5OOQtYMH4h6I0nSsKchNAySU
+
+use alloc::collections::BTreeMap;
+use core::cell::UnsafeCell;
+use core::fmt::{self, Write};
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use axhal::time::monotonic_time_nanos;
+use kernel_guard::IrqSave;
+
+use crate::AxTaskRef;
+
+/// Number of events kept for each CPU.
+pub const EVENTS_PER_CPU: usize = 4096;
+
+/// Maximum length of the task names kept in spawn events.
+const NAME_LEN: usize = 16;
+
+#[derive(Clone, Copy)]
+enum EventKind {
+    None,
+    Switch { prev: u64, next: u64 },
+    Wakeup { task: u64, cpu: usize },
+    IrqEnter(usize),
+    IrqExit(usize),
+    Spawn { task: u64, name: [u8; NAME_LEN] },
+    Exit { task: u64, exit_code: i32 },
+}
+
+#[derive(Clone, Copy)]
+struct Event {
+    /// Monotonic time in nanoseconds.
+    ts: u64,
+    kind: EventKind,
+}
+
+struct CpuTrace {
+    events: [UnsafeCell<Event>; EVENTS_PER_CPU],
+    /// Total number of events recorded.
+    head: AtomicUsize,
+}
+
+unsafe impl Sync for CpuTrace {}
+
+impl CpuTrace {
+    const fn new() -> Self {
+        Self {
+            events: [const {
+                UnsafeCell::new(Event {
+                    ts: 0,
+                    kind: EventKind::None,
+                })
+            }; EVENTS_PER_CPU],
+            head: AtomicUsize::new(0),
+        }
+    }
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static TRACES: [CpuTrace; axconfig::SMP] = [const { CpuTrace::new() }; axconfig::SMP];
+
+fn record(kind: EventKind) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let _guard = IrqSave::new();
+    let trace = &TRACES[axhal::cpu::this_cpu_id()];
+    let head = trace.head.load(Ordering::Relaxed);
+    let event = Event {
+        ts: monotonic_time_nanos(),
+        kind,
+    };
+    // Safety: only the current CPU writes its buffer, with IRQs disabled.
+    unsafe { *trace.events[head % EVENTS_PER_CPU].get() = event };
+    trace.head.store(head + 1, Ordering::Release);
+}
+
+pub(crate) fn on_switch(prev: &AxTaskRef, next: &AxTaskRef) {
+    record(EventKind::Switch {
+        prev: prev.id().as_u64(),
+        next: next.id().as_u64(),
+    });
+}
+
+pub(crate) fn on_wakeup(task_id: u64, cpu: usize) {
+    record(EventKind::Wakeup { task: task_id, cpu });
+}
+
+pub(crate) fn on_spawn(task: &AxTaskRef) {
+    let mut name = [0; NAME_LEN];
+    let len = task.name().len().min(NAME_LEN);
+    name[..len].copy_from_slice(&task.name().as_bytes()[..len]);
+    record(EventKind::Spawn {
+        task: task.id().as_u64(),
+        name,
+    });
+}
+
+pub(crate) fn on_exit(task: &AxTaskRef, exit_code: i32) {
+    record(EventKind::Exit {
+        task: task.id().as_u64(),
+        exit_code,
+    });
+}
+
+#[cfg(feature = "irq")]
+fn on_irq(irq_num: usize, entry: bool) {
+    record(if entry {
+        EventKind::IrqEnter(irq_num)
+    } else {
+        EventKind::IrqExit(irq_num)
+    });
+}
+
+pub(crate) fn init() {
+    #[cfg(feature = "irq")]
+    axhal::irq::set_irq_hook(Some(on_irq));
+}
+
+/// Starts (or resumes) recording scheduler events.
+pub fn start() {
+    ENABLED.store(true, Ordering::Release);
+}
+
+/// Stops recording scheduler events, the recorded ones are kept.
+pub fn stop() {
+    ENABLED.store(false, Ordering::Release);
+}
+
+/// Whether scheduler events are being recorded.
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Acquire)
+}
+
+/// Discards all recorded events.
+///
+/// It should be called when recording is stopped.
+pub fn clear() {
+    for trace in TRACES.iter() {
+        trace.head.store(0, Ordering::Release);
+    }
+}
+
+/// Returns the events recorded on `cpu_id`, from the oldest.
+fn cpu_events(cpu_id: usize) -> impl Iterator<Item = Event> {
+    let trace = &TRACES[cpu_id];
+    let head = trace.head.load(Ordering::Acquire);
+    // The oldest slot may be being overwritten by an event recorded after
+    // the check of `ENABLED`, skip it.
+    let count = head.min(EVENTS_PER_CPU - 1);
+    (head - count..head).map(move |i| unsafe { *trace.events[i % EVENTS_PER_CPU].get() })
+}
+
+/// A string escaped in JSON.
+struct JsonStr<'a>(&'a str);
+
+impl fmt::Display for JsonStr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for c in self.0.chars() {
+            match c {
+                '"' => f.write_str("\\\"")?,
+                '\\' => f.write_str("\\\\")?,
+                c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+                c => f.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A timestamp in microseconds, as used by the trace format.
+struct Micros(u64);
+
+impl fmt::Display for Micros {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{:03}", self.0 / 1000, self.0 % 1000)
+    }
+}
+
+/// The common fields of an event on a CPU.
+struct Common {
+    cpu_id: usize,
+    ts: u64,
+}
+
+impl fmt::Display for Common {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "\"pid\":0,\"tid\":{},\"ts\":{}",
+            self.cpu_id,
+            Micros(self.ts)
+        )
+    }
+}
+
+struct TaskNames(BTreeMap<u64, [u8; NAME_LEN]>);
+
+impl TaskNames {
+    fn get(&self, id: u64) -> TaskLabel {
+        let name = self.0.get(&id).map(|name| {
+            let len = name.iter().position(|&b| b == 0).unwrap_or(NAME_LEN);
+            // The name may be truncated in the middle of a character.
+            match core::str::from_utf8(&name[..len]) {
+                Ok(s) => s,
+                Err(e) => core::str::from_utf8(&name[..e.valid_up_to()]).unwrap(),
+            }
+        });
+        TaskLabel { id, name }
+    }
+}
+
+struct TaskLabel<'a> {
+    id: u64,
+    name: Option<&'a str>,
+}
+
+impl fmt::Display for TaskLabel<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.name {
+            Some(name) if !name.is_empty() => write!(f, "Task({}, {})", self.id, JsonStr(name)),
+            _ => write!(f, "Task({})", self.id),
+        }
+    }
+}
+
+/// Writes the recorded events in the Chrome trace event format (JSON).
+///
+/// Recording is paused while dumping. Tasks spawned before the retained
+/// events are shown by their IDs only.
+pub fn dump_chrome_trace<W: Write>(out: &mut W) -> fmt::Result {
+    let enabled = ENABLED.swap(false, Ordering::AcqRel);
+    let res = write_chrome_trace(out);
+    if enabled {
+        start();
+    }
+    res
+}
+
+fn write_chrome_trace<W: Write>(out: &mut W) -> fmt::Result {
+    let mut names = TaskNames(BTreeMap::new());
+    for cpu_id in 0..axconfig::SMP {
+        for event in cpu_events(cpu_id) {
+            if let EventKind::Spawn { task, name } = event.kind {
+                names.0.insert(task, name);
+            }
+        }
+    }
+
+    out.write_str("{\"traceEvents\":[\n")?;
+    out.write_str(r#"{"name":"process_name","ph":"M","pid":0,"args":{"name":"ArceOS"}}"#)?;
+    for cpu_id in 0..axconfig::SMP {
+        write!(
+            out,
+            ",\n{{\"name\":\"thread_name\",\"ph\":\"M\",\"pid\":0,\"tid\":{},\"args\":{{\"name\":\"CPU {}\"}}}}",
+            cpu_id, cpu_id
+        )?;
+
+        // The running task and since when.
+        let mut running: Option<(u64, u64)> = None;
+        let mut first_ts = None::<u64>;
+        let mut last_ts = 0;
+        let mut irq_depth = 0;
+        for event in cpu_events(cpu_id) {
+            let ts = event.ts;
+            first_ts.get_or_insert(ts);
+            last_ts = ts;
+            let common = Common { cpu_id, ts };
+            match event.kind {
+                EventKind::None => {}
+                EventKind::Switch { prev, next } => {
+                    let (task, start) = running.unwrap_or((prev, first_ts.unwrap()));
+                    write_slice(out, cpu_id, names.get(task), start, ts)?;
+                    running = Some((next, ts));
+                }
+                EventKind::Wakeup { task, cpu } => write!(
+                    out,
+                    ",\n{{\"name\":\"wakeup {}\",\"ph\":\"i\",\"s\":\"t\",{},\"args\":{{\"target_cpu\":{}}}}}",
+                    names.get(task),
+                    common,
+                    cpu
+                )?,
+                EventKind::IrqEnter(irq) => {
+                    irq_depth += 1;
+                    write!(out, ",\n{{\"name\":\"IRQ {}\",\"ph\":\"B\",{}}}", irq, common)?;
+                }
+                EventKind::IrqExit(irq) => {
+                    // Skip the exits whose entries are not retained.
+                    if irq_depth > 0 {
+                        irq_depth -= 1;
+                        write!(out, ",\n{{\"name\":\"IRQ {}\",\"ph\":\"E\",{}}}", irq, common)?;
+                    }
+                }
+                EventKind::Spawn { task, .. } => write!(
+                    out,
+                    ",\n{{\"name\":\"spawn {}\",\"ph\":\"i\",\"s\":\"t\",{}}}",
+                    names.get(task),
+                    common
+                )?,
+                EventKind::Exit { task, exit_code } => write!(
+                    out,
+                    ",\n{{\"name\":\"exit {}\",\"ph\":\"i\",\"s\":\"t\",{},\"args\":{{\"exit_code\":{}}}}}",
+                    names.get(task),
+                    common,
+                    exit_code
+                )?,
+            }
+        }
+        if let Some((task, start)) = running {
+            write_slice(out, cpu_id, names.get(task), start, last_ts)?;
+        }
+    }
+    out.write_str("\n]}\n")
+}
+
+/// Writes a complete event for a task running from `start` to `end`.
+fn write_slice<W: Write>(
+    out: &mut W,
+    cpu_id: usize,
+    task: TaskLabel,
+    start: u64,
+    end: u64,
+) -> fmt::Result {
+    write!(
+        out,
+        ",\n{{\"name\":\"{}\",\"ph\":\"X\",\"pid\":0,\"tid\":{},\"ts\":{},\"dur\":{}}}",
+        task,
+        cpu_id,
+        Micros(start),
+        Micros(end - start)
+    )
+}
+
+/// Writes the recorded events in the Chrome trace event format to the
+/// console.
+pub fn dump_to_console() {
+    struct Console;
+
+    impl Write for Console {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            axhal::console::write_bytes(s.as_bytes());
+            Ok(())
+        }
+    }
+
+    dump_chrome_trace(&mut Console).unwrap();
+}
@@ -164,6 +164,63 @@ impl WaitQueue {
         timeout
     }
 
+    /// Blocks the current task and put it into the wait queue if `condition`
+    /// is true when checked with the wait queue locked, until other tasks
+    /// notify it, or the optional `deadline` has been reached.
+    ///
+    /// Returns `None` if the task is not blocked, otherwise returns whether it
+    /// has timed out. Unlike other waits, the task is not removed from the wait
+    /// queue on timeout, as it may have been moved to another wait queue in
+    /// the meantime. The caller should remove it by
+    /// [`cancel_wait`](Self::cancel_wait) on the right wait queue.
+    pub(crate) fn wait_if<F>(
+        &self,
+        condition: F,
+        _deadline: Option<axhal::time::TimeValue>,
+    ) -> Option<bool>
+    where
+        F: FnOnce(&CurrentTask) -> bool,
+    {
+        let mut rq = current_run_queue::<NoPreemptIrqSave>();
+        let curr = crate::current();
+        let wq = self.queue.lock();
+        if !condition(&curr) {
+            return None;
+        }
+        #[cfg(feature = "irq")]
+        if let Some(deadline) = _deadline {
+            crate::timers::set_alarm_wakeup(deadline, curr.clone());
+        }
+        rq.blocked_resched(wq);
+
+        let timeout = curr.in_wait_queue(); // still in the wait queue, must have timed out
+        #[cfg(feature = "irq")]
+        if _deadline.is_some() {
+            curr.timer_ticket_expired();
+        }
+        Some(timeout)
+    }
+
+    /// Removes the current task from the wait queue after a timeout.
+    ///
+    /// Returns `false` if the task is not in this wait queue.
+    pub(crate) fn cancel_wait(&self, curr: &CurrentTask) -> bool {
+        let mut wq = self.queue.lock();
+        match wq.iter().position(|t| curr.ptr_eq(t)) {
+            Some(index) => {
+                wq.remove(index);
+                curr.set_in_wait_queue(false);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Locks the wait queue, to operate on the blocked tasks in it directly.
+    pub(crate) fn lock(&self) -> WaitQueueGuard {
+        self.queue.lock()
+    }
+
     /// Wakes up one task in the wait queue, usually the first one.
     ///
     /// If `resched` is true, the current task will be preempted when the
@@ -192,7 +249,7 @@ impl WaitQueue {
     ///
     /// If `resched` is true, the current task will be preempted when the
     /// preemption is enabled.
-    pub fn notify_task(&mut self, resched: bool, task: &AxTaskRef) -> bool {
+    pub fn notify_task(&self, resched: bool, task: &AxTaskRef) -> bool {
         let mut wq = self.queue.lock();
         if let Some(index) = wq.iter().position(|t| Arc::ptr_eq(t, task)) {
             unblock_one_task(wq.remove(index).unwrap(), resched);
@@ -203,7 +260,7 @@ impl WaitQueue {
     }
 }
 
-fn unblock_one_task(task: AxTaskRef, resched: bool) {
+pub(crate) fn unblock_one_task(task: AxTaskRef, resched: bool) {
     // Mark task as not in wait queue.
     task.set_in_wait_queue(false);
     // Select run queue by the CPU set of the task.
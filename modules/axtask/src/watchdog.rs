@@ -0,0 +1,280 @@
+//! Soft-lockup and hung-task detector.
+//!
+//! It is driven by the timer IRQ on each CPU, and reports:
+//!
+//! - *Soft lockups*: a CPU that has not run the scheduler for longer than the
+//!   soft-lockup threshold, e.g., a task spinning with preemption disabled,
+//!   or a CPU-bound task that never yields under a cooperative scheduler.
+//!   The current task and a backtrace of its kernel stack are printed.
+//! - *Hard lockups*: a CPU whose timer IRQ has not arrived for longer than
+//!   the soft-lockup threshold, e.g., spinning in a [`kspin::SpinNoIrq`]. It
+//!   is detected by the other CPUs, which only know the ID of the task last
+//!   scheduled on it.
+//! - *Hung tasks*: tasks blocked in a [`WaitQueue`](crate::WaitQueue) for
+//!   longer than the hung-task threshold. Their saved context and a
+//!   backtrace unwound from it are printed.
+//!
+//! Each soft or hard lockup is reported once until the CPU schedules again,
+//! a hung task is reported again every threshold period while still blocked.
+//!
+//! The backtrace is built by following the frame pointers, so it is only
+//! meaningful if the kernel is compiled with `-C force-frame-pointers=yes`.
+
+use core::ops::Range;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use core::time::Duration;
+
+use axhal::arch::TaskContext;
+use axhal::time::{monotonic_time_nanos, NANOS_PER_SEC};
+
+use crate::task::TaskState;
+use crate::AxTaskRef;
+
+const DEFAULT_SOFTLOCKUP_THRESHOLD_SECS: u64 = 10;
+const DEFAULT_HUNG_TASK_THRESHOLD_SECS: u64 = 60;
+
+/// Maximum number of frames printed in a backtrace.
+const MAX_BACKTRACE_FRAMES: usize = 32;
+
+static SOFTLOCKUP_THRESHOLD_NS: AtomicU64 =
+    AtomicU64::new(DEFAULT_SOFTLOCKUP_THRESHOLD_SECS * NANOS_PER_SEC);
+static HUNG_TASK_THRESHOLD_NS: AtomicU64 =
+    AtomicU64::new(DEFAULT_HUNG_TASK_THRESHOLD_SECS * NANOS_PER_SEC);
+
+/// The watchdog state of a CPU, accessed by other CPUs as well.
+struct CpuWatch {
+    /// When the scheduler last ran on the CPU.
+    last_sched: AtomicU64,
+    /// When the timer IRQ last arrived on the CPU, zero if not started.
+    last_tick: AtomicU64,
+    /// ID of the task last scheduled on the CPU.
+    curr_task_id: AtomicU64,
+    soft_reported: AtomicBool,
+    hard_reported: AtomicBool,
+}
+
+static CPU_WATCH: [CpuWatch; axconfig::SMP] = [const {
+    CpuWatch {
+        last_sched: AtomicU64::new(0),
+        last_tick: AtomicU64::new(0),
+        curr_task_id: AtomicU64::new(0),
+        soft_reported: AtomicBool::new(false),
+        hard_reported: AtomicBool::new(false),
+    }
+}; axconfig::SMP];
+
+static LAST_HUNG_CHECK: AtomicU64 = AtomicU64::new(0);
+
+/// Sets the soft-lockup threshold. Zero disables the lockup detection.
+pub fn set_softlockup_threshold(dur: Duration) {
+    SOFTLOCKUP_THRESHOLD_NS.store(dur.as_nanos() as u64, Ordering::Relaxed);
+}
+
+/// Sets the hung-task threshold. Zero disables the hung-task detection.
+pub fn set_hung_task_threshold(dur: Duration) {
+    HUNG_TASK_THRESHOLD_NS.store(dur.as_nanos() as u64, Ordering::Relaxed);
+}
+
+/// Called when the scheduler runs on the current CPU, with IRQs disabled.
+pub(crate) fn on_resched(next: &AxTaskRef) {
+    let watch = &CPU_WATCH[axhal::cpu::this_cpu_id()];
+    watch
+        .last_sched
+        .store(monotonic_time_nanos(), Ordering::Relaxed);
+    watch
+        .curr_task_id
+        .store(next.id().as_u64(), Ordering::Relaxed);
+    watch.soft_reported.store(false, Ordering::Relaxed);
+}
+
+/// Called on each timer tick of the current CPU, with IRQs disabled.
+pub(crate) fn on_timer_tick() {
+    let now = monotonic_time_nanos();
+    let cpu_id = axhal::cpu::this_cpu_id();
+    let watch = &CPU_WATCH[cpu_id];
+    if watch.last_tick.swap(now, Ordering::Relaxed) == 0 {
+        // The first tick on this CPU.
+        watch.last_sched.store(now, Ordering::Relaxed);
+    }
+    watch.hard_reported.store(false, Ordering::Relaxed);
+
+    let threshold = SOFTLOCKUP_THRESHOLD_NS.load(Ordering::Relaxed);
+    if threshold != 0 {
+        check_softlockup(cpu_id, watch, now, threshold);
+        check_hard_lockups(cpu_id, now, threshold);
+    }
+
+    let threshold = HUNG_TASK_THRESHOLD_NS.load(Ordering::Relaxed);
+    if threshold != 0 && axhal::cpu::this_cpu_is_bsp() {
+        // Scan at most once per second.
+        let last = LAST_HUNG_CHECK.load(Ordering::Relaxed);
+        if now - last >= NANOS_PER_SEC {
+            LAST_HUNG_CHECK.store(now, Ordering::Relaxed);
+            check_hung_tasks(now, threshold);
+        }
+    }
+}
+
+fn check_softlockup(cpu_id: usize, watch: &CpuWatch, now: u64, threshold: u64) {
+    let stuck = now.saturating_sub(watch.last_sched.load(Ordering::Relaxed));
+    if stuck < threshold || watch.soft_reported.swap(true, Ordering::Relaxed) {
+        return;
+    }
+    let curr = crate::current();
+    error!(
+        "watchdog: BUG: soft lockup - CPU#{} stuck for {}s! [{}]",
+        cpu_id,
+        stuck / NANOS_PER_SEC,
+        curr.id_name()
+    );
+    error!("  state: {:?}", curr.state());
+    // The IRQ handler disables preemption once.
+    #[cfg(feature = "preempt")]
+    error!("  preemption enabled: {}", curr.can_preempt(1));
+    // The IRQ handler runs on the stack of the interrupted task.
+    match curr.kernel_stack_range() {
+        Some(stack) => print_backtrace(frame_pointer(), None, stack),
+        None => error!("  backtrace unavailable: not on a task stack"),
+    }
+}
+
+fn check_hard_lockups(this_cpu: usize, now: u64, threshold: u64) {
+    for (cpu_id, watch) in CPU_WATCH.iter().enumerate() {
+        if cpu_id == this_cpu {
+            continue;
+        }
+        let last_tick = watch.last_tick.load(Ordering::Relaxed);
+        if last_tick == 0 || now.saturating_sub(last_tick) < threshold {
+            continue;
+        }
+        if !watch.hard_reported.swap(true, Ordering::Relaxed) {
+            error!(
+                "watchdog: BUG: hard lockup - CPU#{} has not handled timer IRQs for {}s! [last task ID {}]",
+                cpu_id,
+                (now - last_tick) / NANOS_PER_SEC,
+                watch.curr_task_id.load(Ordering::Relaxed)
+            );
+        }
+    }
+}
+
+/// Reports the tasks blocked in a wait queue for `threshold` nanoseconds or
+/// more at `now`.
+pub(crate) fn check_hung_tasks(now: u64, threshold: u64) {
+    crate::task::for_each_task(|task| {
+        let since = task.blocked_since();
+        if task.state() == TaskState::Blocked
+            && task.in_wait_queue()
+            && now.saturating_sub(since) >= threshold
+        {
+            error!(
+                "watchdog: INFO: {} blocked for more than {}s.",
+                task.id_name(),
+                (now - since) / NANOS_PER_SEC
+            );
+            error!("  state: {:?}", task.state());
+            // Safety: the context is not changed while the task is blocked.
+            let ctx = unsafe { &*task.ctx_mut_ptr() };
+            error!("  saved context: {:x?}", ctx);
+            match task.kernel_stack_range() {
+                Some(stack) => match saved_frame(ctx, &stack) {
+                    Some((fp, pc)) => print_backtrace(fp, Some(pc), stack),
+                    None => error!("  backtrace unavailable: bad saved context"),
+                },
+                None => error!("  backtrace unavailable: no kernel stack"),
+            }
+            task.set_blocked_since(now);
+        }
+    });
+}
+
+/// Returns the frame pointer of the caller.
+#[inline(always)]
+fn frame_pointer() -> usize {
+    let fp: usize;
+    unsafe {
+        #[cfg(target_arch = "x86_64")]
+        core::arch::asm!("mov {}, rbp", out(reg) fp);
+        #[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))]
+        core::arch::asm!("mv {}, s0", out(reg) fp);
+        #[cfg(target_arch = "aarch64")]
+        core::arch::asm!("mov {}, x29", out(reg) fp);
+    }
+    fp
+}
+
+/// Returns the frame pointer and the program counter saved in the context of
+/// a task switched out, if the frame pointer is within its `stack`.
+fn saved_frame(ctx: &TaskContext, stack: &Range<usize>) -> Option<(usize, usize)> {
+    #[cfg(target_arch = "x86_64")]
+    let (fp, pc) = {
+        const WORD: usize = core::mem::size_of::<usize>();
+        // `rsp` points to the callee-saved registers pushed by the context
+        // switch (r15, r14, r13, r12, rbx, rbp), followed by the return
+        // address.
+        let rsp = ctx.rsp as usize;
+        if rsp < stack.start || rsp + 7 * WORD > stack.end {
+            return None;
+        }
+        // Safety: the saved values are inside the stack.
+        unsafe {
+            (
+                *((rsp + 5 * WORD) as *const usize),
+                *((rsp + 6 * WORD) as *const usize),
+            )
+        }
+    };
+    #[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))]
+    let (fp, pc) = (ctx.s0, ctx.ra);
+    #[cfg(target_arch = "aarch64")]
+    let (fp, pc) = (ctx.r29 as usize, ctx.lr as usize);
+    stack.contains(&fp).then_some((fp, pc))
+}
+
+/// Returns the previous frame pointer and the return address saved in the
+/// frame at `fp`.
+///
+/// # Safety
+///
+/// The saved values must be readable.
+unsafe fn unwind_frame(fp: usize) -> (usize, usize) {
+    const WORD: usize = core::mem::size_of::<usize>();
+    if cfg!(any(target_arch = "riscv32", target_arch = "riscv64")) {
+        // `fp` points to the top of the frame, below which are `ra` and the
+        // previous `fp`.
+        (
+            *((fp - 2 * WORD) as *const usize),
+            *((fp - WORD) as *const usize),
+        )
+    } else {
+        // `fp` points to the saved previous `fp`, followed by the return
+        // address.
+        (*(fp as *const usize), *((fp + WORD) as *const usize))
+    }
+}
+
+/// Prints `pc` (if any) and the return addresses by following the frame
+/// pointers from `fp`, which must be within `stack`.
+fn print_backtrace(mut fp: usize, pc: Option<usize>, stack: Range<usize>) {
+    const WORD: usize = core::mem::size_of::<usize>();
+    error!("  backtrace:");
+    if let Some(pc) = pc {
+        error!("    #0  {:#x}", pc);
+    }
+    for i in pc.is_some() as usize..MAX_BACKTRACE_FRAMES {
+        if fp % WORD != 0 || fp < stack.start + 2 * WORD || fp + 2 * WORD > stack.end {
+            break;
+        }
+        // Safety: both saved values are inside the stack.
+        let (prev_fp, ra) = unsafe { unwind_frame(fp) };
+        if ra == 0 {
+            break;
+        }
+        error!("    #{:<2} {:#x}", i, ra);
+        if prev_fp <= fp {
+            // The stack grows downwards, the caller's frame must be above.
+            break;
+        }
+        fp = prev_fp;
+    }
+}
@@ -30,6 +30,16 @@ qemu_args-$(BLK) += \
   -device virtio-blk-$(vdev-suffix),drive=disk0 \
   -drive id=disk0,if=none,format=raw,file=$(DISK_IMG)
 
+ifneq ($(INITRD),)
+  qemu_args-y += -initrd $(INITRD)
+endif
+
+ifneq ($(SHARED_DIR),)
+  qemu_args-y += \
+    -fsdev local,id=fs0,path=$(SHARED_DIR),security_model=none \
+    -device virtio-9p-$(vdev-suffix),fsdev=fs0,mount_tag=host
+endif
+
 qemu_args-$(NET) += \
   -device virtio-net-$(vdev-suffix),netdev=net0
 
@@ -39,6 +39,39 @@ int pthread_mutex_trylock(pthread_mutex_t *m)
     return 0;
 }
 
+int pthread_mutexattr_init(pthread_mutexattr_t *a)
+{
+    *a = (pthread_mutexattr_t){0};
+    return 0;
+}
+
+int pthread_mutexattr_destroy(pthread_mutexattr_t *a)
+{
+    return 0;
+}
+
+int pthread_mutexattr_getprotocol(const pthread_mutexattr_t *restrict a, int *restrict protocol)
+{
+    *protocol = a->__attr / 8U % 2;
+    return 0;
+}
+
+int pthread_mutexattr_setprotocol(pthread_mutexattr_t *a, int protocol)
+{
+    switch (protocol) {
+    case PTHREAD_PRIO_NONE:
+        a->__attr &= ~8;
+        return 0;
+    case PTHREAD_PRIO_INHERIT:
+        a->__attr |= 8;
+        return 0;
+    case PTHREAD_PRIO_PROTECT:
+        return ENOTSUP;
+    default:
+        return EINVAL;
+    }
+}
+
 // TODO
 int pthread_setname_np(pthread_t thread, const char *name)
 {
@@ -3,16 +3,12 @@
 #include <stddef.h>
 #include <stdio.h>
 
+int ax_sigaction(int signum, const struct sigaction *act, struct sigaction *oldact);
+
 int sigaction_helper(int signum, const struct sigaction *act, struct sigaction *oldact,
                      size_t sigsetsize)
 {
-    if (signum == SIGKILL || signum == SIGSTOP)
-        return -EINVAL;
-
-    if (oldact)
-        *oldact = (struct sigaction){0};
-
-    return 0;
+    return ax_sigaction(signum, act, oldact);
 }
 
 void (*signal(int signum, void (*handler)(int)))(int)
@@ -25,6 +25,7 @@ int dup3(int, int, int);
 off_t lseek(int, off_t, int);
 int fsync(int);
 int fdatasync(int);
+void sync(void);
 
 ssize_t read(int, void *, size_t);
 ssize_t write(int, const void *, size_t);
@@ -1,5 +1,5 @@
 use crate::{ctypes, utils::e};
-use arceos_posix_api::{sys_close, sys_dup, sys_dup2, sys_fcntl};
+use arceos_posix_api::{sys_close, sys_dup, sys_dup2, sys_fcntl, sys_ioctl};
 use axerrno::LinuxError;
 use core::ffi::c_int;
 
@@ -52,3 +52,9 @@ pub unsafe extern "C" fn dup3(old_fd: c_int, new_fd: c_int, flags: c_int) -> c_i
 pub unsafe extern "C" fn ax_fcntl(fd: c_int, cmd: c_int, arg: usize) -> c_int {
     e(sys_fcntl(fd, cmd, arg))
 }
+
+/// Perform a device-specific operation on a file descriptor.
+#[no_mangle]
+pub unsafe extern "C" fn ax_ioctl(fd: c_int, request: usize, arg: usize) -> c_int {
+    e(sys_ioctl(fd, request, arg))
+}
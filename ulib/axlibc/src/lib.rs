@@ -74,6 +74,7 @@ mod mktime;
 mod rand;
 mod resource;
 mod setjmp;
+mod signal;
 mod sys;
 mod time;
 mod unistd;
@@ -87,6 +88,7 @@ pub use self::mktime::mktime;
 pub use self::rand::{rand, random, srand};
 pub use self::resource::{getrlimit, setrlimit};
 pub use self::setjmp::{longjmp, setjmp};
+pub use self::signal::ax_sigaction;
 pub use self::sys::sysconf;
 pub use self::time::{clock_gettime, nanosleep};
 pub use self::unistd::{abort, exit, getpid};
@@ -97,10 +99,10 @@ pub use self::malloc::{free, malloc};
 pub use self::strftime::strftime;
 
 #[cfg(feature = "fd")]
-pub use self::fd_ops::{ax_fcntl, close, dup, dup2, dup3};
+pub use self::fd_ops::{ax_fcntl, ax_ioctl, close, dup, dup2, dup3};
 
 #[cfg(feature = "fs")]
-pub use self::fs::{ax_open, fstat, getcwd, lseek, lstat, rename, stat};
+pub use self::fs::{ax_open, fstat, getcwd, link, lseek, lstat, readlink, rename, stat, symlink};
 
 #[cfg(feature = "net")]
 pub use self::net::{
@@ -113,6 +115,9 @@ pub use self::pthread::{pthread_create, pthread_exit, pthread_join, pthread_self
 #[cfg(feature = "multitask")]
 pub use self::pthread::{pthread_mutex_init, pthread_mutex_lock, pthread_mutex_unlock};
 
+#[cfg(all(feature = "multitask", feature = "irq"))]
+pub use self::time::{alarm, timer_create, timer_delete, timer_gettime, timer_settime};
+
 #[cfg(feature = "pipe")]
 pub use self::pipe::pipe;
 
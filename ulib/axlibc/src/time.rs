@@ -19,3 +19,52 @@ pub unsafe extern "C" fn nanosleep(
 ) -> c_int {
     e(sys_nanosleep(req, rem))
 }
+
+/// Schedule a `SIGALRM` after some seconds
+#[cfg(all(feature = "multitask", feature = "irq"))]
+#[no_mangle]
+pub extern "C" fn alarm(seconds: core::ffi::c_uint) -> core::ffi::c_uint {
+    arceos_posix_api::sys_alarm(seconds)
+}
+
+/// Create a per-process timer
+#[cfg(all(feature = "multitask", feature = "irq"))]
+#[no_mangle]
+pub unsafe extern "C" fn timer_create(
+    clockid: ctypes::clockid_t,
+    sevp: *const ctypes::sigevent,
+    timerid: *mut ctypes::timer_t,
+) -> c_int {
+    e(arceos_posix_api::sys_timer_create(clockid, sevp, timerid))
+}
+
+/// Delete a per-process timer
+#[cfg(all(feature = "multitask", feature = "irq"))]
+#[no_mangle]
+pub extern "C" fn timer_delete(timerid: ctypes::timer_t) -> c_int {
+    e(arceos_posix_api::sys_timer_delete(timerid))
+}
+
+/// Arm or disarm a per-process timer
+#[cfg(all(feature = "multitask", feature = "irq"))]
+#[no_mangle]
+pub unsafe extern "C" fn timer_settime(
+    timerid: ctypes::timer_t,
+    flags: c_int,
+    new_value: *const ctypes::itimerspec,
+    old_value: *mut ctypes::itimerspec,
+) -> c_int {
+    e(arceos_posix_api::sys_timer_settime(
+        timerid, flags, new_value, old_value,
+    ))
+}
+
+/// Get the time remaining on a per-process timer
+#[cfg(all(feature = "multitask", feature = "irq"))]
+#[no_mangle]
+pub unsafe extern "C" fn timer_gettime(
+    timerid: ctypes::timer_t,
+    curr_value: *mut ctypes::itimerspec,
+) -> c_int {
+    e(arceos_posix_api::sys_timer_gettime(timerid, curr_value))
+}
@@ -1,19 +1,19 @@
 //! Inspection and manipulation of the process’s environment.
 
-#[cfg(feature = "fs")]
+#[cfg(any(feature = "fs", feature = "initramfs"))]
 extern crate alloc;
 
-#[cfg(feature = "fs")]
+#[cfg(any(feature = "fs", feature = "initramfs"))]
 use {crate::io, alloc::string::String};
 
 /// Returns the current working directory as a [`String`].
-#[cfg(feature = "fs")]
+#[cfg(any(feature = "fs", feature = "initramfs"))]
 pub fn current_dir() -> io::Result<String> {
     arceos_api::fs::ax_current_dir()
 }
 
 /// Changes the current working directory to the specified path.
-#[cfg(feature = "fs")]
+#[cfg(any(feature = "fs", feature = "initramfs"))]
 pub fn set_current_dir(path: &str) -> io::Result<()> {
     arceos_api::fs::ax_set_current_dir(path)
 }
@@ -1,4 +1,5 @@
 use crate::io::{prelude::*, Result, SeekFrom};
+use crate::time::SystemTime;
 use core::fmt;
 
 use arceos_api::fs as api;
@@ -16,7 +17,7 @@ pub struct File {
 }
 
 /// Metadata information about a file.
-pub struct Metadata(api::AxFileAttr);
+pub struct Metadata(pub(super) api::AxFileAttr);
 
 /// Options and flags which can be used to configure how a file is opened.
 #[derive(Clone, Debug)]
@@ -90,6 +91,12 @@ impl Metadata {
         self.0.is_file()
     }
 
+    /// Returns `true` if this metadata is for a symbolic link, which is only
+    /// possible for [`symlink_metadata`](super::symlink_metadata).
+    pub const fn is_symlink(&self) -> bool {
+        matches!(self.0.file_type(), FileType::SymLink)
+    }
+
     /// Returns the size of the file, in bytes, this metadata is for.
     #[allow(clippy::len_without_is_empty)]
     pub const fn len(&self) -> u64 {
@@ -110,6 +117,16 @@ impl Metadata {
     pub const fn blocks(&self) -> u64 {
         self.0.blocks()
     }
+
+    /// Returns the last modification time listed in this metadata.
+    pub fn modified(&self) -> Result<SystemTime> {
+        Ok(SystemTime::from(self.0.mtime()))
+    }
+
+    /// Returns the last access time of this metadata.
+    pub fn accessed(&self) -> Result<SystemTime> {
+        Ok(SystemTime::from(self.0.atime()))
+    }
 }
 
 impl fmt::Debug for Metadata {
@@ -162,6 +179,50 @@ impl File {
     pub fn metadata(&self) -> Result<Metadata> {
         api::ax_file_attr(&self.inner).map(Metadata)
     }
+
+    /// Attempts to sync all OS-internal metadata to disk.
+    pub fn sync_all(&self) -> Result<()> {
+        api::ax_sync_file(&self.inner)
+    }
+
+    /// Similar to [`sync_all`](File::sync_all), but may not synchronize file
+    /// metadata to the filesystem.
+    pub fn sync_data(&self) -> Result<()> {
+        api::ax_sync_file(&self.inner)
+    }
+
+    /// Acquires an exclusive lock on the file, blocks until it can be
+    /// acquired.
+    ///
+    /// The lock is advisory and owned by this file handle. It is released by
+    /// [`unlock`](File::unlock), or when the handle is dropped.
+    pub fn lock(&self) -> Result<()> {
+        api::ax_lock_file(&self.inner, api::AxLockKind::Exclusive, true)
+    }
+
+    /// Acquires a shared lock on the file, blocks until it can be acquired.
+    pub fn lock_shared(&self) -> Result<()> {
+        api::ax_lock_file(&self.inner, api::AxLockKind::Shared, true)
+    }
+
+    /// Tries to acquire an exclusive lock on the file, returns
+    /// [`WouldBlock`](crate::io::Error::WouldBlock) if others hold locks
+    /// on it.
+    pub fn try_lock(&self) -> Result<()> {
+        api::ax_lock_file(&self.inner, api::AxLockKind::Exclusive, false)
+    }
+
+    /// Tries to acquire a shared lock on the file, returns
+    /// [`WouldBlock`](crate::io::Error::WouldBlock) if others hold an
+    /// exclusive lock on it.
+    pub fn try_lock_shared(&self) -> Result<()> {
+        api::ax_lock_file(&self.inner, api::AxLockKind::Shared, false)
+    }
+
+    /// Releases the lock held by this file handle.
+    pub fn unlock(&self) -> Result<()> {
+        api::ax_unlock_file(&self.inner)
+    }
 }
 
 impl Read for File {
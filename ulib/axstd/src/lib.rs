@@ -27,9 +27,15 @@
 //!     - `sched_fifo`: Use the FIFO cooperative scheduler.
 //!     - `sched_rr`: Use the Round-robin preemptive scheduler.
 //!     - `sched_cfs`: Use the Completely Fair Scheduler (CFS) preemptive scheduler.
+//!     - `stack_usage`: Measure the deepest stack usage of threads ([`thread::stack_max_usage`]).
+//!     - `task_group`: Enable thread groups sharing a CPU quota and a weight ([`thread::ThreadGroup`]).
 //! - Upperlayer stacks
 //!     - `fs`: Enable file system support.
 //!     - `myfs`: Allow users to define their custom filesystems to override the default.
+//!     - `extfs`: Use an ext2/ext4 filesystem on the root device if there is one.
+//!     - `initramfs`: Use an initramfs (loaded by the bootloader or embedded in the kernel image) as the root filesystem.
+//!     - `overlayfs`: Overlay the root filesystem with a RAM filesystem, so that it is never modified.
+//!     - `virtio-9p`: Mount directories shared by the host through virtio-9p (e.g., QEMU `-virtfs`) on `/mnt/<tag>`.
 //!     - `net`: Enable networking support.
 //!     - `dns`: Enable DNS lookup support.
 //!     - `display`: Enable graphics support.
@@ -71,7 +77,7 @@ pub mod sync;
 pub mod thread;
 pub mod time;
 
-#[cfg(feature = "fs")]
+#[cfg(any(feature = "fs", feature = "initramfs"))]
 pub mod fs;
 #[cfg(feature = "net")]
 pub mod net;
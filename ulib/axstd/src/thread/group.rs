@@ -0,0 +1,68 @@
+//! Thread groups with CPU bandwidth limits.
+
+use core::time::Duration;
+
+use arceos_api::task::{self as api, AxTaskGroupHandle};
+
+use super::JoinHandle;
+use crate::io;
+
+/// A group of threads sharing a CPU quota and a weight.
+///
+/// A thread spawned by a member joins the same group, so a job can be capped
+/// by putting the thread that starts it into a group, without changing the
+/// code of the job.
+///
+/// The weight only takes effect with the `sched_cfs` feature.
+#[derive(Clone)]
+pub struct ThreadGroup {
+    inner: AxTaskGroupHandle,
+}
+
+impl ThreadGroup {
+    /// Creates a new empty group, with no CPU quota and the default weight
+    /// (1024, the weight of nice 0).
+    pub fn new(name: &str) -> Self {
+        Self {
+            inner: api::ax_task_group_create(name),
+        }
+    }
+
+    /// Returns the name of the group.
+    pub fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    /// Limits the CPU time of the group to `quota` per `period`, or removes
+    /// the limit if `quota` is [`None`]. E.g., a quota of 30ms per 100ms caps
+    /// the group at 30% of one CPU.
+    pub fn set_cpu_quota(&self, quota: Option<Duration>, period: Duration) -> io::Result<()> {
+        api::ax_task_group_set_bandwidth(&self.inner, quota, period)
+    }
+
+    /// Sets the weight shared by the members.
+    pub fn set_weight(&self, weight: usize) -> io::Result<()> {
+        api::ax_task_group_set_weight(&self.inner, weight)
+    }
+
+    /// Returns the total CPU time used by the members while in the group.
+    pub fn cpu_usage(&self) -> Duration {
+        api::ax_task_group_usage(&self.inner)
+    }
+
+    /// Moves the thread into this group, from the group it was in.
+    pub fn add<T>(&self, thread: &JoinHandle<T>) {
+        api::ax_task_group_add(&self.inner, &thread.native);
+    }
+
+    /// Moves the current thread into this group, from the group it was in.
+    pub fn join_current(&self) {
+        api::ax_task_group_add_current(&self.inner);
+    }
+
+    /// Removes the thread from this group, and restores the priority it had
+    /// before joining. Returns `false` if it is not a member.
+    pub fn remove<T>(&self, thread: &JoinHandle<T>) -> bool {
+        api::ax_task_group_remove(&self.inner, &thread.native)
+    }
+}
@@ -5,6 +5,11 @@ mod multi;
 #[cfg(feature = "multitask")]
 pub use multi::*;
 
+#[cfg(feature = "task_group")]
+mod group;
+#[cfg(feature = "task_group")]
+pub use group::ThreadGroup;
+
 use arceos_api::task as api;
 
 /// Current thread gives up the CPU time voluntarily, and switches to another
@@ -39,3 +44,17 @@ pub fn sleep(dur: core::time::Duration) {
 pub fn sleep_until(deadline: arceos_api::time::AxTimeValue) {
     api::ax_sleep_until(deadline);
 }
+
+/// Returns the deepest usage of the current thread's stack so far, in bytes,
+/// or `None` if it runs on the boot stack (e.g., the main thread).
+#[cfg(feature = "stack_usage")]
+pub fn stack_max_usage() -> Option<usize> {
+    api::ax_current_stack_max_usage()
+}
+
+/// Sets the percentage of its stack a thread may use without a warning when
+/// it exits.
+#[cfg(feature = "stack_usage")]
+pub fn set_stack_usage_warn_percent(percent: usize) {
+    api::ax_set_stack_usage_warn_percent(percent);
+}
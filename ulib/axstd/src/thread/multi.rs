@@ -159,7 +159,7 @@ unsafe impl<T> Sync for Packet<T> {}
 /// means that there is no longer any handle to the thread and no way to `join`
 /// on it.
 pub struct JoinHandle<T> {
-    native: AxTaskHandle,
+    pub(super) native: AxTaskHandle,
     thread: Thread,
     packet: Arc<Packet<T>>,
 }